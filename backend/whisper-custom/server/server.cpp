@@ -4,6 +4,7 @@
 #include "httplib.h"
 #include "json.hpp"
 
+#include <algorithm>
 #include <cmath>
 #include <fstream>
 #include <cstdio>
@@ -12,6 +13,7 @@
 #include <vector>
 #include <cstring>
 #include <sstream>
+#include <stdexcept>
 
 #if defined(_MSC_VER)
 #pragma warning(disable: 4244 4267) // possible loss of data
@@ -250,6 +252,41 @@ bool convert_to_wav(const std::string & temp_filename, std::string & error_resp)
     return true;
 }
 
+// check the model header before handing the file to whisper, which can crash or
+// produce nonsense on a model written for a different format version
+bool check_model_format(const std::string & path, std::string & error) {
+    std::ifstream file(path, std::ios::binary);
+    uint32_t magic = 0;
+    if (!file.read(reinterpret_cast<char *>(&magic), sizeof(magic))) {
+        error = "model file is empty or unreadable, please re-download";
+        return false;
+    }
+    if (magic == 0x46554747) { // "GGUF"
+        error = "model format incompatible (GGUF is not a whisper model format), please re-download";
+        return false;
+    }
+    if (magic != GGML_FILE_MAGIC) {
+        error = "model format incompatible, please re-download";
+        return false;
+    }
+    // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer, n_text_ctx,
+    // n_text_state, n_text_head, n_text_layer, n_mels, ftype
+    int32_t hparams[11] = {};
+    if (!file.read(reinterpret_cast<char *>(hparams), sizeof(hparams))) {
+        error = "model file is truncated, please re-download";
+        return false;
+    }
+    const int32_t ftype = hparams[10];
+    const int32_t qnt_version = ftype / GGML_QNT_VERSION_FACTOR;
+    // f32 and f16 models don't depend on the quantization version
+    if (ftype % GGML_QNT_VERSION_FACTOR > 1 && qnt_version != GGML_QNT_VERSION) {
+        error = "model quantization version " + std::to_string(qnt_version) + " is incompatible with this server (version " +
+                std::to_string(GGML_QNT_VERSION) + "), please re-download";
+        return false;
+    }
+    return true;
+}
+
 std::string estimate_diarization_speaker(std::vector<std::vector<float>> pcmf32s, int64_t t0, int64_t t1, bool id_only = false) {
     std::string speaker = "";
     const int64_t n_samples = pcmf32s[0].size();
@@ -578,6 +615,13 @@ int main(int argc, char ** argv) {
         }
     }
 
+    std::string model_error;
+    if (!check_model_format(params.model, model_error)) {
+        fprintf(stderr, "[ERROR] %s: %s\n", params.model.c_str(), model_error.c_str());
+        fflush(stderr);
+        return 3;
+    }
+
     struct whisper_context * ctx = whisper_init_from_file_with_params(params.model.c_str(), cparams);
 
     if (ctx == nullptr) {
@@ -1009,11 +1053,46 @@ int main(int argc, char ** argv) {
         }
 
         auto audio_file = req.get_file_value("audio");
+        // optional transcript context from the client, used as the initial prompt
+        std::string stream_prompt = params.prompt;
+        if (req.has_file("prompt")) {
+            stream_prompt = req.get_file_value("prompt").content;
+        }
+        // optional number of candidates sampled when decoding falls back to a higher temperature,
+        // and beam search and sampling temperature, used by offline multi-pass transcription
+        int stream_best_of = -1;
+        int stream_beam_size = -1;
+        float stream_temperature = -1.0f;
+        try {
+            if (req.has_file("best_of")) {
+                stream_best_of = std::max(1, std::stoi(req.get_file_value("best_of").content));
+            }
+            if (req.has_file("beam_size")) {
+                stream_beam_size = std::max(1, std::stoi(req.get_file_value("beam_size").content));
+            }
+            if (req.has_file("temperature")) {
+                stream_temperature = std::stof(req.get_file_value("temperature").content);
+                if (!std::isfinite(stream_temperature) || stream_temperature < 0.0f) {
+                    throw std::invalid_argument("temperature must be a non-negative number");
+                }
+            }
+        } catch (const std::exception & e) {
+            fprintf(stderr, "[ERROR] Invalid decoding parameter in /stream request: %s\n", e.what());
+            res.status = 400;
+            res.set_content(json{{"error", "invalid best_of, beam_size or temperature"}}.dump(), "application/json");
+            return;
+        }
+        // skipping temperature fallback trades accuracy on hard audio for speed when the client falls behind
+        const bool stream_no_fallback = req.has_file("no_fallback") && req.get_file_value("no_fallback").content == "true";
+        // a stateless request is transcribed on its own, without the overlap kept between stream requests
+        const bool stateless = req.has_file("stateless") && req.get_file_value("stateless").content == "true";
         const float* audio_data = reinterpret_cast<const float*>(audio_file.content.c_str());
         int n_samples = audio_file.content.size() / sizeof(float);
 
         // Add new samples to buffer
-        audio_buffer.insert(audio_buffer.end(), audio_data, audio_data + n_samples);
+        std::vector<float> stateless_buffer;
+        std::vector<float> & pass_buffer = stateless ? stateless_buffer : audio_buffer;
+        pass_buffer.insert(pass_buffer.end(), audio_data, audio_data + n_samples);
 
         // Calculate minimum required samples
         const int min_samples = (MIN_AUDIO_LENGTH_MS * 16000) / 1000;
@@ -1022,15 +1101,30 @@ int main(int argc, char ** argv) {
         response["segments"] = json::array();
 
         // Only process if we have enough audio data
-        if (audio_buffer.size() >= min_samples) {
+        if (stateless || pass_buffer.size() >= min_samples) {
             // Run inference
-            whisper_full_params wparams = whisper_full_default_params(WHISPER_SAMPLING_GREEDY);
+            const bool beam_search = stream_beam_size > 1;
+            whisper_full_params wparams = whisper_full_default_params(beam_search ? WHISPER_SAMPLING_BEAM_SEARCH : WHISPER_SAMPLING_GREEDY);
             wparams.print_progress = false;
             wparams.print_special = params.print_special;
             wparams.language = params.language.c_str();
             wparams.n_threads = params.n_threads;
+            wparams.initial_prompt = stream_prompt.c_str();
+            wparams.token_timestamps = true;
+            if (stream_best_of > 0) {
+                wparams.greedy.best_of = stream_best_of;
+            }
+            if (beam_search) {
+                wparams.beam_search.beam_size = stream_beam_size;
+            }
+            if (stream_temperature >= 0.0f) {
+                wparams.temperature = stream_temperature;
+            }
+            if (stream_no_fallback) {
+                wparams.temperature_inc = 0.0f;
+            }
             
-            if (whisper_full(ctx, wparams, audio_buffer.data(), audio_buffer.size()) != 0) {
+            if (whisper_full(ctx, wparams, pass_buffer.data(), pass_buffer.size()) != 0) {
                 res.set_content("{\"error\":\"failed to process audio\"}", "application/json");
                 return;
             }
@@ -1047,15 +1141,59 @@ int main(int argc, char ** argv) {
                 segment["text"] = text;
                 segment["t0"] = t0;
                 segment["t1"] = t1;
+
+                // word timings and confidence, so the client can merge the overlap between requests
+                segment["words"] = json::array();
+                std::string word_text;
+                int64_t word_t0 = 0;
+                int64_t word_t1 = 0;
+                float word_p = 0.0f;
+                int word_tokens = 0;
+                auto flush_word = [&]() {
+                    if (word_tokens > 0) {
+                        segment["words"].push_back(json{
+                            {"text", word_text},
+                            {"t0", word_t0},
+                            {"t1", word_t1},
+                            {"p", word_p / word_tokens},
+                        });
+                    }
+                    word_text.clear();
+                    word_p = 0.0f;
+                    word_tokens = 0;
+                };
+                const int n_tokens = whisper_full_n_tokens(ctx, i);
+                for (int j = 0; j < n_tokens; ++j) {
+                    whisper_token_data token = whisper_full_get_token_data(ctx, i, j);
+                    if (token.id >= whisper_token_eot(ctx)) {
+                        continue;
+                    }
+                    const std::string token_text = whisper_full_get_token_text(ctx, i, j);
+                    // a leading space starts a new word
+                    if (word_tokens > 0 && !token_text.empty() && token_text[0] == ' ') {
+                        flush_word();
+                    }
+                    if (word_tokens == 0) {
+                        word_t0 = token.t0;
+                    }
+                    word_text += token_text;
+                    word_t1 = token.t1;
+                    word_p += token.p;
+                    word_tokens++;
+                }
+                flush_word();
+
                 response["segments"].push_back(segment);
             }
+            // detected language, so the client can pick a model for it
+            response["language"] = whisper_lang_str(whisper_full_lang_id(ctx));
 
             // Keep a small overlap for context
             const int overlap_samples = (200 * 16000) / 1000; // 200ms overlap
-            if (audio_buffer.size() > overlap_samples) {
-                audio_buffer.erase(audio_buffer.begin(), audio_buffer.end() - overlap_samples);
+            if (pass_buffer.size() > overlap_samples) {
+                pass_buffer.erase(pass_buffer.begin(), pass_buffer.end() - overlap_samples);
             } else {
-                audio_buffer.clear();
+                pass_buffer.clear();
             }
         }
 
@@ -1083,18 +1221,25 @@ int main(int argc, char ** argv) {
             return;
         }
 
-        // clean up
-        whisper_free(ctx);
-
-        // whisper init
-        ctx = whisper_init_from_file_with_params(model.c_str(), cparams);
+        std::string model_error;
+        if (!check_model_format(model, model_error))
+        {
+            fprintf(stderr, "[ERROR] 'model': %s: %s\n", model.c_str(), model_error.c_str());
+            fflush(stderr);
+            res.set_content(json{{"error", model_error}}.dump(), "application/json");
+            return;
+        }
 
-        // TODO perhaps load prior model here instead of exit
-        if (ctx == nullptr) {
-            fprintf(stderr, "[ERROR] Model init failed, no model loaded must exit\n");
+        // load the new model before freeing the current one, so a failed load keeps the server usable
+        struct whisper_context * new_ctx = whisper_init_from_file_with_params(model.c_str(), cparams);
+        if (new_ctx == nullptr) {
+            fprintf(stderr, "[ERROR] Model init failed for %s, keeping the current model\n", model.c_str());
             fflush(stderr);
-            exit(1);
+            res.set_content("{\"error\":\"model failed to load, it may be incompatible, please re-download\"}", "application/json");
+            return;
         }
+        whisper_free(ctx);
+        ctx = new_ctx;
 
         // initialize openvino encoder. this has no effect on whisper.cpp builds that don't have OpenVINO configured
         whisper_ctx_init_openvino_encoder(ctx, nullptr, params.openvino_encode_device.c_str(), nullptr);
@@ -1120,7 +1265,8 @@ int main(int argc, char ** argv) {
     });
 
     svr.set_error_handler([](const Request &req, Response &res) {
-        if (res.status == 400) {
+        // keep the reason a handler gave for rejecting the request
+        if (res.status == 400 && res.body.empty()) {
             res.set_content("Invalid request", "text/plain");
         } else if (res.status != 500) {
             res.set_content("File Not Found (" + req.path + ")", "text/plain");
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use chrono::Utc;
-use log::debug;
+use log::{debug, warn};
 use realfft::num_complex::{Complex32, ComplexFloat};
 use realfft::RealFftPlanner;
 use rubato::{
@@ -10,8 +10,16 @@ use std::path::PathBuf;
 
 use super::encode::encode_single_audio; // Correct path to encode module
 
+/// Root mean square of `audio`, 0 for an empty slice.
+pub fn rms(audio: &[f32]) -> f32 {
+    if audio.is_empty() {
+        return 0.0;
+    }
+    (audio.iter().map(|&x| x * x).sum::<f32>() / audio.len() as f32).sqrt()
+}
+
 pub fn normalize_v2(audio: &[f32]) -> Vec<f32> {
-    let rms = (audio.iter().map(|&x| x * x).sum::<f32>() / audio.len() as f32).sqrt();
+    let rms = rms(audio);
     let peak = audio
         .iter()
         .fold(0.0f32, |max, &sample| max.max(sample.abs()));
@@ -124,6 +132,47 @@ pub fn audio_to_mono(audio: &[f32], channels: u16) -> Vec<f32> {
     mono_samples
 }
 
+/// Like `audio_to_mono`, but only averages the channels listed in `selection`.
+/// An empty selection averages all channels.
+pub fn audio_to_mono_selected(audio: &[f32], channels: u16, selection: &[usize]) -> Vec<f32> {
+    if selection.is_empty() {
+        return audio_to_mono(audio, channels);
+    }
+    let mut mono_samples = Vec::with_capacity(audio.len() / channels as usize);
+
+    for frame in audio.chunks(channels as usize) {
+        let (sum, count) = selection
+            .iter()
+            .filter_map(|&channel| frame.get(channel))
+            .fold((0.0f32, 0usize), |(sum, count), &sample| (sum + sample, count + 1));
+        mono_samples.push(if count > 0 { sum / count as f32 } else { 0.0 });
+    }
+
+    mono_samples
+}
+
+/// Drops channel indices the device doesn't have. Returns an empty selection
+/// (all channels) if nothing valid remains.
+pub fn validate_channel_selection(selection: &[usize], channels: u16) -> Vec<usize> {
+    let mut valid: Vec<usize> = selection
+        .iter()
+        .copied()
+        .filter(|&channel| channel < channels as usize)
+        .collect();
+    valid.sort_unstable();
+    valid.dedup();
+
+    if valid.len() != selection.len() {
+        warn!(
+            "Channel selection {:?} doesn't match a {}-channel device, using {:?}",
+            selection,
+            channels,
+            if valid.is_empty() { "all channels".to_string() } else { format!("{:?}", valid) }
+        );
+    }
+    valid
+}
+
 pub fn resample(input: &[f32], from_sample_rate: u32, to_sample_rate: u32) -> Result<Vec<f32>> {
     debug!("Resampling audio");
     let params = SincInterpolationParameters {
@@ -175,3 +224,30 @@ pub fn write_audio_to_file(
     }
     Ok(file_path_clone)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixes_only_the_selected_channels() {
+        // Two frames of six channels; channels 0 and 1 carry the signal
+        let audio = [0.2, 0.4, 0.9, 0.9, 0.9, 0.9, -0.2, -0.6, 0.9, 0.9, 0.9, 0.9];
+        let mono = audio_to_mono_selected(&audio, 6, &[0, 1]);
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0] - 0.3).abs() < 1e-6);
+        assert!((mono[1] + 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn an_empty_selection_mixes_every_channel() {
+        let audio = [0.0, 0.6, 0.2, 0.2];
+        assert_eq!(audio_to_mono_selected(&audio, 2, &[]), audio_to_mono(&audio, 2));
+    }
+
+    #[test]
+    fn drops_channels_the_device_does_not_have() {
+        assert_eq!(validate_channel_selection(&[3, 1, 1, 8], 4), vec![1, 3]);
+        assert!(validate_channel_selection(&[2], 2).is_empty());
+    }
+}
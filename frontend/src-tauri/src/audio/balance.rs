@@ -0,0 +1,181 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::audio_processing::rms;
+
+/// Settings for the automatic mic/system loudness balance applied before mixing.
+/// Off by default: while enabled the sources are also mixed evenly instead of
+/// favouring the mic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceBalanceConfig {
+    pub enabled: bool,
+    /// Fraction of the way the long-term level moves towards each new block (0..1).
+    pub level_smoothing: f32,
+    /// Fraction of the way the applied gain moves towards its target per block (0..1).
+    pub gain_smoothing: f32,
+    pub min_gain: f32,
+    pub max_gain: f32,
+    /// Blocks quieter than this RMS are treated as silence and don't update the level.
+    pub silence_rms: f32,
+}
+
+impl Default for SourceBalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level_smoothing: 0.02,
+            gain_smoothing: 0.05,
+            min_gain: 0.25,
+            max_gain: 4.0,
+            silence_rms: 0.002,
+        }
+    }
+}
+
+/// Tracks the long-term RMS of the mic and system sources and applies slowly
+/// varying corrective gains so both reach the mix at a comparable level.
+pub struct SourceBalancer {
+    config: SourceBalanceConfig,
+    mic_level: Option<f32>,
+    system_level: Option<f32>,
+    mic_gain: f32,
+    system_gain: f32,
+}
+
+impl SourceBalancer {
+    pub fn new(config: SourceBalanceConfig) -> Self {
+        Self {
+            config,
+            mic_level: None,
+            system_level: None,
+            mic_gain: 1.0,
+            system_gain: 1.0,
+        }
+    }
+
+    /// Applies new settings, keeping the measured levels and current gains.
+    pub fn update_config(&mut self, config: SourceBalanceConfig) {
+        self.config = config;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Forgets the measured levels and returns to unity gain, e.g. after a
+    /// device change made the old levels meaningless.
+    pub fn reset(&mut self) {
+        self.mic_level = None;
+        self.system_level = None;
+        self.mic_gain = 1.0;
+        self.system_gain = 1.0;
+    }
+
+    /// Current (mic, system) gains.
+    pub fn gains(&self) -> (f32, f32) {
+        (self.mic_gain, self.system_gain)
+    }
+
+    /// Updates the level estimates from this block and applies the gains in place.
+    pub fn process(&mut self, mic: &mut [f32], system: &mut [f32]) {
+        if !self.config.enabled {
+            return;
+        }
+
+        self.mic_level = Self::update_level(self.mic_level, mic, &self.config);
+        self.system_level = Self::update_level(self.system_level, system, &self.config);
+
+        // Only rebalance once both sources have been heard, otherwise hold the gains
+        if let (Some(mic_level), Some(system_level)) = (self.mic_level, self.system_level) {
+            let target = (mic_level * system_level).sqrt();
+            let mic_target = (target / mic_level).clamp(self.config.min_gain, self.config.max_gain);
+            let system_target = (target / system_level).clamp(self.config.min_gain, self.config.max_gain);
+
+            self.mic_gain += (mic_target - self.mic_gain) * self.config.gain_smoothing;
+            self.system_gain += (system_target - self.system_gain) * self.config.gain_smoothing;
+            debug!(
+                "Source balance: mic level {:.4} gain {:.2}, system level {:.4} gain {:.2}",
+                mic_level, self.mic_gain, system_level, self.system_gain
+            );
+        }
+
+        for sample in mic.iter_mut() {
+            *sample *= self.mic_gain;
+        }
+        for sample in system.iter_mut() {
+            *sample *= self.system_gain;
+        }
+    }
+
+    fn update_level(level: Option<f32>, samples: &[f32], config: &SourceBalanceConfig) -> Option<f32> {
+        if samples.is_empty() {
+            return level;
+        }
+        let block_rms = rms(samples);
+        if block_rms < config.silence_rms {
+            return level;
+        }
+        Some(match level {
+            Some(level) => level + (block_rms - level) * config.level_smoothing,
+            None => block_rms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> SourceBalanceConfig {
+        SourceBalanceConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gains_converge_towards_equal_levels() {
+        let mut balancer = SourceBalancer::new(enabled());
+        let (mut mic, mut system) = (vec![0.0; 160], vec![0.0; 160]);
+        for _ in 0..500 {
+            mic.fill(0.4);
+            system.fill(0.1);
+            balancer.process(&mut mic, &mut system);
+        }
+
+        let (mic_gain, system_gain) = balancer.gains();
+        assert!((mic_gain - 0.5).abs() < 0.01, "mic gain {}", mic_gain);
+        assert!((system_gain - 2.0).abs() < 0.02, "system gain {}", system_gain);
+        assert!((mic[0] - system[0]).abs() < 0.005);
+    }
+
+    #[test]
+    fn holds_unity_gain_until_both_sources_are_heard() {
+        let mut balancer = SourceBalancer::new(enabled());
+        let mut mic = vec![0.4; 160];
+        let mut system = vec![0.0; 160];
+        balancer.process(&mut mic, &mut system);
+        assert_eq!(balancer.gains(), (1.0, 1.0));
+        assert_eq!(mic, vec![0.4; 160]);
+    }
+
+    #[test]
+    fn gains_stay_within_their_limits() {
+        let mut balancer = SourceBalancer::new(SourceBalanceConfig {
+            gain_smoothing: 1.0,
+            ..enabled()
+        });
+        balancer.process(&mut [0.9; 160], &mut [0.003; 160]);
+        assert_eq!(balancer.gains(), (0.25, 4.0));
+    }
+
+    #[test]
+    fn leaves_the_sources_alone_by_default() {
+        let mut balancer = SourceBalancer::new(SourceBalanceConfig::default());
+        let (mut mic, mut system) = (vec![0.4; 160], vec![0.1; 160]);
+        balancer.process(&mut mic, &mut system);
+        assert!(!balancer.is_enabled());
+        assert_eq!((mic[0], system[0]), (0.4, 0.1));
+    }
+}
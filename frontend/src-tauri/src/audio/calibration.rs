@@ -0,0 +1,377 @@
+use anyhow::{anyhow, Context, Result};
+use cpal::traits::StreamTrait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot;
+
+use super::core::{AudioDevice, AudioStream};
+use super::monitor::build_output_stream;
+
+// Window used to find where the tone arrives at the microphone
+const ONSET_WINDOW_MS: u32 = 10;
+// Recording continues this long after the tone, in case the echo arrives late
+const TAIL_MS: u64 = 500;
+// Longest wait for the output device to start taking the tone
+const TONE_START_TIMEOUT: Duration = Duration::from_secs(2);
+// Echo louder than this, relative to the played tone, means the speakers reach the mic
+const BLEED_THRESHOLD_DB: f32 = -40.0;
+
+/// Settings for the setup routine that plays a tone and listens for it on the
+/// microphone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    pub tone_hz: f32,
+    pub tone_ms: u32,
+    /// Peak amplitude of the tone, 0.0 to 1.0.
+    pub tone_volume: f32,
+    /// Room noise recorded before the tone, to set the detection threshold.
+    pub noise_ms: u32,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            tone_hz: 1000.0,
+            tone_ms: 1000,
+            tone_volume: 0.3,
+            noise_ms: 1000,
+        }
+    }
+}
+
+/// What calibration measured for one microphone and output pair, with the
+/// settings it suggests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    pub input_device: String,
+    pub output_device: String,
+    pub created_at: String,
+    pub noise_floor_rms: f32,
+    /// Time from playing the tone to hearing it on the microphone. `None` if
+    /// it was never heard, e.g. with headphones.
+    pub echo_delay_ms: Option<u32>,
+    /// Level the microphone heard the tone at, relative to how it was played.
+    pub echo_level_db: Option<f32>,
+    /// Whether the microphone picks up the speakers clearly enough to double
+    /// remote speech in the mix. Headphones or a lower system weight help.
+    pub speaker_bleed: bool,
+    /// Microphone gain that brings audio from the room to the level it was
+    /// played at.
+    pub recommended_mic_gain: f32,
+    /// Silence threshold just above the room's noise floor.
+    pub recommended_silence_rms: f32,
+}
+
+/// The measurements taken from a calibration recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationMeasurement {
+    pub noise_floor_rms: f32,
+    pub echo_delay_ms: Option<u32>,
+    pub echo_rms: Option<f32>,
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+fn tone(sample_rate: u32, config: &CalibrationConfig) -> Vec<f32> {
+    let len = (sample_rate as u64 * config.tone_ms as u64 / 1000) as usize;
+    // Short fades keep the start and end from clicking
+    let fade = (sample_rate / 100).max(1) as usize;
+    let volume = config.tone_volume.clamp(0.0, 1.0);
+    (0..len)
+        .map(|i| {
+            let envelope = (i.min(len - 1 - i) as f32 / fade as f32).min(1.0);
+            let phase = 2.0 * std::f32::consts::PI * config.tone_hz * i as f32 / sample_rate as f32;
+            phase.sin() * volume * envelope
+        })
+        .collect()
+}
+
+/// Finds the tone in `recorded`, which holds `noise_samples` of room noise
+/// followed by the microphone's recording while the tone was played. The echo
+/// delay is measured from `tone_start`, the sample recorded when the first
+/// sample of the tone was played.
+pub fn measure_calibration(
+    recorded: &[f32],
+    sample_rate: u32,
+    noise_samples: usize,
+    tone_start: usize,
+    config: &CalibrationConfig,
+) -> CalibrationMeasurement {
+    let noise_samples = noise_samples.min(recorded.len());
+    let tone_start = tone_start.clamp(noise_samples, recorded.len());
+    let noise_floor_rms = rms(&recorded[..noise_samples]);
+    let threshold = (noise_floor_rms * 4.0).max(0.001);
+    let window = (sample_rate * ONSET_WINDOW_MS / 1000).max(1) as usize;
+
+    let onset = recorded[tone_start..]
+        .chunks(window)
+        .position(|block| rms(block) > threshold)
+        .map(|index| index * window);
+    let Some(onset) = onset else {
+        return CalibrationMeasurement {
+            noise_floor_rms,
+            echo_delay_ms: None,
+            echo_rms: None,
+        };
+    };
+
+    let tone_len = (sample_rate as u64 * config.tone_ms as u64 / 1000) as usize;
+    let start = tone_start + onset;
+    let end = (start + tone_len).min(recorded.len());
+    CalibrationMeasurement {
+        noise_floor_rms,
+        echo_delay_ms: Some((onset as u64 * 1000 / sample_rate.max(1) as u64) as u32),
+        echo_rms: Some(rms(&recorded[start..end])),
+    }
+}
+
+/// Turns measurements into a profile with recommended settings.
+pub fn build_profile(
+    measurement: &CalibrationMeasurement,
+    input_device: &str,
+    output_device: &str,
+    config: &CalibrationConfig,
+) -> CalibrationProfile {
+    // A full-scale sine's RMS is its peak over √2
+    let played_rms = config.tone_volume.clamp(0.0, 1.0) / std::f32::consts::SQRT_2;
+    let echo_level_db = measurement
+        .echo_rms
+        .filter(|echo_rms| *echo_rms > 0.0 && played_rms > 0.0)
+        .map(|echo_rms| 20.0 * (echo_rms / played_rms).log10());
+    let recommended_mic_gain = match measurement.echo_rms {
+        Some(echo_rms) if echo_rms > 0.0 => (played_rms / echo_rms).clamp(0.25, 4.0),
+        _ => 1.0,
+    };
+
+    CalibrationProfile {
+        input_device: input_device.to_string(),
+        output_device: output_device.to_string(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        noise_floor_rms: measurement.noise_floor_rms,
+        echo_delay_ms: measurement.echo_delay_ms,
+        echo_level_db,
+        speaker_bleed: echo_level_db.is_some_and(|level| level > BLEED_THRESHOLD_DB),
+        recommended_mic_gain,
+        recommended_silence_rms: (measurement.noise_floor_rms * 3.0).max(0.001),
+    }
+}
+
+// Plays `config`'s tone once on its own thread, like the monitor stream.
+// Returns once the output device has taken the first samples of the tone.
+async fn play_tone(
+    output_device: &str,
+    config: &CalibrationConfig,
+    running: Arc<AtomicBool>,
+) -> Result<thread::JoinHandle<()>> {
+    let pending: Arc<Mutex<VecDeque<f32>>> = Arc::new(Mutex::new(VecDeque::new()));
+    let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+    let muted = Arc::new(AtomicBool::new(false));
+    let (ready_tx, ready_rx) = oneshot::channel::<Result<()>>();
+    let device_name = output_device.to_string();
+    let config = config.clone();
+
+    let handle = thread::spawn(move || {
+        // The rate asked for is only a preference, the tone is made at whatever the device runs at
+        let stream = match build_output_stream(&device_name, 48000, pending.clone(), volume, muted) {
+            Ok((stream, output_rate)) => {
+                let tone = tone(output_rate, &config);
+                let tone_len = tone.len();
+                if let Ok(mut pending) = pending.lock() {
+                    pending.extend(tone);
+                }
+                if let Err(e) = stream.play() {
+                    ready_tx.send(Err(anyhow!("Failed to play calibration tone: {}", e))).ok();
+                    return;
+                }
+                // Opening the stream takes a while, the echo delay counts from the first played sample
+                let deadline = Instant::now() + TONE_START_TIMEOUT;
+                while Instant::now() < deadline && pending.lock().is_ok_and(|pending| pending.len() == tone_len) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                ready_tx.send(Ok(())).ok();
+                stream
+            }
+            Err(e) => {
+                ready_tx.send(Err(e)).ok();
+                return;
+            }
+        };
+        while running.load(Ordering::Acquire) {
+            thread::sleep(Duration::from_millis(20));
+        }
+        drop(stream);
+    });
+
+    ready_rx
+        .await
+        .map_err(|_| anyhow!("Calibration tone thread exited before playing"))??;
+    Ok(handle)
+}
+
+/// Records the room through `input`, plays a tone on `output_device` and
+/// measures how it comes back on the microphone.
+pub async fn run_calibration(
+    input: Arc<AudioDevice>,
+    output_device: &str,
+    config: &CalibrationConfig,
+) -> Result<CalibrationProfile> {
+    let is_running = Arc::new(AtomicBool::new(true));
+    let stream = AudioStream::from_device(input.clone(), is_running.clone()).await?;
+    let sample_rate = stream.device_config.sample_rate().0;
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+
+    let mut receiver = stream.subscribe().await;
+    let buffer = recorded.clone();
+    let collector = tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(chunk) => {
+                    if let Ok(mut buffer) = buffer.lock() {
+                        buffer.extend(chunk);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => warn!("Calibration recording skipped {} buffers", skipped),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    info!("Calibrating {} against {}", input.name, output_device);
+    tokio::time::sleep(Duration::from_millis(config.noise_ms as u64)).await;
+    let noise_samples = recorded.lock().map(|buffer| buffer.len()).unwrap_or(0);
+
+    let playing = Arc::new(AtomicBool::new(true));
+    let mut tone_start = noise_samples;
+    let played = match play_tone(output_device, config, playing.clone()).await {
+        Ok(player) => {
+            tone_start = recorded.lock().map(|buffer| buffer.len()).unwrap_or(noise_samples);
+            tokio::time::sleep(Duration::from_millis(config.tone_ms as u64 + TAIL_MS)).await;
+            playing.store(false, Ordering::Release);
+            if !matches!(tokio::task::spawn_blocking(move || player.join()).await, Ok(Ok(()))) {
+                warn!("Calibration tone thread panicked");
+            }
+            Ok(())
+        }
+        Err(e) => Err(e),
+    };
+
+    is_running.store(false, Ordering::SeqCst);
+    if let Err(e) = stream.stop().await {
+        warn!("Failed to stop calibration input stream: {}", e);
+    }
+    collector.abort();
+    played?;
+
+    let recorded = recorded.lock().map(|buffer| buffer.clone()).unwrap_or_default();
+    let measurement = measure_calibration(&recorded, sample_rate, noise_samples, tone_start, config);
+    let profile = build_profile(&measurement, &input.name, output_device, config);
+    info!(
+        "Calibration: noise floor {:.4}, echo delay {:?} ms, echo level {:?} dB",
+        profile.noise_floor_rms, profile.echo_delay_ms, profile.echo_level_db
+    );
+    Ok(profile)
+}
+
+pub fn calibration_profile_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("couldn't find the data directory")?;
+    Ok(data_dir.join("com.meetily.ai").join("calibration.json"))
+}
+
+pub fn save_calibration_profile(profile: &CalibrationProfile) -> Result<()> {
+    let path = calibration_profile_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(profile)?)?;
+    Ok(())
+}
+
+/// The last saved profile, `None` if calibration has never been run.
+pub fn load_calibration_profile() -> Result<Option<CalibrationProfile>> {
+    let path = calibration_profile_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn room_noise(len: usize) -> Vec<f32> {
+        let mut seed = 7u32;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5) * 0.002
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_echo_of_the_tone_through_the_speakers() {
+        let config = CalibrationConfig::default();
+        // One second of room noise, 150 ms until the tone arrives, then the tone at half its level
+        let mut recorded = room_noise(SAMPLE_RATE as usize + 2400);
+        recorded.extend(tone(SAMPLE_RATE, &config).iter().map(|sample| sample * 0.5));
+
+        let noise = SAMPLE_RATE as usize;
+        let measurement = measure_calibration(&recorded, SAMPLE_RATE, noise, noise, &config);
+        assert_eq!(measurement.echo_delay_ms, Some(150));
+        assert!(measurement.noise_floor_rms < 0.001);
+
+        let profile = build_profile(&measurement, "Built-in Microphone", "Speakers", &config);
+        let level = profile.echo_level_db.unwrap();
+        assert!((level + 6.0).abs() < 0.5, "echo at {} dB", level);
+        assert!(profile.speaker_bleed);
+        assert!((profile.recommended_mic_gain - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn the_echo_delay_counts_from_the_first_played_sample() {
+        let config = CalibrationConfig::default();
+        // The output stream took 100 ms to open, the tone arrived 50 ms after it started playing
+        let mut recorded = room_noise(SAMPLE_RATE as usize + 2400);
+        recorded.extend(tone(SAMPLE_RATE, &config).iter().map(|sample| sample * 0.5));
+
+        let noise = SAMPLE_RATE as usize;
+        let measurement = measure_calibration(&recorded, SAMPLE_RATE, noise, noise + 1600, &config);
+        assert_eq!(measurement.echo_delay_ms, Some(50));
+    }
+
+    #[test]
+    fn headphones_leave_no_echo() {
+        let config = CalibrationConfig::default();
+        let recorded = room_noise(SAMPLE_RATE as usize * 3);
+        let noise = SAMPLE_RATE as usize;
+        let measurement = measure_calibration(&recorded, SAMPLE_RATE, noise, noise, &config);
+        assert_eq!(measurement.echo_delay_ms, None);
+
+        let profile = build_profile(&measurement, "Built-in Microphone", "Headphones", &config);
+        assert!(!profile.speaker_bleed);
+        assert_eq!(profile.recommended_mic_gain, 1.0);
+        assert!(profile.recommended_silence_rms >= 0.001);
+
+        // Saved profiles are read back as written
+        let saved: CalibrationProfile = serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+        assert_eq!(saved.output_device, "Headphones");
+        assert_eq!(saved.echo_delay_ms, None);
+    }
+}
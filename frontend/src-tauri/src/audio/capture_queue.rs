@@ -0,0 +1,233 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Notify;
+
+// Overflow is logged for the first drop and then every this many
+const DROP_LOG_INTERVAL: u64 = 100;
+
+/// What the capture queue does when the collection task falls behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureOverflowPolicy {
+    /// Drop the oldest queued batch to make room.
+    DropOldest,
+    /// Keep the queued batches and stop taking new ones from the stream until
+    /// there is room. The stream can't be paused, so its broadcast buffer fills
+    /// up meanwhile and then overwrites its oldest unread batches: audio is
+    /// still dropped, only at the stream and newer than what the queue holds.
+    #[serde(alias = "backpressure")]
+    DropAtStream,
+    /// Keep growing past the limit, trading memory for never dropping audio.
+    Expand,
+}
+
+/// Settings for the queue between each capture stream and the collection task.
+/// Takes effect from the next recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureQueueConfig {
+    /// Capture batches held, typically 10 ms of audio each.
+    pub max_batches: usize,
+    pub overflow_policy: CaptureOverflowPolicy,
+}
+
+impl Default for CaptureQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_batches: 1000,
+            overflow_policy: CaptureOverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// Bounded queue of captured batches with an explicit overflow policy.
+pub struct CaptureQueue {
+    name: String,
+    config: CaptureQueueConfig,
+    batches: Mutex<VecDeque<Vec<f32>>>,
+    space: Notify,
+    dropped: AtomicU64,
+}
+
+impl CaptureQueue {
+    pub fn new(name: &str, config: CaptureQueueConfig) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            config,
+            batches: Mutex::new(VecDeque::new()),
+            space: Notify::new(),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    pub async fn push(&self, batch: Vec<f32>) {
+        let max_batches = self.config.max_batches.max(1);
+        let mut batch = Some(batch);
+        while let Some(pending) = batch.take() {
+            // Registered before checking, so a drain in between still wakes us
+            let space = self.space.notified();
+            {
+                let Ok(mut batches) = self.batches.lock() else {
+                    return;
+                };
+                if batches.len() < max_batches || self.config.overflow_policy == CaptureOverflowPolicy::Expand {
+                    batches.push_back(pending);
+                    return;
+                }
+                if self.config.overflow_policy == CaptureOverflowPolicy::DropOldest {
+                    batches.pop_front();
+                    batches.push_back(pending);
+                    drop(batches);
+                    self.record_drop(1);
+                    return;
+                }
+                batch = Some(pending);
+            }
+            space.await;
+        }
+    }
+
+    /// Takes every queued sample, oldest first.
+    pub fn drain(&self) -> Vec<f32> {
+        let samples = match self.batches.lock() {
+            Ok(mut batches) => batches.drain(..).flatten().collect(),
+            Err(_) => Vec::new(),
+        };
+        self.space.notify_waiters();
+        samples
+    }
+
+    /// Discards the queued audio, e.g. when its stream is replaced.
+    pub fn clear(&self) {
+        if let Ok(mut batches) = self.batches.lock() {
+            batches.clear();
+        }
+        self.space.notify_waiters();
+    }
+
+    /// Batches dropped by the queue or by the stream ahead of it.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn record_drop(&self, batches: u64) {
+        let previous = self.dropped.fetch_add(batches, Ordering::Relaxed);
+        if previous == 0 || previous / DROP_LOG_INTERVAL != (previous + batches) / DROP_LOG_INTERVAL {
+            warn!("{} capture queue has dropped {} batches", self.name, previous + batches);
+        }
+    }
+}
+
+/// Moves batches from a capture stream into a queue until the stream closes
+/// or this is dropped.
+pub struct CaptureForwarder {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl CaptureForwarder {
+    pub fn spawn(mut receiver: broadcast::Receiver<Vec<f32>>, queue: Arc<CaptureQueue>) -> Self {
+        let task = tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(batch) => queue.push(batch).await,
+                    Err(RecvError::Lagged(skipped)) => queue.record_drop(skipped),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for CaptureForwarder {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn queue(max_batches: usize, overflow_policy: CaptureOverflowPolicy) -> Arc<CaptureQueue> {
+        CaptureQueue::new(
+            "test",
+            CaptureQueueConfig {
+                max_batches,
+                overflow_policy,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_newest_batches() {
+        let queue = queue(2, CaptureOverflowPolicy::DropOldest);
+        for i in 0..4 {
+            queue.push(vec![i as f32]).await;
+        }
+        assert_eq!(queue.drain(), vec![2.0, 3.0]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[tokio::test]
+    async fn expand_never_drops() {
+        let queue = queue(1, CaptureOverflowPolicy::Expand);
+        for i in 0..3 {
+            queue.push(vec![i as f32]).await;
+        }
+        assert_eq!(queue.drain(), vec![0.0, 1.0, 2.0]);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_at_stream_waits_for_a_drain() {
+        let queue = queue(1, CaptureOverflowPolicy::DropAtStream);
+        queue.push(vec![1.0]).await;
+
+        let pushing = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push(vec![2.0]).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!pushing.is_finished());
+
+        assert_eq!(queue.drain(), vec![1.0]);
+        tokio::time::timeout(Duration::from_secs(1), pushing)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(queue.drain(), vec![2.0]);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn drop_at_stream_loses_the_batches_the_stream_overwrites() {
+        let queue = queue(1, CaptureOverflowPolicy::DropAtStream);
+        let (sender, receiver) = broadcast::channel(2);
+        let _forwarder = CaptureForwarder::spawn(receiver, queue.clone());
+        let settle = || tokio::time::sleep(Duration::from_millis(20));
+
+        sender.send(vec![0.0]).unwrap();
+        settle().await;
+        // Held by the forwarder while the queue is full
+        sender.send(vec![1.0]).unwrap();
+        settle().await;
+        // Only the last two fit in the stream's buffer
+        for i in 2..5 {
+            sender.send(vec![i as f32]).unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            settle().await;
+            received.extend(queue.drain());
+        }
+        assert_eq!(received, vec![0.0, 1.0, 3.0, 4.0]);
+        assert_eq!(queue.dropped(), 1);
+    }
+}
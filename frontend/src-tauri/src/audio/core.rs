@@ -1,25 +1,19 @@
-use super::audio_processing::audio_to_mono; 
+use super::audio_processing::{audio_to_mono_selected, validate_channel_selection};
+use super::priority::{apply_to_current_thread, CaptureThreadConfig};
+use super::test_source::AudioTestGenerator;
+use crate::metrics::METRICS;
 use anyhow::{anyhow, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::StreamError;
 use lazy_static::lazy_static;
 use log::{ error, info, warn, debug};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::mpsc;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{fmt, thread};
 use tokio::sync::{broadcast, oneshot};
-lazy_static! {
-    pub static ref LAST_AUDIO_CAPTURE: AtomicU64 = AtomicU64::new(
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs()
-    );
-}
-
 #[derive(Clone, Debug, PartialEq)]
 pub enum AudioTranscriptionEngine {
     Deepgram,
@@ -70,6 +64,18 @@ impl AudioDevice {
         AudioDevice { name, device_type }
     }
 
+    /// Whether this is a virtual loopback device such as BlackHole or VB-Cable.
+    pub fn is_virtual(&self) -> bool {
+        is_virtual_audio_device(&self.name)
+    }
+
+    // Virtual devices carry system audio but are recorded from like microphones,
+    // so a virtual device picked as system audio is opened as an input first
+    fn virtual_capture_device(&self) -> Option<AudioDevice> {
+        (self.device_type == DeviceType::Output && self.is_virtual())
+            .then(|| AudioDevice::new(self.name.clone(), DeviceType::Input))
+    }
+
     pub fn from_name(name: &str) -> Result<Self> {
         if name.trim().is_empty() {
             return Err(anyhow!("Device name cannot be empty"));
@@ -93,6 +99,209 @@ impl AudioDevice {
 
         Ok(AudioDevice::new(name, device_type))
     }
+
+    /// Report the sample rates, channel counts and sample formats this device supports
+    pub fn capabilities(&self) -> Result<DeviceCapabilities> {
+        let device = find_cpal_device(self)?;
+
+        let is_default = match self.device_type {
+            DeviceType::Input => default_input_device().map(|d| d.name == self.name),
+            DeviceType::Output => default_output_device().map(|d| d.name == self.name),
+        }
+        .unwrap_or(false);
+
+        let mut capabilities = DeviceCapabilities {
+            sample_rates: Vec::new(),
+            channel_counts: Vec::new(),
+            sample_formats: Vec::new(),
+            is_default,
+            is_virtual: self.is_virtual(),
+        };
+
+        match supported_config_ranges(&device, &self.device_type) {
+            Ok(ranges) if !ranges.is_empty() => {
+                for range in &ranges {
+                    capabilities.add_range(range);
+                }
+            }
+            Ok(_) | Err(_) => {
+                // Some drivers refuse to enumerate configs; fall back to whatever the default is
+                warn!("Could not enumerate supported configs for {}, using default config", self);
+                let default_config = match self.device_type {
+                    DeviceType::Input => device.default_input_config(),
+                    DeviceType::Output => device
+                        .default_output_config()
+                        .or_else(|_| device.default_input_config()),
+                };
+                match default_config {
+                    Ok(config) => {
+                        capabilities.sample_rates.push(config.sample_rate().0);
+                        capabilities.channel_counts.push(config.channels());
+                        capabilities
+                            .sample_formats
+                            .push(config.sample_format().to_string());
+                    }
+                    Err(e) => warn!("No default config available for {}: {}", self, e),
+                }
+            }
+        }
+
+        capabilities.dedup();
+        Ok(capabilities)
+    }
+}
+
+// Standard rates reported when a device advertises a continuous range
+const COMMON_SAMPLE_RATES: [u32; 9] = [
+    8000, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 192000,
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DeviceCapabilities {
+    pub sample_rates: Vec<u32>,
+    pub channel_counts: Vec<u16>,
+    pub sample_formats: Vec<String>,
+    pub is_default: bool,
+    /// Virtual loopback device, usually carrying another app's audio.
+    pub is_virtual: bool,
+}
+
+// Name fragments of common virtual audio drivers, lowercase
+const VIRTUAL_DEVICE_PATTERNS: [&str; 7] = [
+    "blackhole",
+    "soundflower",
+    "loopback audio",
+    "vb-audio",
+    "vb-cable",
+    "cable output",
+    "voicemeeter",
+];
+
+/// Whether `name` belongs to a known virtual audio driver. These route audio
+/// between apps and are often used to capture meeting audio.
+pub fn is_virtual_audio_device(name: &str) -> bool {
+    let name = name.to_lowercase();
+    VIRTUAL_DEVICE_PATTERNS.iter().any(|pattern| name.contains(pattern))
+}
+
+impl DeviceCapabilities {
+    fn add_range(&mut self, range: &cpal::SupportedStreamConfigRange) {
+        let min_rate = range.min_sample_rate().0;
+        let max_rate = range.max_sample_rate().0;
+        self.sample_rates.push(min_rate);
+        self.sample_rates.push(max_rate);
+        self.sample_rates.extend(
+            COMMON_SAMPLE_RATES
+                .iter()
+                .copied()
+                .filter(|rate| *rate >= min_rate && *rate <= max_rate),
+        );
+        self.channel_counts.push(range.channels());
+        self.sample_formats.push(range.sample_format().to_string());
+    }
+
+    fn dedup(&mut self) {
+        self.sample_rates.sort_unstable();
+        self.sample_rates.dedup();
+        self.channel_counts.sort_unstable();
+        self.channel_counts.dedup();
+        self.sample_formats.sort();
+        self.sample_formats.dedup();
+    }
+}
+
+// Virtual devices are recorded from as inputs but carry system audio,
+// so they are offered as system audio sources as well
+fn offer_virtual_inputs_as_system_audio(devices: &mut Vec<AudioDevice>) {
+    let virtual_inputs: Vec<String> = devices
+        .iter()
+        .filter(|d| d.device_type == DeviceType::Input && d.is_virtual())
+        .map(|d| d.name.clone())
+        .collect();
+    for name in virtual_inputs {
+        if !devices.iter().any(|d| d.device_type == DeviceType::Output && d.name == name) {
+            devices.push(AudioDevice::new(name, DeviceType::Output));
+        }
+    }
+}
+
+fn supported_config_ranges(
+    device: &cpal::Device,
+    device_type: &DeviceType,
+) -> Result<Vec<cpal::SupportedStreamConfigRange>> {
+    match device_type {
+        DeviceType::Input => Ok(device.supported_input_configs()?.collect()),
+        DeviceType::Output => {
+            // Loopback/monitor sources are opened as inputs on macOS and Linux
+            match device.supported_output_configs() {
+                Ok(configs) => {
+                    let configs: Vec<_> = configs.collect();
+                    if configs.is_empty() {
+                        Ok(device.supported_input_configs()?.collect())
+                    } else {
+                        Ok(configs)
+                    }
+                }
+                Err(_) => Ok(device.supported_input_configs()?.collect()),
+            }
+        }
+    }
+}
+
+// Look up the cpal device backing an AudioDevice using the same hosts as get_device_and_config
+fn find_cpal_device(audio_device: &AudioDevice) -> Result<cpal::Device> {
+    if let Some(capture_device) = audio_device.virtual_capture_device() {
+        if let Ok(device) = find_cpal_device(&capture_device) {
+            return Ok(device);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    let host = cpal::host_from_id(cpal::HostId::Wasapi).unwrap_or_else(|_| cpal::default_host());
+
+    #[cfg(not(target_os = "windows"))]
+    let host = cpal::default_host();
+
+    match audio_device.device_type {
+        DeviceType::Input => {
+            for device in host.input_devices()? {
+                if device.name().map(|name| name == audio_device.name).unwrap_or(false) {
+                    return Ok(device);
+                }
+            }
+        }
+        DeviceType::Output => {
+            #[cfg(target_os = "macos")]
+            {
+                if let Ok(sck_host) = cpal::host_from_id(cpal::HostId::ScreenCaptureKit) {
+                    for device in sck_host.input_devices()? {
+                        if device.name().map(|name| name == audio_device.name).unwrap_or(false) {
+                            return Ok(device);
+                        }
+                    }
+                }
+            }
+
+            #[cfg(target_os = "linux")]
+            {
+                if let Ok(pulse_host) = cpal::host_from_id(cpal::HostId::Pulse) {
+                    for device in pulse_host.input_devices()? {
+                        if device.name().map(|name| name == audio_device.name).unwrap_or(false) {
+                            return Ok(device);
+                        }
+                    }
+                }
+            }
+
+            for device in host.output_devices()? {
+                if device.name().map(|name| name == audio_device.name).unwrap_or(false) {
+                    return Ok(device);
+                }
+            }
+        }
+    }
+
+    Err(anyhow!("Device not found: {}", audio_device.name))
 }
 
 impl fmt::Display for AudioDevice {
@@ -232,6 +441,67 @@ fn configure_linux_audio(host: &cpal::Host) -> Result<Vec<AudioDevice>> {
     Ok(devices)
 }
 
+// The last enumerated device list and when it was taken
+struct DeviceListCache {
+    listed: Mutex<Option<(Instant, Vec<AudioDevice>)>>,
+}
+
+impl DeviceListCache {
+    fn new() -> Self {
+        Self {
+            listed: Mutex::new(None),
+        }
+    }
+
+    async fn get_or_list<F, Fut>(&self, max_age: Duration, list: F) -> Result<Vec<AudioDevice>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Vec<AudioDevice>>>,
+    {
+        if let Ok(cache) = self.listed.lock() {
+            if let Some((listed_at, devices)) = cache.as_ref() {
+                if listed_at.elapsed() < max_age {
+                    return Ok(devices.clone());
+                }
+            }
+        }
+
+        let devices = list().await?;
+        if let Ok(mut cache) = self.listed.lock() {
+            *cache = Some((Instant::now(), devices.clone()));
+        }
+        Ok(devices)
+    }
+
+    fn invalidate(&self) {
+        if let Ok(mut cache) = self.listed.lock() {
+            if cache.take().is_some() {
+                debug!("Audio device list cache invalidated");
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref DEVICE_LIST_CACHE: DeviceListCache = DeviceListCache::new();
+}
+
+/// Device lists younger than this are served from the cache
+pub const DEVICE_LIST_MAX_AGE: Duration = Duration::from_secs(2);
+
+/// The device list from the last enumeration if it is younger than `max_age`,
+/// otherwise a fresh one. Enumeration is slow on some hosts (WASAPI in
+/// particular), so UIs polling for device changes should use this.
+pub async fn list_audio_devices_cached(max_age: Duration) -> Result<Vec<AudioDevice>> {
+    DEVICE_LIST_CACHE.get_or_list(max_age, list_audio_devices).await
+}
+
+/// Forgets the cached device list, so the next cached lookup enumerates again.
+/// Called when a device disappears or can't be opened.
+pub fn invalidate_device_cache() {
+    DEVICE_LIST_CACHE.invalidate();
+}
+
 pub async fn list_audio_devices() -> Result<Vec<AudioDevice>> {
     let host = cpal::default_host();
     let mut devices = Vec::new();
@@ -280,6 +550,8 @@ pub async fn list_audio_devices() -> Result<Vec<AudioDevice>> {
         }
     }
 
+    offer_virtual_inputs_as_system_audio(&mut devices);
+
     // Add any additional devices from the default host
     if let Ok(other_devices) = host.devices() {
         for device in other_devices {
@@ -348,6 +620,125 @@ pub fn default_output_device() -> Result<AudioDevice> {
     }
 }
 
+/// Ordered device preferences, tried in turn when recording starts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DeviceFallbackConfig {
+    pub input: Vec<String>,
+    pub output: Vec<String>,
+    /// Channels of the input/output device mixed into mono, e.g. [0, 1] on a
+    /// multichannel interface. Empty averages all channels.
+    pub input_channels: Vec<usize>,
+    pub output_channels: Vec<usize>,
+    pub missing_device: MissingDevicePolicy,
+}
+
+/// What a recording does when the machine has no device at all for a source,
+/// e.g. a headless server without a microphone.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingDevicePolicy {
+    /// Refuse to start the recording.
+    #[default]
+    Fail,
+    /// Record silence for the missing source and carry on with the other one.
+    RecordSilence,
+}
+
+/// The host reports no devices of the requested type at all, as opposed to
+/// the configured devices failing to open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoAudioDevices(pub DeviceType);
+
+impl fmt::Display for NoAudioDevices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No {} audio devices available", device_type_label(&self.0))
+    }
+}
+
+impl std::error::Error for NoAudioDevices {}
+
+fn ensure_device_available(devices: &[AudioDevice], device_type: &DeviceType) -> Result<(), NoAudioDevices> {
+    if devices.iter().any(|device| &device.device_type == device_type) {
+        Ok(())
+    } else {
+        Err(NoAudioDevices(device_type.clone()))
+    }
+}
+
+/// Picks the first device from `preferences` that can be opened. An entry of
+/// "default" (or an empty list) stands for the system default device.
+pub async fn select_device_with_fallback(
+    preferences: &[String],
+    device_type: DeviceType,
+) -> Result<AudioDevice> {
+    // Report an empty device list plainly instead of a failure per candidate
+    if let Ok(devices) = list_audio_devices_cached(DEVICE_LIST_MAX_AGE).await {
+        ensure_device_available(&devices, &device_type)?;
+    }
+
+    first_usable_device(preferences, &device_type, |candidate| {
+        let device = if candidate.eq_ignore_ascii_case("default") {
+            match device_type {
+                DeviceType::Input => default_input_device(),
+                DeviceType::Output => default_output_device(),
+            }
+        } else {
+            Ok(AudioDevice::new(candidate.to_string(), device_type.clone()))
+        };
+        async move {
+            let device = device?;
+            get_device_and_config(&device).await?;
+            Ok(device)
+        }
+    })
+    .await
+}
+
+// Tries `open` on each preference in turn and returns the first device it opens
+async fn first_usable_device<F, Fut>(
+    preferences: &[String],
+    device_type: &DeviceType,
+    mut open: F,
+) -> Result<AudioDevice>
+where
+    F: FnMut(&str) -> Fut,
+    Fut: std::future::Future<Output = Result<AudioDevice>>,
+{
+    let default_only = [String::from("default")];
+    let candidates = if preferences.is_empty() { &default_only[..] } else { preferences };
+    let mut failures = Vec::new();
+
+    for candidate in candidates {
+        match open(candidate).await {
+            Ok(device) => {
+                if !failures.is_empty() {
+                    warn!("Falling back to {} device {} after {} failed", device_type_label(device_type), device.name, failures.len());
+                }
+                return Ok(device);
+            }
+            Err(e) => {
+                warn!("Could not open {} device {}: {}", device_type_label(device_type), candidate, e);
+                invalidate_device_cache();
+                failures.push(format!("{} ({})", candidate, e));
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "No usable {} device. Tried: {}",
+        device_type_label(device_type),
+        failures.join("; ")
+    ))
+}
+
+fn device_type_label(device_type: &DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Input => "input",
+        DeviceType::Output => "output",
+    }
+}
+
 pub fn trigger_audio_permission() -> Result<()> {
     let host = cpal::default_host();
     let device = host
@@ -386,16 +777,295 @@ pub struct AudioStream {
     stream_control: mpsc::Sender<StreamControl>,
     stream_thread: Option<Arc<tokio::sync::Mutex<Option<thread::JoinHandle<()>>>>>,
     is_disconnected: Arc<AtomicBool>,
+    channel_fallback: Arc<AtomicBool>,
+    permission_revoked: Arc<AtomicBool>,
+    /// Microseconds between capture and the callback, `UNKNOWN_LATENCY` until the host reports it.
+    input_latency_us: Arc<AtomicU64>,
+}
+
+const UNKNOWN_LATENCY: u64 = u64::MAX;
+
+// How long the host held the latest batch before handing it over, when it says
+fn record_input_latency(latency_us: &AtomicU64, info: &cpal::InputCallbackInfo) {
+    let timestamp = info.timestamp();
+    store_input_latency(latency_us, timestamp.callback.duration_since(&timestamp.capture));
+}
+
+// A batch without a usable timestamp keeps the last latency reported
+fn store_input_latency(latency_us: &AtomicU64, latency: Option<Duration>) {
+    if let Some(latency) = latency {
+        latency_us.store((latency.as_micros() as u64).min(UNKNOWN_LATENCY - 1), Ordering::Relaxed);
+    }
+}
+
+fn load_input_latency(latency_us: &AtomicU64) -> Option<Duration> {
+    match latency_us.load(Ordering::Relaxed) {
+        UNKNOWN_LATENCY => None,
+        micros => Some(Duration::from_micros(micros)),
+    }
+}
+
+// Stream errors meaning the OS refused access rather than the device failing.
+// macOS reports a revoked Screen Recording grant through TCC.
+const PERMISSION_ERROR_PATTERNS: &[&str] = &[
+    "permission denied",
+    "access denied",
+    "not authorized",
+    "tcc",
+];
+
+/// Whether a stream error means capture permission was denied or revoked.
+pub fn is_permission_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    PERMISSION_ERROR_PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+/// Settings for carrying on when the OS revokes capture permission mid-recording,
+/// e.g. Screen Recording on macOS for system audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionRecoveryConfig {
+    /// Off by default: losing permission stops the recording.
+    pub enabled: bool,
+    /// How often reopening the system audio stream is attempted until it works.
+    pub retry_secs: u64,
+}
+
+impl Default for PermissionRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retry_secs: 10,
+        }
+    }
 }
 
+/// Name of the device reported for a generated stream.
+pub const TEST_SOURCE_DEVICE_NAME: &str = "Test signal";
+
+// How often a generated stream delivers a frame, like a typical capture callback
+const GENERATED_FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
 enum StreamControl {
     Stop(oneshot::Sender<()>),
 }
 
+/// How long a recoverable device error has to persist before the stream is
+/// treated as disconnected. Either threshold being reached is enough, as is no
+/// audio arriving for the grace period after an error.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisconnectGracePolicy {
+    pub grace_period_ms: u64,
+    pub max_consecutive_errors: u32,
+}
+
+impl Default for DisconnectGracePolicy {
+    fn default() -> Self {
+        Self {
+            grace_period_ms: 1500,
+            max_consecutive_errors: 5,
+        }
+    }
+}
+
+// How often the stream thread checks whether an erroring device went quiet
+const DISCONNECT_WATCHDOG_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Per-stream capture options.
+#[derive(Clone, Debug, Default)]
+pub struct StreamOptions {
+    pub grace_policy: DisconnectGracePolicy,
+    /// Device channels mixed into mono. Empty means all channels.
+    pub channel_selection: Vec<usize>,
+    pub capture_thread: CaptureThreadConfig,
+    /// Close only this stream when capture permission is revoked, so it can be
+    /// reopened, instead of stopping the whole recording.
+    pub recover_from_permission_loss: bool,
+}
+
+/// Tracks consecutive stream errors. Any successful audio callback resets it,
+/// so a single transient error never tears down the stream.
+struct DisconnectDebouncer {
+    policy: DisconnectGracePolicy,
+    started_at: Instant,
+    consecutive_errors: AtomicU32,
+    first_error_ms: AtomicU64,
+}
+
+impl DisconnectDebouncer {
+    fn new(policy: DisconnectGracePolicy) -> Self {
+        Self {
+            policy,
+            started_at: Instant::now(),
+            consecutive_errors: AtomicU32::new(0),
+            first_error_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an error and returns true once the error condition has
+    /// persisted long enough to be treated as a disconnection.
+    fn record_error(&self) -> bool {
+        let now_ms = self.started_at.elapsed().as_millis() as u64;
+        let count = self.consecutive_errors.fetch_add(1, Ordering::AcqRel) + 1;
+        if count == 1 {
+            self.first_error_ms.store(now_ms, Ordering::Release);
+        }
+        let persisted_ms = now_ms.saturating_sub(self.first_error_ms.load(Ordering::Acquire));
+
+        count >= self.policy.max_consecutive_errors
+            || (count > 1 && persisted_ms >= self.policy.grace_period_ms)
+    }
+
+    /// Whether the grace period has passed since an error without any audio
+    /// callback in between. A device that stopped delivering audio may not
+    /// report a second error, so this is checked on a timer.
+    fn grace_expired(&self) -> bool {
+        if self.error_count() == 0 {
+            return false;
+        }
+        let now_ms = self.started_at.elapsed().as_millis() as u64;
+        now_ms.saturating_sub(self.first_error_ms.load(Ordering::Acquire)) >= self.policy.grace_period_ms
+    }
+
+    fn reset(&self) {
+        if self.consecutive_errors.load(Ordering::Relaxed) != 0 {
+            self.consecutive_errors.store(0, Ordering::Release);
+        }
+    }
+
+    fn error_count(&self) -> u32 {
+        self.consecutive_errors.load(Ordering::Acquire)
+    }
+}
+
+// Flags the stream as disconnected and stops the recording. Returns false when it
+// already was, so it is only acted on once.
+fn mark_disconnected(is_disconnected: &AtomicBool, is_running: &std::sync::Weak<AtomicBool>) -> bool {
+    if is_disconnected.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+    invalidate_device_cache();
+    if let Some(arc) = is_running.upgrade() {
+        arc.store(false, Ordering::Relaxed);
+    }
+    true
+}
+
+/// Smallest buffer passed on from the capture callback, as a fraction of a second.
+/// Shorter callbacks happen around device transitions.
+const MIN_CAPTURE_FRAME_SECS: f32 = 0.01;
+
+/// Holds back the very short buffers cpal delivers around device transitions and
+/// passes them on once a full frame has accumulated. Empty buffers are dropped.
+struct CaptureFrameBuffer {
+    min_samples: usize,
+    pending: Vec<f32>,
+}
+
+impl CaptureFrameBuffer {
+    fn new(sample_rate: u32) -> Self {
+        let min_samples = ((sample_rate as f32 * MIN_CAPTURE_FRAME_SECS) as usize).max(1);
+        Self {
+            min_samples,
+            pending: Vec::with_capacity(min_samples),
+        }
+    }
+
+    /// Returns the samples to send on, if a full frame is available.
+    fn push(&mut self, samples: Vec<f32>) -> Option<Vec<f32>> {
+        if samples.is_empty() {
+            METRICS.record_empty_capture_buffer();
+            return None;
+        }
+        if self.pending.is_empty() && samples.len() >= self.min_samples {
+            return Some(samples);
+        }
+        if samples.len() < self.min_samples {
+            METRICS.record_short_capture_buffer();
+        }
+        self.pending.extend(samples);
+        if self.pending.len() < self.min_samples {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.pending, Vec::with_capacity(self.min_samples)))
+    }
+}
+
+/// Callbacks in a row whose length doesn't fit the declared channel count before
+/// the stream is treated as mono. One odd buffer isn't enough, devices sometimes
+/// deliver a partial frame when starting.
+const MAX_CHANNEL_MISMATCHES: u32 = 5;
+
+/// Mixes callback data down to mono. Some drivers misreport the channel count,
+/// which would garble the audio when de-interleaving, so if the buffers keep
+/// failing to divide into whole frames the stream is read as mono instead.
+struct ChannelLayout {
+    channels: u16,
+    selection: Vec<usize>,
+    mismatches: u32,
+    fallen_back: Arc<AtomicBool>,
+}
+
+impl ChannelLayout {
+    fn new(channels: u16, selection: Vec<usize>, fallen_back: Arc<AtomicBool>) -> Self {
+        Self {
+            channels,
+            selection,
+            mismatches: 0,
+            fallen_back,
+        }
+    }
+
+    fn to_mono(&mut self, data: &[f32], device_name: &str) -> Vec<f32> {
+        if self.channels > 1 {
+            if data.len() % self.channels as usize != 0 {
+                self.mismatches += 1;
+                if self.mismatches >= MAX_CHANNEL_MISMATCHES {
+                    warn!(
+                        "audio device {} delivered {} buffers in a row that don't fit {} channels, treating it as mono",
+                        device_name, self.mismatches, self.channels
+                    );
+                    self.channels = 1;
+                    self.selection.clear();
+                    self.fallen_back.store(true, Ordering::Release);
+                }
+            } else {
+                self.mismatches = 0;
+            }
+        }
+        audio_to_mono_selected(data, self.channels, &self.selection)
+    }
+}
+
+/// Checks a stream's channel count against the counts the device lists. A count
+/// the device doesn't have would garble every buffer, so the device's first
+/// listed count is used instead. Devices that list nothing are trusted as is.
+fn device_channel_count(configured: u16, supported: &[u16], device_name: &str) -> u16 {
+    match supported.first() {
+        Some(&device_channels) if !supported.contains(&configured) => {
+            warn!(
+                "audio device {} doesn't support {} channels, reading it as {} channels",
+                device_name, configured, device_channels
+            );
+            device_channels
+        }
+        _ => configured,
+    }
+}
+
 impl AudioStream {
     pub async fn from_device(
         device: Arc<AudioDevice>,
         is_running: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        Self::from_device_with_options(device, is_running, StreamOptions::default()).await
+    }
+
+    pub async fn from_device_with_options(
+        device: Arc<AudioDevice>,
+        is_running: Arc<AtomicBool>,
+        options: StreamOptions,
     ) -> Result<Self> {
         info!("Initializing audio stream for device: {}", device.to_string());
         let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
@@ -459,7 +1129,23 @@ impl AudioStream {
         let (stream_control_tx, stream_control_rx) = mpsc::channel();
 
         let is_disconnected_clone = is_disconnected.clone();
+        let is_disconnected_for_watchdog = is_disconnected.clone();
         let stream_control_tx_clone = stream_control_tx.clone();
+        let debouncer = Arc::new(DisconnectDebouncer::new(options.grace_policy));
+        let supported_channels: Vec<u16> = supported_config_ranges(&cpal_audio_device, &device.device_type)
+            .map(|ranges| ranges.iter().map(|range| range.channels()).collect())
+            .unwrap_or_default();
+        let layout_channels = device_channel_count(channels, &supported_channels, &device.name);
+        let channel_selection = validate_channel_selection(&options.channel_selection, layout_channels);
+        let capture_thread = options.capture_thread;
+        let recover_from_permission_loss = options.recover_from_permission_loss;
+        let permission_revoked = Arc::new(AtomicBool::new(false));
+        let permission_revoked_clone = permission_revoked.clone();
+        let input_latency_us = Arc::new(AtomicU64::new(UNKNOWN_LATENCY));
+        let input_latency_for_data = input_latency_us.clone();
+        let mut frame_buffer = CaptureFrameBuffer::new(config.sample_rate().0);
+        let channel_fallback = Arc::new(AtomicBool::new(false));
+        let mut channel_layout = ChannelLayout::new(layout_channels, channel_selection, channel_fallback.clone());
         let stream_thread = Arc::new(tokio::sync::Mutex::new(Some(thread::spawn(move || {
             let device = device_clone;
             let device_name = device.to_string();
@@ -468,6 +1154,12 @@ impl AudioStream {
             info!("Starting audio stream thread for device: {}", device_name);
             let is_running_weak_for_error = is_running_weak_2.clone();
             let is_running_weak_for_data = is_running_weak_2.clone();
+            let is_running_weak_for_watchdog = is_running_weak_2.clone();
+            let debouncer_for_error = debouncer.clone();
+            let debouncer_for_data = debouncer.clone();
+            let device_name_for_data = device_name.clone();
+            // Callbacks run on a thread owned by the audio backend, so it can only be configured from inside one
+            let mut capture_thread_configured = false;
             let error_callback = move |err: StreamError| {
                 if err
                     .to_string()
@@ -482,19 +1174,40 @@ impl AudioStream {
                         .unwrap();
 
                     is_disconnected_clone.store(true, Ordering::Relaxed);
-                } else if err.to_string().to_lowercase().contains("permission denied") || 
-                         err.to_string().to_lowercase().contains("access denied") {
-                    error!("Permission denied for audio device {}. Please check microphone permissions.", device_name_clone);
-                    if let Some(arc) = is_running_weak_for_error.upgrade() {
+                    invalidate_device_cache();
+                } else if is_permission_error(&err.to_string()) {
+                    error!("Permission denied for audio device {}: {}", device_name_clone, err);
+                    if permission_revoked_clone.swap(true, Ordering::Relaxed) {
+                        return;
+                    }
+                    if recover_from_permission_loss {
+                        // Only this stream goes away, the recording waits for it to be reopened
+                        is_disconnected_clone.store(true, Ordering::Relaxed);
+                        stream_control_tx_clone
+                            .send(StreamControl::Stop(oneshot::channel().0))
+                            .ok();
+                    } else if let Some(arc) = is_running_weak_for_error.upgrade() {
                         arc.store(false, Ordering::Relaxed);
                     }
                 } else {
                     error!("an error occurred on the audio stream: {}", err);
                     if err.to_string().contains("device is no longer valid") {
-                        warn!("audio device disconnected. stopping recording.");
-                        if let Some(arc) = is_running_weak_for_error.upgrade() {
-                            arc.store(false, Ordering::Relaxed);
+                        // Some devices report this transiently, only give up once it persists
+                        if !debouncer_for_error.record_error() {
+                            warn!(
+                                "audio device {} reported an error ({} in a row), waiting before treating it as disconnected",
+                                device_name_clone,
+                                debouncer_for_error.error_count()
+                            );
+                            return;
                         }
+                        if !mark_disconnected(&is_disconnected_clone, &is_running_weak_for_error) {
+                            return;
+                        }
+                        warn!("audio device disconnected. stopping recording.");
+                        stream_control_tx_clone
+                            .send(StreamControl::Stop(oneshot::channel().0))
+                            .ok();
                     }
                 }
             };
@@ -503,7 +1216,7 @@ impl AudioStream {
                 cpal::SampleFormat::F32 => {
                     match cpal_audio_device.build_input_stream(
                         &config.into(),
-                        move |data: &[f32], _: &_| {
+                        move |data: &[f32], info: &cpal::InputCallbackInfo| {
                             log::debug!("Audio callback triggered (F32)");
                             if let Some(arc) = is_running_weak_for_data.upgrade() {
                                 if !arc.load(Ordering::Relaxed) {
@@ -514,9 +1227,18 @@ impl AudioStream {
                                 log::debug!("Audio callback: is_running Arc was dropped, returning early (F32)");
                                 return;
                             }
-                            let mono = audio_to_mono(data, channels);
-                            debug!("Received audio chunk: {} samples", mono.len());
-                            if let Err(e) = tx.send(mono) {
+                            debouncer_for_data.reset();
+                            record_input_latency(&input_latency_for_data, info);
+                            if !capture_thread_configured {
+                                capture_thread_configured = true;
+                                apply_to_current_thread(&capture_thread, &device_name_for_data);
+                            }
+                            let mono = channel_layout.to_mono(data, &device_name_for_data);
+                            let Some(frame) = frame_buffer.push(mono) else {
+                                return;
+                            };
+                            debug!("Received audio chunk: {} samples", frame.len());
+                            if let Err(e) = tx.send(frame) {
                                 error!("Failed to send audio data: {}", e);
                             }
                         },
@@ -533,7 +1255,7 @@ impl AudioStream {
                 cpal::SampleFormat::I16 => {
                     match cpal_audio_device.build_input_stream(
                         &config.into(),
-                        move |data: &[i16], _: &_| {
+                        move |data: &[i16], info: &cpal::InputCallbackInfo| {
                             log::debug!("Audio callback triggered (I16)");
                             if let Some(arc) = is_running_weak_for_data.upgrade() {
                                 if !arc.load(Ordering::Relaxed) {
@@ -544,9 +1266,18 @@ impl AudioStream {
                                 log::debug!("Audio callback: is_running Arc was dropped, returning early (I16)");
                                 return;
                             }
-                            let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
-                            debug!("Received audio chunk: {} samples", mono.len());
-                            if let Err(e) = tx.send(mono) {
+                            debouncer_for_data.reset();
+                            record_input_latency(&input_latency_for_data, info);
+                            if !capture_thread_configured {
+                                capture_thread_configured = true;
+                                apply_to_current_thread(&capture_thread, &device_name_for_data);
+                            }
+                            let mono = channel_layout.to_mono(bytemuck::cast_slice(data), &device_name_for_data);
+                            let Some(frame) = frame_buffer.push(mono) else {
+                                return;
+                            };
+                            debug!("Received audio chunk: {} samples", frame.len());
+                            if let Err(e) = tx.send(frame) {
                                 error!("Failed to send audio data: {}", e);
                             }
                         },
@@ -563,7 +1294,7 @@ impl AudioStream {
                 cpal::SampleFormat::I32 => {
                     match cpal_audio_device.build_input_stream(
                         &config.into(),
-                        move |data: &[i32], _: &_| {
+                        move |data: &[i32], info: &cpal::InputCallbackInfo| {
                             log::debug!("Audio callback triggered (I32)");
                             if let Some(arc) = is_running_weak_for_data.upgrade() {
                                 if !arc.load(Ordering::Relaxed) {
@@ -574,9 +1305,18 @@ impl AudioStream {
                                 log::debug!("Audio callback: is_running Arc was dropped, returning early (I32)");
                                 return;
                             }
-                            let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
-                            debug!("Received audio chunk: {} samples", mono.len());
-                            if let Err(e) = tx.send(mono) {
+                            debouncer_for_data.reset();
+                            record_input_latency(&input_latency_for_data, info);
+                            if !capture_thread_configured {
+                                capture_thread_configured = true;
+                                apply_to_current_thread(&capture_thread, &device_name_for_data);
+                            }
+                            let mono = channel_layout.to_mono(bytemuck::cast_slice(data), &device_name_for_data);
+                            let Some(frame) = frame_buffer.push(mono) else {
+                                return;
+                            };
+                            debug!("Received audio chunk: {} samples", frame.len());
+                            if let Err(e) = tx.send(frame) {
                                 error!("Failed to send audio data: {}", e);
                             }
                         },
@@ -593,7 +1333,7 @@ impl AudioStream {
                 cpal::SampleFormat::I8 => {
                     match cpal_audio_device.build_input_stream(
                         &config.into(),
-                        move |data: &[i8], _: &_| {
+                        move |data: &[i8], info: &cpal::InputCallbackInfo| {
                             log::debug!("Audio callback triggered (I8)");
                             if let Some(arc) = is_running_weak_for_data.upgrade() {
                                 if !arc.load(Ordering::Relaxed) {
@@ -604,9 +1344,18 @@ impl AudioStream {
                                 log::debug!("Audio callback: is_running Arc was dropped, returning early (I8)");
                                 return;
                             }
-                            let mono = audio_to_mono(bytemuck::cast_slice(data), channels);
-                            debug!("Received audio chunk: {} samples", mono.len());
-                            if let Err(e) = tx.send(mono) {
+                            debouncer_for_data.reset();
+                            record_input_latency(&input_latency_for_data, info);
+                            if !capture_thread_configured {
+                                capture_thread_configured = true;
+                                apply_to_current_thread(&capture_thread, &device_name_for_data);
+                            }
+                            let mono = channel_layout.to_mono(bytemuck::cast_slice(data), &device_name_for_data);
+                            let Some(frame) = frame_buffer.push(mono) else {
+                                return;
+                            };
+                            debug!("Received audio chunk: {} samples", frame.len());
+                            if let Err(e) = tx.send(frame) {
                                 error!("Failed to send audio data: {}", e);
                             }
                         },
@@ -638,18 +1387,34 @@ impl AudioStream {
                 return;
             }
             info!("Audio stream started successfully for device: {}", device_name);
-            if let Ok(StreamControl::Stop(response)) = stream_control_rx.recv() {
-                info!("stopping audio stream...");
-                // First stop the stream
-                if let Err(e) = stream.pause() {
-                    error!("failed to pause stream: {}", e);
-                }
-                // Close the stream to release OS resources
-                drop(stream);
-                // Signal completion
+            let response = loop {
+                match stream_control_rx.recv_timeout(DISCONNECT_WATCHDOG_INTERVAL) {
+                    Ok(StreamControl::Stop(response)) => break Some(response),
+                    Err(mpsc::RecvTimeoutError::Timeout) if debouncer.grace_expired() => {
+                        if mark_disconnected(&is_disconnected_for_watchdog, &is_running_weak_for_watchdog) {
+                            warn!(
+                                "audio device {} delivered no audio for {} ms after an error, treating it as disconnected",
+                                device_name, debouncer.policy.grace_period_ms
+                            );
+                        }
+                        break None;
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            };
+            info!("stopping audio stream...");
+            // First stop the stream
+            if let Err(e) = stream.pause() {
+                error!("failed to pause stream: {}", e);
+            }
+            // Close the stream to release OS resources
+            drop(stream);
+            // Signal completion
+            if let Some(response) = response {
                 response.send(()).ok();
-                info!("audio stream stopped and cleaned up");
             }
+            info!("audio stream stopped and cleaned up");
         }))));
 
         Ok(AudioStream {
@@ -659,9 +1424,86 @@ impl AudioStream {
             stream_control: stream_control_tx,
             stream_thread: Some(stream_thread),
             is_disconnected,
+            channel_fallback,
+            permission_revoked,
+            input_latency_us,
         })
     }
 
+    /// A mono stream fed by `generator` in real time instead of a device. It is
+    /// consumed like any other stream, so the pipeline can run without hardware.
+    pub fn from_generator(mut generator: AudioTestGenerator, is_running: Arc<AtomicBool>) -> Self {
+        let sample_rate = generator.sample_rate();
+        info!("Initializing generated audio stream at {} Hz", sample_rate);
+        let device = Arc::new(AudioDevice::new(TEST_SOURCE_DEVICE_NAME.to_string(), DeviceType::Input));
+        let config = cpal::SupportedStreamConfig::new(
+            1,
+            cpal::SampleRate(sample_rate),
+            cpal::SupportedBufferSize::Unknown,
+            cpal::SampleFormat::F32,
+        );
+        let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
+        let tx_clone = tx.clone();
+        let (stream_control_tx, stream_control_rx) = mpsc::channel();
+        let is_running_weak = Arc::downgrade(&is_running);
+
+        let stream_thread = thread::spawn(move || {
+            let started = Instant::now();
+            let mut sent_samples: u64 = 0;
+            loop {
+                match stream_control_rx.recv_timeout(GENERATED_FRAME_INTERVAL) {
+                    Ok(StreamControl::Stop(response)) => {
+                        response.send(()).ok();
+                        break;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                }
+                let running = is_running_weak.upgrade().map(|arc| arc.load(Ordering::Relaxed)).unwrap_or(false);
+                if !running {
+                    continue;
+                }
+                // Send what real capture would have delivered by now, so the stream keeps pace with the clock
+                let due = (started.elapsed().as_secs_f64() * sample_rate as f64) as u64;
+                let frame = generator.next_frame(due.saturating_sub(sent_samples) as usize);
+                sent_samples = due;
+                if !frame.is_empty() && tx.send(frame).is_err() {
+                    debug!("No receivers for generated audio");
+                }
+            }
+            info!("Generated audio stream stopped");
+        });
+
+        AudioStream {
+            device,
+            device_config: config,
+            transmitter: Arc::new(tx_clone),
+            stream_control: stream_control_tx,
+            stream_thread: Some(Arc::new(tokio::sync::Mutex::new(Some(stream_thread)))),
+            is_disconnected: Arc::new(AtomicBool::new(false)),
+            channel_fallback: Arc::new(AtomicBool::new(false)),
+            permission_revoked: Arc::new(AtomicBool::new(false)),
+            input_latency_us: Arc::new(AtomicU64::new(UNKNOWN_LATENCY)),
+        }
+    }
+
+    /// Whether the device's buffers didn't match its reported channel count and
+    /// it is being read as mono.
+    pub fn channel_fallback(&self) -> bool {
+        self.channel_fallback.load(Ordering::Acquire)
+    }
+
+    /// Whether the OS refused or revoked capture permission for the device.
+    pub fn permission_revoked(&self) -> bool {
+        self.permission_revoked.load(Ordering::Acquire)
+    }
+
+    /// Time between the device capturing audio and the stream receiving it, as
+    /// last reported by the host. `None` if the host doesn't report it.
+    pub fn input_latency(&self) -> Option<Duration> {
+        load_input_latency(&self.input_latency_us)
+    }
+
     pub async fn subscribe(&self) -> broadcast::Receiver<Vec<f32>> {
         self.transmitter.subscribe()
     }
@@ -860,6 +1702,21 @@ fn get_windows_device(audio_device: &AudioDevice) -> Result<(cpal::Device, cpal:
 
 pub async fn get_device_and_config(
     audio_device: &AudioDevice,
+) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    if let Some(capture_device) = audio_device.virtual_capture_device() {
+        match find_device_and_config(&capture_device) {
+            Ok(found) => {
+                info!("Capturing virtual device {} as an input", audio_device.name);
+                return Ok(found);
+            }
+            Err(e) => debug!("Virtual device {} isn't available as an input: {}", audio_device.name, e),
+        }
+    }
+    find_device_and_config(audio_device)
+}
+
+fn find_device_and_config(
+    audio_device: &AudioDevice,
 ) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
     #[cfg(target_os = "windows")]
     {
@@ -922,3 +1779,326 @@ pub async fn get_device_and_config(
         Err(anyhow!("Device not found: {}", audio_device.name))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_list_each_supported_config_once() {
+        let mut capabilities = DeviceCapabilities {
+            sample_rates: Vec::new(),
+            channel_counts: Vec::new(),
+            sample_formats: Vec::new(),
+            is_default: true,
+            is_virtual: false,
+        };
+        for (channels, min_rate, max_rate, format) in [
+            (2, 44100, 48000, cpal::SampleFormat::I16),
+            (1, 8000, 48000, cpal::SampleFormat::F32),
+            (2, 11025, 11025, cpal::SampleFormat::F32),
+        ] {
+            capabilities.add_range(&cpal::SupportedStreamConfigRange::new(
+                channels,
+                cpal::SampleRate(min_rate),
+                cpal::SampleRate(max_rate),
+                cpal::SupportedBufferSize::Unknown,
+                format,
+            ));
+        }
+        capabilities.dedup();
+
+        assert_eq!(
+            capabilities.sample_rates,
+            vec![8000, 11025, 16000, 22050, 32000, 44100, 48000]
+        );
+        assert_eq!(capabilities.channel_counts, vec![1, 2]);
+        assert_eq!(capabilities.sample_formats, vec!["f32", "i16"]);
+    }
+
+    #[test]
+    fn a_single_transient_error_is_not_a_disconnection() {
+        let debouncer = DisconnectDebouncer::new(DisconnectGracePolicy::default());
+        assert!(!debouncer.record_error());
+        debouncer.reset();
+        assert_eq!(debouncer.error_count(), 0);
+        assert!(!debouncer.record_error());
+    }
+
+    #[test]
+    fn repeated_errors_are_a_disconnection() {
+        let debouncer = DisconnectDebouncer::new(DisconnectGracePolicy {
+            grace_period_ms: 60_000,
+            max_consecutive_errors: 3,
+        });
+        assert!(!debouncer.record_error());
+        assert!(!debouncer.record_error());
+        assert!(debouncer.record_error());
+    }
+
+    #[test]
+    fn errors_persisting_past_the_grace_period_are_a_disconnection() {
+        let debouncer = DisconnectDebouncer::new(DisconnectGracePolicy {
+            grace_period_ms: 20,
+            max_consecutive_errors: 100,
+        });
+        assert!(!debouncer.record_error());
+        thread::sleep(Duration::from_millis(30));
+        assert!(debouncer.record_error());
+    }
+
+    #[test]
+    fn a_device_silent_since_an_error_is_a_disconnection_after_the_grace_period() {
+        let debouncer = DisconnectDebouncer::new(DisconnectGracePolicy {
+            grace_period_ms: 20,
+            max_consecutive_errors: 100,
+        });
+        assert!(!debouncer.grace_expired());
+        assert!(!debouncer.record_error());
+        assert!(!debouncer.grace_expired());
+        thread::sleep(Duration::from_millis(30));
+        assert!(debouncer.grace_expired());
+
+        // Audio arriving in between means the device recovered
+        debouncer.reset();
+        assert!(!debouncer.grace_expired());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_device_that_opens() {
+        let preferences = vec!["USB Mic".to_string(), "Built-in Mic".to_string()];
+        let mut tried = Vec::new();
+        let device = first_usable_device(&preferences, &DeviceType::Input, |candidate| {
+            tried.push(candidate.to_string());
+            let result = if candidate == "USB Mic" {
+                Err(anyhow!("device unavailable"))
+            } else {
+                Ok(AudioDevice::new(candidate.to_string(), DeviceType::Input))
+            };
+            async move { result }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(device.name, "Built-in Mic");
+        assert_eq!(tried, preferences);
+    }
+
+    #[tokio::test]
+    async fn reports_every_device_that_failed() {
+        let error = first_usable_device(&[], &DeviceType::Output, |_| async { Err(anyhow!("unavailable")) })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No usable output device. Tried: default (unavailable)"
+        );
+    }
+
+    #[test]
+    fn short_capture_buffers_are_merged_into_full_frames() {
+        let mut frames = CaptureFrameBuffer::new(16000);
+        for i in 0..159 {
+            assert_eq!(frames.push(vec![i as f32]), None);
+        }
+        assert_eq!(frames.push(Vec::new()), None);
+
+        let frame = frames.push(vec![159.0]).unwrap();
+        assert_eq!(frame, (0..160).map(|i| i as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn full_capture_buffers_pass_straight_through() {
+        let mut frames = CaptureFrameBuffer::new(16000);
+        assert_eq!(frames.push(vec![0.5; 480]), Some(vec![0.5; 480]));
+        assert_eq!(frames.push(vec![0.1; 100]), None);
+        // A full buffer behind a held-back one is sent along with it
+        assert_eq!(frames.push(vec![0.2; 480]).map(|frame| frame.len()), Some(580));
+    }
+
+    #[test]
+    fn misreported_channel_counts_fall_back_to_mono() {
+        let fallen_back = Arc::new(AtomicBool::new(false));
+        let mut layout = ChannelLayout::new(2, Vec::new(), fallen_back.clone());
+        // A stereo buffer is mixed down
+        assert_eq!(layout.to_mono(&[0.2, 0.4, 0.6, 0.8], "test").len(), 2);
+
+        // Odd-length buffers can't be stereo
+        for _ in 1..MAX_CHANNEL_MISMATCHES {
+            layout.to_mono(&[0.1; 441], "test");
+            assert!(!fallen_back.load(Ordering::Acquire));
+        }
+        assert_eq!(layout.to_mono(&[0.1; 441], "test").len(), 441);
+        assert!(fallen_back.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn an_odd_buffer_now_and_then_keeps_the_channel_count() {
+        let fallen_back = Arc::new(AtomicBool::new(false));
+        let mut layout = ChannelLayout::new(2, Vec::new(), fallen_back.clone());
+        for _ in 0..3 * MAX_CHANNEL_MISMATCHES {
+            layout.to_mono(&[0.1; 441], "test");
+            layout.to_mono(&[0.1; 440], "test");
+        }
+        assert!(!fallen_back.load(Ordering::Acquire));
+        assert_eq!(layout.to_mono(&[0.1; 440], "test").len(), 220);
+    }
+
+    #[test]
+    fn a_channel_count_the_device_doesnt_have_is_replaced() {
+        assert_eq!(device_channel_count(2, &[1, 2], "test"), 2);
+        assert_eq!(device_channel_count(6, &[2], "test"), 2);
+        // Nothing listed, nothing to check against
+        assert_eq!(device_channel_count(6, &[], "test"), 6);
+    }
+
+    #[test]
+    fn virtual_devices_are_offered_as_system_audio() {
+        let mut devices = vec![
+            AudioDevice::new("MacBook Pro Microphone".to_string(), DeviceType::Input),
+            AudioDevice::new("BlackHole 2ch".to_string(), DeviceType::Input),
+            AudioDevice::new("CABLE Output (VB-Audio Virtual Cable)".to_string(), DeviceType::Input),
+            AudioDevice::new("CABLE Output (VB-Audio Virtual Cable)".to_string(), DeviceType::Output),
+        ];
+        offer_virtual_inputs_as_system_audio(&mut devices);
+
+        let blackhole = AudioDevice::new("BlackHole 2ch".to_string(), DeviceType::Output);
+        assert!(blackhole.is_virtual());
+        assert_eq!(devices.iter().filter(|d| **d == blackhole).count(), 1);
+        assert_eq!(devices.len(), 5);
+        assert!(!devices[0].is_virtual());
+        // Opened through the matching input device
+        assert_eq!(
+            blackhole.virtual_capture_device(),
+            Some(AudioDevice::new("BlackHole 2ch".to_string(), DeviceType::Input))
+        );
+    }
+
+    #[tokio::test]
+    async fn rapid_device_lookups_enumerate_once_until_invalidated() {
+        let cache = DeviceListCache::new();
+        let enumerations = AtomicU32::new(0);
+        let list = || async {
+            enumerations.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![AudioDevice::new(
+                "Built-in Microphone".to_string(),
+                DeviceType::Input,
+            )])
+        };
+        let max_age = Duration::from_secs(60);
+
+        let first = cache.get_or_list(max_age, list).await.unwrap();
+        let second = cache.get_or_list(max_age, list).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(enumerations.load(Ordering::SeqCst), 1);
+
+        cache.invalidate();
+        cache.get_or_list(max_age, list).await.unwrap();
+        assert_eq!(enumerations.load(Ordering::SeqCst), 2);
+
+        // An expired list is enumerated again too
+        cache.get_or_list(Duration::ZERO, list).await.unwrap();
+        assert_eq!(enumerations.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn a_generated_stream_delivers_the_signal_like_a_device() {
+        use super::super::test_source::TestSignal;
+
+        let signal = TestSignal::Tone {
+            frequency_hz: 440.0,
+            amplitude: 0.5,
+        };
+        let is_running = Arc::new(AtomicBool::new(true));
+        let stream = AudioStream::from_generator(AudioTestGenerator::new(signal, 16000), is_running.clone());
+        let mut receiver = stream.subscribe().await;
+
+        let mut samples = Vec::new();
+        while samples.len() < 4000 {
+            let frame = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            samples.extend(frame);
+        }
+        let rms = (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt();
+        assert!((rms - 0.5 / 2f32.sqrt()).abs() < 0.05, "rms {}", rms);
+
+        is_running.store(false, Ordering::Relaxed);
+        stream.stop().await.unwrap();
+    }
+
+    #[test]
+    fn an_empty_device_list_reports_no_audio_devices() {
+        let error = ensure_device_available(&[], &DeviceType::Input).unwrap_err();
+        assert_eq!(error, NoAudioDevices(DeviceType::Input));
+        assert_eq!(error.to_string(), "No input audio devices available");
+
+        // A microphone alone doesn't count as system audio
+        let devices = [AudioDevice::new("Built-in Microphone".to_string(), DeviceType::Input)];
+        assert!(ensure_device_available(&devices, &DeviceType::Input).is_ok());
+        let error = anyhow::Error::from(ensure_device_available(&devices, &DeviceType::Output).unwrap_err());
+        assert_eq!(
+            error.downcast_ref::<NoAudioDevices>(),
+            Some(&NoAudioDevices(DeviceType::Output))
+        );
+    }
+
+    #[tokio::test]
+    async fn silence_stands_in_for_a_missing_device() {
+        use super::super::test_source::TestSignal;
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let generator = AudioTestGenerator::new(TestSignal::Silence, 16000);
+        let stream = AudioStream::from_generator(generator, is_running.clone());
+        let mut receiver = stream.subscribe().await;
+
+        let mut samples = Vec::new();
+        while samples.len() < 1600 {
+            let frame = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            samples.extend(frame);
+        }
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+
+        is_running.store(false, Ordering::Relaxed);
+        stream.stop().await.unwrap();
+    }
+
+    #[test]
+    fn a_revoked_capture_permission_is_not_a_disconnect() {
+        let revoked = StreamError::BackendSpecific {
+            err: cpal::BackendSpecificError {
+                description: "SCStream failed: The user declined TCCs for application, window, display capture"
+                    .to_string(),
+            },
+        };
+        assert!(is_permission_error(&revoked.to_string()));
+        assert!(is_permission_error("Access denied to audio endpoint"));
+
+        assert!(!is_permission_error(&StreamError::DeviceNotAvailable.to_string()));
+        assert!(!is_permission_error("The requested device is no longer available"));
+        assert!(!is_permission_error("device is no longer valid"));
+    }
+
+    // cpal can't build an `InputCallbackInfo` outside the crate, so these cover
+    // what `record_input_latency` does with the latency it reads from one
+    #[test]
+    fn input_latency_is_unknown_until_reported() {
+        let latency_us = AtomicU64::new(UNKNOWN_LATENCY);
+        assert_eq!(load_input_latency(&latency_us), None);
+
+        store_input_latency(&latency_us, Some(Duration::from_millis(180)));
+        assert_eq!(load_input_latency(&latency_us), Some(Duration::from_millis(180)));
+    }
+
+    #[test]
+    fn batches_without_a_timestamp_keep_the_last_latency() {
+        let latency_us = AtomicU64::new(UNKNOWN_LATENCY);
+        store_input_latency(&latency_us, Some(Duration::from_millis(20)));
+        store_input_latency(&latency_us, None);
+        assert_eq!(load_input_latency(&latency_us), Some(Duration::from_millis(20)));
+    }
+}
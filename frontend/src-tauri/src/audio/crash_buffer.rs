@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Header: magic, version, sample rate, reserved, capacity and total samples written
+const MAGIC: &[u8; 4] = b"MMCB";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 32;
+const WRITTEN_OFFSET: u64 = 24;
+const SAMPLE_BYTES: u64 = 4;
+
+/// Settings for keeping the last minutes of captured audio on disk while
+/// recording, so they can be recovered if the app crashes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashBufferConfig {
+    pub enabled: bool,
+    /// Older audio is overwritten once the buffer holds this much.
+    pub max_minutes: u32,
+}
+
+impl Default for CrashBufferConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_minutes: 10,
+        }
+    }
+}
+
+pub fn default_crash_buffer_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("couldn't find the data directory")?;
+    Ok(data_dir.join("com.meetily.ai").join("crash_buffer.raw"))
+}
+
+/// Fixed-size ring of mono f32 samples in a file. Writes go through the OS
+/// page cache, which survives the app crashing, so nothing is synced.
+pub struct CrashBuffer {
+    file: File,
+    capacity: u64,
+    written: u64,
+}
+
+impl CrashBuffer {
+    /// Creates the buffer at `path`, replacing any existing one.
+    pub fn create(path: &Path, sample_rate: u32, max_minutes: u32) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let capacity = (sample_rate as u64 * 60 * max_minutes.max(1) as u64).max(1);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&capacity.to_le_bytes());
+        header.extend_from_slice(&0u64.to_le_bytes());
+        file.write_all(&header)?;
+
+        info!("Keeping up to {} minutes of audio in {}", max_minutes, path.display());
+        Ok(Self {
+            file,
+            capacity,
+            written: 0,
+        })
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        // Only the newest `capacity` samples can survive the wrap-around
+        let skip = samples.len().saturating_sub(self.capacity as usize);
+        let mut remaining = &samples[skip..];
+        self.written += skip as u64;
+
+        while !remaining.is_empty() {
+            let position = self.written % self.capacity;
+            let run = remaining.len().min((self.capacity - position) as usize);
+            let bytes: Vec<u8> = remaining[..run].iter().flat_map(|sample| sample.to_le_bytes()).collect();
+            self.file.seek(SeekFrom::Start(HEADER_LEN + position * SAMPLE_BYTES))?;
+            self.file.write_all(&bytes)?;
+            self.written += run as u64;
+            remaining = &remaining[run..];
+        }
+
+        self.file.seek(SeekFrom::Start(WRITTEN_OFFSET))?;
+        self.file.write_all(&self.written.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// The audio left in a buffer by a session that didn't stop cleanly, oldest
+/// sample first, with its sample rate. `None` if there is no buffer.
+pub fn read_crash_buffer(path: &Path) -> Result<Option<(u32, Vec<f32>)>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut header = [0u8; HEADER_LEN as usize];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err(anyhow!("{} is not a crash buffer", path.display()));
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into()?);
+    if version != VERSION {
+        return Err(anyhow!("Unsupported crash buffer version {}", version));
+    }
+    let sample_rate = u32::from_le_bytes(header[8..12].try_into()?);
+    let capacity = u64::from_le_bytes(header[16..24].try_into()?).max(1);
+    let written = u64::from_le_bytes(header[24..32].try_into()?);
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    let stored: Vec<f32> = data
+        .chunks_exact(SAMPLE_BYTES as usize)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect();
+
+    // Once wrapped, the oldest sample is the one the next write would overwrite
+    let samples = if written <= capacity {
+        stored.into_iter().take(written as usize).collect()
+    } else {
+        let split = ((written % capacity) as usize).min(stored.len());
+        let mut ordered = stored[split..].to_vec();
+        ordered.extend_from_slice(&stored[..split]);
+        ordered
+    };
+    Ok(Some((sample_rate, samples)))
+}
+
+/// Converts a crash buffer left behind by an unclean exit into a WAV file next
+/// to it and deletes the buffer. Returns the WAV path, or `None` if there was
+/// nothing to recover.
+pub fn recover_crash_buffer(path: &Path) -> Result<Option<PathBuf>> {
+    let Some((sample_rate, samples)) = read_crash_buffer(path)? else {
+        return Ok(None);
+    };
+    if samples.is_empty() {
+        std::fs::remove_file(path)?;
+        return Ok(None);
+    }
+
+    let name = format!("recovered_{}.wav", chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
+    let wav_path = path.with_file_name(name);
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&wav_path, spec)?;
+    for sample in &samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+
+    if let Err(e) = std::fs::remove_file(path) {
+        warn!("Failed to remove recovered crash buffer {}: {}", path.display(), e);
+    }
+    info!("Recovered {:.1}s of audio to {}", samples.len() as f64 / sample_rate.max(1) as f64, wav_path.display());
+    Ok(Some(wav_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_and_samples_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("round_trip.raw");
+        let mut buffer = CrashBuffer::create(&path, 16000, 1).unwrap();
+        buffer.write(&[0.25, -0.5]).unwrap();
+        buffer.write(&[0.75]).unwrap();
+
+        assert_eq!(read_crash_buffer(&path).unwrap(), Some((16000, vec![0.25, -0.5, 0.75])));
+    }
+
+    #[test]
+    fn keeps_the_newest_samples_in_order_once_wrapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wrapped.raw");
+        let mut buffer = CrashBuffer::create(&path, 16000, 1).unwrap();
+        let capacity = 16000 * 60;
+        let samples: Vec<f32> = (0..capacity + 3).map(|i| i as f32).collect();
+        buffer.write(&samples[..capacity - 1]).unwrap();
+        buffer.write(&samples[capacity - 1..]).unwrap();
+
+        let (sample_rate, recovered) = read_crash_buffer(&path).unwrap().unwrap();
+        assert_eq!(sample_rate, 16000);
+        assert_eq!(recovered, samples[3..]);
+    }
+
+    #[test]
+    fn rejects_files_without_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not_a_buffer.raw");
+        std::fs::write(&path, [0u8; HEADER_LEN as usize]).unwrap();
+
+        assert!(read_crash_buffer(&path).is_err());
+        assert_eq!(read_crash_buffer(&dir.path().join("missing.raw")).unwrap(), None);
+    }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+
+/// Makeup gain is never allowed above this, whatever the config says, so a
+/// steep ratio can't lift the room noise to speech level.
+const MAX_MAKEUP_GAIN_DB: f32 = 12.0;
+
+// Level detection and gain updates run on blocks of this length
+const DETECTION_WINDOW_MS: f32 = 10.0;
+
+/// Settings for the compressor that evens out loud and soft speakers before
+/// transcription. Unlike a level normalizer it only narrows the dynamic range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressorConfig {
+    pub enabled: bool,
+    /// Level above which gain reduction starts, in dBFS (RMS).
+    pub threshold_db: f32,
+    /// Input dB above the threshold per output dB above it.
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Gain added after compression, in dB. Capped at what compression removes
+    /// from a full-scale signal and at 12 dB.
+    pub makeup_gain_db: f32,
+}
+
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -24.0,
+            ratio: 3.0,
+            attack_ms: 10.0,
+            release_ms: 200.0,
+            makeup_gain_db: 6.0,
+        }
+    }
+}
+
+/// Feed-forward RMS compressor applied to the mixed capture stream.
+pub struct Compressor {
+    config: CompressorConfig,
+    window_len: usize,
+    attack_coeff: f32,
+    release_coeff: f32,
+    makeup_gain: f32,
+    envelope: f32,
+    gain: f32,
+    // Gain reached at the end of the current window and the per-sample ramp towards it
+    target: f32,
+    step: f32,
+    // Samples of the current detection window seen so far and their summed squares
+    window_pos: usize,
+    window_energy: f32,
+}
+
+impl Compressor {
+    pub fn new(config: CompressorConfig, sample_rate: u32) -> Self {
+        let window_len = ((sample_rate as f32 * DETECTION_WINDOW_MS / 1000.0) as usize).max(1);
+        let mut compressor = Self {
+            config: CompressorConfig::default(),
+            window_len,
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            makeup_gain: 1.0,
+            envelope: 0.0,
+            gain: 1.0,
+            target: 1.0,
+            step: 0.0,
+            window_pos: 0,
+            window_energy: 0.0,
+        };
+        compressor.update_config(config);
+        compressor
+    }
+
+    /// Applies new settings, keeping the current envelope and gain.
+    pub fn update_config(&mut self, config: CompressorConfig) {
+        let smoothing = |time_ms: f32| {
+            if time_ms <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-DETECTION_WINDOW_MS / time_ms).exp()
+            }
+        };
+        self.attack_coeff = smoothing(config.attack_ms);
+        self.release_coeff = smoothing(config.release_ms);
+
+        let ratio = config.ratio.max(1.0);
+        let full_scale_reduction = (-config.threshold_db).max(0.0) * (1.0 - 1.0 / ratio);
+        let makeup_db = config
+            .makeup_gain_db
+            .clamp(0.0, full_scale_reduction.min(MAX_MAKEUP_GAIN_DB));
+        self.makeup_gain = db_to_gain(makeup_db);
+        self.config = config;
+    }
+
+    /// Drops the level envelope so the next block is measured from scratch.
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.gain = 1.0;
+        self.target = 1.0;
+        self.step = 0.0;
+        self.window_pos = 0;
+        self.window_energy = 0.0;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Compresses `samples` in place. State, including a partly seen detection
+    /// window, carries over between calls, so the stream can be fed in blocks of
+    /// any size. The gain for each window follows the level of the one before it.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if !self.config.enabled {
+            return;
+        }
+
+        for sample in samples.iter_mut() {
+            self.window_energy += *sample * *sample;
+            // Ramp across the window so gain changes don't click
+            self.gain += self.step;
+            *sample = (*sample * self.gain).clamp(-1.0, 1.0);
+
+            self.window_pos += 1;
+            if self.window_pos == self.window_len {
+                let level = (self.window_energy / self.window_len as f32).sqrt();
+                let coeff = if level > self.envelope { self.attack_coeff } else { self.release_coeff };
+                self.envelope += (level - self.envelope) * coeff;
+
+                self.gain = self.target;
+                self.target = self.target_gain(self.envelope);
+                self.step = (self.target - self.gain) / self.window_len as f32;
+                self.window_pos = 0;
+                self.window_energy = 0.0;
+            }
+        }
+    }
+
+    fn target_gain(&self, envelope: f32) -> f32 {
+        if envelope <= 0.0 {
+            return self.makeup_gain;
+        }
+        let level_db = 20.0 * envelope.log10();
+        let over = level_db - self.config.threshold_db;
+        let reduction_db = if over > 0.0 {
+            over * (1.0 - 1.0 / self.config.ratio.max(1.0))
+        } else {
+            0.0
+        };
+        db_to_gain(-reduction_db) * self.makeup_gain
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::audio_processing::rms;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn sine(amplitude: f32, secs: f32) -> Vec<f32> {
+        let len = (SAMPLE_RATE as f32 * secs) as usize;
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn enabled() -> CompressorConfig {
+        CompressorConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn narrows_the_gap_between_loud_and_soft_speech() {
+        let mut samples = sine(0.8, 1.0);
+        samples.extend(sine(0.02, 1.0));
+        let half = samples.len() / 2;
+        let before = rms(&samples[..half]) / rms(&samples[half..]);
+
+        Compressor::new(enabled(), SAMPLE_RATE).process(&mut samples);
+        // Skip the attack and release at the start of each half
+        let settled = SAMPLE_RATE as usize / 2;
+        let after = rms(&samples[settled..half]) / rms(&samples[half + settled..]);
+        assert!(
+            after < before / 2.0,
+            "loud/soft ratio went from {} to {}",
+            before,
+            after
+        );
+    }
+
+    #[test]
+    fn makeup_gain_is_capped() {
+        let config = CompressorConfig {
+            makeup_gain_db: 40.0,
+            ..enabled()
+        };
+        let compressor = Compressor::new(config, SAMPLE_RATE);
+        assert!(compressor.makeup_gain <= db_to_gain(MAX_MAKEUP_GAIN_DB));
+    }
+
+    #[test]
+    fn state_carries_over_between_calls() {
+        let input = sine(0.5, 0.5);
+        let mut whole = input.clone();
+        Compressor::new(enabled(), SAMPLE_RATE).process(&mut whole);
+
+        // Block sizes that don't line up with the 160-sample detection windows
+        let mut split = input;
+        let mut compressor = Compressor::new(enabled(), SAMPLE_RATE);
+        let mut rest = split.as_mut_slice();
+        for len in [37, 160, 1, 250, 99].into_iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (block, tail) = rest.split_at_mut(len.min(rest.len()));
+            compressor.process(block);
+            rest = tail;
+        }
+        assert_eq!(whole, split);
+    }
+
+    #[test]
+    fn leaves_audio_alone_when_disabled() {
+        let input = sine(0.8, 0.1);
+        let mut samples = input.clone();
+        Compressor::new(CompressorConfig::default(), SAMPLE_RATE).process(&mut samples);
+        assert_eq!(samples, input);
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings for the pre-emphasis filter applied to chunks before they are sent
+/// to whisper. Boosting high frequencies helps consonants on dull microphones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreEmphasisConfig {
+    /// Off by default, since already-bright microphones can get worse.
+    pub enabled: bool,
+    /// `a` in `y[n] = x[n] - a * x[n-1]`, usually around 0.97.
+    pub coefficient: f32,
+}
+
+impl Default for PreEmphasisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            coefficient: 0.97,
+        }
+    }
+}
+
+/// First-order pre-emphasis filter. Chunks are consecutive pieces of the same
+/// stream, so the last input sample is carried over to the next call.
+pub struct PreEmphasis {
+    config: PreEmphasisConfig,
+    previous: f32,
+}
+
+impl PreEmphasis {
+    pub fn new(config: PreEmphasisConfig) -> Self {
+        Self { config, previous: 0.0 }
+    }
+
+    pub fn update_config(&mut self, config: PreEmphasisConfig) {
+        self.config = config;
+    }
+
+    /// Filters `samples` in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let coefficient = self.config.coefficient.clamp(0.0, 1.0);
+        for sample in samples.iter_mut() {
+            let input = *sample;
+            *sample = input - coefficient * self.previous;
+            self.previous = input;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    fn enabled() -> PreEmphasisConfig {
+        PreEmphasisConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn boosts_high_frequencies_over_low_ones() {
+        let mut low = sine(100.0, 16000);
+        let mut high = sine(4000.0, 16000);
+        let (low_before, high_before) = (energy(&low), energy(&high));
+
+        PreEmphasis::new(enabled()).process(&mut low);
+        PreEmphasis::new(enabled()).process(&mut high);
+        assert!(energy(&low) < low_before / 10.0);
+        assert!(energy(&high) > high_before);
+    }
+
+    #[test]
+    fn carries_over_between_chunks() {
+        let input = sine(300.0, 1600);
+        let mut whole = input.clone();
+        PreEmphasis::new(enabled()).process(&mut whole);
+
+        let mut split = input;
+        let mut filter = PreEmphasis::new(enabled());
+        let (first, second) = split.split_at_mut(700);
+        filter.process(first);
+        filter.process(second);
+        assert_eq!(whole, split);
+    }
+}
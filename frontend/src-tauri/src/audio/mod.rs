@@ -1,15 +1,49 @@
 // src/audio/mod.rs
 pub mod core;
 pub mod audio_processing;
+pub mod balance;
+pub mod calibration;
+pub mod capture_queue;
+pub mod crash_buffer;
+pub mod dynamics;
+pub mod emphasis;
 pub mod encode;
 pub mod ffmpeg;
+pub mod monitor;
+pub mod padding;
+pub mod preroll;
+pub mod priority;
+pub mod rate_watch;
+pub mod resample;
+pub mod source_gate;
+pub mod test_source;
+pub mod timing;
 
 pub use core::{
-    default_input_device, default_output_device, get_device_and_config, list_audio_devices,
-    parse_audio_device, trigger_audio_permission,
-    AudioDevice, AudioStream, AudioTranscriptionEngine, DeviceControl, DeviceType,
-    LAST_AUDIO_CAPTURE,
+    default_input_device, default_output_device, get_device_and_config, invalidate_device_cache, list_audio_devices,
+    list_audio_devices_cached, DEVICE_LIST_MAX_AGE,
+    parse_audio_device, select_device_with_fallback, trigger_audio_permission,
+    AudioDevice, AudioStream, AudioTranscriptionEngine, DeviceCapabilities, DeviceControl, DeviceType,
+    DeviceFallbackConfig, DisconnectGracePolicy, MissingDevicePolicy, NoAudioDevices, PermissionRecoveryConfig,
+    StreamOptions,
 };
+pub use balance::{SourceBalanceConfig, SourceBalancer};
+pub use calibration::{
+    load_calibration_profile, run_calibration, save_calibration_profile, CalibrationConfig, CalibrationProfile,
+};
+pub use capture_queue::{CaptureForwarder, CaptureOverflowPolicy, CaptureQueue, CaptureQueueConfig};
+pub use crash_buffer::{default_crash_buffer_path, recover_crash_buffer, CrashBuffer, CrashBufferConfig};
+pub use dynamics::{Compressor, CompressorConfig};
+pub use emphasis::{PreEmphasis, PreEmphasisConfig};
+pub use monitor::{monitor_may_loop, AudioMonitor, MonitorConfig};
+pub use padding::{pad_chunk, ChunkPaddingConfig};
+pub use preroll::{PrerollBuffer, SystemPrerollConfig};
+pub use priority::CaptureThreadConfig;
+pub use rate_watch::{SampleRateChange, SampleRateChangeConfig, SampleRateWatcher};
+pub use resample::StreamResampler;
+pub use source_gate::{CaptureSource, SourceGate, SourceGateState};
+pub use test_source::{AudioTestGenerator, TestSignal, TestSourceConfig};
+pub use timing::{ChunkClock, ChunkPlacement, ChunkTimingConfig};
 pub use encode::{
     encode_single_audio, AudioInput
 };
\ No newline at end of file
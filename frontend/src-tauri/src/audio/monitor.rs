@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use tokio::sync::broadcast;
+
+// Audio buffered for playback at most. Anything older is dropped so the
+// monitor doesn't drift behind the speaker.
+const MAX_MONITOR_LATENCY_SECS: f32 = 0.2;
+
+/// Settings for playing the captured microphone audio back through an output device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub enabled: bool,
+    /// Playback device name, or "default" for the system default.
+    pub device: String,
+    /// Linear gain applied to the monitored audio, 0.0 to 1.0.
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: "default".to_string(),
+            volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+/// Whether monitoring through `monitor_device` would be picked up again by the
+/// system audio capture of `system_device`, feeding back into the recording.
+pub fn monitor_may_loop(monitor_device: &str, system_device: &str) -> bool {
+    let monitor = if monitor_device.eq_ignore_ascii_case("default") {
+        match cpal::default_host().default_output_device().and_then(|device| device.name().ok()) {
+            Some(name) => name,
+            None => return false,
+        }
+    } else {
+        monitor_device.to_string()
+    };
+    monitor == system_device
+}
+
+// Converts between sample rates by linear interpolation, carrying the position
+// across batches. Good enough for listening, not for transcription.
+struct LinearResampler {
+    step: f64,
+    position: f64,
+    previous: f32,
+}
+
+impl LinearResampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate as f64,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut VecDeque<f32>) {
+        // position is measured from `previous`, which sits just before input[0]
+        while self.position < input.len() as f64 {
+            let index = self.position.floor() as usize;
+            let fraction = (self.position - index as f64) as f32;
+            let before = if index == 0 { self.previous } else { input[index - 1] };
+            output.push_back(before + (input[index] - before) * fraction);
+            self.position += self.step;
+        }
+        self.position -= input.len() as f64;
+        if let Some(&last) = input.last() {
+            self.previous = last;
+        }
+    }
+}
+
+/// Plays captured audio on an output device. The cpal stream lives on its own
+/// thread, like the capture streams, and is closed when the monitor is stopped.
+pub struct AudioMonitor {
+    volume: Arc<AtomicU32>,
+    muted: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+    stream_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl AudioMonitor {
+    /// Starts playing the mono audio from `receiver`, captured at `sample_rate`.
+    pub fn start(
+        mut receiver: broadcast::Receiver<Vec<f32>>,
+        sample_rate: u32,
+        config: &MonitorConfig,
+    ) -> Result<Self> {
+        let volume = Arc::new(AtomicU32::new(config.volume.clamp(0.0, 1.0).to_bits()));
+        let muted = Arc::new(AtomicBool::new(config.muted));
+        let running = Arc::new(AtomicBool::new(true));
+        let pending: Arc<Mutex<VecDeque<f32>>> = Arc::new(Mutex::new(VecDeque::new()));
+
+        // The stream is built on its thread, which reports back whether that worked
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<u32>>();
+        let device_name = config.device.clone();
+        let thread_running = running.clone();
+        let thread_volume = volume.clone();
+        let thread_muted = muted.clone();
+        let thread_pending = pending.clone();
+        let stream_thread = thread::spawn(move || {
+            let stream = match build_output_stream(&device_name, sample_rate, thread_pending, thread_volume, thread_muted) {
+                Ok((stream, output_rate)) => {
+                    if let Err(e) = stream.play() {
+                        ready_tx.send(Err(anyhow!("Failed to start monitor stream: {}", e))).ok();
+                        return;
+                    }
+                    ready_tx.send(Ok(output_rate)).ok();
+                    stream
+                }
+                Err(e) => {
+                    ready_tx.send(Err(e)).ok();
+                    return;
+                }
+            };
+            while thread_running.load(Ordering::Acquire) {
+                thread::sleep(std::time::Duration::from_millis(50));
+            }
+            drop(stream);
+            info!("Audio monitor stream closed");
+        });
+
+        let output_rate = ready_rx
+            .recv()
+            .map_err(|_| anyhow!("Monitor thread exited before the stream was created"))??;
+        info!("Monitoring captured audio on {} at {} Hz", config.device, output_rate);
+
+        // Feed captured audio to the output callback, dropping the oldest when behind
+        let feed_running = running.clone();
+        let max_pending = (output_rate as f32 * MAX_MONITOR_LATENCY_SECS) as usize;
+        tokio::spawn(async move {
+            let mut resampler = LinearResampler::new(sample_rate, output_rate);
+            while feed_running.load(Ordering::Acquire) {
+                match receiver.recv().await {
+                    Ok(samples) => {
+                        if let Ok(mut pending) = pending.lock() {
+                            resampler.process(&samples, &mut pending);
+                            let excess = pending.len().saturating_sub(max_pending);
+                            pending.drain(..excess);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Audio monitor skipped {} capture buffers", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            volume,
+            muted,
+            running,
+            stream_thread: Some(stream_thread),
+        })
+    }
+
+    /// Applies volume and mute changes to the running monitor.
+    pub fn update_config(&self, config: &MonitorConfig) {
+        self.volume.store(config.volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        self.muted.store(config.muted, Ordering::Relaxed);
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(handle) = self.stream_thread.take() {
+            if handle.join().is_err() {
+                error!("Audio monitor thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for AudioMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn find_output_device(name: &str) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    if name.eq_ignore_ascii_case("default") {
+        return host
+            .default_output_device()
+            .ok_or_else(|| anyhow!("No default output device for monitoring"));
+    }
+    for device in host.output_devices()? {
+        if device.name().map(|device_name| device_name == name).unwrap_or(false) {
+            return Ok(device);
+        }
+    }
+    Err(anyhow!("Monitor device not found: {}", name))
+}
+
+// Opens an f32 output stream, at the capture rate if the device supports it
+pub(super) fn build_output_stream(
+    device_name: &str,
+    sample_rate: u32,
+    pending: Arc<Mutex<VecDeque<f32>>>,
+    volume: Arc<AtomicU32>,
+    muted: Arc<AtomicBool>,
+) -> Result<(cpal::Stream, u32)> {
+    let device = find_output_device(device_name)?;
+    let default_config = device.default_output_config()?;
+    let matching_rate = device.supported_output_configs()?.find(|range| {
+        range.sample_format() == cpal::SampleFormat::F32
+            && range.min_sample_rate().0 <= sample_rate
+            && range.max_sample_rate().0 >= sample_rate
+    });
+    let config = match matching_rate {
+        Some(range) => range.with_sample_rate(cpal::SampleRate(sample_rate)),
+        None if default_config.sample_format() == cpal::SampleFormat::F32 => default_config,
+        None => return Err(anyhow!("Monitor device {} has no f32 output format", device_name)),
+    };
+    let channels = config.channels() as usize;
+    let output_rate = config.sample_rate().0;
+
+    let stream = device.build_output_stream(
+        &config.into(),
+        move |data: &mut [f32], _: &_| {
+            let gain = if muted.load(Ordering::Relaxed) {
+                0.0
+            } else {
+                f32::from_bits(volume.load(Ordering::Relaxed))
+            };
+            let mut pending = match pending.lock() {
+                Ok(pending) => pending,
+                Err(_) => {
+                    data.fill(0.0);
+                    return;
+                }
+            };
+            // Same mono sample on every output channel
+            for frame in data.chunks_mut(channels) {
+                let sample = pending.pop_front().unwrap_or(0.0) * gain;
+                frame.fill(sample);
+            }
+        },
+        |err| warn!("Audio monitor stream error: {}", err),
+        None,
+    )?;
+    Ok((stream, output_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resampling_keeps_the_rate_ratio_across_batches() {
+        let mut resampler = LinearResampler::new(16000, 48000);
+        let mut output = VecDeque::new();
+        for _ in 0..10 {
+            resampler.process(&[0.5; 160], &mut output);
+        }
+        assert_eq!(output.len(), 4800);
+        // Only the first input sample is interpolated from the silence before it
+        assert!(output.iter().skip(3).all(|&sample| (sample - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn monitoring_the_captured_device_may_loop() {
+        assert!(monitor_may_loop("MacBook Pro Speakers", "MacBook Pro Speakers"));
+        assert!(!monitor_may_loop("Headphones", "MacBook Pro Speakers"));
+    }
+
+    // Needs an output device, machines without one skip it
+    #[tokio::test]
+    async fn starts_an_output_stream_when_monitoring_is_enabled() {
+        if cpal::default_host().default_output_device().is_none() {
+            return;
+        }
+        let (sender, receiver) = broadcast::channel(8);
+        let config = MonitorConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut monitor = AudioMonitor::start(receiver, 16000, &config).unwrap();
+        sender.send(vec![0.0; 160]).unwrap();
+        monitor.stop();
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+// whisper computes one mel frame per 10 ms (160 samples at 16 kHz)
+const WHISPER_FRAME_MS: u32 = 10;
+
+/// Settings for padding chunks with silence before they are sent to whisper.
+///
+/// whisper.cpp skips input shorter than one second outright, and a length that
+/// isn't a whole number of mel frames leaves a partial frame at the end. Padding
+/// with trailing silence avoids both without changing what was said.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkPaddingConfig {
+    pub enabled: bool,
+    /// Shorter chunks are padded with silence up to this length.
+    pub min_chunk_ms: u32,
+}
+
+impl Default for ChunkPaddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_chunk_ms: 1000,
+        }
+    }
+}
+
+/// Pads `samples` with trailing silence to at least the configured minimum and
+/// to a whole number of whisper frames. Returns how many samples were added.
+pub fn pad_chunk(samples: &mut Vec<f32>, sample_rate: u32, config: &ChunkPaddingConfig) -> usize {
+    if !config.enabled || samples.is_empty() {
+        return 0;
+    }
+
+    let frame = (sample_rate * WHISPER_FRAME_MS / 1000).max(1) as usize;
+    let min_samples = (sample_rate as u64 * config.min_chunk_ms as u64 / 1000) as usize;
+    let target = samples.len().max(min_samples).div_ceil(frame) * frame;
+
+    let added = target - samples.len();
+    samples.resize(target, 0.0);
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_a_short_chunk_to_the_minimum_length() {
+        let mut samples = vec![0.5; 4000];
+        assert_eq!(pad_chunk(&mut samples, 16000, &ChunkPaddingConfig::default()), 12000);
+        assert_eq!(samples.len(), 16000);
+        assert_eq!(samples[3999], 0.5);
+        assert_eq!(samples[4000], 0.0);
+    }
+
+    #[test]
+    fn rounds_a_long_chunk_up_to_whole_frames() {
+        let mut samples = vec![0.5; 32001];
+        assert_eq!(pad_chunk(&mut samples, 16000, &ChunkPaddingConfig::default()), 159);
+        assert_eq!(samples.len() % 160, 0);
+    }
+
+    #[test]
+    fn leaves_empty_chunks_and_disabled_padding_alone() {
+        let mut empty = Vec::new();
+        assert_eq!(pad_chunk(&mut empty, 16000, &ChunkPaddingConfig::default()), 0);
+
+        let disabled = ChunkPaddingConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let mut samples = vec![0.5; 100];
+        assert_eq!(pad_chunk(&mut samples, 16000, &disabled), 0);
+        assert_eq!(samples.len(), 100);
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::core::{AudioDevice, AudioStream, StreamOptions};
+
+/// Settings for keeping the system-audio stream open while not recording, so
+/// remote speech from just before a recording starts is transcribed with it.
+/// The audio is only held in memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemPrerollConfig {
+    pub enabled: bool,
+    /// Most recent audio kept, in seconds.
+    pub seconds: u32,
+}
+
+impl Default for SystemPrerollConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seconds: 15,
+        }
+    }
+}
+
+/// A system-audio stream kept open between recordings, holding its last few
+/// seconds of audio.
+pub struct PrerollBuffer {
+    stream: Arc<AudioStream>,
+    is_running: Arc<AtomicBool>,
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl PrerollBuffer {
+    pub async fn start(device: Arc<AudioDevice>, options: StreamOptions, seconds: u32) -> Result<Self> {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let stream = AudioStream::from_device_with_options(device, is_running.clone(), options).await?;
+        Ok(Self::from_stream(stream, is_running, seconds).await)
+    }
+
+    async fn from_stream(stream: AudioStream, is_running: Arc<AtomicBool>, seconds: u32) -> Self {
+        let stream = Arc::new(stream);
+        let capacity = stream.device_config.sample_rate().0 as usize * seconds.max(1) as usize;
+        let samples = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+
+        let mut receiver = stream.subscribe().await;
+        let buffer = samples.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(chunk) => {
+                        if let Ok(mut buffer) = buffer.lock() {
+                            buffer.extend(chunk);
+                            let excess = buffer.len().saturating_sub(capacity);
+                            buffer.drain(..excess);
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => warn!("System audio pre-roll skipped {} buffers", skipped),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        info!("Keeping the last {}s of system audio from {}", seconds, stream.device.name);
+        Self {
+            stream,
+            is_running,
+            samples,
+            task,
+        }
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.stream.device.name
+    }
+
+    /// Closes the stream and returns the buffered audio, oldest first, with its
+    /// sample rate.
+    pub async fn finish(self) -> (u32, Vec<f32>) {
+        self.is_running.store(false, Ordering::SeqCst);
+        if let Err(e) = self.stream.stop().await {
+            warn!("Failed to stop system audio pre-roll stream: {}", e);
+        }
+        self.task.abort();
+        let samples = self
+            .samples
+            .lock()
+            .map(|mut buffer| buffer.drain(..).collect())
+            .unwrap_or_default();
+        (self.stream.device_config.sample_rate().0, samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::test_source::{AudioTestGenerator, TestSignal};
+
+    #[tokio::test]
+    async fn holds_the_most_recent_audio_until_the_recording_starts() {
+        let signal = TestSignal::Tone {
+            frequency_hz: 440.0,
+            amplitude: 0.5,
+        };
+        let is_running = Arc::new(AtomicBool::new(true));
+        let stream = AudioStream::from_generator(AudioTestGenerator::new(signal, 16000), is_running.clone());
+        let preroll = PrerollBuffer::from_stream(stream, is_running, 1).await;
+
+        // More than the one second kept
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        let (sample_rate, samples) = preroll.finish().await;
+        assert_eq!(sample_rate, 16000);
+        assert_eq!(samples.len(), 16000);
+        assert!(samples.iter().any(|&sample| sample.abs() > 0.4));
+    }
+}
@@ -0,0 +1,158 @@
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+/// Scheduling options for the thread that runs the capture callbacks. Under
+/// heavy whisper CPU load a default-priority callback thread can be preempted
+/// long enough to overrun the device buffer, which drops samples.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CaptureThreadConfig {
+    /// Raise the callback thread's scheduling priority where the OS allows it.
+    pub raise_priority: bool,
+    /// Pin the callback thread to this CPU core (Linux and Windows only).
+    pub pin_to_core: Option<usize>,
+}
+
+/// Applies `config` to the calling thread. Unsupported platforms and missing
+/// permissions are skipped; returns whether anything was changed.
+pub fn apply_to_current_thread(config: &CaptureThreadConfig, device_name: &str) -> bool {
+    let mut applied = false;
+    if config.raise_priority {
+        if raise_current_thread_priority() {
+            info!("Raised capture thread priority for {}", device_name);
+            applied = true;
+        } else {
+            debug!("Could not raise capture thread priority for {}, keeping default", device_name);
+        }
+    }
+    if let Some(core) = config.pin_to_core {
+        if pin_current_thread(core) {
+            info!("Pinned capture thread for {} to core {}", device_name, core);
+            applied = true;
+        } else {
+            debug!("Could not pin capture thread for {} to core {}", device_name, core);
+        }
+    }
+    applied
+}
+
+#[cfg(target_os = "linux")]
+fn raise_current_thread_priority() -> bool {
+    // Nice values are per thread on Linux. Lowering them needs CAP_SYS_NICE or a
+    // suitable RLIMIT_NICE, so failure here is expected for most desktop users.
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        libc::setpriority(libc::PRIO_PROCESS, tid, -10) == 0
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn raise_current_thread_priority() -> bool {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_HIGHEST};
+    unsafe { SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_HIGHEST) != 0 }
+}
+
+// CoreAudio already runs IO callbacks on a real-time thread
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn raise_current_thread_priority() -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(core: usize) -> bool {
+    if core >= libc::CPU_SETSIZE as usize {
+        return false;
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn pin_current_thread(core: usize) -> bool {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadAffinityMask};
+    if core >= usize::BITS as usize {
+        return false;
+    }
+    unsafe { SetThreadAffinityMask(GetCurrentThread(), 1usize << core) != 0 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn pin_current_thread(_core: usize) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_leaves_the_thread_alone() {
+        assert!(!apply_to_current_thread(&CaptureThreadConfig::default(), "test"));
+    }
+
+    #[test]
+    fn impossible_settings_are_skipped() {
+        let config = CaptureThreadConfig {
+            raise_priority: false,
+            pin_to_core: Some(usize::MAX),
+        };
+        assert!(!std::thread::spawn(move || apply_to_current_thread(&config, "test"))
+            .join()
+            .unwrap());
+    }
+
+    // Raising the priority usually needs privileges, so it may be skipped, but
+    // it must only be reported when it took effect
+    #[test]
+    fn raising_the_priority_succeeds_or_is_skipped() {
+        let config = CaptureThreadConfig {
+            raise_priority: true,
+            pin_to_core: None,
+        };
+        let (applied, _priority) = std::thread::spawn(move || {
+            let applied = apply_to_current_thread(&config, "test");
+            #[cfg(target_os = "linux")]
+            let priority = unsafe {
+                let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+                Some(libc::getpriority(libc::PRIO_PROCESS, tid))
+            };
+            #[cfg(not(target_os = "linux"))]
+            let priority: Option<i32> = None;
+            (applied, priority)
+        })
+        .join()
+        .unwrap();
+
+        #[cfg(target_os = "linux")]
+        assert_eq!(applied, _priority == Some(-10));
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        assert!(!applied);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pins_to_an_available_core() {
+        fn allowed_cores() -> Vec<usize> {
+            unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                assert_eq!(libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set), 0);
+                (0..libc::CPU_SETSIZE as usize).filter(|&core| libc::CPU_ISSET(core, &set)).collect()
+            }
+        }
+
+        // The test may run restricted to some cores, e.g. in a container
+        let core = allowed_cores()[0];
+        let config = CaptureThreadConfig {
+            raise_priority: false,
+            pin_to_core: Some(core),
+        };
+        let (applied, cores) = std::thread::spawn(move || (apply_to_current_thread(&config, "test"), allowed_cores()))
+            .join()
+            .unwrap();
+        assert!(applied);
+        assert_eq!(cores, vec![core]);
+    }
+}
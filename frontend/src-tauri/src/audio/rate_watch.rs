@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// Rates devices switch between, e.g. Bluetooth headsets moving between the
+// call (HFP) and music (A2DP) profiles
+const COMMON_SAMPLE_RATES: &[u32] = &[8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000, 88200, 96000];
+// Windows in a row that must disagree with the stream's rate before it is changed
+const MISMATCHED_WINDOWS: u32 = 2;
+
+/// Settings for noticing a capture device silently switching sample rate
+/// mid-stream, by comparing how much audio arrives with the rate the stream
+/// was opened at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SampleRateChangeConfig {
+    pub enabled: bool,
+    /// Length of each measurement.
+    pub window_secs: u64,
+    /// Deviation from the expected rate, in percent, treated as a rate change.
+    pub tolerance_percent: u32,
+}
+
+impl Default for SampleRateChangeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 2,
+            tolerance_percent: 15,
+        }
+    }
+}
+
+/// Payload of the `audio-sample-rate-changed` event.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SampleRateChange {
+    pub device: String,
+    pub previous_rate: u32,
+    pub new_rate: u32,
+}
+
+/// Measures the rate a stream actually delivers audio at.
+pub struct SampleRateWatcher {
+    config: SampleRateChangeConfig,
+    expected_rate: u32,
+    window_start: Option<Instant>,
+    window_samples: usize,
+    mismatches: u32,
+}
+
+impl SampleRateWatcher {
+    pub fn new(config: SampleRateChangeConfig, expected_rate: u32) -> Self {
+        Self {
+            config,
+            expected_rate,
+            window_start: None,
+            window_samples: 0,
+            mismatches: 0,
+        }
+    }
+
+    pub fn update_config(&mut self, config: SampleRateChangeConfig) {
+        self.config = config;
+    }
+
+    pub fn expected_rate(&self) -> u32 {
+        self.expected_rate
+    }
+
+    /// Starts measuring again, e.g. after the stream was reopened at `expected_rate`.
+    pub fn reset(&mut self, expected_rate: u32) {
+        self.expected_rate = expected_rate;
+        self.window_start = None;
+        self.window_samples = 0;
+        self.mismatches = 0;
+    }
+
+    /// Notes `samples` received at `now`. Returns the stream's new rate once the
+    /// audio has kept arriving at a different rate.
+    pub fn observe(&mut self, samples: usize, now: Instant) -> Option<u32> {
+        if !self.config.enabled {
+            return None;
+        }
+        let Some(window_start) = self.window_start else {
+            // Measure from the first batch, the time before it says nothing about the rate
+            self.window_start = Some(now);
+            return None;
+        };
+        self.window_samples += samples;
+        let elapsed = now.duration_since(window_start);
+        if elapsed < Duration::from_secs(self.config.window_secs.max(1)) {
+            return None;
+        }
+
+        let measured = self.window_samples as f64 / elapsed.as_secs_f64();
+        self.window_start = Some(now);
+        self.window_samples = 0;
+
+        // A stalled stream delivers nothing, which is a disconnect rather than a new rate
+        let deviation = (measured - self.expected_rate as f64).abs() / self.expected_rate.max(1) as f64;
+        if measured < COMMON_SAMPLE_RATES[0] as f64 / 2.0 || deviation * 100.0 < self.config.tolerance_percent as f64 {
+            self.mismatches = 0;
+            return None;
+        }
+        self.mismatches += 1;
+        if self.mismatches < MISMATCHED_WINDOWS {
+            return None;
+        }
+
+        let new_rate = nearest_common_rate(measured);
+        if new_rate == self.expected_rate {
+            self.mismatches = 0;
+            return None;
+        }
+        self.reset(new_rate);
+        Some(new_rate)
+    }
+}
+
+fn nearest_common_rate(measured: f64) -> u32 {
+    COMMON_SAMPLE_RATES
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            (*a as f64 - measured)
+                .abs()
+                .partial_cmp(&(*b as f64 - measured).abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .unwrap_or(measured as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher(expected_rate: u32) -> SampleRateWatcher {
+        let config = SampleRateChangeConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        SampleRateWatcher::new(config, expected_rate)
+    }
+
+    // Feeds one batch per 100 ms from `from_ms` up to `to_ms`, each holding
+    // 100 ms of audio at `rate`, and returns when a new rate was reported
+    fn feed(watcher: &mut SampleRateWatcher, start: Instant, from_ms: u64, to_ms: u64, rate: u32) -> Vec<(u64, u32)> {
+        (from_ms..=to_ms)
+            .step_by(100)
+            .filter_map(|ms| {
+                let now = start + Duration::from_millis(ms);
+                watcher.observe(rate as usize / 10, now).map(|new_rate| (ms, new_rate))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_a_device_that_keeps_delivering_at_another_rate() {
+        let start = Instant::now();
+        let mut watcher = watcher(48000);
+        assert!(feed(&mut watcher, start, 0, 4000, 48000).is_empty());
+
+        // A headset dropping to its call profile: two 2 s windows at 16 kHz
+        assert_eq!(feed(&mut watcher, start, 4100, 8000, 16000), vec![(8000, 16000)]);
+        assert_eq!(watcher.expected_rate(), 16000);
+        assert!(feed(&mut watcher, start, 8100, 14000, 16000).is_empty());
+    }
+
+    #[test]
+    fn ignores_a_single_odd_window_and_a_stalled_stream() {
+        let start = Instant::now();
+        let mut watcher = watcher(48000);
+        assert!(feed(&mut watcher, start, 0, 2000, 48000).is_empty());
+        assert!(feed(&mut watcher, start, 2100, 4000, 16000).is_empty());
+        assert!(feed(&mut watcher, start, 4100, 6000, 48000).is_empty());
+        assert!(feed(&mut watcher, start, 6100, 12000, 0).is_empty());
+        assert_eq!(watcher.expected_rate(), 48000);
+
+        let mut disabled = SampleRateWatcher::new(SampleRateChangeConfig::default(), 48000);
+        assert!(feed(&mut disabled, start, 0, 10000, 16000).is_empty());
+    }
+
+    #[test]
+    fn rounds_a_measured_rate_to_the_nearest_common_one() {
+        assert_eq!(nearest_common_rate(43_870.0), 44100);
+        assert_eq!(nearest_common_rate(15_900.0), 16000);
+    }
+}
@@ -0,0 +1,102 @@
+/// Converts a stream of batches from one sample rate to another by linear
+/// interpolation, carrying the position over between batches so batch edges
+/// don't click or drift.
+pub struct StreamResampler {
+    from_rate: u32,
+    to_rate: u32,
+    // Position of the next output sample, in input samples after `previous`
+    position: f64,
+    previous: Option<f32>,
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            from_rate: from_rate.max(1),
+            to_rate: to_rate.max(1),
+            position: 0.0,
+            previous: None,
+        }
+    }
+
+    pub fn from_rate(&self) -> u32 {
+        self.from_rate
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.from_rate == self.to_rate || input.is_empty() {
+            return input.to_vec();
+        }
+        let step = self.from_rate as f64 / self.to_rate as f64;
+        let mut output = Vec::with_capacity((input.len() as f64 / step) as usize + 1);
+
+        // Index -1 is the last sample of the previous batch
+        let sample_at = |index: isize| -> Option<f32> {
+            if index < 0 {
+                self.previous
+            } else {
+                input.get(index as usize).copied()
+            }
+        };
+        let mut position = if self.previous.is_some() { self.position - 1.0 } else { self.position };
+        loop {
+            let index = position.floor() as isize;
+            let (Some(a), Some(b)) = (sample_at(index), sample_at(index + 1)) else {
+                break;
+            };
+            let fraction = (position - index as f64) as f32;
+            output.push(a + (b - a) * fraction);
+            position += step;
+        }
+
+        // Continue from the last sample of this batch next time
+        self.position = position - (input.len() as f64 - 1.0);
+        self.previous = input.last().copied();
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency_hz: f32, sample_rate: u32, index: usize) -> f32 {
+        (2.0 * std::f32::consts::PI * frequency_hz * index as f32 / sample_rate as f32).sin()
+    }
+
+    #[test]
+    fn converts_a_stream_of_batches_without_drifting() {
+        let mut resampler = StreamResampler::new(44100, 48000);
+        let mut output = Vec::new();
+        for batch in 0..100 {
+            let input: Vec<f32> = (0..441).map(|i| sine(440.0, 44100, batch * 441 + i)).collect();
+            output.extend(resampler.process(&input));
+        }
+        assert!(output.len().abs_diff(48000) <= 1, "{} samples", output.len());
+
+        // Batch edges don't click: every sample stays on the original tone
+        for (index, &sample) in output.iter().enumerate() {
+            let expected = sine(440.0, 48000, index);
+            assert!(
+                (sample - expected).abs() < 0.005,
+                "sample {}: {} vs {}",
+                index,
+                sample,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn a_new_format_gets_its_own_conversion() {
+        let mut same_rate = StreamResampler::new(48000, 48000);
+        assert_eq!(same_rate.process(&[0.1, 0.2, 0.3]), vec![0.1, 0.2, 0.3]);
+
+        // After a device comes back at 16 kHz, its audio still reaches 48 kHz
+        let mut resampler = StreamResampler::new(16000, 48000);
+        assert_eq!(resampler.from_rate(), 16000);
+        let output: Vec<f32> = (0..10).flat_map(|_| resampler.process(&[0.25; 160])).collect();
+        assert!(output.len().abs_diff(4800) <= 3, "{} samples", output.len());
+        assert!(output.iter().all(|&sample| (sample - 0.25).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// A capture source that can be muted or soloed while recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureSource {
+    Microphone,
+    System,
+}
+
+/// Which sources feed transcription. Capture keeps running for gated sources,
+/// their audio is replaced with silence before mixing. A solo overrides mutes.
+/// Payload of the `source-gate-changed` event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceGateState {
+    pub microphone_muted: bool,
+    pub system_muted: bool,
+    pub solo: Option<CaptureSource>,
+}
+
+impl SourceGateState {
+    pub fn feeds_transcription(&self, source: CaptureSource) -> bool {
+        match self.solo {
+            Some(solo) => solo == source,
+            None => match source {
+                CaptureSource::Microphone => !self.microphone_muted,
+                CaptureSource::System => !self.system_muted,
+            },
+        }
+    }
+}
+
+/// Mute and solo state shared between the commands and the collection task.
+pub struct SourceGate {
+    state: Mutex<SourceGateState>,
+}
+
+impl SourceGate {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(SourceGateState {
+                microphone_muted: false,
+                system_muted: false,
+                solo: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> SourceGateState {
+        self.state.lock().map(|state| *state).unwrap_or_default()
+    }
+
+    pub fn set_muted(&self, source: CaptureSource, muted: bool) -> SourceGateState {
+        self.update(|state| match source {
+            CaptureSource::Microphone => state.microphone_muted = muted,
+            CaptureSource::System => state.system_muted = muted,
+        })
+    }
+
+    /// Feeds only `source` to transcription, or every unmuted source for `None`.
+    pub fn set_solo(&self, source: Option<CaptureSource>) -> SourceGateState {
+        self.update(|state| state.solo = source)
+    }
+
+    pub fn reset(&self) -> SourceGateState {
+        self.update(|state| *state = SourceGateState::default())
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SourceGateState)) -> SourceGateState {
+        match self.state.lock() {
+            Ok(mut state) => {
+                change(&mut state);
+                *state
+            }
+            Err(_) => SourceGateState::default(),
+        }
+    }
+}
+
+impl Default for SourceGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn muted_sources_stop_feeding_transcription() {
+        let gate = SourceGate::new();
+        let state = gate.set_muted(CaptureSource::System, true);
+        assert!(state.feeds_transcription(CaptureSource::Microphone));
+        assert!(!state.feeds_transcription(CaptureSource::System));
+        assert_eq!(gate.state(), state);
+    }
+
+    #[test]
+    fn solo_overrides_mutes() {
+        let gate = SourceGate::new();
+        gate.set_muted(CaptureSource::Microphone, true);
+        let state = gate.set_solo(Some(CaptureSource::Microphone));
+        assert!(state.feeds_transcription(CaptureSource::Microphone));
+        assert!(!state.feeds_transcription(CaptureSource::System));
+
+        let state = gate.set_solo(None);
+        assert!(!state.feeds_transcription(CaptureSource::Microphone));
+        assert!(state.feeds_transcription(CaptureSource::System));
+    }
+
+    #[test]
+    fn reset_feeds_every_source() {
+        let gate = SourceGate::new();
+        gate.set_muted(CaptureSource::Microphone, true);
+        gate.set_solo(Some(CaptureSource::System));
+        assert_eq!(gate.reset(), SourceGateState::default());
+        assert!(gate.state().feeds_transcription(CaptureSource::Microphone));
+    }
+}
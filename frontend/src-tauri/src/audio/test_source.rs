@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+// Voice-like fundamental and syllable rate for the speech pattern
+const SPEECH_FUNDAMENTAL_HZ: f64 = 140.0;
+const SYLLABLES_PER_SEC: f64 = 4.0;
+
+/// A synthetic signal played in place of a capture device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TestSignal {
+    Silence,
+    /// A steady sine wave. `amplitude` is linear, 0.0 to 1.0.
+    Tone { frequency_hz: f32, amplitude: f32 },
+    /// Voiced bursts with a syllable rhythm, separated by pauses. Loud enough
+    /// to pass silence detection, but not intelligible to whisper.
+    SpeechPattern { speech_ms: u32, pause_ms: u32, amplitude: f32 },
+}
+
+/// Settings for recording a generated signal instead of the microphone, for
+/// checking the pipeline without audio hardware.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestSourceConfig {
+    pub enabled: bool,
+    pub signal: TestSignal,
+    pub sample_rate: u32,
+}
+
+impl Default for TestSourceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signal: TestSignal::SpeechPattern {
+                speech_ms: 2000,
+                pause_ms: 1000,
+                amplitude: 0.3,
+            },
+            sample_rate: 48000,
+        }
+    }
+}
+
+/// Produces a `TestSignal` as consecutive frames of mono samples.
+pub struct AudioTestGenerator {
+    signal: TestSignal,
+    sample_rate: u32,
+    position: u64,
+}
+
+impl AudioTestGenerator {
+    pub fn new(signal: TestSignal, sample_rate: u32) -> Self {
+        Self {
+            signal,
+            sample_rate: sample_rate.max(1),
+            position: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The next `len` samples of the signal.
+    pub fn next_frame(&mut self, len: usize) -> Vec<f32> {
+        let frame = (0..len as u64)
+            .map(|i| self.sample_at((self.position + i) as f64 / self.sample_rate as f64))
+            .collect();
+        self.position += len as u64;
+        frame
+    }
+
+    fn sample_at(&self, t: f64) -> f32 {
+        match self.signal {
+            TestSignal::Silence => 0.0,
+            TestSignal::Tone { frequency_hz, amplitude } => {
+                amplitude.clamp(0.0, 1.0) * (TAU * frequency_hz as f64 * t).sin() as f32
+            }
+            TestSignal::SpeechPattern { speech_ms, pause_ms, amplitude } => {
+                let period = (speech_ms + pause_ms).max(1) as f64 / 1000.0;
+                let in_period = t % period;
+                if in_period >= speech_ms as f64 / 1000.0 {
+                    return 0.0;
+                }
+                // Fundamental plus two harmonics, opened and closed once per syllable
+                let envelope = 0.5 * (1.0 - (TAU * SYLLABLES_PER_SEC * in_period).cos());
+                let voice = (TAU * SPEECH_FUNDAMENTAL_HZ * t).sin()
+                    + 0.5 * (TAU * 2.0 * SPEECH_FUNDAMENTAL_HZ * t).sin()
+                    + 0.25 * (TAU * 3.0 * SPEECH_FUNDAMENTAL_HZ * t).sin();
+                amplitude.clamp(0.0, 1.0) * (envelope * voice / 1.75) as f32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::audio_processing::rms;
+    use crate::audio::AudioStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn speech_pattern() -> AudioTestGenerator {
+        let signal = TestSignal::SpeechPattern {
+            speech_ms: 1000,
+            pause_ms: 1000,
+            amplitude: 0.3,
+        };
+        AudioTestGenerator::new(signal, 16000)
+    }
+
+    #[tokio::test]
+    async fn a_speech_pattern_stream_alternates_speech_and_silence() {
+        let signal = TestSignal::SpeechPattern {
+            speech_ms: 500,
+            pause_ms: 500,
+            amplitude: 0.3,
+        };
+        let is_running = Arc::new(AtomicBool::new(true));
+        let stream = AudioStream::from_generator(AudioTestGenerator::new(signal, 16000), is_running.clone());
+        let mut receiver = stream.subscribe().await;
+
+        let mut samples = Vec::new();
+        while samples.len() < 24000 {
+            let frame = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            samples.extend(frame);
+        }
+        // The stream may start anywhere in the pattern, but 100 ms windows land
+        // inside both a burst and a pause
+        let windows: Vec<&[f32]> = samples.chunks_exact(1600).collect();
+        assert!(windows.iter().any(|window| rms(window) > 0.05));
+        assert!(windows.iter().any(|window| window.iter().all(|&sample| sample == 0.0)));
+
+        is_running.store(false, Ordering::Relaxed);
+        stream.stop().await.unwrap();
+    }
+
+    #[test]
+    fn frames_continue_where_the_last_one_ended() {
+        let whole = speech_pattern().next_frame(4800);
+        let mut generator = speech_pattern();
+        let pieces: Vec<f32> = (0..10).flat_map(|_| generator.next_frame(480)).collect();
+        assert_eq!(whole, pieces);
+    }
+
+    #[test]
+    fn a_tone_stays_within_its_amplitude() {
+        let signal = TestSignal::Tone {
+            frequency_hz: 440.0,
+            amplitude: 0.25,
+        };
+        let frame = AudioTestGenerator::new(signal, 48000).next_frame(4800);
+        let peak = frame.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak > 0.24 && peak <= 0.25);
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings for placing chunks on the recording's timeline.
+///
+/// By default a chunk is stamped with the time it was cut, which is where its
+/// audio ends. With `preserve_gaps` it is stamped with the time its first
+/// sample was captured instead, so pauses between chunks, including audio lost
+/// while transcription was behind, stay in the transcript timestamps.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkTimingConfig {
+    pub preserve_gaps: bool,
+    /// Moves chunk times back by the input latency the devices report, which
+    /// can be large on Bluetooth headsets. Applies with or without `preserve_gaps`.
+    pub compensate_input_latency: bool,
+}
+
+/// Where a chunk's audio sits on the recording's timeline, in seconds since
+/// recording start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkPlacement {
+    pub start_secs: f64,
+    /// Time between the end of the previous chunk's audio and this chunk's start.
+    pub gap_secs: f64,
+}
+
+/// Tracks the capture time of each chunk's first sample.
+pub struct ChunkClock {
+    config: ChunkTimingConfig,
+    chunk_start: Option<f64>,
+    previous_end: f64,
+    input_latency_secs: f64,
+    timeline_start: f64,
+}
+
+impl ChunkClock {
+    pub fn new(config: ChunkTimingConfig) -> Self {
+        Self {
+            config,
+            chunk_start: None,
+            previous_end: 0.0,
+            input_latency_secs: 0.0,
+            timeline_start: 0.0,
+        }
+    }
+
+    pub fn update_config(&mut self, config: ChunkTimingConfig) {
+        self.config = config;
+    }
+
+    /// Starts the first chunk `secs` before recording start, for audio captured
+    /// ahead of it, so later chunks aren't pushed back by that audio's length.
+    pub fn start_before_recording(&mut self, secs: f64) {
+        self.timeline_start = -secs;
+        self.previous_end = self.timeline_start;
+        self.chunk_start = Some(self.timeline_start);
+    }
+
+    /// Earliest time a chunk can start, before 0 when audio from before recording start leads.
+    pub fn timeline_start(&self) -> f64 {
+        self.timeline_start
+    }
+
+    /// Sets the latency reported by the capture device, used when compensation is enabled.
+    pub fn set_input_latency(&mut self, latency: Option<std::time::Duration>) {
+        self.input_latency_secs = latency.map_or(0.0, |latency| latency.as_secs_f64());
+    }
+
+    /// Seconds capture times are moved back by, 0 unless compensation is enabled.
+    pub fn latency_offset_secs(&self) -> f64 {
+        if self.config.compensate_input_latency {
+            self.input_latency_secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Notes a batch of `samples` collected `elapsed_secs` after recording
+    /// start, before it is added to the chunk being built.
+    pub fn observe(&mut self, elapsed_secs: f64, samples: usize, sample_rate: u32) {
+        if self.chunk_start.is_some() || samples == 0 || sample_rate == 0 {
+            return;
+        }
+        // The batch was captured just before it was collected, or earlier still by the device's latency
+        let batch_start = elapsed_secs - self.latency_offset_secs() - samples as f64 / sample_rate as f64;
+        self.chunk_start = Some(batch_start.max(self.previous_end));
+    }
+
+    /// Ends the current chunk, `duration_secs` of audio before padding. Returns
+    /// its placement, or `None` when gaps aren't preserved.
+    pub fn finish_chunk(&mut self, duration_secs: f64) -> Option<ChunkPlacement> {
+        let start_secs = self.chunk_start.take().unwrap_or(self.previous_end);
+        let gap_secs = start_secs - self.previous_end;
+        self.previous_end = start_secs + duration_secs;
+        self.config.preserve_gaps.then_some(ChunkPlacement { start_secs, gap_secs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(preserve_gaps: bool, compensate_input_latency: bool) -> ChunkTimingConfig {
+        ChunkTimingConfig {
+            preserve_gaps,
+            compensate_input_latency,
+        }
+    }
+
+    #[test]
+    fn chunk_starts_when_its_first_batch_was_captured() {
+        let mut clock = ChunkClock::new(config(true, false));
+        clock.observe(1.5, 8000, 16000);
+        clock.observe(2.0, 8000, 16000);
+        let placement = clock.finish_chunk(1.0).unwrap();
+        assert!((placement.start_secs - 1.0).abs() < 1e-9);
+        assert!((placement.gap_secs - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gaps_are_only_reported_when_preserved() {
+        let mut clock = ChunkClock::new(config(false, false));
+        clock.observe(1.5, 8000, 16000);
+        assert_eq!(clock.finish_chunk(0.5), None);
+    }
+
+    #[test]
+    fn compensation_moves_chunks_back_by_the_input_latency() {
+        let mut clock = ChunkClock::new(config(true, true));
+        clock.set_input_latency(Some(Duration::from_millis(200)));
+        clock.observe(3.0, 16000, 16000);
+        let placement = clock.finish_chunk(1.0).unwrap();
+        assert!((placement.start_secs - 1.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn latency_is_ignored_unless_compensating() {
+        let mut clock = ChunkClock::new(config(true, false));
+        clock.set_input_latency(Some(Duration::from_millis(200)));
+        assert_eq!(clock.latency_offset_secs(), 0.0);
+        clock.observe(3.0, 16000, 16000);
+        assert!((clock.finish_chunk(1.0).unwrap().start_secs - 2.0).abs() < 1e-9);
+
+        clock.update_config(config(false, true));
+        assert!((clock.latency_offset_secs() - 0.2).abs() < 1e-9);
+        clock.set_input_latency(None);
+        assert_eq!(clock.latency_offset_secs(), 0.0);
+    }
+
+    #[test]
+    fn chunks_never_start_before_the_previous_one_ends() {
+        let mut clock = ChunkClock::new(config(true, true));
+        clock.set_input_latency(Some(Duration::from_millis(500)));
+        clock.observe(1.0, 16000, 16000);
+        clock.finish_chunk(1.0);
+        clock.observe(1.2, 1600, 16000);
+        let placement = clock.finish_chunk(0.1).unwrap();
+        assert!((placement.start_secs - 1.0).abs() < 1e-9);
+        assert_eq!(placement.gap_secs, 0.0);
+    }
+
+    #[test]
+    fn speech_after_a_pause_keeps_its_offset() {
+        let mut clock = ChunkClock::new(config(true, false));
+        // One second of speech, cut at 1 s
+        clock.observe(1.0, 16000, 16000);
+        let first = clock.finish_chunk(1.0).unwrap();
+        // Four seconds of silence that never made it into a chunk, then speech again
+        clock.observe(6.0, 16000, 16000);
+        let second = clock.finish_chunk(1.0).unwrap();
+
+        assert!(first.start_secs.abs() < 1e-9);
+        assert!((second.start_secs - 5.0).abs() < 1e-9);
+        assert!((second.gap_secs - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn audio_from_before_recording_start_leads_the_first_chunk() {
+        let mut clock = ChunkClock::new(config(true, false));
+        clock.start_before_recording(2.0);
+        // One second of live audio after two of pre-roll
+        clock.observe(1.0, 16000, 16000);
+        let first = clock.finish_chunk(3.0).unwrap();
+        clock.observe(2.0, 16000, 16000);
+        let second = clock.finish_chunk(1.0).unwrap();
+
+        assert_eq!(clock.timeline_start(), -2.0);
+        assert!((first.start_secs + 2.0).abs() < 1e-9);
+        assert!((second.start_secs - 1.0).abs() < 1e-9);
+        assert_eq!(second.gap_secs, 0.0);
+    }
+}
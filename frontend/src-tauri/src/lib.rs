@@ -11,33 +11,70 @@ pub mod analytics;
 pub mod api;
 pub mod utils;
 pub mod console_utils;
+pub mod transcription;
+pub mod metrics;
+pub mod session_stats;
 
 use audio::{
-    default_input_device, default_output_device, AudioStream,
-    encode_single_audio,
+    default_crash_buffer_path, default_input_device, get_device_and_config, invalidate_device_cache,
+    list_audio_devices_cached, load_calibration_profile, monitor_may_loop, pad_chunk, parse_audio_device,
+    recover_crash_buffer, run_calibration, save_calibration_profile, select_device_with_fallback, AudioDevice,
+    AudioMonitor, AudioStream, AudioTestGenerator, AudioTranscriptionEngine, CalibrationProfile, ChunkClock,
+    ChunkPaddingConfig, Compressor, CrashBuffer, DeviceType, MissingDevicePolicy, NoAudioDevices, PreEmphasis,
+    DEVICE_LIST_MAX_AGE,
+    CaptureForwarder, CaptureQueue, CaptureSource, PrerollBuffer, SampleRateChange, SampleRateWatcher, StreamResampler,
+    SourceBalancer, SourceGate, SourceGateState, StreamOptions, TestSignal, encode_single_audio, audio_processing::rms,
 };
 use ollama::{OllamaModel};
 use analytics::{AnalyticsClient, AnalyticsConfig};
+use metrics::METRICS;
+use transcription::backend::{
+    backend_for_engine, strip_blank_markers, FallbackBackend, ModelEscalationBackend, RawTranscript, RetryReport,
+    TranscriptSegment, TranscriptionBackend, WhisperServerBackend,
+};
+use transcription::overlap::{merge_overlap, TimedWord};
+use transcription::{
+    BatchProgress, BoundaryStrategy, ChunkCoalescing, ChunkDecision, ChunkOutputConfig, ChunkOutputMode, ChunkQueueConfig,
+    ChunkReorderBuffer, ChunkState, ChunkTiming, DeadLetterQueue, DurationBoundary, EnergyDipEndpointing,
+    EnergyEndpointing, ExportSegment, FailedChunk, FileTranscript, FillerFilter, HallucinationLoopConfig,
+    LanguageModelBackend, MeetingTranscript, ModelCompatibility, OfflineProgress, OfflineTranscript, OverlapDetector,
+    OverlapLog, ParagraphConfig,
+    ProcessingEstimate, QualityChange, QualityLevel, QueueOverflowPolicy, RecentFingerprints, RecoveryDedup,
+    RedactionFilter, ReprocessReport, SegmentBoundaryDetector, SilenceAction, SilenceAutoStopper, SilenceTransition,
+    SinkConfidenceConfig, SpeakerTracker, SpeakingTurn, SpeechConfirmationConfig, StatusHeartbeatConfig, TextNormalizer,
+    TranscriptContext, TranscriptSink, TranscriptionConfig, TurnAggregator, WhisperWindowConfig, collapse_loops,
+    finalize_transcript, registered_summarizer, reprocess_failed, starts_paragraph, TICKS_PER_SEC, WHISPER_SAMPLE_RATE,
+};
 use utils::format_timestamp;
 use tauri::{Runtime, AppHandle, Emitter};
 use tauri_plugin_store::StoreExt;
-use log::{info as log_info, error as log_error, debug as log_debug};
-use reqwest::multipart::{Form, Part};
+use log::{info as log_info, error as log_error, debug as log_debug, warn as log_warn};
 use tokio::sync::mpsc;
 
 static RECORDING_FLAG: AtomicBool = AtomicBool::new(false);
+// Set by the reset commands, picked up by the audio collection task of the running session
+static LEVEL_RESET_REQUESTED: AtomicBool = AtomicBool::new(false);
+static CONTEXT_RESET_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SOURCE_GATE: SourceGate = SourceGate::new();
+static DEAD_LETTERS: Mutex<DeadLetterQueue> = Mutex::new(DeadLetterQueue::new());
 static SEQUENCE_COUNTER: AtomicU64 = AtomicU64::new(0);
 static CHUNK_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 static DROPPED_CHUNK_COUNTER: AtomicU64 = AtomicU64::new(0);
+// Set at start when the first response is left out of the timing measurements
+static WARMUP_PENDING: AtomicBool = AtomicBool::new(false);
 static mut MIC_BUFFER: Option<Arc<Mutex<Vec<f32>>>> = None;
 static mut SYSTEM_BUFFER: Option<Arc<Mutex<Vec<f32>>>> = None;
 static mut AUDIO_CHUNK_QUEUE: Option<Arc<Mutex<VecDeque<AudioChunk>>>> = None;
 static mut MIC_STREAM: Option<Arc<AudioStream>> = None;
 static mut SYSTEM_STREAM: Option<Arc<AudioStream>> = None;
+static AUDIO_MONITOR: Mutex<Option<AudioMonitor>> = Mutex::new(None);
+// System audio kept open between recordings, when pre-roll is enabled
+static SYSTEM_PREROLL: Mutex<Option<PrerollBuffer>> = Mutex::new(None);
 static mut IS_RUNNING: Option<Arc<AtomicBool>> = None;
 static mut RECORDING_START_TIME: Option<std::time::Instant> = None;
 static mut TRANSCRIPTION_TASK: Option<tokio::task::JoinHandle<()>> = None;
 static mut AUDIO_COLLECTION_TASK: Option<tokio::task::JoinHandle<()>> = None;
+static AUTOSAVE_TASK: Mutex<Option<tokio::task::JoinHandle<()>>> = Mutex::new(None);
 static mut ANALYTICS_CLIENT: Option<Arc<AnalyticsClient>> = None;
 static mut ERROR_EVENT_EMITTED: bool = false;
 static LAST_TRANSCRIPTION_ACTIVITY: AtomicU64 = AtomicU64::new(0);
@@ -45,14 +82,19 @@ static ACTIVE_WORKERS: AtomicU64 = AtomicU64::new(0);
 
 // Audio configuration constants
 const CHUNK_DURATION_MS: u32 = 30000; // 30 seconds per chunk for better sentence processing
-const WHISPER_SAMPLE_RATE: u32 = 16000; // Whisper's required sample rate
 const WAV_SAMPLE_RATE: u32 = 44100; // WAV file sample rate
+const MIN_STATUS_HEARTBEAT_MS: u64 = 100; // Floor for the status heartbeat interval
 const WAV_CHANNELS: u16 = 2; // Stereo for WAV files
 const WHISPER_CHANNELS: u16 = 1; // Mono for Whisper API
 const SENTENCE_TIMEOUT_MS: u64 = 1000; // Emit incomplete sentence after 1 second of silence
 const MIN_CHUNK_DURATION_MS: u32 = 2000; // Minimum duration before sending chunk
 const MIN_RECORDING_DURATION_MS: u64 = 2000; // 2 seconds minimum
-const MAX_AUDIO_QUEUE_SIZE: usize = 10; // Maximum number of chunks in queue
+const MAX_TRANSCRIPTION_WORKERS: usize = 4; // Upper bound on concurrent whisper requests
+const RECENT_FINGERPRINT_COUNT: usize = 16; // Chunks remembered for duplicate detection
+const SERVER_OVERLAP_TICKS: f32 = 20.0; // Each request starts with the last 200 ms of the previous chunk (10 ms ticks)
+const SERVER_OVERLAP_MS: u32 = 200; // The same overlap in milliseconds
+const MAX_PADDING_MS: u32 = 10; // Padding a chunk past the minimum length adds at most one whisper frame
+const CALIBRATION_AUDIO_MS: u64 = 5000; // Synthetic audio timed when no transcription speed is known yet
 
 // Server configuration constants
 const TRANSCRIPT_SERVER_URL: &str = "http://127.0.0.1:8178";
@@ -67,38 +109,68 @@ struct TranscriptionStatus {
     chunks_in_queue: usize,
     is_processing: bool,
     last_activity_ms: u64,
+    /// Failed chunks kept for `reprocess_failed_chunks`.
+    failed_chunks_kept: usize,
+    /// Seconds of audio transcribed per second, `None` before the first chunk.
+    transcription_speed: Option<f64>,
+    quality_level: QualityLevel,
 }
 
 #[derive(Debug, Serialize, Clone)]
 struct TranscriptUpdate {
     text: String,
+    clean_text: String,
     timestamp: String,
     source: String,
     sequence_id: u64,
     chunk_start_time: f64,
     is_partial: bool,
+    // Provisional "Speaker N" label when speaker hints are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker_hint: Option<String>,
+    // Pause since the previous sentence ended, when gap reporting is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gap_before_ms: Option<u64>,
+    // Set when paragraph breaks are enabled: the sentence follows a speaker change or a long pause
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starts_paragraph: Option<bool>,
+    // Set when a repeated phrase was cut out of the sentence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hallucination_detected: Option<bool>,
+    // Mean word confidence (0..1), when whisper reported word timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    // Set when overlapping speech is flagged: the microphone and system audio both had speech during the sentence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overlapping_speech: Option<bool>,
+    // Seconds since recording start, for grouping sentences into speaking turns
+    #[serde(skip)]
+    start_secs: f64,
+    #[serde(skip)]
+    end_secs: f64,
+}
+
+// Payload of `chunk-ready`, a chunk as whisper would receive it
+#[derive(Debug, Serialize)]
+struct ChunkReady<'a> {
+    chunk_id: u64,
+    timestamp: f64,
+    sample_rate: u32,
+    samples: &'a [f32],
 }
 
 #[derive(Debug, Clone)]
 struct AudioChunk {
     samples: Vec<f32>,
     timestamp: f64,
+    // Time spent preparing the chunk after the cut
+    prepare_time: Duration,
     chunk_id: u64,
     start_time: std::time::Instant,
     recording_start_time: std::time::Instant,
-}
-
-#[derive(Debug, Deserialize)]
-struct TranscriptSegment {
-    text: String,
-    t0: f32,
-    t1: f32,
-}
-
-#[derive(Debug, Deserialize)]
-struct TranscriptResponse {
-    segments: Vec<TranscriptSegment>,
-    buffer_size_ms: i32,
+    // End of the previous chunk, sent ahead of this one so words cut at the
+    // boundary are heard in full. Silence before the first chunk.
+    overlap: Vec<f32>,
 }
 
 // Helper struct to accumulate transcript segments
@@ -106,23 +178,81 @@ struct TranscriptResponse {
 struct TranscriptAccumulator {
     current_sentence: String,
     sentence_start_time: f32,
+    sentence_speaker: Option<u32>,
+    // Whether a hallucination loop was collapsed in the current sentence
+    sentence_looped: bool,
+    // Sum and count of the word confidences in the current sentence
+    sentence_confidence: (f32, usize),
     last_update_time: std::time::Instant,
     last_segment_hash: u64,
     current_chunk_id: u64,
     current_chunk_start_time: f64,
     recording_start_time: Option<std::time::Instant>,
+    filler_filter: FillerFilter,
+    normalizer: TextNormalizer,
+    redaction: RedactionFilter,
+    loops: HallucinationLoopConfig,
 }
 
 impl TranscriptAccumulator {
-    fn new() -> Self {
+    fn new(config: &TranscriptionConfig) -> Self {
         Self {
             current_sentence: String::new(),
             sentence_start_time: 0.0,
+            sentence_speaker: None,
+            sentence_looped: false,
+            sentence_confidence: (0.0, 0),
             last_update_time: std::time::Instant::now(),
             last_segment_hash: 0,
             current_chunk_id: 0,
             current_chunk_start_time: 0.0,
             recording_start_time: None,
+            filler_filter: FillerFilter::new(config.filler_filter.clone()),
+            normalizer: TextNormalizer::new(config.text_normalization.clone()),
+            redaction: RedactionFilter::new(config.redaction.clone()),
+            loops: config.hallucination_loops.clone(),
+        }
+    }
+
+    // Writes spoken numbers as digits, then redacts the finished sentence and derives
+    // the filler-free variant from it. Normalizing first lets redaction see the digits.
+    fn finish_sentence(&self, sentence: &str) -> (String, String) {
+        let normalized = self.normalizer.normalize(sentence.trim());
+        let (text, redactions) = self.redaction.redact(&normalized);
+        if redactions > 0 {
+            log_info!(chunk_id = self.current_chunk_id; "Redacted {} sensitive match(es)", redactions);
+            METRICS.record_redactions(redactions);
+        }
+        let clean_text = self.filler_filter.clean(&text);
+        (text, clean_text)
+    }
+
+    fn sentence_confidence(&self) -> Option<f32> {
+        let (sum, count) = self.sentence_confidence;
+        (count > 0).then(|| sum / count as f32)
+    }
+
+    // Ends the current sentence and turns it into the next update in sequence.
+    // `start_secs` and `end_secs` are seconds since the recording started.
+    fn take_sentence(&mut self, start_secs: f64, end_secs: f64, is_partial: bool) -> TranscriptUpdate {
+        let sentence = std::mem::take(&mut self.current_sentence);
+        let (text, clean_text) = self.finish_sentence(&sentence);
+        TranscriptUpdate {
+            clean_text,
+            text,
+            timestamp: format_timestamp(start_secs),
+            source: "Mixed Audio".to_string(),
+            sequence_id: SEQUENCE_COUNTER.fetch_add(1, Ordering::SeqCst),
+            chunk_start_time: self.current_chunk_start_time,
+            is_partial,
+            speaker_hint: self.sentence_speaker.take().map(|id| format!("Speaker {}", id)),
+            gap_before_ms: None,
+            starts_paragraph: None,
+            hallucination_detected: self.sentence_looped.then_some(true),
+            confidence: self.sentence_confidence(),
+            overlapping_speech: None,
+            start_secs,
+            end_secs,
         }
     }
 
@@ -134,20 +264,28 @@ impl TranscriptAccumulator {
     }
 
     fn add_segment(&mut self, segment: &TranscriptSegment) -> Option<TranscriptUpdate> {
-        log_info!("Processing new transcript segment: {:?}", segment);
+        // Segment text isn't redacted yet, so it stays out of the info log
+        log_debug!(chunk_id = self.current_chunk_id; "Processing new transcript segment: {:?}", segment);
         
         // Update the last update time
         self.last_update_time = std::time::Instant::now();
 
         // Clean up the text (remove [BLANK_AUDIO], [AUDIO OUT] and trim)
-        let clean_text = segment.text
-            .replace("[BLANK_AUDIO]", "")
-            .replace("[AUDIO OUT]", "")
-            .trim()
-            .to_string();
+        let mut clean_text = strip_blank_markers(&segment.text);
+        
+        // Whisper can get stuck repeating a phrase; keep one copy of it
+        let mut looped = false;
+        if self.loops.enabled {
+            if let Some(collapsed) = collapse_loops(&clean_text, &self.loops) {
+                log_warn!(chunk_id = self.current_chunk_id; "Collapsed repeated phrase in segment");
+                log_debug!(chunk_id = self.current_chunk_id; "Repeated phrase was: {}", clean_text);
+                clean_text = collapsed;
+                looped = true;
+            }
+        }
             
         if !clean_text.is_empty() {
-            log_info!("Clean transcript text: {}", clean_text);
+            log_debug!(chunk_id = self.current_chunk_id; "Clean transcript text: {}", clean_text);
         }
 
         // Skip empty segments or very short segments (less than 1 second)
@@ -166,14 +304,19 @@ impl TranscriptAccumulator {
 
         // Skip if this is a duplicate segment
         if segment_hash == self.last_segment_hash {
-            log_info!("Skipping duplicate segment: {}", clean_text);
+            log_debug!(chunk_id = self.current_chunk_id; "Skipping duplicate segment: {}", clean_text);
             return None;
         }
         self.last_segment_hash = segment_hash;
 
-        // If this is the start of a new sentence, store the start time
+        // If this is the start of a new sentence, store the start time and speaker
         if self.current_sentence.is_empty() {
             self.sentence_start_time = segment.t0;
+            self.sentence_speaker = segment.speaker;
+            self.sentence_looped = false;
+            self.sentence_confidence = (0.0, 0);
+        } else if self.sentence_speaker.is_none() {
+            self.sentence_speaker = segment.speaker;
         }
 
         // Add the new text with proper spacing
@@ -181,37 +324,32 @@ impl TranscriptAccumulator {
             self.current_sentence.push(' ');
         }
         self.current_sentence.push_str(&clean_text);
+        self.sentence_looped |= looped;
+        for word in &segment.words {
+            self.sentence_confidence.0 += word.p;
+            self.sentence_confidence.1 += 1;
+        }
 
         // Check if we have a complete sentence (including common sentence endings)
         let has_sentence_ending = clean_text.ends_with('.') || clean_text.ends_with('?') || clean_text.ends_with('!') ||
                                   clean_text.ends_with("...") || clean_text.ends_with(".\"") || clean_text.ends_with(".'");
         
         if has_sentence_ending {
-            let sentence = std::mem::take(&mut self.current_sentence);
-            let sequence_id = SEQUENCE_COUNTER.fetch_add(1, Ordering::SeqCst);
-            
             // Calculate actual elapsed time from recording start
             let (start_elapsed, end_elapsed) = if let Some(recording_start) = self.recording_start_time {
                 // Calculate when this sentence actually started and ended relative to recording start
-                let sentence_start_elapsed = self.current_chunk_start_time + (self.sentence_start_time as f64 / 1000.0);
-                let sentence_end_elapsed = self.current_chunk_start_time + (segment.t1 as f64 / 1000.0);
+                let sentence_start_elapsed = self.current_chunk_start_time + chunk_secs(self.sentence_start_time);
+                let sentence_end_elapsed = self.current_chunk_start_time + chunk_secs(segment.t1);
                 (sentence_start_elapsed.max(0.0), sentence_end_elapsed.max(0.0))
             } else {
                 // Fallback to chunk-relative times if recording start time not available
-                let sentence_start_elapsed = self.current_chunk_start_time + (self.sentence_start_time as f64 / 1000.0);
-                let sentence_end_elapsed = self.current_chunk_start_time + (segment.t1 as f64 / 1000.0);
+                let sentence_start_elapsed = self.current_chunk_start_time + chunk_secs(self.sentence_start_time);
+                let sentence_end_elapsed = self.current_chunk_start_time + chunk_secs(segment.t1);
                 (sentence_start_elapsed.max(0.0), sentence_end_elapsed.max(0.0))
             };
             
-            let update = TranscriptUpdate {
-                text: sentence.trim().to_string(),
-                timestamp: format!("{}", format_timestamp(start_elapsed)),
-                source: "Mixed Audio".to_string(),
-                sequence_id,
-                chunk_start_time: self.current_chunk_start_time,
-                is_partial: false,
-            };
-            log_info!("Generated transcript update: {:?}", update);
+            let update = self.take_sentence(start_elapsed, end_elapsed, false);
+            log_info!(chunk_id = self.current_chunk_id; "Generated transcript update: {:?}", update);
             Some(update)
         } else {
             None
@@ -221,30 +359,20 @@ impl TranscriptAccumulator {
     fn check_timeout(&mut self) -> Option<TranscriptUpdate> {
         if !self.current_sentence.is_empty() && 
            self.last_update_time.elapsed() > Duration::from_millis(SENTENCE_TIMEOUT_MS) {
-            let sentence = std::mem::take(&mut self.current_sentence);
-            let sequence_id = SEQUENCE_COUNTER.fetch_add(1, Ordering::SeqCst);
-            
             // Calculate actual elapsed time from recording start for timeout
             let (start_elapsed, end_elapsed) = if let Some(recording_start) = self.recording_start_time {
                 // For timeout, we know the sentence started at sentence_start_time and is timing out now
-                let sentence_start_elapsed = self.current_chunk_start_time + (self.sentence_start_time as f64 / 1000.0);
+                let sentence_start_elapsed = self.current_chunk_start_time + chunk_secs(self.sentence_start_time);
                 let sentence_end_elapsed = sentence_start_elapsed + (SENTENCE_TIMEOUT_MS as f64 / 1000.0);
                 (sentence_start_elapsed.max(0.0), sentence_end_elapsed.max(0.0))
             } else {
                 // Fallback to chunk-relative times
-                let sentence_start_elapsed = self.current_chunk_start_time + (self.sentence_start_time as f64 / 1000.0);
+                let sentence_start_elapsed = self.current_chunk_start_time + chunk_secs(self.sentence_start_time);
                 let sentence_end_elapsed = sentence_start_elapsed + (SENTENCE_TIMEOUT_MS as f64 / 1000.0);
                 (sentence_start_elapsed.max(0.0), sentence_end_elapsed.max(0.0))
             };
             
-            let update = TranscriptUpdate {
-                text: sentence.trim().to_string(),
-                timestamp: format!("{}", format_timestamp(start_elapsed)),
-                source: "Mixed Audio".to_string(),
-                sequence_id,
-                chunk_start_time: self.current_chunk_start_time,
-                is_partial: true,
-            };
+            let update = self.take_sentence(start_elapsed, end_elapsed, true);
             Some(update)
         } else {
             None
@@ -252,111 +380,1037 @@ impl TranscriptAccumulator {
     }
 }
 
-async fn audio_collection_task<R: Runtime>(
+// Transcribed segments for one chunk, waiting to be released in chunk order
+#[derive(Debug)]
+struct ChunkTranscript {
+    chunk_id: u64,
+    timestamp: f64,
+    recording_start_time: std::time::Instant,
+    // Length of the audio whisper saw for this chunk, in segment time units
+    audio_ticks: f32,
+    segments: Vec<TranscriptSegment>,
+}
+
+// Shared by all transcription workers so segments reach the accumulator in chunk
+// order even though chunks are transcribed concurrently
+struct TranscriptEmitter {
+    accumulator: TranscriptAccumulator,
+    reorder: ChunkReorderBuffer<ChunkTranscript>,
+    // Recent sentences passed to whisper as the prompt, when enabled
+    context: Option<TranscriptContext>,
+    // Catches the same audio being queued twice, e.g. after a stream restart
+    recent_fingerprints: RecentFingerprints,
+    // Words at the end of the last chunk, held back to merge with the start of the next one
+    overlap_tail: Vec<TimedWord>,
+    overlap_tail_chunk: Option<u64>,
+    // Offset from the held-back chunk's timeline to the next chunk's
+    overlap_tail_shift: f32,
+    // Provisional speaker labels, when enabled
+    speakers: Option<SpeakerTracker>,
+    // Sentences grouped into speaking turns, when enabled
+    turns: Option<TurnAggregator>,
+    report_gaps: bool,
+    // End of the last emitted sentence, in seconds since recording start
+    last_sentence_end: Option<f64>,
+    // Drops sentences transcribed twice around a stream recovery, when enabled
+    recovery_dedup: RecoveryDedup,
+    paragraphs: ParagraphConfig,
+    // Source and speaker hint of the last emitted sentence
+    last_speaker: Option<(String, Option<String>)>,
+    // Every emitted sentence, kept for the summarizer or autosave when either is used
+    transcript: Option<MeetingTranscript>,
+    // Times both sources had speech, when overlapping speech is flagged
+    overlaps: Option<OverlapLog>,
+    sink_confidence: SinkConfidenceConfig,
+}
+
+impl TranscriptEmitter {
+    fn new(first_chunk_id: u64, config: &TranscriptionConfig) -> Self {
+        Self {
+            accumulator: TranscriptAccumulator::new(config),
+            reorder: ChunkReorderBuffer::new(
+                first_chunk_id,
+                config.chunk_queue.max_queued_chunks + MAX_TRANSCRIPTION_WORKERS,
+            ),
+            context: config
+                .prompt_context
+                .enabled
+                .then(|| TranscriptContext::new(config.prompt_context.max_chars, config.prompt_context.max_tokens)),
+            recent_fingerprints: RecentFingerprints::new(RECENT_FINGERPRINT_COUNT),
+            overlap_tail: Vec::new(),
+            overlap_tail_chunk: None,
+            overlap_tail_shift: 0.0,
+            speakers: config
+                .speaker_hints
+                .enabled
+                .then(|| SpeakerTracker::new(config.speaker_hints.clone())),
+            turns: config
+                .speaking_turns
+                .enabled
+                .then(|| TurnAggregator::new(config.speaking_turns.clone())),
+            report_gaps: config.gap_reporting.enabled,
+            last_sentence_end: None,
+            recovery_dedup: RecoveryDedup::new(config.recovery_dedup.clone()),
+            paragraphs: config.paragraphs.clone(),
+            last_speaker: None,
+            transcript: (registered_summarizer().is_some() || config.autosave.enabled).then(MeetingTranscript::default),
+            overlaps: config.overlapping_speech.enabled.then(OverlapLog::default),
+            sink_confidence: config.sink_confidence.clone(),
+        }
+    }
+
+    // Picks up settings changed while recording; sentence and ordering state is kept
+    fn apply_config(&mut self, config: &TranscriptionConfig) {
+        self.accumulator.filler_filter = FillerFilter::new(config.filler_filter.clone());
+        self.accumulator.normalizer = TextNormalizer::new(config.text_normalization.clone());
+        self.accumulator.redaction = RedactionFilter::new(config.redaction.clone());
+        self.accumulator.loops = config.hallucination_loops.clone();
+        if !config.prompt_context.enabled {
+            self.context = None;
+        } else if let Some(context) = self.context.as_mut() {
+            context.set_limits(config.prompt_context.max_chars, config.prompt_context.max_tokens);
+        } else {
+            self.context = Some(TranscriptContext::new(
+                config.prompt_context.max_chars,
+                config.prompt_context.max_tokens,
+            ));
+        }
+        if !config.speaker_hints.enabled {
+            self.speakers = None;
+        } else if let Some(speakers) = self.speakers.as_mut() {
+            speakers.update_config(config.speaker_hints.clone());
+        } else {
+            self.speakers = Some(SpeakerTracker::new(config.speaker_hints.clone()));
+        }
+        if !config.speaking_turns.enabled {
+            self.turns = None;
+        } else if let Some(turns) = self.turns.as_mut() {
+            turns.update_config(config.speaking_turns.clone());
+        } else {
+            self.turns = Some(TurnAggregator::new(config.speaking_turns.clone()));
+        }
+        self.report_gaps = config.gap_reporting.enabled;
+        self.recovery_dedup.update_config(config.recovery_dedup.clone());
+        self.paragraphs = config.paragraphs.clone();
+        if !config.overlapping_speech.enabled {
+            self.overlaps = None;
+        } else if self.overlaps.is_none() {
+            self.overlaps = Some(OverlapLog::default());
+        }
+        self.sink_confidence = config.sink_confidence.clone();
+    }
+
+    fn note_recovery(&mut self, at_secs: f64) {
+        self.recovery_dedup.note_recovery(at_secs);
+    }
+
+    fn note_overlap(&mut self, start_secs: f64, end_secs: f64) {
+        if let Some(overlaps) = self.overlaps.as_mut() {
+            overlaps.push(start_secs, end_secs);
+        }
+    }
+
+    fn is_duplicate(&mut self, samples: &[f32]) -> bool {
+        self.recent_fingerprints
+            .check_and_insert(transcription::fingerprint::fingerprint(samples))
+    }
+
+    // Drops the prompt history; transcript ordering and overlap merging are unaffected
+    fn reset_context(&mut self) {
+        if let Some(context) = self.context.as_mut() {
+            context.clear();
+        }
+    }
+
+    fn prompt(&self) -> Option<String> {
+        self.context.as_ref().and_then(|context| context.prompt())
+    }
+
+    fn remember(&mut self, update: &TranscriptUpdate) {
+        if let Some(context) = self.context.as_mut() {
+            context.push(&update.text);
+        }
+    }
+
+    // Sends a finished sentence to the UI and adds it to the current speaking turn
+    fn emit_update<R: Runtime>(&mut self, mut update: TranscriptUpdate, app_handle: &AppHandle<R>) {
+        if self.recovery_dedup.is_duplicate(&update.text, update.start_secs, update.end_secs) {
+            log_info!(chunk_id = self.accumulator.current_chunk_id; "Dropping sentence repeated after stream recovery: {}", update.text);
+            return;
+        }
+
+        let gap_secs = self.record_gap(&mut update);
+        let speaker = (update.source.clone(), update.speaker_hint.clone());
+        let speaker_changed = self.last_speaker.as_ref() != Some(&speaker);
+        self.last_speaker = Some(speaker);
+        if self.paragraphs.enabled {
+            update.starts_paragraph = Some(starts_paragraph(&self.paragraphs, gap_secs, speaker_changed));
+        }
+        if let Some(overlaps) = self.overlaps.as_ref() {
+            update.overlapping_speech = Some(overlaps.intersects(update.start_secs, update.end_secs));
+        }
+
+        self.remember(&update);
+        // Each sink has its own minimum confidence
+        let to_live = self.sink_confidence.accepts(TranscriptSink::Live, update.confidence);
+        let to_saved = self.sink_confidence.accepts(TranscriptSink::SavedTranscript, update.confidence);
+        let to_turns = self.sink_confidence.accepts(TranscriptSink::SpeakingTurns, update.confidence);
+        if let Some(transcript) = self.transcript.as_mut().filter(|_| to_saved) {
+            transcript.sentences.push(ExportSegment {
+                text: update.text.clone(),
+                start: update.start_secs,
+                end: Some(update.end_secs),
+                speaker: update.speaker_hint.clone(),
+            });
+        }
+        if to_live {
+            log_info!(chunk_id = self.accumulator.current_chunk_id; "Emitting transcript-update event with sequence_id: {}", update.sequence_id);
+            if let Err(e) = app_handle.emit("transcript-update", &update) {
+                log_error!(chunk_id = self.accumulator.current_chunk_id; "Failed to emit transcript update: {}", e);
+            } else {
+                METRICS.record_transcript_update();
+            }
+        } else {
+            log_debug!(chunk_id = self.accumulator.current_chunk_id; "Withholding low-confidence sentence {} from the live transcript", update.sequence_id);
+        }
+        if !to_turns {
+            return;
+        }
+
+        let finished_turn = self.turns.as_mut().and_then(|turns| {
+            turns.push(
+                &update.source,
+                update.speaker_hint.as_deref(),
+                &update.text,
+                update.sequence_id,
+                update.start_secs,
+                update.end_secs,
+            )
+        });
+        if let Some(turn) = finished_turn {
+            emit_turn(&turn, app_handle);
+        }
+    }
+
+    // Seconds of silence before `update`, also set as its `gap_before_ms` when gaps are reported
+    fn record_gap(&mut self, update: &mut TranscriptUpdate) -> f64 {
+        // The first sentence has no pause before it
+        let gap_secs = self.last_sentence_end.map_or(0.0, |end| (update.start_secs - end).max(0.0));
+        self.last_sentence_end = Some(self.last_sentence_end.map_or(update.end_secs, |end| end.max(update.end_secs)));
+        if self.report_gaps {
+            update.gap_before_ms = Some((gap_secs * 1000.0).round() as u64);
+        }
+        gap_secs
+    }
+
+    // Ends a speaking turn nobody has added to for a while
+    fn flush_idle_turn<R: Runtime>(&mut self, app_handle: &AppHandle<R>) {
+        if let Some(turn) = self.turns.as_mut().and_then(|turns| turns.flush_idle()) {
+            emit_turn(&turn, app_handle);
+        }
+    }
+
+    fn finish_turn<R: Runtime>(&mut self, app_handle: &AppHandle<R>) {
+        if let Some(turn) = self.turns.as_mut().and_then(|turns| turns.finish()) {
+            emit_turn(&turn, app_handle);
+        }
+    }
+
+    fn complete<R: Runtime>(&mut self, chunk: ChunkTranscript, app_handle: &AppHandle<R>) {
+        let ready = self.reorder.complete(chunk.chunk_id, chunk);
+        self.emit_ready(ready, app_handle);
+    }
+
+    fn skip<R: Runtime>(&mut self, chunk_id: u64, app_handle: &AppHandle<R>) {
+        let ready = self.reorder.skip(chunk_id);
+        self.emit_ready(ready, app_handle);
+    }
+
+    fn flush<R: Runtime>(&mut self, app_handle: &AppHandle<R>) {
+        let ready = self.reorder.flush();
+        self.emit_ready(ready, app_handle);
+        self.release_overlap_tail(app_handle);
+    }
+
+    fn emit_ready<R: Runtime>(&mut self, chunks: Vec<ChunkTranscript>, app_handle: &AppHandle<R>) {
+        for chunk in chunks {
+            let has_words = chunk.segments.iter().all(|segment| !segment.words.is_empty() || segment.text.trim().is_empty());
+            let follows_tail = self.overlap_tail_chunk.is_some_and(|tail_chunk| tail_chunk + 1 == chunk.chunk_id);
+            // The held-back words can only be merged into the chunk right after them
+            if !has_words || !follows_tail {
+                self.release_overlap_tail(app_handle);
+            }
+
+            self.accumulator.set_chunk_context(chunk.chunk_id, chunk.timestamp, chunk.recording_start_time);
+            let segments = if has_words {
+                self.merge_overlap(chunk.chunk_id, chunk.audio_ticks, chunk.segments)
+            } else {
+                chunk.segments
+            };
+            self.emit_segments(chunk.chunk_id, segments, app_handle);
+        }
+    }
+
+    // Replaces the start of this chunk with the confidence-weighted merge of the
+    // previous chunk's tail, and holds back this chunk's own tail for the next one
+    fn merge_overlap(&mut self, chunk_id: u64, audio_ticks: f32, segments: Vec<TranscriptSegment>) -> Vec<TranscriptSegment> {
+        let voices: Vec<_> = segments.iter().map(|segment| segment.voice).collect();
+        let mut words: Vec<(usize, TimedWord)> = segments
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, segment)| segment.words.into_iter().map(move |word| (index, word)))
+            .collect();
+
+        let tail = std::mem::take(&mut self.overlap_tail);
+        self.overlap_tail_chunk = None;
+        if !tail.is_empty() {
+            let head_len = words.iter().take_while(|(_, word)| word.t0 < SERVER_OVERLAP_TICKS).count();
+            let head: Vec<TimedWord> = words.drain(..head_len).map(|(_, word)| word).collect();
+            let shifted_tail: Vec<TimedWord> = tail
+                .into_iter()
+                .map(|word| TimedWord {
+                    t0: (word.t0 - self.overlap_tail_shift).max(0.0),
+                    t1: (word.t1 - self.overlap_tail_shift).max(0.0),
+                    ..word
+                })
+                .collect();
+            let merged = merge_overlap(&shifted_tail, &head);
+            log_debug!(chunk_id = chunk_id; "Merged {} overlapping words into {}", shifted_tail.len() + head.len(), merged.len());
+            let first_index = words.first().map_or(0, |(index, _)| *index);
+            words.splice(0..0, merged.into_iter().map(|word| (first_index, word)));
+        }
+
+        let tail_start = audio_ticks - SERVER_OVERLAP_TICKS;
+        let tail_len = words.iter().rev().take_while(|(_, word)| word.t0 >= tail_start).count();
+        if tail_len > 0 {
+            self.overlap_tail = words.drain(words.len() - tail_len..).map(|(_, word)| word).collect();
+            self.overlap_tail_chunk = Some(chunk_id);
+            self.overlap_tail_shift = tail_start;
+        }
+
+        // Rebuilt segments keep the voice measured for the segment their words came from
+        let rebuild = |index: Option<usize>, words: Vec<TimedWord>| {
+            TranscriptSegment::from_words(words).map(|mut segment| {
+                segment.voice = index.and_then(|index| voices.get(index).copied().flatten());
+                segment
+            })
+        };
+        let mut merged_segments = Vec::new();
+        let mut current_index = None;
+        let mut current_words = Vec::new();
+        for (index, word) in words {
+            if current_index != Some(index) && !current_words.is_empty() {
+                merged_segments.extend(rebuild(current_index, std::mem::take(&mut current_words)));
+            }
+            current_index = Some(index);
+            current_words.push(word);
+        }
+        merged_segments.extend(rebuild(current_index, current_words));
+        merged_segments
+    }
+
+    // Emits held-back words on their own, when no following chunk will overlap them
+    fn release_overlap_tail<R: Runtime>(&mut self, app_handle: &AppHandle<R>) {
+        if let Some(chunk_id) = self.overlap_tail_chunk.take() {
+            let tail = std::mem::take(&mut self.overlap_tail);
+            if let Some(segment) = TranscriptSegment::from_words(tail) {
+                self.emit_segments(chunk_id, vec![segment], app_handle);
+            }
+        }
+    }
+
+    fn emit_segments<R: Runtime>(&mut self, chunk_id: u64, segments: Vec<TranscriptSegment>, app_handle: &AppHandle<R>) {
+        for mut segment in segments {
+            if let (Some(speakers), Some(voice)) = (self.speakers.as_mut(), segment.voice.as_ref()) {
+                segment.speaker = Some(speakers.assign(voice));
+                log_debug!(chunk_id = chunk_id; "Segment assigned to speaker {:?} ({} speakers so far)", segment.speaker, speakers.speaker_count());
+            }
+
+            log_debug!(chunk_id = chunk_id; "Processing segment: {} ({} - {})",
+                     segment.text.trim(), format_timestamp(chunk_secs(segment.t0)), format_timestamp(chunk_secs(segment.t1)));
+
+            // Add segment to accumulator and check for complete sentence
+            if let Some(update) = self.accumulator.add_segment(&segment) {
+                self.emit_update(update, app_handle);
+            }
+        }
+    }
+}
+
+// Runs the registered summarizer on a finished transcript and sends both to the UI
+async fn summarize_transcript<R: Runtime>(transcript: MeetingTranscript, app_handle: AppHandle<R>) {
+    let Some(summarizer) = registered_summarizer() else {
+        return;
+    };
+    log_info!("Summarizing {} transcript sentences", transcript.sentences.len());
+    let finalized = finalize_transcript(summarizer.as_ref(), transcript).await;
+    if let Err(e) = app_handle.emit("transcript-finalized", &finalized) {
+        log_error!("Failed to emit transcript-finalized event: {}", e);
+    }
+}
+
+fn emit_rate_change<R: Runtime>(app_handle: &AppHandle<R>, device: &str, previous_rate: u32, new_rate: u32) {
+    log_info!("{} switched from {} Hz to {} Hz, resampling from the new rate", device, previous_rate, new_rate);
+    let change = SampleRateChange {
+        device: device.to_string(),
+        previous_rate,
+        new_rate,
+    };
+    if let Err(e) = app_handle.emit("audio-sample-rate-changed", &change) {
+        log_error!("Failed to emit audio-sample-rate-changed event: {}", e);
+    }
+}
+
+fn emit_turn<R: Runtime>(turn: &SpeakingTurn, app_handle: &AppHandle<R>) {
+    log_debug!("Speaking turn {:.1}s - {:.1}s with {} sentences", turn.start, turn.end, turn.sequence_ids.len());
+    if let Err(e) = app_handle.emit("speaking-turn-completed", turn) {
+        log_error!("Failed to emit speaking turn: {}", e);
+    }
+}
+
+// Seconds into the chunk's own audio of a segment time. Segment times count from
+// the start of the previous chunk's end sent ahead of the chunk.
+fn chunk_secs(ticks: f32) -> f64 {
+    ((ticks as f64 - SERVER_OVERLAP_TICKS as f64) / TICKS_PER_SEC).max(0.0)
+}
+
+// Voice features of the audio under a segment. Segment times include the end of
+// the previous chunk sent ahead of this one, which isn't in `audio`.
+fn segment_voice(audio: &[f32], t0: f32, t1: f32) -> Option<transcription::VoiceFeatures> {
+    let samples_per_tick = WHISPER_SAMPLE_RATE as f32 / TICKS_PER_SEC as f32;
+    let start = (((t0 - SERVER_OVERLAP_TICKS) * samples_per_tick).max(0.0) as usize).min(audio.len());
+    let end = (((t1 - SERVER_OVERLAP_TICKS) * samples_per_tick).max(0.0) as usize).min(audio.len());
+    transcription::speakers::voice_features(&audio[start..end.max(start)], WHISPER_SAMPLE_RATE)
+}
+
+// Mixes the sources with the given (mic, system) weights, padding the shorter one
+// with silence. Sources `gate` keeps out of transcription are mixed in as silence.
+fn mix_sources(mic: &[f32], system: &[f32], weights: (f32, f32), gate: Option<&SourceGateState>) -> Vec<f32> {
+    let feeds = |source| match gate {
+        Some(gate) => gate.feeds_transcription(source),
+        None => true,
+    };
+    let mic_weight = if feeds(CaptureSource::Microphone) { weights.0 } else { 0.0 };
+    let system_weight = if feeds(CaptureSource::System) { weights.1 } else { 0.0 };
+    (0..mic.len().max(system.len()))
+        .map(|i| {
+            let mic_sample = mic.get(i).copied().unwrap_or(0.0);
+            let system_sample = system.get(i).copied().unwrap_or(0.0);
+            mic_sample * mic_weight + system_sample * system_weight
+        })
+        .collect()
+}
+
+// Number of concurrent transcription workers, bounded to keep memory and server load in check
+fn transcription_worker_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, MAX_TRANSCRIPTION_WORKERS)
+}
+
+// Where a chunk cut now began, in seconds since the recording started, with
+// the capture latency taken off. Anchored to the recording start, which the
+// collection task may have started well after. No earlier than `earliest_secs`,
+// which is before 0 when pre-roll leads the first chunk.
+fn fallback_chunk_start(
+    since_recording_start: Duration,
+    latency_offset_secs: f64,
+    chunk_duration: f64,
+    earliest_secs: f64,
+) -> f64 {
+    let cut_secs = since_recording_start.as_secs_f64() - latency_offset_secs;
+    (cut_secs - chunk_duration).max(earliest_secs)
+}
+
+// (mic, system) weights sources are mixed with, evenly once they're balanced
+fn mix_weights(balancer: &SourceBalancer) -> (f32, f32) {
+    if balancer.is_enabled() { (0.5, 0.5) } else { (0.8, 0.2) }
+}
+
+// The start of the first chunk from system audio captured before recording
+// start, mixed and gated like live system audio. The chunk clock starts that
+// far before recording start, so later chunks keep their place on the timeline.
+fn preroll_chunk(
+    preroll: &[f32],
+    weights: (f32, f32),
+    gate: &SourceGateState,
+    chunk_clock: &mut ChunkClock,
+    sample_rate: u32,
+) -> Vec<f32> {
+    chunk_clock.start_before_recording(preroll.len() as f64 / sample_rate as f64);
+    mix_sources(&[], preroll, weights, Some(gate))
+}
+
+// Longest chunk, in samples at `sample_rate`, that fits whisper's window
+// together with the overlap sent ahead of it and padding
+fn max_window_samples(config: &WhisperWindowConfig, sample_rate: u32) -> usize {
+    let budget_ms = config.window_ms.saturating_sub(SERVER_OVERLAP_MS + MAX_PADDING_MS).max(MIN_CHUNK_DURATION_MS);
+    (sample_rate as u64 * budget_ms as u64 / 1000) as usize
+}
+
+// Splits off the audio in `chunk` past whisper's window, to start the next chunk
+fn split_at_window(chunk: &mut Vec<f32>, config: &WhisperWindowConfig, sample_rate: u32) -> Option<Vec<f32>> {
+    let window_samples = max_window_samples(config, sample_rate);
+    (config.split_long_chunks && chunk.len() > window_samples).then(|| chunk.split_off(window_samples))
+}
+
+// Longest chunk the chunking settings can produce, before the overlap sent ahead of it
+fn longest_chunk_ms(config: &TranscriptionConfig) -> u64 {
+    let mut longest = CHUNK_DURATION_MS as u64;
+    if config.chunk_coalescing.enabled {
+        longest += config.chunk_coalescing.max_delay_ms;
+    }
+    if config.system_preroll.enabled {
+        longest += config.system_preroll.seconds as u64 * 1000;
+    }
+    longest
+}
+
+// Wraps `backend` with the fallback server and the larger escalation model, when configured
+fn with_backup_servers(mut backend: Box<dyn TranscriptionBackend>, config: &TranscriptionConfig) -> Box<dyn TranscriptionBackend> {
+    let fallback_config = &config.fallback_server;
+    if fallback_config.enabled {
+        log_info!("Falling back to the transcription server at {} when the primary is unavailable", fallback_config.server_url);
+        backend = Box::new(FallbackBackend::new(
+            backend,
+            Box::new(WhisperServerBackend::new(&fallback_config.server_url)),
+            Duration::from_secs(fallback_config.primary_retry_secs),
+        ));
+    }
+    let escalation_config = &config.model_escalation;
+    if escalation_config.enabled {
+        log_info!("Re-transcribing low-confidence chunks with the larger model at {}", escalation_config.server_url);
+        backend = Box::new(ModelEscalationBackend::new(
+            backend,
+            Box::new(WhisperServerBackend::new(&escalation_config.server_url)),
+            escalation_config,
+        ));
+    }
+    backend
+}
+
+/// Chunking heuristic used for live recordings.
+fn default_boundary_strategy(config: &TranscriptionConfig) -> Box<dyn BoundaryStrategy> {
+    let chunk_samples = (WHISPER_SAMPLE_RATE as f32 * (CHUNK_DURATION_MS as f32 / 1000.0)) as usize;
+    let min_samples = (WHISPER_SAMPLE_RATE as f32 * (MIN_CHUNK_DURATION_MS as f32 / 1000.0)) as usize;
+    let duration_boundary = DurationBoundary::new(
+        chunk_samples,
+        min_samples,
+        Duration::from_millis(CHUNK_DURATION_MS as u64),
+    );
+    let mut strategy: Box<dyn BoundaryStrategy> = if config.endpointing.enabled {
+        Box::new(EnergyEndpointing::new(duration_boundary, config.endpointing.clone()))
+    } else {
+        Box::new(duration_boundary)
+    };
+    if config.energy_dips.enabled {
+        strategy = Box::new(EnergyDipEndpointing::new(strategy, config.energy_dips.clone()));
+    }
+    if config.chunk_coalescing.enabled {
+        Box::new(ChunkCoalescing::new(strategy, config.chunk_coalescing.clone()))
+    } else {
+        strategy
+    }
+}
+
+// A chunk cut while nothing transcribes is skipped in chunk ordering, so chunks
+// transcribed after the output mode is switched back aren't held back for it
+fn skip_if_untranscribed<R: Runtime>(
+    mode: ChunkOutputMode,
+    chunk_id: u64,
+    emitter: &Mutex<TranscriptEmitter>,
+    app_handle: &AppHandle<R>,
+) {
+    if !mode.transcribes() {
+        if let Ok(mut emitter_guard) = emitter.lock() {
+            emitter_guard.skip(chunk_id, app_handle);
+        }
+    }
+}
+
+// Carries out the resets requested by the commands since the last iteration.
+// Level tracking and the prompt context can be reset independently of each other.
+fn apply_requested_resets(
+    level_reset: &AtomicBool,
+    context_reset: &AtomicBool,
+    balancer: &mut SourceBalancer,
+    compressor: &mut Compressor,
+    boundary: &mut dyn BoundaryStrategy,
+    segment_detector: &mut SegmentBoundaryDetector,
+    emitter: &Mutex<TranscriptEmitter>,
+) {
+    if level_reset.swap(false, Ordering::SeqCst) {
+        log_info!("Resetting level and speech detection state");
+        balancer.reset();
+        compressor.reset();
+        boundary.reset();
+        segment_detector.reset();
+    }
+    if context_reset.swap(false, Ordering::SeqCst) {
+        log_info!("Resetting transcript prompt context");
+        if let Ok(mut emitter_guard) = emitter.lock() {
+            emitter_guard.reset_context();
+        }
+    }
+}
+
+// What the audio collection task works with for one recording. The processing
+// state and settings start from the config the recording was started with and
+// follow it as it changes.
+struct AudioCollection<R: Runtime> {
     mic_stream: Arc<AudioStream>,
     system_stream: Arc<AudioStream>,
     is_running: Arc<AtomicBool>,
     sample_rate: u32,
     recording_start_time: std::time::Instant,
     app_handle: AppHandle<R>,
-) -> Result<(), String> {
+    emitter: Arc<Mutex<TranscriptEmitter>>,
+    balancer: SourceBalancer,
+    compressor: Compressor,
+    pre_emphasis: PreEmphasis,
+    boundary: Box<dyn BoundaryStrategy>,
+    queue_config: ChunkQueueConfig,
+    chunk_output: ChunkOutputConfig,
+    whisper_window: WhisperWindowConfig,
+    padding_config: ChunkPaddingConfig,
+    segment_detector: SegmentBoundaryDetector,
+    overlap_detector: OverlapDetector,
+    auto_stopper: SilenceAutoStopper,
+    chunk_clock: ChunkClock,
+    confirmation: SpeechConfirmationConfig,
+    crash_buffer: Option<CrashBuffer>,
+    // System audio captured before the recording started
+    preroll: Vec<f32>,
+}
+
+async fn audio_collection_task<R: Runtime>(collection: AudioCollection<R>) -> Result<(), String> {
+    let AudioCollection {
+        mic_stream,
+        mut system_stream,
+        is_running,
+        sample_rate,
+        recording_start_time,
+        app_handle,
+        emitter,
+        mut balancer,
+        mut compressor,
+        mut pre_emphasis,
+        mut boundary,
+        mut queue_config,
+        mut chunk_output,
+        mut whisper_window,
+        mut padding_config,
+        mut segment_detector,
+        mut overlap_detector,
+        mut auto_stopper,
+        mut chunk_clock,
+        mut confirmation,
+        mut crash_buffer,
+        preroll,
+    } = collection;
     log_info!("Audio collection task started");
     
-    let mut mic_receiver = mic_stream.subscribe().await;
-    let mut system_receiver = system_stream.subscribe().await;
+    // Capture is decoupled from this loop so a slow iteration overflows by policy
+    let capture_queue = transcription::config::current_config().capture_queue;
+    let mic_queue = CaptureQueue::new("Microphone", capture_queue.clone());
+    let system_queue = CaptureQueue::new("System audio", capture_queue);
+    let _mic_forwarder = CaptureForwarder::spawn(mic_stream.subscribe().await, mic_queue.clone());
+    let mut _system_forwarder = CaptureForwarder::spawn(system_stream.subscribe().await, system_queue.clone());
     
     let chunk_samples = (WHISPER_SAMPLE_RATE as f32 * (CHUNK_DURATION_MS as f32 / 1000.0)) as usize;
-    let min_samples = (WHISPER_SAMPLE_RATE as f32 * (MIN_CHUNK_DURATION_MS as f32 / 1000.0)) as usize;
     let mut current_chunk: Vec<f32> = Vec::with_capacity(chunk_samples);
+    let overlap_samples = (WHISPER_SAMPLE_RATE * SERVER_OVERLAP_MS / 1000) as usize;
+    let mut previous_tail = vec![0.0; overlap_samples];
     let mut last_chunk_time = std::time::Instant::now();
-    let chunk_start_time = std::time::Instant::now();
+    let mut config_generation = transcription::config::generation();
+    let mut channel_warnings_sent = [false; 2];
+    let mut permission_warnings_sent = [false; 2];
+    let mut last_reopen_attempt: Option<std::time::Instant> = None;
+    // System audio is mixed sample by sample with the microphone, so it's brought to the mic's rate
+    let mut system_resampler = StreamResampler::new(system_stream.device_config.sample_rate().0, sample_rate);
+    // The microphone only needs resampling once it has switched away from the rate it was opened at
+    let mut mic_resampler = StreamResampler::new(sample_rate, sample_rate);
+    let rate_changes = transcription::config::current_config().sample_rate_changes;
+    let mut mic_rate_watch = SampleRateWatcher::new(rate_changes.clone(), sample_rate);
+    let mut system_rate_watch = SampleRateWatcher::new(rate_changes, system_resampler.from_rate());
+    
+    // Remote audio from just before the recording started leads the first chunk
+    if !preroll.is_empty() {
+        log_info!("Starting with {:.1}s of system audio pre-roll", preroll.len() as f64 / sample_rate as f64);
+        current_chunk = preroll_chunk(&preroll, mix_weights(&balancer), &SOURCE_GATE.state(), &mut chunk_clock, sample_rate);
+    }
     
     while is_running.load(Ordering::SeqCst) {
+        // Tell the user once per device if its channel count was misreported
+        for (stream, warned) in [&mic_stream, &system_stream].into_iter().zip(channel_warnings_sent.iter_mut()) {
+            if !*warned && stream.channel_fallback() {
+                *warned = true;
+                let message = format!(
+                    "{} reported the wrong number of channels, recording it as mono",
+                    stream.device.name
+                );
+                if let Err(e) = app_handle.emit("audio-channel-mismatch", &message) {
+                    log_error!("Failed to emit channel mismatch warning: {}", e);
+                }
+            }
+        }
+        
+        // Ask the user to grant capture permission again if the OS took it away
+        for (stream, warned) in [&mic_stream, &system_stream].into_iter().zip(permission_warnings_sent.iter_mut()) {
+            if !*warned && stream.permission_revoked() {
+                *warned = true;
+                let message = format!(
+                    "Recording permission for {} was revoked. Grant it again in your system's privacy settings.",
+                    stream.device.name
+                );
+                if let Err(e) = app_handle.emit("audio-permission-revoked", &message) {
+                    log_error!("Failed to emit permission revoked event: {}", e);
+                }
+            }
+        }
+        
+        // System audio is reopened once permission has been granted again
+        if system_stream.permission_revoked() {
+            let config = transcription::config::current_config();
+            let retry_interval = Duration::from_secs(config.permission_recovery.retry_secs.max(1));
+            let retry_due = match last_reopen_attempt {
+                Some(attempt) => attempt.elapsed() >= retry_interval,
+                None => true,
+            };
+            if config.permission_recovery.enabled && retry_due {
+                last_reopen_attempt = Some(std::time::Instant::now());
+                let options = StreamOptions {
+                    grace_policy: config.disconnect_grace.clone(),
+                    channel_selection: config.audio_devices.output_channels.clone(),
+                    capture_thread: config.capture_thread.clone(),
+                    recover_from_permission_loss: true,
+                    ..Default::default()
+                };
+                match AudioStream::from_device_with_options(system_stream.device.clone(), is_running.clone(), options).await {
+                    Ok(stream) => {
+                        log_info!("Reopened system audio stream for {}", stream.device.name);
+                        // The device may come back in a different format
+                        let system_rate = stream.device_config.sample_rate().0;
+                        if system_rate != system_resampler.from_rate() {
+                            log_info!("System audio is now {} Hz, was {} Hz", system_rate, system_resampler.from_rate());
+                            balancer.reset();
+                        }
+                        system_resampler = StreamResampler::new(system_rate, sample_rate);
+                        system_rate_watch.reset(system_rate);
+                        let stream = Arc::new(stream);
+                        system_queue.clear();
+                        _system_forwarder = CaptureForwarder::spawn(stream.subscribe().await, system_queue.clone());
+                        unsafe {
+                            SYSTEM_STREAM = Some(stream.clone());
+                        }
+                        system_stream = stream;
+                        permission_warnings_sent[1] = false;
+                        if let Ok(mut emitter_guard) = emitter.lock() {
+                            emitter_guard.note_recovery(recording_start_time.elapsed().as_secs_f64());
+                        }
+                        if let Err(e) = app_handle.emit("audio-permission-restored", &system_stream.device.name) {
+                            log_error!("Failed to emit permission restored event: {}", e);
+                        }
+                    }
+                    Err(e) => log_debug!("System audio still unavailable: {}", e),
+                }
+            }
+        }
+        
+        // Apply settings changed mid-recording without restarting capture
+        let latest_generation = transcription::config::generation();
+        if latest_generation != config_generation {
+            config_generation = latest_generation;
+            let config = transcription::config::current_config();
+            log_info!("Applying updated transcription config to the running session");
+            balancer.update_config(config.source_balance.clone());
+            compressor.update_config(config.compressor.clone());
+            pre_emphasis.update_config(config.pre_emphasis.clone());
+            boundary = default_boundary_strategy(&config);
+            queue_config = config.chunk_queue.clone();
+            chunk_output = config.chunk_output.clone();
+            whisper_window = config.whisper_window.clone();
+            padding_config = config.chunk_padding.clone();
+            segment_detector.update_config(config.segment_boundaries.clone());
+            overlap_detector.update_config(config.overlapping_speech.clone());
+            auto_stopper.update_config(config.silence_auto_stop.clone());
+            chunk_clock.update_config(config.chunk_timing.clone());
+            confirmation = config.speech_confirmation.clone();
+            mic_rate_watch.update_config(config.sample_rate_changes.clone());
+            system_rate_watch.update_config(config.sample_rate_changes.clone());
+            if let Some(monitor) = AUDIO_MONITOR.lock().ok().as_ref().and_then(|slot| slot.as_ref()) {
+                monitor.update_config(&config.monitor);
+            }
+            if let Ok(mut emitter_guard) = emitter.lock() {
+                emitter_guard.apply_config(&config);
+            }
+        }
+        
+        apply_requested_resets(
+            &LEVEL_RESET_REQUESTED,
+            &CONTEXT_RESET_REQUESTED,
+            &mut balancer,
+            &mut compressor,
+            boundary.as_mut(),
+            &mut segment_detector,
+            &emitter,
+        );
+        
         // Collect audio samples
-        let mut new_samples = Vec::new();
-        let mut mic_samples = Vec::new();
-        let mut system_samples = Vec::new();
+        let mut mic_samples = mic_queue.drain();
+        let mut system_samples = system_queue.drain();
+        log_debug!("Received {} mic samples, {} system samples", mic_samples.len(), system_samples.len());
         
-        // Get microphone samples
-        while let Ok(chunk) = mic_receiver.try_recv() {
-            log_debug!("Received {} mic samples", chunk.len());
-            mic_samples.extend(chunk);
+        // Bluetooth headsets can change rate with their profile without the stream reporting it
+        let received_at = std::time::Instant::now();
+        if let Some(new_rate) = mic_rate_watch.observe(mic_samples.len(), received_at) {
+            emit_rate_change(&app_handle, &mic_stream.device.name, mic_resampler.from_rate(), new_rate);
+            mic_resampler = StreamResampler::new(new_rate, sample_rate);
+            balancer.reset();
+        }
+        if let Some(new_rate) = system_rate_watch.observe(system_samples.len(), received_at) {
+            emit_rate_change(&app_handle, &system_stream.device.name, system_resampler.from_rate(), new_rate);
+            system_resampler = StreamResampler::new(new_rate, sample_rate);
+            balancer.reset();
+        }
+        mic_samples = mic_resampler.process(&mic_samples);
+        system_samples = system_resampler.process(&system_samples);
+        
+        // Bring both sources to a comparable loudness before mixing
+        balancer.process(&mut mic_samples, &mut system_samples);
+        
+        // People talking over each other are hard to transcribe, those sentences get flagged
+        if !mic_samples.is_empty() || !system_samples.is_empty() {
+            let elapsed_secs = recording_start_time.elapsed().as_secs_f64();
+            if let Some((start, end)) = overlap_detector.update(elapsed_secs, rms(&mic_samples), rms(&system_samples)) {
+                log_debug!("Overlapping speech from {} to {}", format_timestamp(start), format_timestamp(end));
+                if let Ok(mut emitter_guard) = emitter.lock() {
+                    emitter_guard.note_overlap(start, end);
+                }
+            }
+        }
+        
+        // Mix samples (80% mic, 20% system, or evenly once balanced)
+        let weights = mix_weights(&balancer);
+        let raw_mix = mix_sources(&mic_samples, &system_samples, weights, None);
+        
+        // Keep the raw mix on disk so it survives a crash
+        if let Some(buffer) = crash_buffer.as_mut() {
+            if let Err(e) = buffer.write(&raw_mix) {
+                log_error!("Failed to write crash recovery buffer, no longer keeping it: {}", e);
+                crash_buffer = None;
+            }
         }
         
-        // Get system audio samples
-        while let Ok(chunk) = system_receiver.try_recv() {
-            log_debug!("Received {} system samples", chunk.len());
-            system_samples.extend(chunk);
+        // Muted sources keep capturing but are heard as silence from here on
+        let gate = SOURCE_GATE.state();
+        let mut new_samples = if gate.feeds_transcription(CaptureSource::Microphone) && gate.feeds_transcription(CaptureSource::System) {
+            raw_mix
+        } else {
+            mix_sources(&mic_samples, &system_samples, weights, Some(&gate))
+        };
+        
+        // Silence detection keeps working on the uncompressed level
+        let latest_rms = rms(&new_samples);
+        
+        // Even out loud and soft speakers before the audio reaches whisper
+        compressor.process(&mut new_samples);
+        
+        // Long silences split the meeting into segments the UI can show as chapters
+        if !new_samples.is_empty() {
+            if let Some(boundary) = segment_detector.update(recording_start_time.elapsed(), latest_rms) {
+                log_info!("Segment boundary {} after silence starting at {}", boundary.segment_index, format_timestamp(boundary.silence_start));
+                if let Err(e) = app_handle.emit("segment-boundary", &boundary) {
+                    log_error!("Failed to emit segment-boundary event: {}", e);
+                }
+            }
+            
+            // An empty room shouldn't keep producing chunks for hours
+            match auto_stopper.update(recording_start_time.elapsed(), latest_rms) {
+                Some(SilenceTransition::Triggered(auto_stop)) => {
+                    log_info!(
+                        "No audio since {}, auto-{} the recording",
+                        format_timestamp(auto_stop.silence_start),
+                        if auto_stop.action == SilenceAction::Stop { "stopping" } else { "pausing" }
+                    );
+                    if let Err(e) = app_handle.emit("silence-auto-stop", &auto_stop) {
+                        log_error!("Failed to emit silence-auto-stop event: {}", e);
+                    }
+                }
+                Some(SilenceTransition::Resumed { at }) => {
+                    log_info!("Speech at {}, resuming the recording", format_timestamp(at));
+                    if let Err(e) = app_handle.emit("silence-auto-resume", at) {
+                        log_error!("Failed to emit silence-auto-resume event: {}", e);
+                    }
+                }
+                None => {}
+            }
         }
         
-        // Mix samples (80% mic, 20% system)
-        let max_len = mic_samples.len().max(system_samples.len());
-        for i in 0..max_len {
-            let mic_sample = if i < mic_samples.len() { mic_samples[i] } else { 0.0 };
-            let system_sample = if i < system_samples.len() { system_samples[i] } else { 0.0 };
-            new_samples.push((mic_sample * 0.8) + (system_sample * 0.2));
+        // While auto-paused the audio is dropped, the chunk clock sees the gap on resume
+        if auto_stopper.is_paused() {
+            new_samples.clear();
         }
         
-        // Add samples to current chunk
+        // Add samples to current chunk. The chunk's audio starts with whichever source
+        // captured earliest, i.e. the one with the larger input latency.
+        chunk_clock.set_input_latency(mic_stream.input_latency().max(system_stream.input_latency()));
+        chunk_clock.observe(recording_start_time.elapsed().as_secs_f64(), new_samples.len(), sample_rate);
         for sample in new_samples {
             current_chunk.push(sample);
         }
         
         // Check if we should create a chunk
-        let should_create_chunk = current_chunk.len() >= chunk_samples || 
-                                (current_chunk.len() >= min_samples && 
-                                 last_chunk_time.elapsed() >= Duration::from_millis(CHUNK_DURATION_MS as u64));
+        let chunk_state = ChunkState {
+            buffered_samples: current_chunk.len(),
+            sample_rate,
+            since_last_chunk: last_chunk_time.elapsed(),
+            latest_rms,
+        };
+        let should_create_chunk = boundary.decide(&chunk_state) == ChunkDecision::CreateChunk;
         
-        if should_create_chunk && !current_chunk.is_empty() {
+        // Audio past whisper's window is held back for the next chunk rather than cut off
+        let buffered_secs = current_chunk.len() as f64 / sample_rate as f64;
+        let split = split_at_window(&mut current_chunk, &whisper_window, sample_rate);
+        
+        if (should_create_chunk || split.is_some()) && !current_chunk.is_empty() {
+            if split.is_some() {
+                log_debug!("Splitting {:.2}s of audio at whisper's {}ms window", buffered_secs, whisper_window.window_ms);
+            }
+            let held_back = split.unwrap_or_default();
+            let prepare_started = std::time::Instant::now();
+            let chunk_duration = current_chunk.len() as f64 / sample_rate as f64;
             // Process chunk for Whisper API
-            let whisper_samples = if sample_rate != WHISPER_SAMPLE_RATE {
+            let mut whisper_samples = if sample_rate != WHISPER_SAMPLE_RATE {
                 log_debug!("Resampling audio from {} to {}", sample_rate, WHISPER_SAMPLE_RATE);
                 resample_audio(&current_chunk, sample_rate, WHISPER_SAMPLE_RATE)
             } else {
                 current_chunk.clone()
             };
+            // Chunks whose only loud audio is a transient, like a cough or a door, would come back as junk
+            if confirmation.enabled && !transcription::has_sustained_voice(&whisper_samples, WHISPER_SAMPLE_RATE, &confirmation) {
+                log_debug!("Dropping {:.2}s chunk without sustained voiced speech", chunk_duration);
+                chunk_clock.finish_chunk(chunk_duration);
+                // The next chunk doesn't follow on from this one, so it gets no overlap from before the gap
+                previous_tail = vec![0.0; overlap_samples];
+                current_chunk = held_back;
+                if !current_chunk.is_empty() {
+                    chunk_clock.observe(recording_start_time.elapsed().as_secs_f64(), current_chunk.len(), sample_rate);
+                }
+                last_chunk_time = std::time::Instant::now();
+                continue;
+            }
+            pre_emphasis.process(&mut whisper_samples);
+            // The next chunk's overlap is the end of this chunk's speech, not its padding
+            let tail = whisper_samples[whisper_samples.len().saturating_sub(overlap_samples)..].to_vec();
+            let padded = pad_chunk(&mut whisper_samples, WHISPER_SAMPLE_RATE, &padding_config);
+            if padded > 0 {
+                log_debug!("Padded chunk with {} samples of silence", padded);
+            }
             
             // Create audio chunk
             let chunk_id = CHUNK_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
-            let chunk_timestamp = chunk_start_time.elapsed().as_secs_f64();
+            let chunk_timestamp = match chunk_clock.finish_chunk(chunk_duration) {
+                Some(placement) => {
+                    if placement.gap_secs > 0.1 {
+                        log_debug!("Chunk {} starts {:.2}s after the previous one", chunk_id, placement.gap_secs);
+                    }
+                    placement.start_secs
+                }
+                None => fallback_chunk_start(
+                    recording_start_time.elapsed(),
+                    chunk_clock.latency_offset_secs(),
+                    chunk_duration,
+                    chunk_clock.timeline_start(),
+                ),
+            };
             let audio_chunk = AudioChunk {
+                overlap: std::mem::replace(&mut previous_tail, tail),
                 samples: whisper_samples,
                 timestamp: chunk_timestamp,
+                prepare_time: prepare_started.elapsed(),
                 chunk_id,
                 start_time: std::time::Instant::now(),
                 recording_start_time,
             };
             
-            // Add to queue (with overflow protection)
-            unsafe {
-                if let Some(queue) = &AUDIO_CHUNK_QUEUE {
-                    if let Ok(mut queue_guard) = queue.lock() {
-                        // Remove oldest chunks if queue is full
-                        while queue_guard.len() >= MAX_AUDIO_QUEUE_SIZE {
-                            if let Some(dropped_chunk) = queue_guard.pop_front() {
+            // External transcribers get the chunk as soon as it's cut
+            if chunk_output.mode.emits_chunks() {
+                let payload = ChunkReady {
+                    chunk_id,
+                    timestamp: chunk_timestamp,
+                    sample_rate: WHISPER_SAMPLE_RATE,
+                    samples: &audio_chunk.samples,
+                };
+                if let Err(e) = app_handle.emit("chunk-ready", &payload) {
+                    log_error!("Failed to emit chunk-ready event: {}", e);
+                }
+            }
+            
+            skip_if_untranscribed(chunk_output.mode, chunk_id, &emitter, &app_handle);
+            
+            if chunk_output.mode.transcribes() {
+                // Under backpressure, hold the chunk until a worker has taken one off the queue
+                if queue_config.overflow_policy == QueueOverflowPolicy::Backpressure {
+                    let mut waited = false;
+                    while is_running.load(Ordering::SeqCst) && queued_chunk_count() >= queue_config.max_queued_chunks {
+                        if !waited {
+                            log_info!("Chunk queue full, holding chunk {} until a worker catches up", chunk_id);
+                            waited = true;
+                        }
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    }
+                }
+            
+                // Add to queue (with overflow protection)
+                let mut dropped_chunk_ids = Vec::new();
+                unsafe {
+                    if let Some(queue) = &AUDIO_CHUNK_QUEUE {
+                        if let Ok(mut queue_guard) = queue.lock() {
+                            // Remove oldest chunks if queue is full
+                            let dropped_chunks = push_bounded(&mut queue_guard, audio_chunk, queue_config.max_queued_chunks);
+                            for dropped_chunk in dropped_chunks {
+                                dropped_chunk_ids.push(dropped_chunk.chunk_id);
                                 let drop_count = DROPPED_CHUNK_COUNTER.fetch_add(1, Ordering::SeqCst) + 1;
                                 log_info!("Dropped old audio chunk {} due to queue overflow (total drops: {})", dropped_chunk.chunk_id, drop_count);
-                                
+                                METRICS.record_dropped_chunk();
+                        
+                                if let Err(e) = app_handle.emit("chunk-skipped", dropped_chunk.chunk_id) {
+                                    log_error!("Failed to emit chunk-skipped event: {}", e);
+                                }
+                        
                                 // // Emit warning event every 10th drop
                                 // if drop_count % 10 == 0 {
                                 if drop_count == 1 {
                                     let warning_message = format!("Transcription process is very slow. Audio chunk {} was dropped. Please choose a smaller model, or run whisper natively.", dropped_chunk.chunk_id);
                                     log_info!("Emitting chunk-drop-warning event: {}", warning_message);
-                                    
+                            
                                     if let Err(e) = app_handle.emit("chunk-drop-warning", &warning_message) {
                                         log_error!("Failed to emit chunk-drop-warning event: {}", e);
                                     }
                                 }
                             }
+                            log_info!("Added chunk {} to queue (queue size: {})", chunk_id, queue_guard.len());
+                        }
+                    }
+                }
+
+                // Dropped chunks will never be transcribed, so don't let them hold back later ones
+                if !dropped_chunk_ids.is_empty() {
+                    if let Ok(mut emitter_guard) = emitter.lock() {
+                        for dropped_chunk_id in dropped_chunk_ids {
+                            emitter_guard.skip(dropped_chunk_id, &app_handle);
                         }
-                        queue_guard.push_back(audio_chunk);
-                        log_info!("Added chunk {} to queue (queue size: {})", chunk_id, queue_guard.len());
                     }
                 }
             }
-            
-            // Reset for next chunk
-            current_chunk.clear();
+
+            // Reset for next chunk, which starts with any held-back audio
+            current_chunk = held_back;
+            if !current_chunk.is_empty() {
+                chunk_clock.observe(recording_start_time.elapsed().as_secs_f64(), current_chunk.len(), sample_rate);
+            }
             last_chunk_time = std::time::Instant::now();
         }
         
@@ -368,71 +1422,97 @@ async fn audio_collection_task<R: Runtime>(
     Ok(())
 }
 
-async fn send_audio_chunk(chunk: Vec<f32>, client: &reqwest::Client, stream_url: &str) -> Result<TranscriptResponse, String> {
-    log_debug!("Preparing to send audio chunk of size: {}", chunk.len());
-    
-    // Convert f32 samples to bytes
-    let bytes: Vec<u8> = chunk.iter()
-        .flat_map(|&sample| {
-            let clamped = sample.max(-1.0).min(1.0);
-            clamped.to_le_bytes().to_vec()
-        })
-        .collect();
-    
-    // Retry configuration
-    let max_retries = 3;
-    let mut retry_count = 0;
-    let mut last_error = String::new();
-
-    while retry_count <= max_retries {
-        if retry_count > 0 {
-            // Exponential backoff: wait 2^retry_count * 100ms
-            let delay = Duration::from_millis(100 * (2_u64.pow(retry_count as u32)));
-            log::info!("Retry attempt {} of {}. Waiting {:?} before retry...", 
-                      retry_count, max_retries, delay);
-            tokio::time::sleep(delay).await;
-        }
-
-        // Create fresh multipart form for each attempt since Form can't be reused
-        let part = Part::bytes(bytes.clone())
-            .file_name("audio.raw")
-            .mime_str("audio/x-raw")
-            .unwrap();
-        let form = Form::new().part("audio", part);
-
-        match client.post(stream_url)
-            .multipart(form)
-            .send()
-            .await {
-                Ok(response) => {
-                    match response.json::<TranscriptResponse>().await {
-                        Ok(transcript) => return Ok(transcript),
-                        Err(e) => {
-                            last_error = e.to_string();
-                            log::error!("Failed to parse response: {}", last_error);
-                        }
-                    }
-                }
-                Err(e) => {
-                    last_error = e.to_string();
-                    log::error!("Request failed: {}", last_error);
-                }
+// Writes the transcript so far to disk at the configured interval, skipping
+// ticks where no sentence was added
+async fn transcript_autosave_task(is_running: Arc<AtomicBool>, emitter: Arc<Mutex<TranscriptEmitter>>) {
+    let path = match transcription::autosave::default_autosave_path() {
+        Ok(path) => path,
+        Err(e) => {
+            log_error!("Transcript autosave disabled: {}", e);
+            return;
+        }
+    };
+    let mut saved_sentences = 0;
+    while is_running.load(Ordering::SeqCst) {
+        let config = transcription::config::current_config().autosave;
+        tokio::time::sleep(Duration::from_secs(config.interval_secs.max(1))).await;
+        if !config.enabled {
+            continue;
+        }
+        let transcript = match emitter.lock() {
+            Ok(emitter_guard) => emitter_guard.transcript.clone(),
+            Err(_) => None,
+        };
+        let Some(transcript) = transcript.filter(|transcript| transcript.sentences.len() != saved_sentences) else {
+            continue;
+        };
+        match transcription::autosave::write_autosave(&path, &transcript) {
+            Ok(()) => {
+                saved_sentences = transcript.sentences.len();
+                log_debug!("Autosaved {} transcript sentences", saved_sentences);
             }
+            Err(e) => log_error!("Failed to autosave transcript: {}", e),
+        }
+    }
+}
+
+/// Appends `item`, first removing the oldest items so the queue holds at most
+/// `max_len`. Returns the removed items, oldest first.
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T, max_len: usize) -> Vec<T> {
+    let excess = (queue.len() + 1).saturating_sub(max_len.max(1));
+    let dropped = queue.drain(..excess).collect();
+    queue.push_back(item);
+    dropped
+}
 
-        retry_count += 1;
+// Emits `transcription-status` at the configured interval while recording. The
+// status is sampled once per tick, so changes in between coalesce into one event.
+async fn status_heartbeat_task<R: Runtime>(is_running: Arc<AtomicBool>, app_handle: AppHandle<R>) {
+    run_status_heartbeat(
+        is_running,
+        || transcription::config::current_config().status_heartbeat,
+        || {
+            if let Err(e) = app_handle.emit("transcription-status", get_transcription_status()) {
+                log_error!("Failed to emit transcription status: {}", e);
+            }
+        },
+    )
+    .await;
+}
+
+async fn run_status_heartbeat(
+    is_running: Arc<AtomicBool>,
+    mut current_config: impl FnMut() -> StatusHeartbeatConfig,
+    mut emit: impl FnMut(),
+) {
+    while is_running.load(Ordering::SeqCst) {
+        let config = current_config();
+        tokio::time::sleep(Duration::from_millis(config.interval_ms.max(MIN_STATUS_HEARTBEAT_MS))).await;
+        if !config.enabled || !is_running.load(Ordering::SeqCst) {
+            continue;
+        }
+        emit();
     }
+}
 
-    Err(format!("Failed after {} retries. Last error: {}", max_retries, last_error))
+fn queued_chunk_count() -> usize {
+    unsafe {
+        if let Some(queue) = &AUDIO_CHUNK_QUEUE {
+            if let Ok(queue_guard) = queue.lock() {
+                return queue_guard.len();
+            }
+        }
+    }
+    0
 }
 
 async fn transcription_worker<R: Runtime>(
-    client: reqwest::Client,
-    stream_url: String,
+    backend: Arc<dyn TranscriptionBackend>,
     app_handle: AppHandle<R>,
     worker_id: usize,
+    emitter: Arc<Mutex<TranscriptEmitter>>,
 ) {
     log_info!("Transcription worker {} started", worker_id);
-    let mut accumulator = TranscriptAccumulator::new();
     
     // Increment active worker count
     ACTIVE_WORKERS.fetch_add(1, Ordering::SeqCst);
@@ -465,14 +1545,12 @@ async fn transcription_worker<R: Runtime>(
             break;
         }
         // Check for timeout on current sentence
-        if let Some(update) = accumulator.check_timeout() {
-            log_info!("Worker {}: Emitting timeout transcript-update event with sequence_id: {}", worker_id, update.sequence_id);
-            
-            if let Err(e) = app_handle.emit("transcript-update", &update) {
-                log_error!("Worker {}: Failed to send timeout transcript update: {}", worker_id, e);
-            } else {
-                log_info!("Worker {}: Successfully emitted timeout transcript-update event", worker_id);
+        if let Ok(mut guard) = emitter.lock() {
+            if let Some(update) = guard.accumulator.check_timeout() {
+                log_info!("Worker {}: Emitting timed out sentence with sequence_id: {}", worker_id, update.sequence_id);
+                guard.emit_update(update, &app_handle);
             }
+            guard.flush_idle_turn(&app_handle);
         }
         
         // Try to get a chunk from the queue
@@ -501,35 +1579,149 @@ async fn transcription_worker<R: Runtime>(
                 Ordering::SeqCst
             );
             
-            // Set chunk context in accumulator
-            accumulator.set_chunk_context(chunk.chunk_id, chunk.timestamp, chunk.recording_start_time);
+            // Skip audio that was just transcribed, it would only produce duplicate text
+            let is_duplicate = emitter
+                .lock()
+                .map(|mut guard| guard.is_duplicate(&chunk.samples))
+                .unwrap_or(false);
+            if is_duplicate {
+                log_info!("Worker {}: Chunk {} matches recently transcribed audio, skipping", worker_id, chunk.chunk_id);
+                if let Ok(mut emitter_guard) = emitter.lock() {
+                    emitter_guard.skip(chunk.chunk_id, &app_handle);
+                }
+                continue;
+            }
             
             // Send chunk for transcription
-            match send_audio_chunk(chunk.samples, &client, &stream_url).await {
-                Ok(response) => {
+            let prompt = emitter.lock().ok().and_then(|guard| guard.prompt());
+            if let Some(prompt) = &prompt {
+                log_debug!("Worker {}: Using {} chars of transcript context for chunk {}", worker_id, prompt.len(), chunk.chunk_id);
+            }
+            
+            // Whisper also sees the end of the previous chunk sent ahead of this one
+            let audio_ticks = chunk.samples.len() as f32 / WHISPER_SAMPLE_RATE as f32 * TICKS_PER_SEC as f32 + SERVER_OVERLAP_TICKS;
+            let audio_ms = chunk.samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+            let request_started = std::time::Instant::now();
+            transcription::estimate::request_started();
+            
+            // Keep the audio to measure each segment's voice once whisper has placed the segments
+            let config = transcription::config::current_config();
+            let speaker_audio = config.speaker_hints.enabled.then(|| chunk.samples.clone());
+            let profiling = config.chunk_profiling;
+            // Chunks that fail outright are kept, if configured, to be transcribed again later
+            let dead_letters = config.dead_letters;
+            let retained_audio = dead_letters.enabled.then(|| chunk.samples.clone());
+            
+            let result = match transcription::backend::validate_audio(&chunk.samples) {
+                Ok(()) => {
+                    let mut request = chunk.overlap;
+                    request.extend(chunk.samples);
+                    backend.transcribe(chunk.chunk_id, request, prompt).await
+                }
+                Err(e) => Err(e),
+            };
+            let whisper_time = request_started.elapsed();
+            let processing_time = transcription::estimate::request_finished();
+            match result {
+                Ok(mut response) => {
+                    if let Some(audio) = &speaker_audio {
+                        for segment in response.segments.iter_mut() {
+                            segment.voice = segment_voice(audio, segment.t0, segment.t1);
+                        }
+                    }
                     log_info!("Worker {}: Received {} transcript segments for chunk {}", 
                              worker_id, response.segments.len(), chunk.chunk_id);
-                    
-                    for segment in response.segments {
-                        log_info!("Worker {}: Processing segment: {} ({} - {})", 
-                                 worker_id, segment.text.trim(), format_timestamp(segment.t0 as f64), format_timestamp(segment.t1 as f64));
-                        
-                        // Add segment to accumulator and check for complete sentence
-                        if let Some(update) = accumulator.add_segment(&segment) {
-                            log_info!("Worker {}: Emitting transcript-update event with sequence_id: {}", worker_id, update.sequence_id);
-                            
-                            // Emit the update
-                            if let Err(e) = app_handle.emit("transcript-update", &update) {
-                                log_error!("Worker {}: Failed to emit transcript update: {}", worker_id, e);
-                            } else {
-                                log_info!("Worker {}: Successfully emitted transcript-update event", worker_id);
+                    let warmup = WARMUP_PENDING.swap(false, Ordering::SeqCst);
+                    if warmup {
+                        log_debug!("Worker {}: Leaving warm-up chunk {} out of the timing measurements", worker_id, chunk.chunk_id);
+                        METRICS.record_warmup_chunk();
+                    } else {
+                        METRICS.record_transcribed_chunk(chunk.start_time.elapsed().as_millis() as u64);
+                    }
+                    // Silence and noise legitimately come back without speech
+                    if response.segments.iter().all(|segment| segment.is_blank()) {
+                        log_debug!("Worker {}: No speech in chunk {}", worker_id, chunk.chunk_id);
+                        METRICS.record_silent_chunk();
+                    }
+                    if !warmup {
+                        transcription::estimate::record_processing(audio_ms, processing_time.as_millis() as u64);
+                        // Trade decoding quality for speed while whisper is slower than the audio
+                        if let Some(speed) = transcription::estimate::transcription_speed() {
+                            if let Some(level) = transcription::throttle::observe_speed(&config.quality_throttle, speed) {
+                                log_info!("Worker {}: Transcribing {:.2}x real time, quality now {:?}", worker_id, speed, level);
+                                if let Err(e) = app_handle.emit("quality-throttle-changed", QualityChange { level, speed }) {
+                                    log_error!("Worker {}: Failed to emit quality-throttle-changed event: {}", worker_id, e);
+                                }
                             }
                         }
                     }
+                    
+                    if !response.failed_attempts.is_empty() {
+                        let report = RetryReport {
+                            chunk_id: chunk.chunk_id,
+                            failed_attempts: std::mem::take(&mut response.failed_attempts),
+                        };
+                        if let Err(e) = app_handle.emit("transcription-retries", &report) {
+                            log_error!("Worker {}: Failed to emit retry report: {}", worker_id, e);
+                        }
+                    }
+                    if let Some(raw) = response.raw.take() {
+                        let raw_transcript = RawTranscript {
+                            chunk_id: chunk.chunk_id,
+                            chunk_start_time: chunk.timestamp,
+                            response: raw,
+                        };
+                        if let Err(e) = app_handle.emit("transcript-raw", &raw_transcript) {
+                            log_error!("Worker {}: Failed to emit raw transcript: {}", worker_id, e);
+                        }
+                    }
+                    
+                    // Hand the segments to the shared emitter, which releases them in chunk order
+                    if let Ok(mut emitter_guard) = emitter.lock() {
+                        emitter_guard.complete(ChunkTranscript {
+                            chunk_id: chunk.chunk_id,
+                            timestamp: chunk.timestamp,
+                            recording_start_time: chunk.recording_start_time,
+                            audio_ticks,
+                            segments: response.segments,
+                        }, &app_handle);
+                    }
+                    
+                    if profiling.enabled {
+                        let timing = ChunkTiming::measure(
+                            chunk.chunk_id,
+                            chunk.prepare_time,
+                            chunk.start_time,
+                            request_started,
+                            whisper_time,
+                            std::time::Instant::now(),
+                        );
+                        transcription::profiling::record_chunk_timing(timing, profiling.max_chunks);
+                    }
                 }
                 Err(e) => {
                     log_error!("Worker {}: Transcription error for chunk {}: {}", 
                               worker_id, chunk.chunk_id, e);
+                    METRICS.record_failed_chunk();
+                    if let Some(samples) = retained_audio {
+                        let failed = FailedChunk {
+                            chunk_id: chunk.chunk_id,
+                            timestamp: chunk.timestamp,
+                            samples,
+                            error: e.to_string(),
+                        };
+                        if let Ok(mut queue) = DEAD_LETTERS.lock() {
+                            let dropped = queue.push(failed, dead_letters.max_chunks);
+                            if dropped > 0 {
+                                log_warn!("Worker {}: Dropped {} old failed chunks to keep chunk {}", worker_id, dropped, chunk.chunk_id);
+                            }
+                        }
+                    }
+                    
+                    // Don't let the failed chunk hold back chunks other workers finished
+                    if let Ok(mut emitter_guard) = emitter.lock() {
+                        emitter_guard.skip(chunk.chunk_id, &app_handle);
+                    }
                     
                     // Handle error similar to original logic
                     static mut ERROR_COUNT: u32 = 0;
@@ -548,22 +1740,25 @@ async fn transcription_worker<R: Runtime>(
                         }
                         LAST_ERROR_TIME = Some(now);
                         
-                        if ERROR_COUNT == 1 && !ERROR_EVENT_EMITTED {
+                        // With failed chunks kept for later, one failure isn't worth losing the rest of the
+                        // meeting. The user is still told, the UI keeps recording while is_recording says so.
+                        if ERROR_COUNT == 1 && !ERROR_EVENT_EMITTED && dead_letters.enabled {
+                            log_error!("Worker {}: Transcription failing, keeping failed chunks for reprocessing", worker_id);
+                            if let Err(emit_err) = app_handle.emit("transcript-error", e.user_message()) {
+                                log_error!("Worker {}: Failed to emit transcript error: {}", worker_id, emit_err);
+                            }
+                            ERROR_EVENT_EMITTED = true;
+                        } else if ERROR_COUNT == 1 && !ERROR_EVENT_EMITTED {
                             log_error!("Worker {}: Too many transcription errors, stopping recording", worker_id);
-                            let error_msg = if e.contains("Failed to connect") || e.contains("Connection refused") {
-                                "Transcription service is not available. Please check if the server is running.".to_string()
-                            } else if e.contains("timeout") {
-                                "Transcription service is not responding. Please check your connection.".to_string()
-                            } else {
-                                format!("Transcription service error: {}", e)
-                            };
+                            let error_msg = e.user_message();
                             
+                            // Stopped before the event goes out, so the UI sees the recording has ended
+                            RECORDING_FLAG.store(false, Ordering::SeqCst);
                             if let Err(emit_err) = app_handle.emit("transcript-error", error_msg) {
                                 log_error!("Worker {}: Failed to emit transcript error: {}", worker_id, emit_err);
                             }
                             
                             ERROR_EVENT_EMITTED = true;
-                            RECORDING_FLAG.store(false, Ordering::SeqCst);
                             if let Some(is_running) = &IS_RUNNING {
                                 is_running.store(false, Ordering::SeqCst);
                             }
@@ -614,42 +1809,38 @@ async fn transcription_worker<R: Runtime>(
         }
     }
     
-    // Emit any remaining transcript when worker stops
-    if let Some(update) = accumulator.check_timeout() {
-        log_info!("Worker {}: Emitting final transcript-update event with sequence_id: {}", worker_id, update.sequence_id);
-        
-        if let Err(e) = app_handle.emit("transcript-update", &update) {
-            log_error!("Worker {}: Failed to send final transcript update: {}", worker_id, e);
-        } else {
-            log_info!("Worker {}: Successfully emitted final transcript-update event", worker_id);
-        }
-    }
+    // Decrement active worker count
+    let remaining_workers = ACTIVE_WORKERS.fetch_sub(1, Ordering::SeqCst).saturating_sub(1);
     
-    // Also flush any partial sentence that might not have been emitted
-    if !accumulator.current_sentence.is_empty() {
-        let sequence_id = SEQUENCE_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let update = TranscriptUpdate {
-            text: accumulator.current_sentence.trim().to_string(),
-            timestamp: format!("{}", format_timestamp(accumulator.current_chunk_start_time + (accumulator.sentence_start_time as f64 / 1000.0))),
-            source: "Mixed Audio".to_string(),
-            sequence_id,
-            chunk_start_time: accumulator.current_chunk_start_time,
-            is_partial: true,
-        };
-        log_info!("Worker {}: Flushing final partial sentence: {} with sequence_id: {}", worker_id, update.text, update.sequence_id);
-        
-        if let Err(e) = app_handle.emit("transcript-update", &update) {
-            log_error!("Worker {}: Failed to send final partial transcript: {}", worker_id, e);
-        } else {
-            log_info!("Worker {}: Successfully emitted final partial transcript-update event", worker_id);
+    // The last worker out flushes whatever the shared accumulator still holds
+    if remaining_workers == 0 {
+        if let Ok(mut emitter_guard) = emitter.lock() {
+            emitter_guard.flush(&app_handle);
+
+            // Emit any remaining transcript when worker stops
+            if let Some(update) = emitter_guard.accumulator.check_timeout() {
+                log_info!("Worker {}: Emitting final transcript update", worker_id);
+                emitter_guard.emit_update(update, &app_handle);
+            }
+            
+            // Also flush any partial sentence that might not have been emitted
+            let accumulator = &mut emitter_guard.accumulator;
+            if !accumulator.current_sentence.is_empty() {
+                let start_secs = accumulator.current_chunk_start_time + chunk_secs(accumulator.sentence_start_time);
+                let update = accumulator.take_sentence(start_secs, start_secs, true);
+                log_info!("Worker {}: Flushing final partial sentence: {}", worker_id, update.text);
+                emitter_guard.emit_update(update, &app_handle);
+            }
+            emitter_guard.finish_turn(&app_handle);
+            
+            if let Some(transcript) = emitter_guard.transcript.take() {
+                tokio::spawn(summarize_transcript(transcript, app_handle.clone()));
+            }
         }
     }
     
-    // Decrement active worker count
-    ACTIVE_WORKERS.fetch_sub(1, Ordering::SeqCst);
-    
     // Check if this was the last active worker and emit completion event
-    if ACTIVE_WORKERS.load(Ordering::SeqCst) == 0 {
+    if remaining_workers == 0 {
         let should_emit = unsafe {
             if let Some(queue) = &AUDIO_CHUNK_QUEUE {
                 if let Ok(queue_guard) = queue.lock() {
@@ -678,6 +1869,77 @@ async fn transcription_worker<R: Runtime>(
     log_info!("Transcription worker {} ended", worker_id);
 }
 
+// Picks the device for one source. Returns None if the machine has no device
+// of that type at all and the config allows recording silence in its place.
+async fn select_source_device<R: Runtime>(
+    app: &AppHandle<R>,
+    preferences: &[String],
+    device_type: DeviceType,
+    policy: MissingDevicePolicy,
+) -> Result<Option<Arc<AudioDevice>>, String> {
+    match select_device_with_fallback(preferences, device_type.clone()).await {
+        Ok(device) => Ok(Some(Arc::new(device))),
+        Err(e) if policy == MissingDevicePolicy::RecordSilence && e.downcast_ref::<NoAudioDevices>().is_some() => {
+            log_warn!("{}, recording silence in its place", e);
+            if let Err(emit_error) = app.emit("audio-device-missing", e.to_string()) {
+                log_error!("Failed to emit audio-device-missing event: {}", emit_error);
+            }
+            Ok(None)
+        }
+        Err(e) => {
+            log_error!("Failed to get {:?} device: {}", device_type, e);
+            Err(e.to_string())
+        }
+    }
+}
+
+// Opens or closes the standby system-audio stream to match the config. It is
+// never open while recording, when the recording's own stream takes its place.
+async fn sync_system_preroll() {
+    if RECORDING_FLAG.load(Ordering::SeqCst) {
+        return;
+    }
+    let config = transcription::config::current_config();
+    let running = SYSTEM_PREROLL.lock().map(|slot| slot.is_some()).unwrap_or(false);
+    if config.system_preroll.enabled == running {
+        return;
+    }
+    if !config.system_preroll.enabled {
+        let standby = SYSTEM_PREROLL.lock().ok().and_then(|mut slot| slot.take());
+        if let Some(standby) = standby {
+            standby.finish().await;
+            log_info!("Closed the standby system audio stream");
+        }
+        return;
+    }
+
+    let device = match select_device_with_fallback(&config.audio_devices.output, DeviceType::Output).await {
+        Ok(device) => Arc::new(device),
+        Err(e) => {
+            log_warn!("No system audio device for pre-roll: {}", e);
+            return;
+        }
+    };
+    let options = StreamOptions {
+        grace_policy: config.disconnect_grace.clone(),
+        channel_selection: config.audio_devices.output_channels.clone(),
+        capture_thread: config.capture_thread.clone(),
+        ..Default::default()
+    };
+    match PrerollBuffer::start(device, options, config.system_preroll.seconds).await {
+        // A recording that started meanwhile opens the device itself
+        Ok(standby) if RECORDING_FLAG.load(Ordering::SeqCst) => {
+            standby.finish().await;
+        }
+        Ok(standby) => {
+            if let Ok(mut slot) = SYSTEM_PREROLL.lock() {
+                *slot = Some(standby);
+            }
+        }
+        Err(e) => log_error!("Failed to open the standby system audio stream: {}", e),
+    }
+}
+
 #[tauri::command]
 async fn start_recording<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
     log_info!("Attempting to start recording...");
@@ -723,6 +1985,16 @@ async fn start_recording<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
     unsafe {
         RECORDING_START_TIME = Some(std::time::Instant::now());
     }
+    SOURCE_GATE.reset();
+    // Failed chunks are placed on the previous recording's timeline, they can't be reprocessed into this one
+    if let Ok(mut queue) = DEAD_LETTERS.lock() {
+        if !queue.is_empty() {
+            log_warn!("Discarding {} failed chunks from the previous recording", queue.len());
+            queue.clear();
+        }
+    }
+    transcription::throttle::reset();
+    transcription::estimate::reset_throughput();
 
     // Initialize audio buffers and queue
     unsafe {
@@ -732,37 +2004,137 @@ async fn start_recording<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
         log_info!("Initialized audio buffers and chunk queue");
     }
     
-    // Get default devices
-    let mic_device = Arc::new(default_input_device().map_err(|e| {
-        log_error!("Failed to get default input device: {}", e);
-        e.to_string()
-    })?);
+    // Settings are fixed for the lifetime of this recording session
+    let transcription_config = transcription::config::current_config();
+    let longest_chunk = longest_chunk_ms(&transcription_config) + SERVER_OVERLAP_MS as u64;
+    if longest_chunk > transcription_config.whisper_window.window_ms as u64 {
+        if transcription_config.whisper_window.split_long_chunks {
+            log_info!("Chunks can reach {}ms, longer ones are split at whisper's {}ms window", longest_chunk, transcription_config.whisper_window.window_ms);
+        } else {
+            log_warn!(
+                "Chunks can reach {}ms but whisper only hears {}ms at a time, the rest will be cut off",
+                longest_chunk,
+                transcription_config.whisper_window.window_ms
+            );
+        }
+    }
+    
+    // Pick the first usable device from the configured preferences. A test
+    // signal replaces the microphone entirely, so none is needed then.
+    let devices = &transcription_config.audio_devices;
+    let mic_device = if transcription_config.test_source.enabled {
+        None
+    } else {
+        select_source_device(&app, &devices.input, DeviceType::Input, devices.missing_device).await?
+    };
+    let system_device = select_source_device(&app, &devices.output, DeviceType::Output, devices.missing_device).await?;
+    if mic_device.is_none() && system_device.is_none() && !transcription_config.test_source.enabled {
+        return Err("No audio devices available".to_string());
+    }
     
-    let system_device = Arc::new(default_output_device().map_err(|e| {
-        log_error!("Failed to get default output device: {}", e);
-        e.to_string()
-    })?);
+    for device in mic_device.iter().chain(system_device.iter()) {
+        log_info!("Using audio device: {}", device);
+        if let Err(e) = app.emit("audio-device-selected", device.as_ref()) {
+            log_error!("Failed to emit audio-device-selected event: {}", e);
+        }
+    }
     
     // Create audio streams
     let is_running = Arc::new(AtomicBool::new(true));
     
     // Create microphone stream
-    let mic_stream = AudioStream::from_device(mic_device.clone(), is_running.clone())
-        .await
-        .map_err(|e| {
-            log_error!("Failed to create microphone stream: {}", e);
-            e.to_string()
-        })?;
+    let mic_options = StreamOptions {
+        grace_policy: transcription_config.disconnect_grace.clone(),
+        channel_selection: transcription_config.audio_devices.input_channels.clone(),
+        capture_thread: transcription_config.capture_thread.clone(),
+        ..Default::default()
+    };
+    let mic_stream = match &mic_device {
+        Some(mic_device) => AudioStream::from_device_with_options(mic_device.clone(), is_running.clone(), mic_options)
+            .await
+            .map_err(|e| {
+                log_error!("Failed to create microphone stream: {}", e);
+                e.to_string()
+            })?,
+        None => {
+            let test_source = &transcription_config.test_source;
+            let (signal, sample_rate) = if test_source.enabled {
+                (test_source.signal.clone(), test_source.sample_rate)
+            } else {
+                // Silence stands in for a missing microphone at the system audio's rate
+                let system_rate = match &system_device {
+                    Some(device) => get_device_and_config(device).await.ok().map(|(_, config)| config.sample_rate().0),
+                    None => None,
+                };
+                (TestSignal::Silence, system_rate.unwrap_or(test_source.sample_rate))
+            };
+            log_info!("Recording a generated {:?} instead of the microphone", signal);
+            let generator = AudioTestGenerator::new(signal, sample_rate);
+            AudioStream::from_generator(generator, is_running.clone())
+        }
+    };
     let mic_stream = Arc::new(mic_stream);
     
     // Create system audio stream
-    let system_stream = AudioStream::from_device(system_device.clone(), is_running.clone())
-        .await
-        .map_err(|e| {
-            log_error!("Failed to create system stream: {}", e);
-            e.to_string()
-        })?;
+    let system_options = StreamOptions {
+        grace_policy: transcription_config.disconnect_grace.clone(),
+        channel_selection: transcription_config.audio_devices.output_channels.clone(),
+        capture_thread: transcription_config.capture_thread.clone(),
+        recover_from_permission_loss: transcription_config.permission_recovery.enabled,
+        ..Default::default()
+    };
+    // Close the standby stream before the device is opened again, keeping what it
+    // captured if it was recording the same device
+    let standby = SYSTEM_PREROLL.lock().ok().and_then(|mut slot| slot.take());
+    let mut preroll = Vec::new();
+    if let Some(standby) = standby {
+        let same_device = system_device.as_ref().is_some_and(|device| device.name == standby.device_name());
+        let (preroll_rate, samples) = standby.finish().await;
+        let mic_rate = mic_stream.device_config.sample_rate().0;
+        if same_device && !samples.is_empty() {
+            preroll = if preroll_rate != mic_rate {
+                resample_audio(&samples, preroll_rate, mic_rate)
+            } else {
+                samples
+            };
+        }
+    }
+    let system_stream = match &system_device {
+        Some(system_device) => AudioStream::from_device_with_options(system_device.clone(), is_running.clone(), system_options)
+            .await
+            .map_err(|e| {
+                log_error!("Failed to create system stream: {}", e);
+                e.to_string()
+            })?,
+        None => {
+            // Mixed sample by sample with the microphone, so it has to match its rate
+            let generator = AudioTestGenerator::new(TestSignal::Silence, mic_stream.device_config.sample_rate().0);
+            AudioStream::from_generator(generator, is_running.clone())
+        }
+    };
     let system_stream = Arc::new(system_stream);
+    
+    // Optionally play the microphone back so the user can check their levels
+    if transcription_config.monitor.enabled {
+        if monitor_may_loop(&transcription_config.monitor.device, &system_stream.device.name) {
+            log_warn!("Monitor device {} is also captured as system audio", transcription_config.monitor.device);
+            let warning = "Monitoring plays into the device recorded as system audio, which will echo into the recording";
+            if let Err(e) = app.emit("audio-monitor-warning", warning) {
+                log_error!("Failed to emit audio monitor warning: {}", e);
+            }
+        }
+        let receiver = mic_stream.subscribe().await;
+        let sample_rate = mic_stream.device_config.sample_rate().0;
+        match AudioMonitor::start(receiver, sample_rate, &transcription_config.monitor) {
+            Ok(monitor) => {
+                if let Ok(mut slot) = AUDIO_MONITOR.lock() {
+                    *slot = Some(monitor);
+                }
+            }
+            // Monitoring is a convenience, the recording goes ahead without it
+            Err(e) => log_error!("Failed to start audio monitor: {}", e),
+        }
+    }
 
     unsafe {
         MIC_STREAM = Some(mic_stream.clone());
@@ -770,12 +2142,22 @@ async fn start_recording<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
         IS_RUNNING = Some(is_running.clone());
     }
     
-    // Create HTTP client for transcription
-    let client = reqwest::Client::new();
-    
-    // Use hardcoded transcript server URL
-    let stream_url = format!("{}/stream", TRANSCRIPT_SERVER_URL);
-    log_info!("Using hardcoded stream URL: {}", stream_url);
+    // Backend the transcription workers send chunks to
+    let mut backend = backend_for_engine(&AudioTranscriptionEngine::default(), TRANSCRIPT_SERVER_URL)?;
+    log_info!("Using {} transcription backend at {}", backend.name(), TRANSCRIPT_SERVER_URL);
+    let backend_config = transcription::config::current_config();
+    if backend_config.language_models.enabled {
+        log_info!("Switching models once the spoken language is detected");
+        backend = Box::new(LanguageModelBackend::new(
+            backend,
+            TRANSCRIPT_SERVER_URL,
+            backend_config.language_models.clone(),
+        ));
+    }
+    let backend: Arc<dyn TranscriptionBackend> = Arc::from(with_backup_servers(backend, &backend_config));
+    session_stats::begin_session(backend.name());
+    WARMUP_PENDING.store(backend_config.warmup.exclude_first_chunk, Ordering::SeqCst);
+    transcription::profiling::clear_chunk_timings();
 
     let device_config = mic_stream.device_config.clone();
     let sample_rate = device_config.sample_rate().0;
@@ -788,47 +2170,115 @@ async fn start_recording<R: Runtime>(app: AppHandle<R>) -> Result<(), String> {
         RECORDING_START_TIME.unwrap_or_else(|| std::time::Instant::now()) 
     };
     
+    // Shared emitter that puts concurrently transcribed chunks back in order
+    let emitter = Arc::new(Mutex::new(TranscriptEmitter::new(
+        CHUNK_ID_COUNTER.load(Ordering::SeqCst),
+        &transcription_config,
+    )));
+    
+    // A transcript autosaved by a recording that didn't stop cleanly is kept for restoring
+    match transcription::autosave::default_autosave_path().and_then(|path| transcription::autosave::preserve_autosave(&path)) {
+        Ok(Some(path)) => {
+            log_warn!("Kept the autosaved transcript of a recording that didn't stop cleanly at {}", path.display());
+            if let Err(e) = app.emit("transcript-autosave-recovered", path.to_string_lossy().to_string()) {
+                log_error!("Failed to emit transcript-autosave-recovered event: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log_error!("Failed to keep the previous transcript autosave: {}", e),
+    }
+    
+    // Keep the last minutes of audio on disk, after saving any left by a crash
+    let crash_buffer = if transcription_config.crash_buffer.enabled {
+        match default_crash_buffer_path() {
+            Ok(path) => {
+                match recover_crash_buffer(&path) {
+                    Ok(Some(wav_path)) => {
+                        log_warn!("Saved audio from a recording that didn't stop cleanly to {}", wav_path.display());
+                        if let Err(e) = app.emit("crash-audio-recovered", wav_path.to_string_lossy().to_string()) {
+                            log_error!("Failed to emit crash-audio-recovered event: {}", e);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => log_error!("Failed to recover crash buffer: {}", e),
+                }
+                CrashBuffer::create(&path, sample_rate, transcription_config.crash_buffer.max_minutes)
+                    .map_err(|e| log_error!("Failed to create crash recovery buffer: {}", e))
+                    .ok()
+            }
+            Err(e) => {
+                log_error!("Failed to create crash recovery buffer: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    
     // Start audio collection task
     let audio_collection_handle = {
-        let mic_stream_clone = mic_stream.clone();
-        let system_stream_clone = system_stream.clone();
-        let is_running_clone = is_running.clone();
-        let app_handle_clone = app.clone();
+        let collection = AudioCollection {
+            mic_stream: mic_stream.clone(),
+            system_stream: system_stream.clone(),
+            is_running: is_running.clone(),
+            sample_rate,
+            recording_start_time,
+            app_handle: app.clone(),
+            emitter: emitter.clone(),
+            balancer: SourceBalancer::new(transcription_config.source_balance.clone()),
+            compressor: Compressor::new(transcription_config.compressor.clone(), sample_rate),
+            pre_emphasis: PreEmphasis::new(transcription_config.pre_emphasis.clone()),
+            boundary: default_boundary_strategy(&transcription_config),
+            queue_config: transcription_config.chunk_queue.clone(),
+            chunk_output: transcription_config.chunk_output.clone(),
+            whisper_window: transcription_config.whisper_window.clone(),
+            padding_config: transcription_config.chunk_padding.clone(),
+            segment_detector: SegmentBoundaryDetector::new(transcription_config.segment_boundaries.clone()),
+            overlap_detector: OverlapDetector::new(transcription_config.overlapping_speech.clone()),
+            auto_stopper: SilenceAutoStopper::new(transcription_config.silence_auto_stop.clone()),
+            chunk_clock: ChunkClock::new(transcription_config.chunk_timing.clone()),
+            confirmation: transcription_config.speech_confirmation.clone(),
+            crash_buffer,
+            preroll,
+        };
         tokio::spawn(async move {
-            if let Err(e) = audio_collection_task(
-                mic_stream_clone,
-                system_stream_clone,
-                is_running_clone,
-                sample_rate,
-                recording_start_time,
-                app_handle_clone,
-            ).await {
+            if let Err(e) = audio_collection_task(collection).await {
                 log_error!("Audio collection task error: {}", e);
             }
         })
     };
     
-    // Start multiple transcription workers
-    const NUM_WORKERS: usize = 3;
+    // Start a bounded pool of transcription workers
+    let num_workers = transcription_worker_count();
+    log_info!("Starting {} transcription workers", num_workers);
     let mut worker_handles = Vec::new();
     
-    for worker_id in 0..NUM_WORKERS {
-        let client_clone = client.clone();
-        let stream_url_clone = stream_url.clone();
+    for worker_id in 0..num_workers {
+        let backend_clone = backend.clone();
         let app_handle_clone = app.clone();
+        let emitter_clone = emitter.clone();
         
         let worker_handle = tokio::spawn(async move {
             transcription_worker(
-                client_clone,
-                stream_url_clone,
+                backend_clone,
                 app_handle_clone,
                 worker_id,
+                emitter_clone,
             ).await;
         });
         
         worker_handles.push(worker_handle);
     }
     
+    // Periodic status for UIs that want to show pipeline health without polling
+    tokio::spawn(status_heartbeat_task(is_running.clone(), app.clone()));
+    if transcription_config.autosave.enabled {
+        let autosave_task = tokio::spawn(transcript_autosave_task(is_running.clone(), emitter.clone()));
+        if let Ok(mut slot) = AUTOSAVE_TASK.lock() {
+            *slot = Some(autosave_task);
+        }
+    }
+    
     // Store task handles globally
     unsafe {
         AUDIO_COLLECTION_TASK = Some(audio_collection_handle);
@@ -868,6 +2318,10 @@ async fn stop_recording(args: RecordingArgs) -> Result<(), String> {
     RECORDING_FLAG.store(false, Ordering::SeqCst);
     log_info!("Recording flag set to false");
     
+    if let Some(mut monitor) = AUDIO_MONITOR.lock().ok().and_then(|mut slot| slot.take()) {
+        monitor.stop();
+    }
+    
     unsafe {
         // Stop the running flag for audio streams first
         if let Some(is_running) = &IS_RUNNING {
@@ -882,6 +2336,19 @@ async fn stop_recording(args: RecordingArgs) -> Result<(), String> {
                 tokio::time::sleep(Duration::from_millis(50)).await;
             }
             
+            // The aborted task can't clean up after itself, and a clean stop leaves nothing to recover
+            if transcription::config::current_config().crash_buffer.enabled {
+                match default_crash_buffer_path() {
+                    Ok(path) if path.exists() => {
+                        if let Err(e) = std::fs::remove_file(&path) {
+                            log_error!("Failed to remove crash recovery buffer: {}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log_error!("Failed to remove crash recovery buffer: {}", e),
+                }
+            }
+            
             // Wait for transcription workers to complete processing remaining chunks
             if TRANSCRIPTION_TASK.is_some() {
                 log_info!("Waiting for transcription workers to complete...");
@@ -921,6 +2388,21 @@ async fn stop_recording(args: RecordingArgs) -> Result<(), String> {
                     log_error!("Transcription worker cleanup timeout after {} seconds", MAX_WAIT_TIME / 1000);
                 }
                 
+                // A write still due would bring the autosave back after it's removed, and get
+                // the finished recording offered for restoring
+                let autosave_task = AUTOSAVE_TASK.lock().ok().and_then(|mut slot| slot.take());
+                if let Some(task) = autosave_task {
+                    task.abort();
+                    let _ = task.await;
+                }
+                
+                // The finished transcript has been handed to the UI, so the autosave isn't needed
+                if let Err(e) = transcription::autosave::default_autosave_path()
+                    .and_then(|path| transcription::autosave::remove_autosave(&path))
+                {
+                    log_error!("Failed to remove transcript autosave: {}", e);
+                }
+                
                 // Now stop the transcription task
                 if let Some(task) = TRANSCRIPTION_TASK.take() {
                     log_info!("Stopping transcription task...");
@@ -965,6 +2447,18 @@ async fn stop_recording(args: RecordingArgs) -> Result<(), String> {
         }
     }
     
+    // Workers are done, so the session's counters are final
+    let stats_config = transcription::config::current_config().session_stats;
+    if let Some(summary) = session_stats::end_session() {
+        if stats_config.enabled {
+            let saved = session_stats::default_stats_path()
+                .and_then(|path| session_stats::append_session(&path, &summary, stats_config.max_sessions));
+            if let Err(e) = saved {
+                log_error!("Failed to save session stats: {}", e);
+            }
+        }
+    }
+    
     // Get final buffers
     let mic_data = unsafe {
         if let Some(buffer) = &MIC_BUFFER {
@@ -1070,72 +2564,442 @@ async fn stop_recording(args: RecordingArgs) -> Result<(), String> {
         }
     }
 
-    /*
-    // Save the recording
-    log_info!("Saving recording to: {}", args.save_path);
-    match fs::write(&args.save_path, wav_file) {
-        Ok(_) => log_info!("Successfully saved recording"),
-        Err(e) => {
-            let err_msg = format!("Failed to save recording: {}", e);
-            log_error!("{}", err_msg);
-            return Err(err_msg);
+    /*
+    // Save the recording
+    log_info!("Saving recording to: {}", args.save_path);
+    match fs::write(&args.save_path, wav_file) {
+        Ok(_) => log_info!("Successfully saved recording"),
+        Err(e) => {
+            let err_msg = format!("Failed to save recording: {}", e);
+            log_error!("{}", err_msg);
+            return Err(err_msg);
+        }
+    }
+    */
+    
+    // Clean up
+    unsafe {
+        MIC_BUFFER = None;
+        SYSTEM_BUFFER = None;
+        MIC_STREAM = None;
+        SYSTEM_STREAM = None;
+        IS_RUNNING = None;
+        RECORDING_START_TIME = None;
+        TRANSCRIPTION_TASK = None;
+        AUDIO_COLLECTION_TASK = None;
+        AUDIO_CHUNK_QUEUE = None;
+    }
+    
+    // Back to standby now that the recording's system stream is closed
+    sync_system_preroll().await;
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn is_recording() -> bool {
+    RECORDING_FLAG.load(Ordering::SeqCst)
+}
+
+#[tauri::command]
+fn get_transcription_status() -> TranscriptionStatus {
+    let chunks_in_queue = unsafe {
+        if let Some(queue) = &AUDIO_CHUNK_QUEUE {
+            if let Ok(queue_guard) = queue.lock() {
+                queue_guard.len()
+            } else {
+                0
+            }
+        } else {
+            0
+        }
+    };
+    
+    let is_processing = ACTIVE_WORKERS.load(Ordering::SeqCst) > 0 || chunks_in_queue > 0;
+    
+    let last_activity_ms = LAST_TRANSCRIPTION_ACTIVITY.load(Ordering::SeqCst);
+    let current_time_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let elapsed_since_activity = if last_activity_ms > 0 {
+        current_time_ms.saturating_sub(last_activity_ms)
+    } else {
+        u64::MAX
+    };
+    
+    TranscriptionStatus {
+        chunks_in_queue,
+        is_processing,
+        last_activity_ms: elapsed_since_activity,
+        failed_chunks_kept: dead_letter_count(),
+        transcription_speed: transcription::estimate::transcription_speed(),
+        quality_level: transcription::throttle::current_level(),
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn metrics_gauges() -> metrics::GaugeSnapshot {
+    metrics::GaugeSnapshot {
+        queued_chunks: queued_chunk_count(),
+        queue_capacity: transcription::config::current_config().chunk_queue.max_queued_chunks,
+        active_workers: ACTIVE_WORKERS.load(Ordering::SeqCst),
+        is_recording: RECORDING_FLAG.load(Ordering::SeqCst),
+        dead_letter_chunks: dead_letter_count(),
+        transcription_speed: transcription::estimate::transcription_speed().unwrap_or(0.0),
+    }
+}
+
+fn dead_letter_count() -> usize {
+    DEAD_LETTERS.lock().map(|queue| queue.len()).unwrap_or(0)
+}
+
+#[tauri::command]
+fn get_transcription_config() -> TranscriptionConfig {
+    transcription::config::current_config()
+}
+
+#[tauri::command]
+fn set_transcription_config(config: TranscriptionConfig) -> Result<(), String> {
+    log_info!("Updating transcription config: {:?}", config);
+    transcription::config::replace_config(config, RECORDING_FLAG.load(Ordering::SeqCst))?;
+    tauri::async_runtime::spawn(sync_system_preroll());
+    Ok(())
+}
+
+#[tauri::command]
+fn update_transcription_config(patch: serde_json::Value) -> Result<TranscriptionConfig, String> {
+    log_info!("Applying partial transcription config: {}", patch);
+    let updated = transcription::config::update_config(patch, RECORDING_FLAG.load(Ordering::SeqCst))?;
+    tauri::async_runtime::spawn(sync_system_preroll());
+    Ok(updated)
+}
+
+/// Resets the speech/silence and level tracking of the running recording
+/// (source balance, compressor, endpointing and segment detection), keeping the
+/// transcript context used for whisper prompts. Useful after a device change
+/// made the measured levels jump.
+#[tauri::command]
+fn reset_level_tracking() -> Result<(), String> {
+    if !RECORDING_FLAG.load(Ordering::SeqCst) {
+        return Err("Not recording".to_string());
+    }
+    LEVEL_RESET_REQUESTED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Clears the recent transcript passed to whisper as the prompt, keeping the
+/// level and speech detection state.
+#[tauri::command]
+fn reset_transcript_context() -> Result<(), String> {
+    if !RECORDING_FLAG.load(Ordering::SeqCst) {
+        return Err("Not recording".to_string());
+    }
+    CONTEXT_RESET_REQUESTED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Transcribes the chunks kept after failing, emitting `failed-chunk-transcribed`
+/// for each one that now succeeds. Chunks that fail again stay queued.
+#[tauri::command]
+async fn reprocess_failed_chunks<R: Runtime>(app: AppHandle<R>) -> Result<ReprocessReport, String> {
+    if is_recording() {
+        return Err("Can't reprocess failed chunks while recording".to_string());
+    }
+    let count = dead_letter_count();
+    if count == 0 {
+        return Ok(ReprocessReport::default());
+    }
+    log_info!("Reprocessing {} failed chunks", count);
+
+    let config = transcription::config::current_config();
+    let backend = backend_for_engine(&AudioTranscriptionEngine::default(), TRANSCRIPT_SERVER_URL)?;
+    let backend = with_backup_servers(backend, &config);
+    let report = reprocess_failed(backend.as_ref(), &DEAD_LETTERS, &config.dead_letters, |recovered| {
+        if let Err(e) = app.emit("failed-chunk-transcribed", &recovered) {
+            log_error!("Failed to emit failed-chunk-transcribed event: {}", e);
+        }
+    })
+    .await;
+    Ok(report)
+}
+
+/// Mutes or unmutes a source for transcription without stopping its capture.
+#[tauri::command]
+fn mute_source<R: Runtime>(app: AppHandle<R>, source: CaptureSource, muted: bool) -> Result<SourceGateState, String> {
+    if !RECORDING_FLAG.load(Ordering::SeqCst) {
+        return Err("Not recording".to_string());
+    }
+    let state = SOURCE_GATE.set_muted(source, muted);
+    log_info!("{:?} {} for transcription", source, if muted { "muted" } else { "unmuted" });
+    emit_source_gate(&app, state);
+    Ok(state)
+}
+
+/// Transcribes only `source` until called again with `None`, which restores the
+/// sources' own mute state.
+#[tauri::command]
+fn solo_source<R: Runtime>(app: AppHandle<R>, source: Option<CaptureSource>) -> Result<SourceGateState, String> {
+    if !RECORDING_FLAG.load(Ordering::SeqCst) {
+        return Err("Not recording".to_string());
+    }
+    let state = SOURCE_GATE.set_solo(source);
+    log_info!("Transcription solo set to {:?}", source);
+    emit_source_gate(&app, state);
+    Ok(state)
+}
+
+#[tauri::command]
+fn validate_model(model_path: String) -> ModelCompatibility {
+    let compatibility = transcription::check_model(std::path::Path::new(&model_path));
+    if let Some(error) = &compatibility.error {
+        log_warn!("Model {} can't be loaded: {}", model_path, error);
+    }
+    compatibility
+}
+
+/// The models configured for language switching, with whether each can be loaded
+/// and how much memory it needs.
+#[tauri::command]
+fn list_models() -> Vec<ModelCompatibility> {
+    let config = transcription::config::current_config().language_models;
+    config
+        .model_paths()
+        .iter()
+        .map(|path| {
+            let file = config.model_file(path).unwrap_or_else(|| path.into());
+            transcription::check_model(&file)
+        })
+        .collect()
+}
+
+#[tauri::command]
+fn get_source_gate() -> SourceGateState {
+    SOURCE_GATE.state()
+}
+
+fn emit_source_gate<R: Runtime>(app: &AppHandle<R>, state: SourceGateState) {
+    if let Err(e) = app.emit("source-gate-changed", state) {
+        log_error!("Failed to emit source-gate-changed event: {}", e);
+    }
+}
+
+/// Estimates how long transcribing `file_duration_ms` of audio will take, based on
+/// how fast the whisper server has handled chunks so far. Without any history a
+/// short calibration request is timed first.
+#[tauri::command]
+async fn estimate_processing(file_duration_ms: u64) -> Result<ProcessingEstimate, String> {
+    let (real_time_factor, measurements) = match transcription::estimate::real_time_factor() {
+        Some(measured) => measured,
+        None => {
+            // The calibration request would hold up live chunks waiting on the server
+            if RECORDING_FLAG.load(Ordering::SeqCst) {
+                return Err("No transcription timing yet, try again once the first chunk is transcribed".to_string());
+            }
+            calibrate_real_time_factor().await?
+        }
+    };
+    Ok(transcription::estimate::estimate(file_duration_ms, real_time_factor, measurements, WHISPER_SAMPLE_RATE))
+}
+
+// Times one synthetic request against the whisper server
+async fn calibrate_real_time_factor() -> Result<(f64, u64), String> {
+    log_info!("Calibrating transcription speed with a {} ms request", CALIBRATION_AUDIO_MS);
+    let sample_count = WHISPER_SAMPLE_RATE as u64 * CALIBRATION_AUDIO_MS / 1000;
+    let samples: Vec<f32> = (0..sample_count)
+        .map(|i| 0.1 * (i as f32 * 2.0 * std::f32::consts::PI * 220.0 / WHISPER_SAMPLE_RATE as f32).sin())
+        .collect();
+
+    let backend = backend_for_engine(&AudioTranscriptionEngine::default(), TRANSCRIPT_SERVER_URL)?;
+    let started = std::time::Instant::now();
+    backend
+        .transcribe(0, samples, None)
+        .await
+        .map_err(|e| format!("Calibration request failed: {}", e))?;
+    transcription::estimate::record_processing(CALIBRATION_AUDIO_MS, started.elapsed().as_millis() as u64);
+
+    transcription::estimate::real_time_factor().ok_or_else(|| "Calibration produced no measurement".to_string())
+}
+
+/// Transcribes a WAV file for quality rather than speed, decoding every chunk
+/// several ways and keeping the best result. Progress is reported with
+/// `offline-transcription-progress` events.
+#[tauri::command]
+async fn transcribe_file_offline<R: Runtime>(app: AppHandle<R>, file_path: String) -> Result<OfflineTranscript, String> {
+    // Each pass holds the server for the length of a chunk, which live transcription can't afford
+    if is_recording() {
+        return Err("Can't transcribe a file while recording".to_string());
+    }
+    let samples = transcription::read_wav_for_whisper(std::path::Path::new(&file_path))
+        .map_err(|e| format!("Failed to read audio file: {}", e))?;
+    let config = transcription::config::current_config().offline;
+    log_info!(
+        "Transcribing {:.1}s from {} with {} decoding passes",
+        samples.len() as f64 / WHISPER_SAMPLE_RATE as f64,
+        file_path,
+        config.passes.len()
+    );
+
+    let backend = WhisperServerBackend::new(TRANSCRIPT_SERVER_URL);
+    transcription::transcribe_offline(&backend, &samples, &config, |chunks_done, chunks_total| {
+        let progress = OfflineProgress { chunks_done, chunks_total };
+        if let Err(e) = app.emit("offline-transcription-progress", progress) {
+            log_error!("Failed to emit offline transcription progress: {}", e);
+        }
+    })
+    .await
+    .map_err(|e| e.user_message())
+}
+
+#[tauri::command]
+async fn transcribe_files<R: Runtime>(
+    app: AppHandle<R>,
+    file_paths: Vec<String>,
+    concurrency: Option<usize>,
+) -> Result<Vec<FileTranscript>, String> {
+    if is_recording() {
+        return Err("Can't transcribe files while recording".to_string());
+    }
+    let config = transcription::config::current_config();
+    let concurrency = transcription::batch_concurrency(concurrency, &config.offline);
+    log_info!("Batch transcribing {} files, {} at a time", file_paths.len(), concurrency);
+
+    let backend = backend_for_engine(&AudioTranscriptionEngine::default(), TRANSCRIPT_SERVER_URL)?;
+    let backend: Arc<dyn TranscriptionBackend> = Arc::from(with_backup_servers(backend, &config));
+    let paths = file_paths.into_iter().map(std::path::PathBuf::from).collect();
+    let results = transcription::transcribe_files(backend, paths, concurrency, &config.offline, move |progress: BatchProgress| {
+        if let Err(e) = app.emit("batch-transcription-progress", progress) {
+            log_error!("Failed to emit batch transcription progress: {}", e);
         }
+    })
+    .await;
+    log_info!(
+        "Batch transcription finished, {} of {} files failed",
+        results.iter().filter(|result| result.error.is_some()).count(),
+        results.len()
+    );
+    Ok(results)
+}
+
+#[tauri::command]
+async fn get_audio_devices(refresh: Option<bool>) -> Result<Vec<AudioDevice>, String> {
+    if refresh.unwrap_or(false) {
+        invalidate_device_cache();
     }
-    */
-    
-    // Clean up
-    unsafe {
-        MIC_BUFFER = None;
-        SYSTEM_BUFFER = None;
-        MIC_STREAM = None;
-        SYSTEM_STREAM = None;
-        IS_RUNNING = None;
-        RECORDING_START_TIME = None;
-        TRANSCRIPTION_TASK = None;
-        AUDIO_COLLECTION_TASK = None;
-        AUDIO_CHUNK_QUEUE = None;
+    list_audio_devices_cached(DEVICE_LIST_MAX_AGE)
+        .await
+        .map_err(|e| format!("Failed to list audio devices: {}", e))
+}
+
+#[tauri::command]
+fn get_recent_sessions(limit: Option<usize>) -> Result<Vec<session_stats::SessionSummary>, String> {
+    session_stats::default_stats_path()
+        .and_then(|path| session_stats::read_recent_sessions(&path, limit.unwrap_or(20)))
+        .map_err(|e| format!("Failed to read session stats: {}", e))
+}
+
+/// Per-stage timings of recently transcribed chunks, oldest first. Empty unless
+/// chunk profiling is enabled.
+#[tauri::command]
+fn get_chunk_timings(limit: Option<usize>) -> Vec<transcription::ChunkTiming> {
+    transcription::profiling::recent_chunk_timings(limit.unwrap_or(50))
+}
+
+/// Plays a test tone while listening on the microphone and saves what was
+/// measured as the calibration profile. Defaults to the system devices.
+#[tauri::command]
+async fn run_audio_calibration(input_device: Option<String>, output_device: Option<String>) -> Result<CalibrationProfile, String> {
+    if is_recording() {
+        return Err("Can't calibrate while recording".to_string());
     }
-    
-    Ok(())
+    let input = match input_device {
+        Some(name) => parse_audio_device(&name),
+        None => default_input_device(),
+    }
+    .map_err(|e| format!("Failed to find the input device: {}", e))?;
+    let output_device = output_device.unwrap_or_else(|| "default".to_string());
+
+    let config = transcription::config::current_config().calibration;
+    let profile = run_calibration(Arc::new(input), &output_device, &config)
+        .await
+        .map_err(|e| format!("Calibration failed: {}", e))?;
+    if let Err(e) = save_calibration_profile(&profile) {
+        log_error!("Failed to save calibration profile: {}", e);
+    }
+    Ok(profile)
 }
 
 #[tauri::command]
-fn is_recording() -> bool {
-    RECORDING_FLAG.load(Ordering::SeqCst)
+fn get_calibration_profile() -> Result<Option<CalibrationProfile>, String> {
+    load_calibration_profile().map_err(|e| format!("Failed to read calibration profile: {}", e))
 }
 
+/// The autosaved transcript of a recording that didn't stop cleanly, from the
+/// autosave itself or the newest copy kept aside when a recording started.
+/// `None` if there is nothing to restore.
 #[tauri::command]
-fn get_transcription_status() -> TranscriptionStatus {
-    let chunks_in_queue = unsafe {
-        if let Some(queue) = &AUDIO_CHUNK_QUEUE {
-            if let Ok(queue_guard) = queue.lock() {
-                queue_guard.len()
-            } else {
-                0
-            }
-        } else {
-            0
-        }
+fn get_autosaved_transcript() -> Result<Option<MeetingTranscript>, String> {
+    if is_recording() {
+        return Err("Can't restore a transcript while recording".to_string());
+    }
+    let path = latest_autosave_path().map_err(|e| format!("Failed to find transcript autosave: {}", e))?;
+    match path {
+        Some(path) => transcription::autosave::read_autosave(&path)
+            .map_err(|e| format!("Failed to read transcript autosave: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Deletes the transcript `get_autosaved_transcript` would restore, once the
+/// user has restored or declined it.
+#[tauri::command]
+fn discard_autosaved_transcript() -> Result<(), String> {
+    if is_recording() {
+        return Err("Can't discard a transcript while recording".to_string());
+    }
+    if let Some(path) = latest_autosave_path().map_err(|e| format!("Failed to find transcript autosave: {}", e))? {
+        transcription::autosave::remove_autosave(&path).map_err(|e| format!("Failed to remove transcript autosave: {}", e))?;
+    }
+    Ok(())
+}
+
+// The autosave left by the last recording, or the newest one moved aside since
+fn latest_autosave_path() -> anyhow::Result<Option<std::path::PathBuf>> {
+    let path = transcription::autosave::default_autosave_path()?;
+    if path.exists() {
+        return Ok(Some(path));
+    }
+    let Some(dir) = path.parent() else {
+        return Ok(None);
     };
-    
-    let is_processing = ACTIVE_WORKERS.load(Ordering::SeqCst) > 0 || chunks_in_queue > 0;
-    
-    let last_activity_ms = LAST_TRANSCRIPTION_ACTIVITY.load(Ordering::SeqCst);
-    let current_time_ms = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
-    let elapsed_since_activity = if last_activity_ms > 0 {
-        current_time_ms.saturating_sub(last_activity_ms)
-    } else {
-        u64::MAX
+    let mut recovered: Vec<_> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("recovered_transcript_") && name.ends_with(".json"))
+            })
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
     };
-    
-    TranscriptionStatus {
-        chunks_in_queue,
-        is_processing,
-        last_activity_ms: elapsed_since_activity,
+    // The timestamp in the name sorts chronologically
+    recovered.sort();
+    Ok(recovered.pop())
+}
+
+/// Saves audio left by a recording that didn't stop cleanly as a WAV file and
+/// returns its path, or `None` if there is nothing to recover.
+#[tauri::command]
+fn recover_crash_audio() -> Result<Option<String>, String> {
+    if is_recording() {
+        return Err("Can't recover audio while recording".to_string());
     }
+    default_crash_buffer_path()
+        .and_then(|path| recover_crash_buffer(&path))
+        .map(|wav_path| wav_path.map(|path| path.to_string_lossy().to_string()))
+        .map_err(|e| format!("Failed to recover crash audio: {}", e))
 }
 
 #[tauri::command]
@@ -1158,6 +3022,9 @@ async fn save_transcript(file_path: String, content: String) -> Result<(), Strin
         }
     }
 
+    // Rewrite for tools that can't read UTF-8, if configured
+    let content = transcription::encode_output(&content, &transcription::config::current_config().output_encoding);
+
     // Write content to file
     std::fs::write(&file_path, content)
         .map_err(|e| format!("Failed to write transcript: {}", e))?;
@@ -1166,6 +3033,39 @@ async fn save_transcript(file_path: String, content: String) -> Result<(), Strin
     Ok(())
 }
 
+#[tauri::command]
+fn format_transcript_paragraphs(segments: Vec<transcription::ExportSegment>) -> String {
+    transcription::assemble_paragraphs(&segments, &transcription::config::current_config().paragraphs)
+}
+
+#[tauri::command]
+async fn export_transcript_html(
+    file_path: String,
+    title: String,
+    segments: Vec<transcription::ExportSegment>,
+    audio_path: Option<String>,
+) -> Result<(), String> {
+    log::info!("Exporting {} transcript segments as HTML to: {}", segments.len(), file_path);
+    let config = transcription::config::current_config().html_export;
+
+    let audio = match audio_path {
+        Some(audio_path) => Some(
+            transcription::ExportAudio::from_file(std::path::Path::new(&audio_path), &config)
+                .map_err(|e| format!("Failed to read audio file: {}", e))?,
+        ),
+        None => None,
+    };
+    let html = transcription::render_html(&title, &segments, audio.as_ref(), &config);
+
+    if let Some(parent) = std::path::Path::new(&file_path).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    std::fs::write(&file_path, html).map_err(|e| format!("Failed to write HTML transcript: {}", e))?;
+
+    log::info!("HTML transcript exported successfully");
+    Ok(())
+}
+
 // Analytics commands
 #[tauri::command]
 async fn init_analytics() -> Result<(), String> {
@@ -1441,6 +3341,16 @@ pub fn run() {
                 log::error!("Failed to trigger audio permission: {}", e);
             }
 
+            #[cfg(feature = "metrics")]
+            {
+                let addr = std::env::var("MEETILY_METRICS_ADDR").unwrap_or_else(|_| "127.0.0.1:9464".to_string());
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = metrics::serve(&addr, metrics_gauges).await {
+                        log::error!("Metrics endpoint stopped: {}", e);
+                    }
+                });
+            }
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -1448,8 +3358,32 @@ pub fn run() {
             stop_recording,
             is_recording,
             get_transcription_status,
+            get_transcription_config,
+            set_transcription_config,
+            update_transcription_config,
+            reset_level_tracking,
+            reset_transcript_context,
+            mute_source,
+            solo_source,
+            get_source_gate,
+            validate_model,
+            list_models,
+            reprocess_failed_chunks,
+            estimate_processing,
+            get_audio_devices,
+            get_recent_sessions,
+            recover_crash_audio,
+            transcribe_file_offline,
+            transcribe_files,
+            run_audio_calibration,
+            get_calibration_profile,
+            get_autosaved_transcript,
+            discard_autosaved_transcript,
+            get_chunk_timings,
             read_audio_file,
             save_transcript,
+            export_transcript_html,
+            format_transcript_paragraphs,
             init_analytics,
             disable_analytics,
             track_event,
@@ -1523,3 +3457,572 @@ fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     
     resampled
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ChunkTimingConfig;
+
+    // Each message with its `chunk_id` field, if it has one
+    static CAPTURED_LOGS: std::sync::Mutex<Vec<(String, Option<u64>)>> = std::sync::Mutex::new(Vec::new());
+
+    struct CapturingLogger;
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            if let Ok(mut logs) = CAPTURED_LOGS.lock() {
+                let chunk_id = record.key_values().get(log::kv::Key::from("chunk_id")).and_then(|value| value.to_u64());
+                logs.push((record.args().to_string(), chunk_id));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn captured_logs() -> Vec<(String, Option<u64>)> {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            let _ = log::set_logger(&CapturingLogger);
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        CAPTURED_LOGS.lock().map(|logs| logs.clone()).unwrap_or_default()
+    }
+
+    fn segment(text: &str, t0: f32, t1: f32) -> TranscriptSegment {
+        TranscriptSegment {
+            text: text.to_string(),
+            t0,
+            t1,
+            words: Vec::new(),
+            voice: None,
+            speaker: None,
+        }
+    }
+
+    #[test]
+    fn transcript_log_lines_carry_the_chunk_id() {
+        captured_logs();
+        let mut emitter = TranscriptEmitter::new(0, &TranscriptionConfig::default());
+        emitter
+            .accumulator
+            .set_chunk_context(4242, 0.0, std::time::Instant::now());
+        emitter
+            .accumulator
+            .add_segment(&segment("Chunk id test.", 20.0, 200.0))
+            .unwrap();
+
+        let logs = captured_logs();
+        for stage in ["Processing new transcript segment", "Generated transcript update"] {
+            assert!(
+                logs.iter().any(|(message, chunk_id)| message.starts_with(stage) && *chunk_id == Some(4242)),
+                "no {:?} log line for chunk 4242",
+                stage
+            );
+        }
+    }
+
+    #[test]
+    fn chunk_queue_stays_bounded_when_flooded() {
+        let mut queue = VecDeque::new();
+        let mut dropped = Vec::new();
+        let mut transcribed = Vec::new();
+        for chunk_id in 0..50u64 {
+            dropped.extend(push_bounded(&mut queue, chunk_id, 3));
+            assert!(queue.len() <= 3);
+            // A worker only gets through every fifth chunk
+            if chunk_id % 5 == 4 {
+                transcribed.extend(queue.pop_front());
+            }
+        }
+
+        assert_eq!(&dropped[..2], &[0, 1]);
+        assert_eq!(dropped.len() + transcribed.len() + queue.len(), 50);
+        assert_eq!(queue, VecDeque::from([48, 49]));
+    }
+
+    #[test]
+    fn resetting_level_tracking_keeps_the_prompt_context() {
+        let mut config = TranscriptionConfig::default();
+        config.prompt_context.enabled = true;
+        config.source_balance.enabled = true;
+        let mut emitter = TranscriptEmitter::new(0, &config);
+        emitter.context.as_mut().unwrap().push("We agreed on the budget.");
+        let emitter = Mutex::new(emitter);
+
+        let mut balancer = SourceBalancer::new(config.source_balance.clone());
+        balancer.process(&mut [0.4; 160], &mut [0.1; 160]);
+        assert_ne!(balancer.gains(), (1.0, 1.0));
+        let mut compressor = Compressor::new(config.compressor.clone(), 16000);
+        let mut boundary = default_boundary_strategy(&config);
+        let mut segment_detector = SegmentBoundaryDetector::new(config.segment_boundaries.clone());
+        segment_detector.update(Duration::from_secs(1), 0.1);
+
+        let level_reset = AtomicBool::new(true);
+        let context_reset = AtomicBool::new(false);
+        let mut apply = |balancer: &mut SourceBalancer, segment_detector: &mut SegmentBoundaryDetector| {
+            apply_requested_resets(
+                &level_reset,
+                &context_reset,
+                balancer,
+                &mut compressor,
+                boundary.as_mut(),
+                segment_detector,
+                &emitter,
+            )
+        };
+        apply(&mut balancer, &mut segment_detector);
+        assert_eq!(balancer.gains(), (1.0, 1.0));
+        // After the reset the silence counts as leading silence again
+        segment_detector.update(Duration::from_secs(2), 0.0);
+        assert_eq!(segment_detector.update(Duration::from_secs(60), 0.0), None);
+        assert_eq!(emitter.lock().unwrap().prompt().as_deref(), Some("We agreed on the budget."));
+        assert!(!level_reset.load(Ordering::SeqCst));
+
+        context_reset.store(true, Ordering::SeqCst);
+        apply(&mut balancer, &mut segment_detector);
+        assert_eq!(emitter.lock().unwrap().prompt(), None);
+        assert!(!context_reset.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn the_prompt_sent_with_the_next_chunk_stays_within_whisper_s_token_limit() {
+        let app = tauri::test::mock_app();
+        let mut config = TranscriptionConfig::default();
+        config.prompt_context.enabled = true;
+        let mut emitter = TranscriptEmitter::new(0, &config);
+        for chunk_id in 0..40 {
+            let sentence = format!("Invoice {} goes to Oluwaseun Adebayo-Okonkwo in Tiruchirappalli.", 4471 + chunk_id);
+            emitter.complete(
+                ChunkTranscript {
+                    chunk_id,
+                    timestamp: chunk_id as f64 * 3.0,
+                    recording_start_time: std::time::Instant::now(),
+                    audio_ticks: 320.0,
+                    segments: vec![segment(&sentence, 20.0, 300.0)],
+                },
+                app.handle(),
+            );
+        }
+
+        let prompt = emitter.prompt().unwrap();
+        assert!(prompt.contains("Invoice 4510"), "{}", prompt);
+        assert!(!prompt.contains("Invoice 4471"), "{}", prompt);
+        assert!(
+            transcription::context::estimate_tokens(&prompt) <= config.prompt_context.max_tokens,
+            "{}",
+            prompt
+        );
+    }
+
+    #[tokio::test]
+    async fn the_status_heartbeat_emits_once_per_interval() {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let emitted = Arc::new(AtomicU64::new(0));
+        let config = StatusHeartbeatConfig {
+            enabled: true,
+            interval_ms: 100,
+        };
+        let counter = emitted.clone();
+        let heartbeat = tokio::spawn(run_status_heartbeat(
+            is_running.clone(),
+            move || config.clone(),
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            },
+        ));
+
+        // One event per 100 ms tick, however often the status changed
+        tokio::time::sleep(Duration::from_millis(450)).await;
+        is_running.store(false, Ordering::SeqCst);
+        heartbeat.await.unwrap();
+        let count = emitted.load(Ordering::SeqCst);
+        assert!((3..=5).contains(&count), "{} events", count);
+    }
+
+    #[tokio::test]
+    async fn a_disabled_status_heartbeat_stays_quiet() {
+        let is_running = Arc::new(AtomicBool::new(true));
+        let emitted = Arc::new(AtomicU64::new(0));
+        let config = StatusHeartbeatConfig {
+            enabled: false,
+            interval_ms: 0,
+        };
+        let counter = emitted.clone();
+        let heartbeat = tokio::spawn(run_status_heartbeat(
+            is_running.clone(),
+            move || config.clone(),
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        is_running.store(false, Ordering::SeqCst);
+        heartbeat.await.unwrap();
+        assert_eq!(emitted.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn chunks_are_split_to_fit_whisper_with_its_overlap() {
+        let window = WhisperWindowConfig::default();
+        // 30 s less the server's 200 ms overlap and 10 ms of padding
+        assert_eq!(max_window_samples(&window, 16000), 16000 * 29790 / 1000);
+        assert_eq!(max_window_samples(&window, 48000), 48000 * 29790 / 1000);
+
+        let tiny = WhisperWindowConfig {
+            window_ms: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            max_window_samples(&tiny, 16000),
+            16000 * MIN_CHUNK_DURATION_MS as usize / 1000
+        );
+    }
+
+    #[test]
+    fn a_long_buffer_is_split_into_chunks_that_fit_the_window() {
+        let window = WhisperWindowConfig::default();
+        for sample_rate in [16000, 48000] {
+            let buffer: Vec<f32> = (0..45 * sample_rate as usize).map(|i| i as f32).collect();
+            let mut current = buffer.clone();
+            let mut chunks = Vec::new();
+            while !current.is_empty() {
+                let held_back = split_at_window(&mut current, &window, sample_rate);
+                chunks.push(std::mem::replace(&mut current, held_back.unwrap_or_default()));
+            }
+
+            assert_eq!(chunks.len(), 2);
+            for chunk in &chunks {
+                assert!(chunk.len() <= 30 * sample_rate as usize, "{} samples", chunk.len());
+            }
+            assert_eq!(chunks.concat(), buffer);
+        }
+
+        let mut unsplit = vec![0.0; 45 * 16000];
+        let disabled = WhisperWindowConfig {
+            split_long_chunks: false,
+            ..Default::default()
+        };
+        assert!(split_at_window(&mut unsplit, &disabled, 16000).is_none());
+        assert_eq!(unsplit.len(), 45 * 16000);
+    }
+
+    #[test]
+    fn pre_roll_and_coalescing_make_chunks_longer_than_the_window() {
+        let mut config = TranscriptionConfig::default();
+        assert_eq!(longest_chunk_ms(&config), CHUNK_DURATION_MS as u64);
+
+        config.chunk_coalescing.enabled = true;
+        config.system_preroll.enabled = true;
+        config.system_preroll.seconds = 10;
+        let longest = longest_chunk_ms(&config);
+        assert_eq!(
+            longest,
+            CHUNK_DURATION_MS as u64 + config.chunk_coalescing.max_delay_ms + 10_000
+        );
+        assert!(longest > config.whisper_window.window_ms as u64);
+    }
+
+    #[test]
+    fn fallback_timestamps_mark_where_the_chunk_began() {
+        // A 30 s chunk cut 65 s into the recording started at 35 s
+        assert_eq!(fallback_chunk_start(Duration::from_secs(65), 0.0, 30.0, 0.0), 35.0);
+        // Audio reaches the app 0.5 s after it was spoken
+        assert_eq!(fallback_chunk_start(Duration::from_secs(65), 0.5, 30.0, 0.0), 34.5);
+        assert_eq!(fallback_chunk_start(Duration::from_secs(1), 0.0, 3.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn pre_roll_leads_the_first_chunk_before_recording_start() {
+        let preroll = vec![0.5; 32000];
+        let mut gate = SourceGateState::default();
+        for preserve_gaps in [true, false] {
+            let mut clock = ChunkClock::new(ChunkTimingConfig {
+                preserve_gaps,
+                compensate_input_latency: false,
+            });
+            let mut chunk = preroll_chunk(&preroll, (0.75, 0.25), &gate, &mut clock, 16000);
+            assert_eq!(chunk, vec![0.125; 32000]);
+
+            // One second of live audio, cut a second into the recording
+            clock.observe(1.0, 16000, 16000);
+            chunk.extend(vec![0.0; 16000]);
+            let duration = chunk.len() as f64 / 16000.0;
+            let start = match clock.finish_chunk(duration) {
+                Some(placement) => placement.start_secs,
+                None => fallback_chunk_start(Duration::from_secs(1), 0.0, duration, clock.timeline_start()),
+            };
+            assert!((start + 2.0).abs() < 1e-9, "first chunk starts at {}", start);
+        }
+
+        // Muted system audio is silent in the pre-roll too
+        gate.system_muted = true;
+        let mut clock = ChunkClock::new(ChunkTimingConfig::default());
+        assert!(preroll_chunk(&preroll, (0.75, 0.25), &gate, &mut clock, 16000).iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn segment_times_are_ticks_after_the_server_overlap() {
+        let mut accumulator = TranscriptAccumulator::new(&TranscriptionConfig::default());
+        accumulator.set_chunk_context(0, 10.0, std::time::Instant::now());
+
+        // 1.2 s into the request is 1.0 s into the chunk, after the 200 ms overlap
+        let first = accumulator.add_segment(&segment("Hello there.", 120.0, 350.0)).unwrap();
+        assert!((first.start_secs - 11.0).abs() < 1e-6);
+        assert!((first.end_secs - 13.3).abs() < 1e-6);
+
+        let second = accumulator.add_segment(&segment("How are you?", 500.0, 700.0)).unwrap();
+        assert!((second.start_secs - 14.8).abs() < 1e-6);
+        assert!((second.end_secs - 16.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_pause_before_each_sentence_is_reported() {
+        let mut config = TranscriptionConfig::default();
+        config.gap_reporting.enabled = true;
+        let mut emitter = TranscriptEmitter::new(0, &config);
+        emitter
+            .accumulator
+            .set_chunk_context(0, 10.0, std::time::Instant::now());
+
+        let mut first = emitter
+            .accumulator
+            .add_segment(&segment("Hello there.", 120.0, 350.0))
+            .unwrap();
+        emitter.record_gap(&mut first);
+        assert_eq!(first.gap_before_ms, Some(0));
+
+        // 500 ms after the first sentence ends, across a chunk boundary
+        emitter
+            .accumulator
+            .set_chunk_context(1, 13.0, std::time::Instant::now());
+        let mut second = emitter
+            .accumulator
+            .add_segment(&segment("How are you?", 100.0, 300.0))
+            .unwrap();
+        emitter.record_gap(&mut second);
+        assert_eq!(second.gap_before_ms, Some(500));
+    }
+
+    #[test]
+    fn a_taken_sentence_carries_its_speaker_and_confidence() {
+        let mut accumulator = TranscriptAccumulator::new(&TranscriptionConfig::default());
+        accumulator.set_chunk_context(7, 0.0, std::time::Instant::now());
+        let mut unfinished = segment("Hello there", 20.0, 200.0);
+        unfinished.speaker = Some(2);
+        unfinished.words = vec![TimedWord {
+            text: "Hello".to_string(),
+            t0: 20.0,
+            t1: 80.0,
+            p: 0.5,
+        }];
+        assert!(accumulator.add_segment(&unfinished).is_none());
+
+        let first = accumulator.take_sentence(5.0, 7.0, true);
+        assert_eq!(first.text, "Hello there");
+        assert_eq!(first.speaker_hint.as_deref(), Some("Speaker 2"));
+        assert_eq!(first.confidence, Some(0.5));
+        assert!(first.is_partial);
+        assert_eq!((first.start_secs, first.end_secs), (5.0, 7.0));
+        assert!(accumulator.current_sentence.is_empty());
+
+        let second = accumulator.take_sentence(7.0, 7.0, false);
+        assert!(second.sequence_id > first.sequence_id);
+        assert_eq!(second.speaker_hint, None);
+    }
+
+    #[test]
+    fn soloing_the_mic_silences_system_audio_in_the_mix() {
+        let mic = [0.5, 0.5];
+        let system = [0.25, 0.25, 0.25];
+        let weights = (0.75, 0.25);
+
+        let raw = mix_sources(&mic, &system, weights, None);
+        assert_eq!(raw, vec![0.4375, 0.4375, 0.0625]);
+
+        let solo = SourceGateState {
+            solo: Some(CaptureSource::Microphone),
+            ..Default::default()
+        };
+        assert_eq!(mix_sources(&mic, &system, weights, Some(&solo)), vec![0.375, 0.375, 0.0]);
+
+        let system_only = SourceGateState {
+            microphone_muted: true,
+            ..Default::default()
+        };
+        assert_eq!(mix_sources(&mic, &system, weights, Some(&system_only)), vec![0.0625, 0.0625, 0.0625]);
+    }
+
+    /// Answers each chunk after a delay that shrinks with its id, so later
+    /// chunks finish first.
+    struct DelayedBackend {
+        chunks: u64,
+        step: Duration,
+    }
+
+    impl TranscriptionBackend for DelayedBackend {
+        fn name(&self) -> &str {
+            "delayed"
+        }
+
+        fn transcribe(
+            &self,
+            chunk_id: u64,
+            _samples: Vec<f32>,
+            _prompt: Option<String>,
+        ) -> transcription::backend::TranscriptionFuture<'_> {
+            let delay = self.step * (self.chunks - chunk_id) as u32;
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                let text = ["Alpha.", "Bravo.", "Charlie.", "Delta."][chunk_id as usize];
+                Ok(transcription::backend::TranscriptResponse {
+                    segments: vec![segment(text, 30.0, 80.0)],
+                    buffer_size_ms: 0,
+                    language: None,
+                    raw: None,
+                    failed_attempts: Vec::new(),
+                })
+            })
+        }
+    }
+
+    // Tests that run workers share the global chunk queue, so they take turns
+    static CHUNK_QUEUE_TURN: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn a_worker_emits_what_its_backend_transcribed() {
+        use tauri::Listener;
+
+        let _turn = CHUNK_QUEUE_TURN.lock().await;
+        let app = tauri::test::mock_app();
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        app.listen_any("transcript-update", {
+            let emitted = emitted.clone();
+            move |event| {
+                let update: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+                emitted.lock().unwrap().push(update["text"].as_str().unwrap_or_default().to_string());
+            }
+        });
+
+        let recording_start_time = std::time::Instant::now();
+        let chunk = AudioChunk {
+            samples: vec![0.01; 16000],
+            timestamp: 0.0,
+            prepare_time: Duration::ZERO,
+            chunk_id: 0,
+            start_time: recording_start_time,
+            recording_start_time,
+            overlap: Vec::new(),
+        };
+        unsafe {
+            AUDIO_CHUNK_QUEUE = Some(Arc::new(Mutex::new(VecDeque::from([chunk]))));
+        }
+
+        let backend = Arc::new(DelayedBackend { chunks: 1, step: Duration::ZERO });
+        let emitter = Arc::new(Mutex::new(TranscriptEmitter::new(0, &TranscriptionConfig::default())));
+        transcription_worker(backend, app.handle().clone(), 0, emitter).await;
+        unsafe {
+            AUDIO_CHUNK_QUEUE = None;
+        }
+
+        assert_eq!(*emitted.lock().unwrap(), ["Alpha."]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn workers_transcribe_concurrently_and_emit_in_chunk_order() {
+        use tauri::Listener;
+
+        let _turn = CHUNK_QUEUE_TURN.lock().await;
+        const CHUNKS: u64 = 4;
+        let step = Duration::from_millis(100);
+        let app = tauri::test::mock_app();
+        // The backend's delays run on the paused clock, so a busy machine can't stretch them
+        let started = tokio::time::Instant::now();
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        app.listen_any("transcript-update", {
+            let emitted = emitted.clone();
+            move |event| {
+                let update: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+                let text = update["text"].as_str().unwrap_or_default().to_string();
+                emitted.lock().unwrap().push((text, started.elapsed()));
+            }
+        });
+
+        let recording_start_time = std::time::Instant::now();
+        let chunks: VecDeque<AudioChunk> = (0..CHUNKS)
+            .map(|chunk_id| AudioChunk {
+                // Distinct audio, so no chunk is skipped as a duplicate
+                samples: vec![0.01 * (chunk_id + 1) as f32; 16000],
+                timestamp: chunk_id as f64,
+                prepare_time: Duration::ZERO,
+                chunk_id,
+                start_time: recording_start_time,
+                recording_start_time,
+                overlap: Vec::new(),
+            })
+            .collect();
+        unsafe {
+            AUDIO_CHUNK_QUEUE = Some(Arc::new(Mutex::new(chunks)));
+        }
+
+        let backend: Arc<dyn TranscriptionBackend> = Arc::new(DelayedBackend { chunks: CHUNKS, step });
+        let emitter = Arc::new(Mutex::new(TranscriptEmitter::new(0, &TranscriptionConfig::default())));
+        let workers: Vec<_> = (0..CHUNKS as usize)
+            .map(|worker_id| {
+                tokio::spawn(transcription_worker(backend.clone(), app.handle().clone(), worker_id, emitter.clone()))
+            })
+            .collect();
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        unsafe {
+            AUDIO_CHUNK_QUEUE = None;
+        }
+
+        let emitted = emitted.lock().unwrap().clone();
+        let texts: Vec<&str> = emitted.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["Alpha.", "Bravo.", "Charlie.", "Delta."]);
+        // The slowest chunk takes 400 ms, one after the other they would take a second
+        let last = emitted.last().unwrap().1;
+        assert!(last < step * 5, "last sentence emitted after {:?}", last);
+    }
+
+    #[test]
+    fn switching_from_chunks_only_to_transcribing_doesnt_stall_the_transcript() {
+        use tauri::Listener;
+
+        let app = tauri::test::mock_app();
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        app.listen_any("transcript-update", {
+            let emitted = emitted.clone();
+            move |event| {
+                let update: serde_json::Value = serde_json::from_str(event.payload()).unwrap();
+                emitted.lock().unwrap().push(update["text"].as_str().unwrap_or_default().to_string());
+            }
+        });
+        let emitter = Mutex::new(TranscriptEmitter::new(0, &TranscriptionConfig::default()));
+
+        // Chunks 0 and 1 are cut for an external transcriber, then transcription is switched on
+        for chunk_id in 0..2 {
+            skip_if_untranscribed(ChunkOutputMode::ChunksOnly, chunk_id, &emitter, app.handle());
+        }
+        skip_if_untranscribed(ChunkOutputMode::Transcribe, 2, &emitter, app.handle());
+        emitter.lock().unwrap().complete(
+            ChunkTranscript {
+                chunk_id: 2,
+                timestamp: 60.0,
+                recording_start_time: std::time::Instant::now(),
+                audio_ticks: 3020.0,
+                segments: vec![segment("Hello there.", 120.0, 350.0)],
+            },
+            app.handle(),
+        );
+
+        assert_eq!(*emitted.lock().unwrap(), ["Hello there."]);
+        assert_eq!(emitter.lock().unwrap().reorder.pending_len(), 0);
+    }
+}
@@ -0,0 +1,336 @@
+// src/metrics.rs
+//
+// Pipeline counters, rendered as OpenMetrics text. The HTTP endpoint is only
+// compiled with the `metrics` feature.
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub struct PipelineMetrics {
+    transcribed_chunks: AtomicU64,
+    warmup_chunks: AtomicU64,
+    silent_chunks: AtomicU64,
+    transcript_updates: AtomicU64,
+    redactions: AtomicU64,
+    empty_capture_buffers: AtomicU64,
+    short_capture_buffers: AtomicU64,
+    dropped_chunks: AtomicU64,
+    request_errors: AtomicU64,
+    response_errors: AtomicU64,
+    failed_chunks: AtomicU64,
+    last_latency_ms: AtomicU64,
+    total_latency_ms: AtomicU64,
+}
+
+pub static METRICS: PipelineMetrics = PipelineMetrics::new();
+
+/// Values that are read from the pipeline at scrape time rather than counted.
+#[derive(Debug, Clone, Default)]
+pub struct GaugeSnapshot {
+    pub queued_chunks: usize,
+    pub queue_capacity: usize,
+    pub active_workers: u64,
+    pub is_recording: bool,
+    pub dead_letter_chunks: usize,
+    /// Seconds of audio transcribed per second, 0 before the first chunk.
+    pub transcription_speed: f64,
+}
+
+/// Counter values at one point in time, to measure what happened in between.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterSnapshot {
+    pub transcribed_chunks: u64,
+    /// Transcribed chunks whose latency wasn't recorded.
+    pub warmup_chunks: u64,
+    pub silent_chunks: u64,
+    pub transcript_updates: u64,
+    pub dropped_chunks: u64,
+    pub failed_chunks: u64,
+    pub total_latency_ms: u64,
+}
+
+impl CounterSnapshot {
+    pub fn since(&self, earlier: &CounterSnapshot) -> CounterSnapshot {
+        CounterSnapshot {
+            transcribed_chunks: self.transcribed_chunks.saturating_sub(earlier.transcribed_chunks),
+            warmup_chunks: self.warmup_chunks.saturating_sub(earlier.warmup_chunks),
+            silent_chunks: self.silent_chunks.saturating_sub(earlier.silent_chunks),
+            transcript_updates: self.transcript_updates.saturating_sub(earlier.transcript_updates),
+            dropped_chunks: self.dropped_chunks.saturating_sub(earlier.dropped_chunks),
+            failed_chunks: self.failed_chunks.saturating_sub(earlier.failed_chunks),
+            total_latency_ms: self.total_latency_ms.saturating_sub(earlier.total_latency_ms),
+        }
+    }
+}
+
+impl Default for PipelineMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineMetrics {
+    pub const fn new() -> Self {
+        Self {
+            transcribed_chunks: AtomicU64::new(0),
+            warmup_chunks: AtomicU64::new(0),
+            silent_chunks: AtomicU64::new(0),
+            transcript_updates: AtomicU64::new(0),
+            redactions: AtomicU64::new(0),
+            empty_capture_buffers: AtomicU64::new(0),
+            short_capture_buffers: AtomicU64::new(0),
+            dropped_chunks: AtomicU64::new(0),
+            request_errors: AtomicU64::new(0),
+            response_errors: AtomicU64::new(0),
+            failed_chunks: AtomicU64::new(0),
+            last_latency_ms: AtomicU64::new(0),
+            total_latency_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// A chunk came back from whisper; latency is measured from when it was queued.
+    pub fn record_transcribed_chunk(&self, latency_ms: u64) {
+        self.transcribed_chunks.fetch_add(1, Ordering::Relaxed);
+        self.last_latency_ms.store(latency_ms, Ordering::Relaxed);
+        self.total_latency_ms.fetch_add(latency_ms, Ordering::Relaxed);
+    }
+
+    /// A chunk came back from whisper but its latency isn't representative, e.g.
+    /// because the server was still warming up.
+    pub fn record_warmup_chunk(&self) {
+        self.transcribed_chunks.fetch_add(1, Ordering::Relaxed);
+        self.warmup_chunks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A transcribed chunk had no speech in it. It still counts as transcribed.
+    pub fn record_silent_chunk(&self) {
+        self.silent_chunks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_transcript_update(&self) {
+        self.transcript_updates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_redactions(&self, count: usize) {
+        self.redactions.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// The capture callback delivered no samples; the buffer was dropped.
+    pub fn record_empty_capture_buffer(&self) {
+        self.empty_capture_buffers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The capture callback delivered less than a frame; it was held back and merged.
+    pub fn record_short_capture_buffer(&self) {
+        self.short_capture_buffers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_chunk(&self) {
+        self.dropped_chunks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_request_error(&self) {
+        self.request_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_response_error(&self) {
+        self.response_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_failed_chunk(&self) {
+        self.failed_chunks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            transcribed_chunks: self.transcribed_chunks.load(Ordering::Relaxed),
+            warmup_chunks: self.warmup_chunks.load(Ordering::Relaxed),
+            silent_chunks: self.silent_chunks.load(Ordering::Relaxed),
+            transcript_updates: self.transcript_updates.load(Ordering::Relaxed),
+            dropped_chunks: self.dropped_chunks.load(Ordering::Relaxed),
+            failed_chunks: self.failed_chunks.load(Ordering::Relaxed),
+            total_latency_ms: self.total_latency_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn render(&self, gauges: &GaugeSnapshot) -> String {
+        let mut out = String::new();
+
+        write_metric(&mut out, "meetily_transcribed_chunks", "counter",
+            "Audio chunks transcribed by whisper.",
+            &[("", self.transcribed_chunks.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, "meetily_silent_chunks", "counter",
+            "Transcribed chunks in which whisper found no speech.",
+            &[("", self.silent_chunks.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, "meetily_transcript_updates", "counter",
+            "Transcript updates emitted to the UI.",
+            &[("", self.transcript_updates.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, "meetily_redactions", "counter",
+            "Sensitive matches redacted from transcripts.",
+            &[("", self.redactions.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, "meetily_degenerate_capture_buffers", "counter",
+            "Capture callbacks with too few samples, by how they were handled.",
+            &[
+                ("kind=\"empty\"", self.empty_capture_buffers.load(Ordering::Relaxed) as f64),
+                ("kind=\"short\"", self.short_capture_buffers.load(Ordering::Relaxed) as f64),
+            ]);
+        write_metric(&mut out, "meetily_dropped_chunks", "counter",
+            "Audio chunks dropped because the queue was full.",
+            &[("", self.dropped_chunks.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, "meetily_transcription_errors", "counter",
+            "Transcription errors by cause.",
+            &[
+                ("cause=\"request\"", self.request_errors.load(Ordering::Relaxed) as f64),
+                ("cause=\"response\"", self.response_errors.load(Ordering::Relaxed) as f64),
+                ("cause=\"retries_exhausted\"", self.failed_chunks.load(Ordering::Relaxed) as f64),
+            ]);
+        write_metric(&mut out, "meetily_chunk_queue_length", "gauge",
+            "Chunks waiting for a transcription worker.",
+            &[("", gauges.queued_chunks as f64)]);
+        let utilization = if gauges.queue_capacity > 0 {
+            gauges.queued_chunks as f64 / gauges.queue_capacity as f64
+        } else {
+            0.0
+        };
+        write_metric(&mut out, "meetily_chunk_queue_utilization", "gauge",
+            "Fraction of the chunk queue in use.",
+            &[("", utilization)]);
+        write_metric(&mut out, "meetily_dead_letter_chunks", "gauge",
+            "Failed chunks kept for transcribing again.",
+            &[("", gauges.dead_letter_chunks as f64)]);
+        write_metric(&mut out, "meetily_active_workers", "gauge",
+            "Transcription workers currently running.",
+            &[("", gauges.active_workers as f64)]);
+        write_metric(&mut out, "meetily_recording", "gauge",
+            "1 while a recording is in progress.",
+            &[("", if gauges.is_recording { 1.0 } else { 0.0 })]);
+        write_metric(&mut out, "meetily_transcription_speed", "gauge",
+            "Seconds of audio transcribed per second; below 1 transcription falls behind.",
+            &[("", gauges.transcription_speed)]);
+        write_metric(&mut out, "meetily_transcription_latency_seconds", "gauge",
+            "Time from queueing the most recent chunk to receiving its transcript.",
+            &[("", self.last_latency_ms.load(Ordering::Relaxed) as f64 / 1000.0)]);
+
+        out.push_str("# EOF\n");
+        out
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(&str, f64)]) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let suffix = if kind == "counter" { "_total" } else { "" };
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(out, "{}{} {}", name, suffix, value);
+        } else {
+            let _ = writeln!(out, "{}{}{{{}}} {}", name, suffix, labels, value);
+        }
+    }
+}
+
+/// Serves `/metrics` on `addr` until the listener fails. Every request gets the
+/// current metrics regardless of path.
+#[cfg(feature = "metrics")]
+pub async fn serve<F>(addr: &str, gauges: F) -> std::io::Result<()>
+where
+    F: Fn() -> GaugeSnapshot + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("Serving pipeline metrics on http://{}/metrics", addr);
+
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let body = METRICS.render(&gauges());
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            if let Err(e) = socket.write_all(response.as_bytes()).await {
+                log::debug!("Failed to write metrics response: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_known_values_as_openmetrics() {
+        let metrics = PipelineMetrics::new();
+        metrics.record_transcribed_chunk(250);
+        metrics.record_transcribed_chunk(1500);
+        metrics.record_dropped_chunk();
+        metrics.record_request_error();
+        let gauges = GaugeSnapshot {
+            queued_chunks: 2,
+            queue_capacity: 8,
+            is_recording: true,
+            ..Default::default()
+        };
+
+        let text = metrics.render(&gauges);
+        for line in [
+            "# TYPE meetily_transcribed_chunks counter",
+            "meetily_transcribed_chunks_total 2",
+            "meetily_dropped_chunks_total 1",
+            "meetily_transcription_errors_total{cause=\"request\"} 1",
+            "meetily_chunk_queue_utilization 0.25",
+            "meetily_recording 1",
+            "meetily_transcription_latency_seconds 1.5",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn every_sample_line_has_a_name_and_a_number() {
+        let text = PipelineMetrics::new().render(&GaugeSnapshot::default());
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let (name, value) = line.rsplit_once(' ').unwrap();
+            assert!(name.starts_with("meetily_"), "{}", line);
+            assert!(value.parse::<f64>().is_ok(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn snapshots_measure_what_happened_in_between() {
+        let metrics = PipelineMetrics::new();
+        metrics.record_transcribed_chunk(100);
+        let earlier = metrics.snapshot();
+        metrics.record_transcribed_chunk(300);
+        metrics.record_failed_chunk();
+
+        let delta = metrics.snapshot().since(&earlier);
+        assert_eq!(delta.transcribed_chunks, 1);
+        assert_eq!(delta.failed_chunks, 1);
+        assert_eq!(delta.total_latency_ms, 300);
+    }
+
+    #[test]
+    fn a_warmup_chunk_is_counted_without_its_latency() {
+        let metrics = PipelineMetrics::new();
+        metrics.record_warmup_chunk();
+        metrics.record_transcribed_chunk(200);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.transcribed_chunks, 2);
+        assert_eq!(snapshot.warmup_chunks, 1);
+        assert_eq!(snapshot.total_latency_ms, 200);
+        let text = metrics.render(&GaugeSnapshot::default());
+        assert!(
+            text.lines()
+                .any(|line| line == "meetily_transcription_latency_seconds 0.2"),
+            "{}",
+            text
+        );
+    }
+}
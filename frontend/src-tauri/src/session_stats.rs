@@ -0,0 +1,234 @@
+// src/session_stats.rs
+//
+// Per-recording summaries appended to a JSON lines file, so transcription
+// performance can be compared across meetings.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::metrics::{CounterSnapshot, METRICS};
+
+/// Schema version written with every record. Bump it when fields change meaning.
+pub const SESSION_STATS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionStatsConfig {
+    /// Off by default, nothing about meetings is written to disk unless asked for.
+    pub enabled: bool,
+    /// Oldest sessions are dropped from the file beyond this many.
+    pub max_sessions: usize,
+}
+
+impl Default for SessionStatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_sessions: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub version: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub transcribed_chunks: u64,
+    /// Transcribed chunks with no speech in them, which aren't errors.
+    #[serde(default)]
+    pub silent_chunks: u64,
+    pub failed_chunks: u64,
+    pub dropped_chunks: u64,
+    pub transcript_updates: u64,
+    /// Failed chunks as a fraction of all chunks sent for transcription.
+    pub error_rate: f64,
+    pub average_latency_ms: f64,
+    /// Transcription backend the session used.
+    pub backend: String,
+}
+
+struct ActiveSession {
+    started_at: DateTime<Utc>,
+    started: Instant,
+    counters: CounterSnapshot,
+    backend: String,
+}
+
+lazy_static! {
+    static ref ACTIVE_SESSION: Mutex<Option<ActiveSession>> = Mutex::new(None);
+}
+
+impl ActiveSession {
+    fn new(backend: &str, counters: CounterSnapshot) -> Self {
+        Self {
+            started_at: Utc::now(),
+            started: Instant::now(),
+            counters,
+            backend: backend.to_string(),
+        }
+    }
+
+    /// The session up to the moment `counters` were taken.
+    fn summarize(self, counters: CounterSnapshot) -> SessionSummary {
+        let counters = counters.since(&self.counters);
+        let attempted = counters.transcribed_chunks + counters.failed_chunks;
+        // Warm-up chunks have no latency recorded, so they'd pull the average down.
+        let timed_chunks = counters.transcribed_chunks.saturating_sub(counters.warmup_chunks);
+
+        SessionSummary {
+            version: SESSION_STATS_VERSION,
+            started_at: self.started_at,
+            duration_secs: self.started.elapsed().as_secs_f64(),
+            transcribed_chunks: counters.transcribed_chunks,
+            silent_chunks: counters.silent_chunks,
+            failed_chunks: counters.failed_chunks,
+            dropped_chunks: counters.dropped_chunks,
+            transcript_updates: counters.transcript_updates,
+            error_rate: if attempted > 0 { counters.failed_chunks as f64 / attempted as f64 } else { 0.0 },
+            average_latency_ms: if timed_chunks > 0 {
+                counters.total_latency_ms as f64 / timed_chunks as f64
+            } else {
+                0.0
+            },
+            backend: self.backend,
+        }
+    }
+}
+
+/// Marks the start of a recording. The pipeline counters are global, so the
+/// summary is the difference between now and the end of the session.
+pub fn begin_session(backend: &str) {
+    if let Ok(mut session) = ACTIVE_SESSION.lock() {
+        *session = Some(ActiveSession::new(backend, METRICS.snapshot()));
+    }
+}
+
+/// Summarizes the recording started with `begin_session`.
+pub fn end_session() -> Option<SessionSummary> {
+    let session = ACTIVE_SESSION.lock().ok()?.take()?;
+    Some(session.summarize(METRICS.snapshot()))
+}
+
+pub fn default_stats_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("couldn't find the data directory")?;
+    Ok(data_dir.join("com.meetily.ai").join("session_stats.jsonl"))
+}
+
+/// Appends `summary` to the stats file, keeping at most `max_sessions` records.
+pub fn append_session(path: &PathBuf, summary: &SessionSummary, max_sessions: usize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let line = serde_json::to_string(summary)?;
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    drop(file);
+
+    let contents = std::fs::read_to_string(path)?;
+    let lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.len() > max_sessions.max(1) {
+        let kept = &lines[lines.len() - max_sessions.max(1)..];
+        std::fs::write(path, kept.join("\n") + "\n")?;
+    }
+
+    info!("Saved session stats to {}", path.display());
+    Ok(())
+}
+
+/// The most recent `limit` sessions, newest last. Records from a newer schema
+/// or that fail to parse are skipped.
+pub fn read_recent_sessions(path: &PathBuf, limit: usize) -> Result<Vec<SessionSummary>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    let sessions: Vec<SessionSummary> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<SessionSummary>(line) {
+            Ok(summary) if summary.version <= SESSION_STATS_VERSION => Some(summary),
+            Ok(summary) => {
+                warn!("Skipping session stats record with unknown version {}", summary.version);
+                None
+            }
+            Err(e) => {
+                warn!("Skipping unreadable session stats record: {}", e);
+                None
+            }
+        })
+        .collect();
+
+    let start = sessions.len().saturating_sub(limit);
+    Ok(sessions[start..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::PipelineMetrics;
+
+    #[test]
+    fn a_session_summary_is_written_and_read_back() {
+        let metrics = PipelineMetrics::new();
+        metrics.record_transcribed_chunk(50);
+        let session = ActiveSession::new("mock", metrics.snapshot());
+        metrics.record_warmup_chunk();
+        metrics.record_transcribed_chunk(100);
+        metrics.record_transcribed_chunk(300);
+        metrics.record_failed_chunk();
+        let summary = session.summarize(metrics.snapshot());
+        assert_eq!(summary.transcribed_chunks, 3);
+        assert_eq!(summary.average_latency_ms, 200.0);
+        assert!((summary.error_rate - 1.0 / 4.0).abs() < 1e-9);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_stats.jsonl");
+        append_session(&path, &summary, 10).unwrap();
+
+        let sessions = read_recent_sessions(&path, 10).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].backend, "mock");
+        assert_eq!(sessions[0].failed_chunks, 1);
+    }
+
+    #[test]
+    fn keeps_the_latest_sessions_and_skips_newer_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_stats.jsonl");
+        let mut summary = SessionSummary {
+            version: SESSION_STATS_VERSION,
+            started_at: Utc::now(),
+            duration_secs: 60.0,
+            transcribed_chunks: 0,
+            silent_chunks: 0,
+            failed_chunks: 0,
+            dropped_chunks: 0,
+            transcript_updates: 0,
+            error_rate: 0.0,
+            average_latency_ms: 0.0,
+            backend: "mock".to_string(),
+        };
+        for transcribed_chunks in 1..=3 {
+            summary.transcribed_chunks = transcribed_chunks;
+            append_session(&path, &summary, 2).unwrap();
+        }
+        summary.version = SESSION_STATS_VERSION + 1;
+        append_session(&path, &summary, 10).unwrap();
+
+        let sessions = read_recent_sessions(&path, 10).unwrap();
+        let counts: Vec<u64> = sessions.iter().map(|session| session.transcribed_chunks).collect();
+        assert_eq!(counts, vec![2, 3]);
+        assert!(read_recent_sessions(&dir.path().join("missing.jsonl"), 10)
+            .unwrap()
+            .is_empty());
+    }
+}
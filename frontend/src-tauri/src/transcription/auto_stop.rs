@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// What to do once every source has been silent for the configured time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SilenceAction {
+    /// Ask the UI to stop and save the recording.
+    Stop,
+    /// Keep the session open but stop sending audio to whisper.
+    Pause,
+}
+
+/// Settings for stopping or pausing an unattended recording once the room has
+/// gone quiet, e.g. after everyone has left.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceAutoStopConfig {
+    pub enabled: bool,
+    pub silence_minutes: u64,
+    /// Audio quieter than this RMS counts as silence.
+    pub silence_rms: f32,
+    pub action: SilenceAction,
+    /// Pick the recording back up when someone speaks again. Only applies to `Pause`.
+    pub resume_on_speech: bool,
+}
+
+impl Default for SilenceAutoStopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            silence_minutes: 15,
+            silence_rms: 0.005,
+            action: SilenceAction::Pause,
+            resume_on_speech: true,
+        }
+    }
+}
+
+/// Payload of the `silence-auto-stop` event. Times are seconds since recording start.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SilenceAutoStop {
+    pub action: SilenceAction,
+    pub silence_start: f64,
+    pub detected_at: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SilenceTransition {
+    Triggered(SilenceAutoStop),
+    Resumed { at: f64 },
+}
+
+/// Tracks how long the mixed audio has been silent and fires once the
+/// configured duration is reached.
+pub struct SilenceAutoStopper {
+    config: SilenceAutoStopConfig,
+    silence_started: Option<Duration>,
+    triggered: Option<SilenceAction>,
+}
+
+impl SilenceAutoStopper {
+    pub fn new(config: SilenceAutoStopConfig) -> Self {
+        Self {
+            config,
+            silence_started: None,
+            triggered: None,
+        }
+    }
+
+    pub fn update_config(&mut self, config: SilenceAutoStopConfig) {
+        if !config.enabled {
+            self.silence_started = None;
+            self.triggered = None;
+        }
+        self.config = config;
+    }
+
+    /// Whether captured audio should currently be left out of chunks.
+    pub fn is_paused(&self) -> bool {
+        self.triggered.is_some()
+    }
+
+    /// Feed the RMS of a batch of audio captured at `elapsed` into the recording.
+    pub fn update(&mut self, elapsed: Duration, rms: f32) -> Option<SilenceTransition> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        if rms >= self.config.silence_rms {
+            self.silence_started = None;
+            // A stop is final, the UI is already tearing the session down
+            if self.triggered == Some(SilenceAction::Pause) && self.config.resume_on_speech {
+                self.triggered = None;
+                return Some(SilenceTransition::Resumed { at: elapsed.as_secs_f64() });
+            }
+            return None;
+        }
+
+        let silence_started = *self.silence_started.get_or_insert(elapsed);
+        if self.triggered.is_some()
+            || elapsed.saturating_sub(silence_started) < Duration::from_secs(self.config.silence_minutes * 60)
+        {
+            return None;
+        }
+
+        self.triggered = Some(self.config.action);
+        Some(SilenceTransition::Triggered(SilenceAutoStop {
+            action: self.config.action,
+            silence_start: silence_started.as_secs_f64(),
+            detected_at: elapsed.as_secs_f64(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stopper(action: SilenceAction) -> SilenceAutoStopper {
+        SilenceAutoStopper::new(SilenceAutoStopConfig {
+            enabled: true,
+            silence_minutes: 1,
+            action,
+            ..Default::default()
+        })
+    }
+
+    fn at(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn triggers_once_after_the_configured_silence() {
+        let mut stopper = stopper(SilenceAction::Stop);
+        assert_eq!(stopper.update(at(10), 0.0), None);
+        assert_eq!(stopper.update(at(69), 0.0), None);
+
+        let expected = SilenceAutoStop {
+            action: SilenceAction::Stop,
+            silence_start: 10.0,
+            detected_at: 70.0,
+        };
+        assert_eq!(
+            stopper.update(at(70), 0.0),
+            Some(SilenceTransition::Triggered(expected))
+        );
+        assert_eq!(stopper.update(at(80), 0.0), None);
+        // A stop isn't undone by speech
+        assert_eq!(stopper.update(at(90), 0.1), None);
+        assert!(stopper.is_paused());
+    }
+
+    #[test]
+    fn speech_restarts_the_silence_timer() {
+        let mut stopper = stopper(SilenceAction::Pause);
+        stopper.update(at(0), 0.0);
+        stopper.update(at(50), 0.1);
+        assert_eq!(stopper.update(at(100), 0.0), None);
+        assert!(matches!(
+            stopper.update(at(160), 0.0),
+            Some(SilenceTransition::Triggered(_))
+        ));
+    }
+
+    #[test]
+    fn a_pause_resumes_on_speech() {
+        let mut stopper = stopper(SilenceAction::Pause);
+        stopper.update(at(0), 0.0);
+        stopper.update(at(60), 0.0);
+        assert!(stopper.is_paused());
+
+        assert_eq!(
+            stopper.update(at(75), 0.1),
+            Some(SilenceTransition::Resumed { at: 75.0 })
+        );
+        assert!(!stopper.is_paused());
+    }
+
+    #[test]
+    fn disabling_clears_a_pause() {
+        let mut stopper = stopper(SilenceAction::Pause);
+        stopper.update(at(0), 0.0);
+        stopper.update(at(60), 0.0);
+
+        stopper.update_config(SilenceAutoStopConfig::default());
+        assert!(!stopper.is_paused());
+        assert_eq!(stopper.update(at(200), 0.0), None);
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::summary::MeetingTranscript;
+
+/// Settings for periodically writing the transcript to disk while recording,
+/// so a crash loses at most one interval of it. Takes effect from the next
+/// recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptAutosaveConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+}
+
+impl Default for TranscriptAutosaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 30,
+        }
+    }
+}
+
+pub fn default_autosave_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("couldn't find the data directory")?;
+    Ok(data_dir.join("com.meetily.ai").join("transcript_autosave.json"))
+}
+
+/// Replaces the autosave at `path`. The transcript is written to a temporary
+/// file first and renamed over the old one, so a crash mid-write leaves the
+/// previous autosave intact.
+pub fn write_autosave(path: &Path, transcript: &MeetingTranscript) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_vec(transcript)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// The transcript autosaved by a recording that didn't stop cleanly, `None` if
+/// there is none.
+pub fn read_autosave(path: &Path) -> Result<Option<MeetingTranscript>> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn remove_autosave(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Moves a leftover autosave aside so a new recording doesn't overwrite it.
+/// Returns where it was moved, or `None` if there was none.
+pub fn preserve_autosave(path: &Path) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let name = format!("recovered_transcript_{}.json", chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
+    let preserved = path.with_file_name(name);
+    std::fs::rename(path, &preserved)?;
+    Ok(Some(preserved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::html_export::ExportSegment;
+
+    fn transcript(sentences: &[&str]) -> MeetingTranscript {
+        MeetingTranscript {
+            sentences: sentences
+                .iter()
+                .enumerate()
+                .map(|(index, text)| ExportSegment {
+                    text: text.to_string(),
+                    start: index as f64 * 2.0,
+                    end: Some(index as f64 * 2.0 + 1.5),
+                    speaker: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn each_autosave_replaces_the_last_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("meetily").join("transcript_autosave.json");
+        assert!(read_autosave(&path).unwrap().is_none());
+
+        write_autosave(&path, &transcript(&["Welcome."])).unwrap();
+        write_autosave(&path, &transcript(&["Welcome.", "Let's begin."])).unwrap();
+
+        let restored = read_autosave(&path).unwrap().unwrap();
+        assert_eq!(restored.sentences.len(), 2);
+        assert_eq!(restored.sentences[1].text, "Let's begin.");
+        assert!(!path.with_extension("json.tmp").exists());
+
+        remove_autosave(&path).unwrap();
+        remove_autosave(&path).unwrap();
+        assert!(read_autosave(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn a_leftover_autosave_is_moved_aside_for_restoring() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transcript_autosave.json");
+        assert_eq!(preserve_autosave(&path).unwrap(), None);
+
+        write_autosave(&path, &transcript(&["Before the crash."])).unwrap();
+        let preserved = preserve_autosave(&path).unwrap().unwrap();
+        assert!(!path.exists());
+        let name = preserved.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("recovered_transcript_"), "{}", name);
+        assert_eq!(
+            read_autosave(&preserved).unwrap().unwrap().sentences[0].text,
+            "Before the crash."
+        );
+    }
+}
@@ -0,0 +1,1387 @@
+use log::{debug, error, info, warn};
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::offline::DecodingPass;
+use super::overlap::TimedWord;
+use super::speakers::VoiceFeatures;
+use super::throttle::{self, QualityLevel};
+use super::WHISPER_SAMPLE_RATE;
+use crate::audio::AudioTranscriptionEngine;
+use crate::metrics::METRICS;
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub t0: f32,
+    pub t1: f32,
+    #[serde(default)]
+    pub words: Vec<TimedWord>,
+    /// Measured from the chunk audio when speaker hints are enabled.
+    #[serde(skip)]
+    pub voice: Option<VoiceFeatures>,
+    /// Provisional speaker id, assigned in chunk order.
+    #[serde(skip)]
+    pub speaker: Option<u32>,
+}
+
+/// Whisper's markers for audio without speech.
+const BLANK_MARKERS: [&str; 2] = ["[BLANK_AUDIO]", "[AUDIO OUT]"];
+
+/// `text` without whisper's no-speech markers, trimmed.
+pub fn strip_blank_markers(text: &str) -> String {
+    BLANK_MARKERS
+        .iter()
+        .fold(text.to_string(), |text, marker| text.replace(marker, ""))
+        .trim()
+        .to_string()
+}
+
+impl TranscriptSegment {
+    /// Whether the segment has no speech, only whitespace or no-speech markers.
+    pub fn is_blank(&self) -> bool {
+        strip_blank_markers(&self.text).is_empty()
+    }
+
+    pub fn from_words(words: Vec<TimedWord>) -> Option<Self> {
+        let t0 = words.first()?.t0;
+        let t1 = words.last()?.t1;
+        let text = words.iter().map(|word| word.text.as_str()).collect();
+        Some(Self {
+            text,
+            t0,
+            t1,
+            words,
+            voice: None,
+            speaker: None,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptResponse {
+    /// Missing or empty when whisper heard no speech, which isn't an error.
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+    pub buffer_size_ms: i32,
+    /// Language whisper detected, e.g. "ja". Reported by newer servers only.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// The response exactly as the server sent it, kept when raw output is enabled.
+    #[serde(skip)]
+    pub raw: Option<serde_json::Value>,
+    /// Attempts that failed before this response, kept when attempt reporting is enabled.
+    #[serde(skip)]
+    pub failed_attempts: Vec<AttemptInfo>,
+}
+
+/// One failed request for a chunk.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttemptInfo {
+    /// 1-based attempt number.
+    pub attempt: u32,
+    /// How long the request took before failing.
+    pub elapsed_ms: u64,
+    pub error: TranscriptionError,
+}
+
+/// Payload of the `transcription-retries` event, sent for chunks that needed
+/// more than one request.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryReport {
+    pub chunk_id: u64,
+    pub failed_attempts: Vec<AttemptInfo>,
+}
+
+/// Settings for passing the backend's unprocessed response on to the frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RawOutputConfig {
+    /// Off by default, the responses include every word and are large.
+    pub enabled: bool,
+}
+
+/// Payload of the `transcript-raw` event, one per transcribed chunk.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawTranscript {
+    pub chunk_id: u64,
+    /// Seconds since recording start.
+    pub chunk_start_time: f64,
+    pub response: serde_json::Value,
+}
+
+/// Reads the server's JSON as a transcript, keeping the JSON itself when `keep_raw` is set.
+fn parse_response(value: serde_json::Value, keep_raw: bool) -> Result<TranscriptResponse, String> {
+    let raw = keep_raw.then(|| value.clone());
+    serde_json::from_value::<TranscriptResponse>(value)
+        .map(|transcript| TranscriptResponse { raw, ..transcript })
+        .map_err(|e| e.to_string())
+}
+
+/// How many times a failed request is retried, with exponential backoff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendRetryConfig {
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after it.
+    pub initial_backoff_ms: u64,
+    /// Report each chunk's failed attempts to the frontend, for tuning the retry settings.
+    pub report_attempts: bool,
+}
+
+impl Default for BackendRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 200,
+            report_attempts: false,
+        }
+    }
+}
+
+/// Settings for raising whisper's `best_of` while recent chunks come back with
+/// low word confidence, and lowering it again once they don't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodingEscalationConfig {
+    pub enabled: bool,
+    /// Mean word probability below which the next chunk is decoded with more candidates.
+    pub min_confidence: f32,
+    pub base_best_of: u32,
+    pub max_best_of: u32,
+    /// Escalation stops, and steps back down, once a request takes longer than
+    /// this fraction of the chunk's duration, so transcription keeps up with the audio.
+    pub max_processing_ratio: f32,
+}
+
+impl Default for DecodingEscalationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_confidence: 0.6,
+            base_best_of: 2,
+            max_best_of: 5,
+            max_processing_ratio: 0.5,
+        }
+    }
+}
+
+/// Mean probability of the response's words, `None` if it has no words.
+fn mean_word_confidence(transcript: &TranscriptResponse) -> Option<f32> {
+    let (sum, count) = transcript
+        .segments
+        .iter()
+        .flat_map(|segment| segment.words.iter())
+        .fold((0.0, 0usize), |(sum, count), word| (sum + word.p, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+/// Why a chunk couldn't be transcribed, so the UI can suggest a fix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TranscriptionError {
+    /// Nothing is listening at the server address, or it dropped the connection.
+    ServerUnavailable { message: String },
+    /// The server accepted the request but didn't answer in time.
+    Timeout { message: String },
+    /// The server failed on its side, e.g. while loading the model.
+    ServerError { status: u16, message: String },
+    /// The server refused the request. Retrying the same audio won't help.
+    Rejected { status: u16, message: String },
+    /// The server answered with something that isn't a transcript.
+    InvalidResponse { message: String },
+    /// The selected engine has no backend.
+    Unsupported { message: String },
+    /// The chunk isn't the audio whisper expects, which points to a bug in the pipeline.
+    InvalidAudio { message: String },
+}
+
+impl TranscriptionError {
+    fn from_request(error: reqwest::Error) -> Self {
+        let message = error.to_string();
+        if error.is_timeout() {
+            Self::Timeout { message }
+        } else if error.is_connect() || error.is_request() || error.is_body() {
+            Self::ServerUnavailable { message }
+        } else {
+            Self::InvalidResponse { message }
+        }
+    }
+
+    fn from_status(status: u16, message: String) -> Self {
+        if status >= 500 {
+            Self::ServerError { status, message }
+        } else {
+            Self::Rejected { status, message }
+        }
+    }
+
+    /// Whether the same request might succeed if sent again.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::ServerUnavailable { .. } | Self::Timeout { .. } | Self::ServerError { .. }
+        )
+    }
+
+    /// What to tell the user, including what they can do about it.
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::ServerUnavailable { .. } => {
+                "Transcription service is not available. Please check if the server is running.".to_string()
+            }
+            Self::Timeout { .. } => {
+                "Transcription service is not responding. It may still be loading the model, or the machine is short on memory.".to_string()
+            }
+            Self::ServerError { status, .. } => format!(
+                "Transcription server failed (HTTP {}). Check the server log; the model may be missing or corrupt.",
+                status
+            ),
+            Self::Rejected { status, .. } => format!("Transcription server rejected the audio (HTTP {}).", status),
+            Self::InvalidResponse { .. } => {
+                "Transcription server sent an unexpected response. Check that the server version matches the app.".to_string()
+            }
+            Self::Unsupported { message } => message.clone(),
+            Self::InvalidAudio { .. } => {
+                "Recorded audio couldn't be prepared for transcription. Please report this with the app log.".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for TranscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ServerUnavailable { message } => write!(f, "server unavailable: {}", message),
+            Self::Timeout { message } => write!(f, "request timed out: {}", message),
+            Self::ServerError { status, message } => write!(f, "server error {}: {}", status, message),
+            Self::Rejected { status, message } => write!(f, "request rejected with {}: {}", status, message),
+            Self::InvalidResponse { message } => write!(f, "invalid response: {}", message),
+            Self::Unsupported { message } => write!(f, "{}", message),
+            Self::InvalidAudio { message } => write!(f, "invalid audio: {}", message),
+        }
+    }
+}
+
+impl From<TranscriptionError> for String {
+    fn from(error: TranscriptionError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Checks that a chunk is what whisper expects: non-empty samples with finite
+/// values. Catches pipeline bugs before they turn into garbled text. Chunks are
+/// always resampled to 16 kHz when they are cut, so the rate isn't checked here.
+pub fn validate_audio(samples: &[f32]) -> Result<(), TranscriptionError> {
+    if samples.is_empty() {
+        return Err(TranscriptionError::InvalidAudio {
+            message: "chunk has no samples".to_string(),
+        });
+    }
+    if let Some(index) = samples.iter().position(|sample| !sample.is_finite()) {
+        return Err(TranscriptionError::InvalidAudio {
+            message: format!("sample {} is {}", index, samples[index]),
+        });
+    }
+    Ok(())
+}
+
+pub type TranscriptionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TranscriptResponse, TranscriptionError>> + Send + 'a>>;
+
+/// A speech-to-text service the transcription workers send chunks to.
+pub trait TranscriptionBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Transcribes one chunk of 16 kHz mono audio. `prompt` is recent transcript
+    /// text the backend may use as context.
+    fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_>;
+
+    /// Transcribes `samples` on their own with the given decoding. Backends that
+    /// can't choose the decoding transcribe them like a live chunk without a prompt.
+    fn transcribe_pass<'a>(
+        &'a self,
+        chunk_id: u64,
+        samples: &'a [f32],
+        _pass: &'a DecodingPass,
+    ) -> TranscriptionFuture<'a> {
+        self.transcribe(chunk_id, samples.to_vec(), None)
+    }
+}
+
+/// Picks the backend for `engine`. Every whisper model is served by the local
+/// whisper server, which decides the model itself.
+pub fn backend_for_engine(
+    engine: &AudioTranscriptionEngine,
+    server_url: &str,
+) -> Result<Box<dyn TranscriptionBackend>, TranscriptionError> {
+    match engine {
+        AudioTranscriptionEngine::Deepgram => Err(TranscriptionError::Unsupported {
+            message: "Deepgram transcription is not available yet".to_string(),
+        }),
+        AudioTranscriptionEngine::WhisperTiny
+        | AudioTranscriptionEngine::WhisperDistilLargeV3
+        | AudioTranscriptionEngine::WhisperLargeV3Turbo
+        | AudioTranscriptionEngine::WhisperLargeV3 => Ok(Box::new(WhisperServerBackend::new(server_url))),
+    }
+}
+
+// Request audio as the server reads it: little-endian f32, clamped to [-1, 1]
+fn to_request_bytes(samples: &[f32]) -> Vec<u8> {
+    samples.iter().flat_map(|&sample| sample.clamp(-1.0, 1.0).to_le_bytes()).collect()
+}
+
+// Decoding options sent along with the audio. Unset ones are left to the server.
+#[derive(Debug, Default)]
+struct StreamParams {
+    prompt: Option<String>,
+    best_of: Option<u32>,
+    beam_size: Option<u32>,
+    temperature: Option<f32>,
+    no_fallback: bool,
+}
+
+/// The bundled whisper.cpp server, reached over its `/stream` endpoint.
+pub struct WhisperServerBackend {
+    client: reqwest::Client,
+    stream_url: String,
+    /// `best_of` sent with the next chunk when decoding escalation is enabled,
+    /// 0 until the first chunk.
+    best_of: AtomicU32,
+}
+
+impl WhisperServerBackend {
+    pub fn new(server_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            stream_url: format!("{}/stream", server_url),
+            best_of: AtomicU32::new(0),
+        }
+    }
+
+    fn current_best_of(&self, config: &DecodingEscalationConfig) -> u32 {
+        let max = config.max_best_of.max(config.base_best_of);
+        match self.best_of.load(Ordering::Relaxed) {
+            0 => config.base_best_of,
+            best_of => best_of.clamp(config.base_best_of, max),
+        }
+    }
+
+    // Steps best_of up after a low-confidence chunk, and down after a confident
+    // one or one that took too long to decode.
+    fn adjust_best_of(
+        &self,
+        chunk_id: u64,
+        config: &DecodingEscalationConfig,
+        best_of: u32,
+        transcript: &TranscriptResponse,
+        elapsed: Duration,
+        audio_secs: f32,
+    ) {
+        let Some(confidence) = mean_word_confidence(transcript) else {
+            return;
+        };
+        let too_slow = elapsed.as_secs_f32() > audio_secs * config.max_processing_ratio;
+        let next = if confidence < config.min_confidence && !too_slow {
+            (best_of + 1).min(config.max_best_of.max(config.base_best_of))
+        } else {
+            best_of.saturating_sub(1).max(config.base_best_of)
+        };
+        if next != best_of {
+            info!(
+                chunk_id = chunk_id;
+                "mean word confidence {:.2}, decoded in {:?}; best_of {} -> {}",
+                confidence, elapsed, best_of, next
+            );
+        }
+        self.best_of.store(next, Ordering::Relaxed);
+    }
+
+    // Sends one `/stream` request. Failures are counted in the metrics and
+    // classified so the caller can tell which ones are worth retrying.
+    fn send_stream(
+        &self,
+        bytes: Vec<u8>,
+        params: &StreamParams,
+        keep_raw: bool,
+    ) -> impl Future<Output = Result<TranscriptResponse, TranscriptionError>> {
+        let part = Part::bytes(bytes).file_name("audio.raw").mime_str("audio/x-raw").unwrap();
+        // Chunks carry their own overlap with the previous chunk, so the server mustn't
+        // add the one it keeps: with several workers that is from whichever request ran last
+        let mut form = Form::new().part("audio", part).text("stateless", "true");
+        if let Some(prompt) = &params.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if let Some(best_of) = params.best_of {
+            form = form.text("best_of", best_of.to_string());
+        }
+        if let Some(beam_size) = params.beam_size {
+            form = form.text("beam_size", beam_size.to_string());
+        }
+        if let Some(temperature) = params.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        if params.no_fallback {
+            form = form.text("no_fallback", "true");
+        }
+
+        let request = self.client.post(&self.stream_url).multipart(form);
+        async move {
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    match response.json::<serde_json::Value>().await {
+                        Ok(value) => parse_response(value, keep_raw).map_err(|message| {
+                            METRICS.record_response_error();
+                            TranscriptionError::InvalidResponse { message }
+                        }),
+                        // The body stalled or the connection dropped while reading it
+                        Err(e) if !e.is_decode() => {
+                            METRICS.record_request_error();
+                            Err(TranscriptionError::from_request(e))
+                        }
+                        Err(e) => {
+                            METRICS.record_response_error();
+                            Err(TranscriptionError::InvalidResponse { message: e.to_string() })
+                        }
+                    }
+                }
+                Ok(response) => {
+                    METRICS.record_response_error();
+                    let status = response.status().as_u16();
+                    let message = response.text().await.unwrap_or_default();
+                    Err(TranscriptionError::from_status(status, message))
+                }
+                Err(e) => {
+                    METRICS.record_request_error();
+                    Err(TranscriptionError::from_request(e))
+                }
+            }
+        }
+    }
+}
+
+impl TranscriptionBackend for WhisperServerBackend {
+    fn name(&self) -> &str {
+        "whisper-server"
+    }
+
+    fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+        Box::pin(async move {
+            debug!(chunk_id = chunk_id; "Preparing to send audio chunk of size: {}", samples.len());
+
+            let bytes = to_request_bytes(&samples);
+
+            let config = super::config::current_config();
+            let retry = config.backend_retry;
+            let keep_raw = config.raw_output.enabled;
+            let escalation = config.decoding_escalation;
+            let escalated_best_of = escalation.enabled.then(|| self.current_best_of(&escalation));
+            // While transcription is falling behind, escalation pauses and chunks get a single candidate
+            let quality = throttle::current_level();
+            let params = StreamParams {
+                prompt,
+                best_of: if quality >= QualityLevel::SingleCandidate { Some(1) } else { escalated_best_of },
+                no_fallback: quality >= QualityLevel::NoFallback,
+                ..Default::default()
+            };
+            // A fresh multipart form for each attempt since a Form can't be reused
+            let send = || self.send_stream(bytes.clone(), &params, keep_raw);
+
+            let (transcript, elapsed) = send_with_retries(chunk_id, &retry, send).await?;
+            if let Some(best_of) = escalated_best_of.filter(|_| quality == QualityLevel::Full) {
+                let audio_secs = samples.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+                self.adjust_best_of(chunk_id, &escalation, best_of, &transcript, elapsed, audio_secs);
+            }
+            Ok(transcript)
+        })
+    }
+
+    // Retried like live chunks. `chunk_id` only labels the log lines.
+    fn transcribe_pass<'a>(
+        &'a self,
+        chunk_id: u64,
+        samples: &'a [f32],
+        pass: &'a DecodingPass,
+    ) -> TranscriptionFuture<'a> {
+        Box::pin(async move {
+            let config = super::config::current_config();
+            let params = StreamParams {
+                beam_size: Some(pass.beam_size),
+                temperature: Some(pass.temperature),
+                ..Default::default()
+            };
+            let bytes = to_request_bytes(samples);
+            let send = || self.send_stream(bytes.clone(), &params, config.raw_output.enabled);
+            let (transcript, _) = send_with_retries(chunk_id, &config.backend_retry, send).await?;
+            Ok(transcript)
+        })
+    }
+}
+
+/// Sends a request with `send` until it succeeds, fails for good or runs out of
+/// retries. Returns the transcript, with the failed attempts when they are
+/// reported, and how long the successful attempt took.
+async fn send_with_retries<F, Fut>(
+    chunk_id: u64,
+    retry: &BackendRetryConfig,
+    mut send: F,
+) -> Result<(TranscriptResponse, Duration), TranscriptionError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<TranscriptResponse, TranscriptionError>>,
+{
+    let mut attempt = 0;
+    let mut failed_attempts = Vec::new();
+
+    loop {
+        if attempt > 0 {
+            // Exponential backoff from the configured initial delay
+            let delay = Duration::from_millis(retry.initial_backoff_ms.saturating_mul(1 << (attempt - 1).min(16)));
+            info!(chunk_id = chunk_id; "Retry attempt {} of {}. Waiting {:?} before retry...",
+                  attempt, retry.max_retries, delay);
+            tokio::time::sleep(delay).await;
+        }
+
+        let attempt_started = Instant::now();
+        match send().await {
+            Ok(transcript) => {
+                return Ok((TranscriptResponse { failed_attempts, ..transcript }, attempt_started.elapsed()));
+            }
+            Err(e) => {
+                error!(chunk_id = chunk_id; "Attempt {} failed: {}", attempt + 1, e);
+                if !e.is_transient() || attempt >= retry.max_retries {
+                    return Err(e);
+                }
+                if retry.report_attempts {
+                    failed_attempts.push(AttemptInfo {
+                        attempt: attempt + 1,
+                        elapsed_ms: attempt_started.elapsed().as_millis() as u64,
+                        error: e,
+                    });
+                }
+            }
+        }
+
+        attempt += 1;
+    }
+}
+
+/// A second whisper server, usually running a small model, that takes over
+/// while the primary one is unreachable or failing, e.g. while it loads a model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallbackServerConfig {
+    pub enabled: bool,
+    pub server_url: String,
+    /// How long chunks go straight to the fallback before the primary is tried again.
+    pub primary_retry_secs: u64,
+}
+
+impl Default for FallbackServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server_url: "http://127.0.0.1:8179".to_string(),
+            primary_retry_secs: 10,
+        }
+    }
+}
+
+/// Sends chunks to the primary backend, switching to the fallback when the
+/// primary fails with a transient error and back once it answers again.
+/// Requests are stateless and carry their own overlap, so the fallback's
+/// results line up although it only sees some of the chunks.
+pub struct FallbackBackend {
+    primary: Box<dyn TranscriptionBackend>,
+    fallback: Box<dyn TranscriptionBackend>,
+    primary_retry: Duration,
+    primary_down_until: Mutex<Option<Instant>>,
+}
+
+impl FallbackBackend {
+    pub fn new(
+        primary: Box<dyn TranscriptionBackend>,
+        fallback: Box<dyn TranscriptionBackend>,
+        primary_retry: Duration,
+    ) -> Self {
+        Self {
+            primary,
+            fallback,
+            primary_retry,
+            primary_down_until: Mutex::new(None),
+        }
+    }
+
+    fn primary_down(&self) -> bool {
+        self.primary_down_until
+            .lock()
+            .map(|until| until.is_some_and(|until| Instant::now() < until))
+            .unwrap_or(false)
+    }
+
+    fn set_primary_down(&self, down: bool) {
+        if let Ok(mut until) = self.primary_down_until.lock() {
+            *until = down.then(|| Instant::now() + self.primary_retry);
+        }
+    }
+}
+
+impl FallbackBackend {
+    // Sends the request `send` makes to the primary, or to the fallback while the
+    // primary is down
+    fn transcribe_with<'a, F>(&'a self, chunk_id: u64, send: F) -> TranscriptionFuture<'a>
+    where
+        F: Fn(&'a dyn TranscriptionBackend) -> TranscriptionFuture<'a> + Send + 'a,
+    {
+        Box::pin(async move {
+            if !self.primary_down() {
+                match send(self.primary.as_ref()).await {
+                    Ok(response) => {
+                        self.set_primary_down(false);
+                        return Ok(response);
+                    }
+                    Err(e) if e.is_transient() => {
+                        warn!(chunk_id = chunk_id; "Primary transcription failed ({}), switching to fallback server", e);
+                        self.set_primary_down(true);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            debug!(chunk_id = chunk_id; "Transcribing with fallback server");
+            send(self.fallback.as_ref()).await
+        })
+    }
+}
+
+impl TranscriptionBackend for FallbackBackend {
+    fn name(&self) -> &str {
+        self.primary.name()
+    }
+
+    fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+        self.transcribe_with(chunk_id, move |backend| backend.transcribe(chunk_id, samples.clone(), prompt.clone()))
+    }
+
+    fn transcribe_pass<'a>(
+        &'a self,
+        chunk_id: u64,
+        samples: &'a [f32],
+        pass: &'a DecodingPass,
+    ) -> TranscriptionFuture<'a> {
+        self.transcribe_with(chunk_id, move |backend| backend.transcribe_pass(chunk_id, samples, pass))
+    }
+}
+
+/// A second whisper server running a larger, slower model. Chunks the primary
+/// transcribes with low confidence are sent to it once more.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelEscalationConfig {
+    pub enabled: bool,
+    pub server_url: String,
+    /// Mean word probability below which a chunk is re-transcribed.
+    pub min_confidence: f32,
+    /// The primary result is kept when the larger model takes longer than this.
+    pub timeout_ms: u64,
+}
+
+impl Default for ModelEscalationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server_url: "http://127.0.0.1:8180".to_string(),
+            min_confidence: 0.5,
+            timeout_ms: 10000,
+        }
+    }
+}
+
+/// Re-transcribes low-confidence chunks on a more accurate backend and keeps
+/// whichever result is more confident. The accurate server only sees these
+/// chunks, which is fine as requests are stateless and carry their own overlap.
+pub struct ModelEscalationBackend {
+    primary: Box<dyn TranscriptionBackend>,
+    accurate: Box<dyn TranscriptionBackend>,
+    min_confidence: f32,
+    timeout: Duration,
+}
+
+impl ModelEscalationBackend {
+    pub fn new(
+        primary: Box<dyn TranscriptionBackend>,
+        accurate: Box<dyn TranscriptionBackend>,
+        config: &ModelEscalationConfig,
+    ) -> Self {
+        Self {
+            primary,
+            accurate,
+            min_confidence: config.min_confidence,
+            timeout: Duration::from_millis(config.timeout_ms),
+        }
+    }
+}
+
+impl ModelEscalationBackend {
+    // Sends the request `send` makes to the primary, and once more to the
+    // accurate backend when the primary result has low confidence
+    fn transcribe_with<'a, F>(&'a self, chunk_id: u64, send: F) -> TranscriptionFuture<'a>
+    where
+        F: Fn(&'a dyn TranscriptionBackend) -> TranscriptionFuture<'a> + Send + 'a,
+    {
+        Box::pin(async move {
+            let response = send(self.primary.as_ref()).await?;
+            let Some(confidence) = mean_word_confidence(&response) else {
+                return Ok(response);
+            };
+            if confidence >= self.min_confidence {
+                return Ok(response);
+            }
+
+            debug!(chunk_id = chunk_id; "mean word confidence {:.2}, retrying with the larger model", confidence);
+            match tokio::time::timeout(self.timeout, send(self.accurate.as_ref())).await {
+                Ok(Ok(escalated)) => match mean_word_confidence(&escalated) {
+                    Some(escalated_confidence) if escalated_confidence > confidence => {
+                        info!(
+                            chunk_id = chunk_id;
+                            "larger model raised mean word confidence from {:.2} to {:.2}",
+                            confidence, escalated_confidence
+                        );
+                        Ok(escalated)
+                    }
+                    _ => Ok(response),
+                },
+                Ok(Err(e)) => {
+                    warn!(chunk_id = chunk_id; "Larger model failed ({}), keeping the primary result", e);
+                    Ok(response)
+                }
+                Err(_) => {
+                    warn!(chunk_id = chunk_id; "Larger model took longer than {:?}, keeping the primary result", self.timeout);
+                    Ok(response)
+                }
+            }
+        })
+    }
+}
+
+impl TranscriptionBackend for ModelEscalationBackend {
+    fn name(&self) -> &str {
+        self.primary.name()
+    }
+
+    fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+        self.transcribe_with(chunk_id, move |backend| backend.transcribe(chunk_id, samples.clone(), prompt.clone()))
+    }
+
+    fn transcribe_pass<'a>(
+        &'a self,
+        chunk_id: u64,
+        samples: &'a [f32],
+        pass: &'a DecodingPass,
+    ) -> TranscriptionFuture<'a> {
+        self.transcribe_with(chunk_id, move |backend| backend.transcribe_pass(chunk_id, samples, pass))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Answers every chunk with `reply`, after failing with `failures` in turn, and
+    /// records the chunk ids and prompts it was sent.
+    struct MockBackend {
+        reply: Result<&'static str, TranscriptionError>,
+        failures: Mutex<Vec<TranscriptionError>>,
+        requests: Arc<Mutex<Vec<(u64, Option<String>)>>>,
+    }
+
+    impl MockBackend {
+        fn new(reply: Result<&'static str, TranscriptionError>) -> Self {
+            Self {
+                reply,
+                failures: Mutex::new(Vec::new()),
+                requests: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        fn failing_first(self, mut failures: Vec<TranscriptionError>) -> Self {
+            failures.reverse();
+            Self {
+                failures: Mutex::new(failures),
+                ..self
+            }
+        }
+    }
+
+    impl TranscriptionBackend for MockBackend {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn transcribe(&self, chunk_id: u64, _samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+            self.requests.lock().unwrap().push((chunk_id, prompt));
+            let reply = match self.failures.lock().unwrap().pop() {
+                Some(failure) => Err(failure),
+                None => self.reply.clone().map(response),
+            };
+            Box::pin(async move { reply })
+        }
+    }
+
+    fn response(text: &str) -> TranscriptResponse {
+        TranscriptResponse {
+            segments: vec![TranscriptSegment {
+                text: text.to_string(),
+                t0: 0.0,
+                t1: 100.0,
+                words: Vec::new(),
+                voice: None,
+                speaker: None,
+            }],
+            buffer_size_ms: 1000,
+            language: None,
+            raw: None,
+            failed_attempts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn whisper_engines_share_the_server_backend() {
+        let backend = backend_for_engine(&AudioTranscriptionEngine::WhisperTiny, "http://127.0.0.1:8178").unwrap();
+        assert_eq!(backend.name(), "whisper-server");
+
+        let deepgram = backend_for_engine(&AudioTranscriptionEngine::Deepgram, "http://127.0.0.1:8178");
+        assert!(matches!(deepgram, Err(TranscriptionError::Unsupported { .. })));
+    }
+
+    #[test]
+    fn server_failures_are_retried_and_rejections_are_not() {
+        let server_error = TranscriptionError::from_status(503, "model loading".to_string());
+        assert!(matches!(
+            server_error,
+            TranscriptionError::ServerError { status: 503, .. }
+        ));
+        assert!(server_error.is_transient());
+
+        let rejected = TranscriptionError::from_status(413, "too large".to_string());
+        assert!(matches!(rejected, TranscriptionError::Rejected { status: 413, .. }));
+        assert!(!rejected.is_transient());
+
+        let invalid = TranscriptionError::InvalidResponse { message: String::new() };
+        assert!(!invalid.is_transient());
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_server_is_reported_as_unavailable() {
+        // Nothing listens on port 1
+        let error = reqwest::Client::new()
+            .get("http://127.0.0.1:1/stream")
+            .send()
+            .await
+            .unwrap_err();
+        let error = TranscriptionError::from_request(error);
+        assert!(matches!(error, TranscriptionError::ServerUnavailable { .. }));
+        assert!(error.is_transient());
+        assert!(error.user_message().contains("check if the server is running"));
+    }
+
+    #[tokio::test]
+    async fn a_server_that_never_answers_times_out_and_is_retried() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Accept the connection and hold it open without answering
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(socket);
+        });
+
+        let error = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap()
+            .get(format!("http://{}/inference", addr))
+            .send()
+            .await
+            .unwrap_err();
+        let error = TranscriptionError::from_request(error);
+        assert!(matches!(error, TranscriptionError::Timeout { .. }));
+        assert!(error.is_transient());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn a_dropped_connection_is_retried() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            drop(socket);
+        });
+
+        let error = reqwest::Client::new()
+            .get(format!("http://{}/inference", addr))
+            .send()
+            .await
+            .unwrap_err();
+        let error = TranscriptionError::from_request(error);
+        assert!(matches!(error, TranscriptionError::ServerUnavailable { .. }));
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn each_failure_suggests_its_own_fix() {
+        let errors = [
+            TranscriptionError::ServerUnavailable { message: String::new() },
+            TranscriptionError::Timeout { message: String::new() },
+            TranscriptionError::from_status(500, String::new()),
+            TranscriptionError::from_status(400, String::new()),
+            TranscriptionError::InvalidResponse { message: String::new() },
+            TranscriptionError::InvalidAudio { message: String::new() },
+        ];
+        let messages: std::collections::HashSet<String> = errors.iter().map(|error| error.user_message()).collect();
+        assert_eq!(messages.len(), errors.len());
+    }
+
+    fn unavailable() -> TranscriptionError {
+        TranscriptionError::ServerUnavailable {
+            message: "connection refused".to_string(),
+        }
+    }
+
+    async fn transcript(backend: &dyn TranscriptionBackend, chunk_id: u64) -> Result<String, TranscriptionError> {
+        let response = backend.transcribe(chunk_id, vec![0.0; 16000], None).await?;
+        Ok(response.segments[0].text.clone())
+    }
+
+    #[tokio::test]
+    async fn the_fallback_transcribes_while_the_primary_is_down() {
+        let primary = MockBackend::new(Ok("primary")).failing_first(vec![unavailable()]);
+        let primary_requests = primary.requests.clone();
+        let fallback = MockBackend::new(Ok("fallback"));
+        let backend = FallbackBackend::new(Box::new(primary), Box::new(fallback), Duration::from_secs(60));
+
+        assert_eq!(transcript(&backend, 0).await, Ok("fallback".to_string()));
+        // The primary isn't tried again until the retry delay has passed
+        assert_eq!(transcript(&backend, 1).await, Ok("fallback".to_string()));
+        assert_eq!(primary_requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn primary_results_resume_once_it_answers_again() {
+        let primary = MockBackend::new(Ok("primary")).failing_first(vec![unavailable()]);
+        let fallback = MockBackend::new(Ok("fallback"));
+        let backend = FallbackBackend::new(Box::new(primary), Box::new(fallback), Duration::ZERO);
+
+        assert_eq!(transcript(&backend, 0).await, Ok("fallback".to_string()));
+        assert_eq!(transcript(&backend, 1).await, Ok("primary".to_string()));
+    }
+
+    #[tokio::test]
+    async fn rejected_audio_is_not_sent_to_the_fallback() {
+        let rejected = TranscriptionError::from_status(400, "bad audio".to_string());
+        let fallback = MockBackend::new(Ok("fallback"));
+        let fallback_requests = fallback.requests.clone();
+        let backend = FallbackBackend::new(
+            Box::new(MockBackend::new(Err(rejected.clone()))),
+            Box::new(fallback),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(transcript(&backend, 0).await, Err(rejected));
+        assert!(fallback_requests.lock().unwrap().is_empty());
+    }
+
+    /// Answers plain requests with "plain" and decoding passes with the pass's beam size.
+    struct PassBackend;
+
+    impl TranscriptionBackend for PassBackend {
+        fn name(&self) -> &str {
+            "pass"
+        }
+
+        fn transcribe(&self, _chunk_id: u64, _samples: Vec<f32>, _prompt: Option<String>) -> TranscriptionFuture<'_> {
+            Box::pin(async move { Ok(response("plain")) })
+        }
+
+        fn transcribe_pass<'a>(
+            &'a self,
+            _chunk_id: u64,
+            _samples: &'a [f32],
+            pass: &'a DecodingPass,
+        ) -> TranscriptionFuture<'a> {
+            Box::pin(async move { Ok(response(&format!("beam {}", pass.beam_size))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn wrapping_backends_keep_the_decoding_pass() {
+        let pass = DecodingPass {
+            beam_size: 5,
+            temperature: 0.0,
+        };
+        let samples = vec![0.0; 16000];
+        let fallback = FallbackBackend::new(Box::new(PassBackend), Box::new(PassBackend), Duration::from_secs(60));
+        let escalation = ModelEscalationBackend::new(
+            Box::new(PassBackend),
+            Box::new(PassBackend),
+            &ModelEscalationConfig::default(),
+        );
+
+        let backends: [&dyn TranscriptionBackend; 2] = [&fallback, &escalation];
+        for backend in backends {
+            let response = backend.transcribe_pass(0, &samples, &pass).await.unwrap();
+            assert_eq!(response.segments[0].text, "beam 5");
+        }
+    }
+
+    #[test]
+    fn keeps_the_raw_response_only_when_asked() {
+        let server_json = serde_json::json!({
+            "segments": [{
+                "text": "Hello",
+                "t0": 0.0,
+                "t1": 50.0,
+                "words": [{ "text": "Hello", "t0": 0.0, "t1": 50.0, "p": 0.92 }],
+                "tokens": [{ "id": 15947, "p": 0.92 }]
+            }],
+            "buffer_size_ms": 1000
+        });
+
+        let with_raw = parse_response(server_json.clone(), true).unwrap();
+        assert_eq!(with_raw.segments[0].text, "Hello");
+        assert_eq!(with_raw.raw.unwrap()["segments"][0]["tokens"][0]["id"], 15947);
+
+        let without_raw = parse_response(server_json, false).unwrap();
+        assert_eq!(without_raw.segments[0].words[0].p, 0.92);
+        assert!(without_raw.raw.is_none());
+
+        assert!(parse_response(serde_json::json!({ "error": "busy" }), true).is_err());
+    }
+
+    fn retries(report_attempts: bool) -> BackendRetryConfig {
+        BackendRetryConfig {
+            max_retries: 3,
+            initial_backoff_ms: 0,
+            report_attempts,
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_each_failed_attempt_before_the_success() {
+        let mut replies = vec![
+            Err(TranscriptionError::Timeout {
+                message: "no answer".to_string(),
+            }),
+            Err(TranscriptionError::from_status(503, "model loading".to_string())),
+            Ok(response("Hello")),
+        ]
+        .into_iter();
+        let send = || std::future::ready(replies.next().unwrap());
+
+        let (transcript, _) = send_with_retries(0, &retries(true), send).await.unwrap();
+        assert_eq!(transcript.segments[0].text, "Hello");
+        let attempts: Vec<(u32, TranscriptionError)> = transcript
+            .failed_attempts
+            .into_iter()
+            .map(|info| (info.attempt, info.error))
+            .collect();
+        assert_eq!(
+            attempts,
+            vec![
+                (
+                    1,
+                    TranscriptionError::Timeout {
+                        message: "no answer".to_string()
+                    }
+                ),
+                (2, TranscriptionError::from_status(503, "model loading".to_string())),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn attempts_are_only_reported_when_asked() {
+        let mut replies = vec![Err(unavailable()), Ok(response("Hello"))].into_iter();
+        let send = || std::future::ready(replies.next().unwrap());
+
+        let (transcript, _) = send_with_retries(0, &retries(false), send).await.unwrap();
+        assert!(transcript.failed_attempts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_retry_or_a_rejection() {
+        let mut sent = 0;
+        let send = || {
+            sent += 1;
+            std::future::ready(Err::<TranscriptResponse, _>(unavailable()))
+        };
+        assert_eq!(
+            send_with_retries(0, &retries(true), send).await.unwrap_err(),
+            unavailable()
+        );
+        assert_eq!(sent, 4);
+
+        let rejected = TranscriptionError::from_status(400, "bad audio".to_string());
+        let send = || std::future::ready(Err::<TranscriptResponse, _>(rejected.clone()));
+        assert_eq!(send_with_retries(0, &retries(true), send).await.unwrap_err(), rejected);
+    }
+
+    #[test]
+    fn silence_comes_back_as_an_empty_transcript_not_an_error() {
+        let no_segments = parse_response(serde_json::json!({ "buffer_size_ms": 1000 }), false).unwrap();
+        assert!(no_segments.segments.is_empty());
+
+        let blank = parse_response(
+            serde_json::json!({
+                "segments": [{ "text": " [BLANK_AUDIO]", "t0": 0.0, "t1": 100.0 }],
+                "buffer_size_ms": 1000
+            }),
+            false,
+        )
+        .unwrap();
+        assert!(blank.segments.iter().all(|segment| segment.is_blank()));
+        assert!(!response("Hello [BLANK_AUDIO]").segments[0].is_blank());
+        assert_eq!(strip_blank_markers(" Hello [BLANK_AUDIO]"), "Hello");
+    }
+
+    fn response_with_confidence(p: f32) -> TranscriptResponse {
+        let mut response = response("Hello");
+        response.segments[0].words = vec![TimedWord {
+            text: "Hello".to_string(),
+            t0: 0.0,
+            t1: 50.0,
+            p,
+        }];
+        response
+    }
+
+    #[test]
+    fn low_confidence_raises_best_of_for_the_next_chunk() {
+        let backend = WhisperServerBackend::new("http://127.0.0.1:8178");
+        let config = DecodingEscalationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let fast = Duration::from_millis(100);
+        assert_eq!(backend.current_best_of(&config), 2);
+
+        for _ in 0..5 {
+            let best_of = backend.current_best_of(&config);
+            backend.adjust_best_of(0, &config, best_of, &response_with_confidence(0.3), fast, 2.0);
+        }
+        assert_eq!(backend.current_best_of(&config), 5);
+
+        backend.adjust_best_of(0, &config, 5, &response_with_confidence(0.9), fast, 2.0);
+        assert_eq!(backend.current_best_of(&config), 4);
+    }
+
+    #[test]
+    fn best_of_steps_down_when_decoding_falls_behind() {
+        let backend = WhisperServerBackend::new("http://127.0.0.1:8178");
+        let config = DecodingEscalationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        // 1.5 s for 2 s of audio is over the default half
+        backend.adjust_best_of(
+            0,
+            &config,
+            4,
+            &response_with_confidence(0.3),
+            Duration::from_millis(1500),
+            2.0,
+        );
+        assert_eq!(backend.current_best_of(&config), 3);
+    }
+
+    #[test]
+    fn validation_rejects_audio_whisper_cannot_read() {
+        assert!(validate_audio(&[0.0, 0.1, -0.1]).is_ok());
+
+        for samples in [Vec::new(), vec![0.0, f32::NAN]] {
+            let error = validate_audio(&samples).unwrap_err();
+            assert!(matches!(error, TranscriptionError::InvalidAudio { .. }), "{:?}", error);
+        }
+        let error = validate_audio(&[0.0, f32::INFINITY]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid audio: sample 1 is inf");
+    }
+
+    /// Answers with a fixed word confidence after `delay`, counting its requests.
+    struct ConfidenceBackend {
+        p: f32,
+        delay: Duration,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl ConfidenceBackend {
+        fn new(p: f32, delay: Duration) -> Self {
+            Self {
+                p,
+                delay,
+                calls: Arc::new(AtomicU32::new(0)),
+            }
+        }
+    }
+
+    impl TranscriptionBackend for ConfidenceBackend {
+        fn name(&self) -> &str {
+            "confidence"
+        }
+
+        fn transcribe(&self, _chunk_id: u64, _samples: Vec<f32>, _prompt: Option<String>) -> TranscriptionFuture<'_> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(response_with_confidence(self.p))
+            })
+        }
+    }
+
+    fn escalation(primary_p: f32, accurate: ConfidenceBackend) -> ModelEscalationBackend {
+        let config = ModelEscalationConfig {
+            enabled: true,
+            timeout_ms: 100,
+            ..Default::default()
+        };
+        let primary = ConfidenceBackend::new(primary_p, Duration::ZERO);
+        ModelEscalationBackend::new(Box::new(primary), Box::new(accurate), &config)
+    }
+
+    #[tokio::test]
+    async fn low_confidence_chunks_are_retried_on_the_larger_model() {
+        let accurate = ConfidenceBackend::new(0.9, Duration::ZERO);
+        let calls = accurate.calls.clone();
+        let response = escalation(0.3, accurate)
+            .transcribe(1, vec![0.0; 16000], None)
+            .await
+            .unwrap();
+        assert_eq!(mean_word_confidence(&response), Some(0.9));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let accurate = ConfidenceBackend::new(0.9, Duration::ZERO);
+        let calls = accurate.calls.clone();
+        let response = escalation(0.8, accurate)
+            .transcribe(2, vec![0.0; 16000], None)
+            .await
+            .unwrap();
+        assert_eq!(mean_word_confidence(&response), Some(0.8));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn a_slow_or_worse_larger_model_keeps_the_primary_result() {
+        let slow = ConfidenceBackend::new(0.9, Duration::from_millis(500));
+        let response = escalation(0.3, slow)
+            .transcribe(1, vec![0.0; 16000], None)
+            .await
+            .unwrap();
+        assert_eq!(mean_word_confidence(&response), Some(0.3));
+
+        let worse = ConfidenceBackend::new(0.2, Duration::ZERO);
+        let response = escalation(0.3, worse)
+            .transcribe(2, vec![0.0; 16000], None)
+            .await
+            .unwrap();
+        assert_eq!(mean_word_confidence(&response), Some(0.3));
+    }
+
+    // Serves one HTTP response per connection from `replies`, in order, and
+    // returns each request it read
+    async fn serve_replies(replies: Vec<(u16, &'static str)>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for (status, body) in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                // Read the headers, then as much body as they announce
+                loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(str::to_string))
+                            .and_then(|value| value.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                requests.push(String::from_utf8_lossy(&request).to_string());
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn decoding_passes_are_retried_and_send_their_settings() {
+        let transcript = r#"{"segments": [{"text": "Hello.", "t0": 0.0, "t1": 100.0}], "buffer_size_ms": 1000}"#;
+        let (url, server) = serve_replies(vec![(503, "model loading"), (200, transcript)]).await;
+        let backend = WhisperServerBackend::new(&url);
+        let pass = DecodingPass {
+            beam_size: 5,
+            temperature: 0.4,
+        };
+
+        let response = backend.transcribe_pass(3, &[0.0; 1600], &pass).await.unwrap();
+        assert_eq!(response.segments[0].text, "Hello.");
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        for field in ["stateless", "beam_size", "temperature"] {
+            assert!(
+                requests[1].contains(&format!("name=\"{}\"", field)),
+                "no {} field",
+                field
+            );
+        }
+        assert!(!requests[1].contains("name=\"best_of\""));
+    }
+
+    #[tokio::test]
+    async fn a_rejected_decoding_pass_is_not_retried() {
+        let (url, server) = serve_replies(vec![(400, "bad audio")]).await;
+        let backend = WhisperServerBackend::new(&url);
+        let pass = DecodingPass {
+            beam_size: 1,
+            temperature: 0.0,
+        };
+
+        let error = backend.transcribe_pass(0, &[0.0; 1600], &pass).await.unwrap_err();
+        assert_eq!(
+            error,
+            TranscriptionError::Rejected {
+                status: 400,
+                message: "bad audio".to_string()
+            }
+        );
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,635 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::speakers::frame_pitch;
+use crate::audio::audio_processing::rms;
+
+// Frames checked for speech confirmation, long enough to hold the lowest voice pitch
+const CONFIRMATION_FRAME_MS: u64 = 30;
+
+/// Snapshot of the chunk being assembled, handed to a `BoundaryStrategy` after
+/// each batch of captured audio.
+#[derive(Debug, Clone)]
+pub struct ChunkState {
+    /// Samples buffered for the current chunk.
+    pub buffered_samples: usize,
+    /// Sample rate of the buffered audio.
+    pub sample_rate: u32,
+    /// Time since the previous chunk was cut.
+    pub since_last_chunk: Duration,
+    /// RMS of the batch that was just appended, 0.0 if nothing arrived.
+    pub latest_rms: f32,
+}
+
+impl ChunkState {
+    pub fn buffered_duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.buffered_samples as f64 / self.sample_rate as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkDecision {
+    /// Keep buffering.
+    Continue,
+    /// Cut the buffered audio into a chunk and send it for transcription.
+    CreateChunk,
+}
+
+/// Decides where the live audio is cut into chunks for whisper.
+pub trait BoundaryStrategy: Send {
+    fn decide(&mut self, state: &ChunkState) -> ChunkDecision;
+
+    /// Forgets any speech/silence tracked so far. Stateless strategies ignore it.
+    fn reset(&mut self) {}
+}
+
+/// The default heuristic: cut once the chunk is full, or once it has reached the
+/// minimum size and the chunk interval has elapsed.
+pub struct DurationBoundary {
+    chunk_samples: usize,
+    min_samples: usize,
+    chunk_interval: Duration,
+}
+
+impl DurationBoundary {
+    pub fn new(chunk_samples: usize, min_samples: usize, chunk_interval: Duration) -> Self {
+        Self {
+            chunk_samples,
+            min_samples,
+            chunk_interval,
+        }
+    }
+}
+
+impl BoundaryStrategy for DurationBoundary {
+    fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+        let full = state.buffered_samples >= self.chunk_samples;
+        let due = state.buffered_samples >= self.min_samples && state.since_last_chunk >= self.chunk_interval;
+        if full || due {
+            ChunkDecision::CreateChunk
+        } else {
+            ChunkDecision::Continue
+        }
+    }
+}
+
+/// Settings for cutting short, complete utterances before the minimum chunk size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointingConfig {
+    /// Off by default, it can fragment longer speech that has natural pauses.
+    pub enabled: bool,
+    /// Batches louder than this RMS count as speech.
+    pub speech_rms: f32,
+    /// Batches quieter than this RMS count as silence.
+    pub silence_rms: f32,
+    /// Speech needed before an endpoint is considered.
+    pub min_speech_ms: u64,
+    /// Silence after speech that marks the utterance as complete.
+    pub trailing_silence_ms: u64,
+}
+
+impl Default for EndpointingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speech_rms: 0.02,
+            silence_rms: 0.008,
+            min_speech_ms: 300,
+            trailing_silence_ms: 600,
+        }
+    }
+}
+
+/// Wraps another strategy and additionally cuts a chunk as soon as an utterance
+/// is followed by confident silence, even if the chunk is still below its minimum.
+pub struct EnergyEndpointing<S: BoundaryStrategy> {
+    inner: S,
+    config: EndpointingConfig,
+    last_buffered: usize,
+    last_elapsed: Duration,
+    speech_started: Option<Duration>,
+    last_speech: Duration,
+}
+
+impl<S: BoundaryStrategy> EnergyEndpointing<S> {
+    pub fn new(inner: S, config: EndpointingConfig) -> Self {
+        Self {
+            inner,
+            config,
+            last_buffered: 0,
+            last_elapsed: Duration::ZERO,
+            speech_started: None,
+            last_speech: Duration::ZERO,
+        }
+    }
+}
+
+impl<S: BoundaryStrategy> BoundaryStrategy for EnergyEndpointing<S> {
+    fn reset(&mut self) {
+        self.last_buffered = 0;
+        self.speech_started = None;
+        self.last_speech = Duration::ZERO;
+        self.inner.reset();
+    }
+
+    fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+        // A shorter buffer or a restarted clock means a chunk was cut since the last call
+        if state.buffered_samples < self.last_buffered || state.since_last_chunk < self.last_elapsed {
+            self.reset();
+        }
+        let received_audio = state.buffered_samples > self.last_buffered;
+        self.last_buffered = state.buffered_samples;
+        self.last_elapsed = state.since_last_chunk;
+
+        if self.inner.decide(state) == ChunkDecision::CreateChunk {
+            self.reset();
+            return ChunkDecision::CreateChunk;
+        }
+        if !received_audio {
+            return ChunkDecision::Continue;
+        }
+
+        let now = state.since_last_chunk;
+        if state.latest_rms >= self.config.speech_rms {
+            self.speech_started.get_or_insert(now);
+            self.last_speech = now;
+            return ChunkDecision::Continue;
+        }
+
+        if let Some(speech_started) = self.speech_started {
+            let speech = self.last_speech.saturating_sub(speech_started);
+            let silence = now.saturating_sub(self.last_speech);
+            if state.latest_rms <= self.config.silence_rms
+                && speech >= Duration::from_millis(self.config.min_speech_ms)
+                && silence >= Duration::from_millis(self.config.trailing_silence_ms)
+            {
+                self.reset();
+                return ChunkDecision::CreateChunk;
+            }
+        }
+        ChunkDecision::Continue
+    }
+}
+
+/// Settings for holding back chunks that would be too short to transcribe well
+/// on their own, so adjacent short utterances are sent to whisper together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkCoalescingConfig {
+    pub enabled: bool,
+    /// Cuts that would leave a chunk shorter than this are deferred.
+    pub min_chunk_ms: u64,
+    /// Longest a deferred cut waits for more speech before the chunk is sent anyway.
+    pub max_delay_ms: u64,
+    /// Batches louder than this RMS count as speech. A deferred cut isn't made
+    /// during speech, even once the delay has run out.
+    pub speech_rms: f32,
+}
+
+impl Default for ChunkCoalescingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_chunk_ms: 3000,
+            max_delay_ms: 1500,
+            speech_rms: 0.02,
+        }
+    }
+}
+
+/// Wraps another strategy and defers its cuts while the chunk is still short,
+/// so a run of short utterances becomes one chunk. A deferred cut is made once
+/// the chunk is long enough, or the delay has run out and the speaker has paused.
+pub struct ChunkCoalescing {
+    inner: Box<dyn BoundaryStrategy>,
+    config: ChunkCoalescingConfig,
+    deferred_at: Option<Duration>,
+}
+
+impl ChunkCoalescing {
+    pub fn new(inner: Box<dyn BoundaryStrategy>, config: ChunkCoalescingConfig) -> Self {
+        Self {
+            inner,
+            config,
+            deferred_at: None,
+        }
+    }
+}
+
+impl BoundaryStrategy for ChunkCoalescing {
+    fn reset(&mut self) {
+        self.deferred_at = None;
+        self.inner.reset();
+    }
+
+    fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+        // A restarted clock means the chunk was cut
+        if self.deferred_at.is_some_and(|deferred_at| state.since_last_chunk < deferred_at) {
+            self.deferred_at = None;
+        }
+
+        let long_enough = state.buffered_duration() >= Duration::from_millis(self.config.min_chunk_ms);
+        let wants_cut = self.inner.decide(state) == ChunkDecision::CreateChunk;
+        if wants_cut && !long_enough {
+            self.deferred_at.get_or_insert(state.since_last_chunk);
+        }
+
+        let Some(deferred_at) = self.deferred_at else {
+            return if wants_cut { ChunkDecision::CreateChunk } else { ChunkDecision::Continue };
+        };
+        let waited = state.since_last_chunk.saturating_sub(deferred_at);
+        let timed_out = waited >= Duration::from_millis(self.config.max_delay_ms);
+        // Cutting while someone is still talking would split a word
+        let speaking = state.latest_rms > self.config.speech_rms;
+        if (wants_cut && long_enough) || (timed_out && !speaking) {
+            self.deferred_at = None;
+            return ChunkDecision::CreateChunk;
+        }
+        ChunkDecision::Continue
+    }
+}
+
+/// Settings for cutting at short dips in loudness, for speakers who rarely
+/// pause long enough for silence-based endpointing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnergyDipConfig {
+    pub enabled: bool,
+    /// A batch quieter than this fraction of the running speech level is part of a dip.
+    pub dip_ratio: f32,
+    /// How long the level has to stay down for the dip to count as a sentence end.
+    pub min_dip_ms: u64,
+    /// Dips are ignored until the chunk is at least this long.
+    pub min_chunk_ms: u64,
+    /// Batches louder than this RMS update the running speech level.
+    pub speech_rms: f32,
+    /// Weight of each speech batch in the running level, 0.0 to 1.0.
+    pub level_smoothing: f32,
+}
+
+impl Default for EnergyDipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dip_ratio: 0.4,
+            min_dip_ms: 120,
+            min_chunk_ms: 5000,
+            speech_rms: 0.01,
+            level_smoothing: 0.02,
+        }
+    }
+}
+
+/// Wraps another strategy and additionally cuts where the level drops well
+/// below the speaker's running level for a moment, as it does at the end of a
+/// sentence, even if it never gets quiet enough to count as silence.
+pub struct EnergyDipEndpointing {
+    inner: Box<dyn BoundaryStrategy>,
+    config: EnergyDipConfig,
+    speech_level: Option<f32>,
+    dip_started: Option<Duration>,
+    last_buffered: usize,
+}
+
+impl EnergyDipEndpointing {
+    pub fn new(inner: Box<dyn BoundaryStrategy>, config: EnergyDipConfig) -> Self {
+        Self {
+            inner,
+            config,
+            speech_level: None,
+            dip_started: None,
+            last_buffered: 0,
+        }
+    }
+}
+
+impl BoundaryStrategy for EnergyDipEndpointing {
+    fn reset(&mut self) {
+        self.speech_level = None;
+        self.dip_started = None;
+        self.last_buffered = 0;
+        self.inner.reset();
+    }
+
+    fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+        // A shorter buffer means a chunk was cut; the speech level carries over
+        if state.buffered_samples < self.last_buffered {
+            self.dip_started = None;
+        }
+        let received_audio = state.buffered_samples > self.last_buffered;
+        self.last_buffered = state.buffered_samples;
+
+        if self.inner.decide(state) == ChunkDecision::CreateChunk {
+            self.dip_started = None;
+            return ChunkDecision::CreateChunk;
+        }
+        if !received_audio {
+            return ChunkDecision::Continue;
+        }
+
+        let rms = state.latest_rms;
+        let Some(level) = self.speech_level else {
+            if rms >= self.config.speech_rms {
+                self.speech_level = Some(rms);
+            }
+            return ChunkDecision::Continue;
+        };
+
+        if rms >= level * self.config.dip_ratio {
+            self.dip_started = None;
+            if rms >= self.config.speech_rms {
+                let smoothing = self.config.level_smoothing.clamp(0.0, 1.0);
+                self.speech_level = Some(level + smoothing * (rms - level));
+            }
+            return ChunkDecision::Continue;
+        }
+
+        let now = state.since_last_chunk;
+        let dip_started = *self.dip_started.get_or_insert(now);
+        let long_enough = state.buffered_duration() >= Duration::from_millis(self.config.min_chunk_ms);
+        if long_enough && now.saturating_sub(dip_started) >= Duration::from_millis(self.config.min_dip_ms) {
+            self.dip_started = None;
+            return ChunkDecision::CreateChunk;
+        }
+        ChunkDecision::Continue
+    }
+}
+
+/// Settings for dropping chunks without sustained voiced speech, so a cough or
+/// a door slam that set off endpointing isn't sent to whisper.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeechConfirmationConfig {
+    pub enabled: bool,
+    /// How long voiced frames have to run back to back for the chunk to count as speech.
+    pub confirm_ms: u64,
+    /// Keeps chunks whose only speech is voiced for less than `confirm_ms`, as
+    /// long as it is loud. Off by default, a loud cough can pass it; a spoken
+    /// "yes" or "no" already lasts longer than `confirm_ms`.
+    pub keep_short_utterances: bool,
+    /// Voiced frames louder than this RMS count as clearly voiced.
+    pub short_utterance_rms: f32,
+    /// How long clearly voiced frames have to run back to back.
+    pub short_utterance_ms: u64,
+}
+
+impl Default for SpeechConfirmationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            confirm_ms: 120,
+            keep_short_utterances: false,
+            short_utterance_rms: 0.03,
+            short_utterance_ms: 60,
+        }
+    }
+}
+
+/// Whether `samples` hold voiced frames, ones with a clear pitch in the voice
+/// range, for at least `confirm_ms` in a row, or loud voiced frames for
+/// `short_utterance_ms` in a row when short utterances are kept.
+pub fn has_sustained_voice(samples: &[f32], sample_rate: u32, config: &SpeechConfirmationConfig) -> bool {
+    let frame_len = (sample_rate as u64 * CONFIRMATION_FRAME_MS / 1000) as usize;
+    if frame_len == 0 {
+        return false;
+    }
+    let needed = config.confirm_ms.div_ceil(CONFIRMATION_FRAME_MS).max(1);
+    let short_needed = config.short_utterance_ms.div_ceil(CONFIRMATION_FRAME_MS).max(1);
+    let mut run = 0;
+    let mut clear_run = 0;
+    for frame in samples.chunks_exact(frame_len) {
+        if frame_pitch(frame, sample_rate).is_none() {
+            run = 0;
+            clear_run = 0;
+            continue;
+        }
+        run += 1;
+        clear_run = if rms(frame) >= config.short_utterance_rms { clear_run + 1 } else { 0 };
+        if run >= needed || (config.keep_short_utterances && clear_run >= short_needed) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn tone(amplitude: f32, ms: u64) -> Vec<f32> {
+        let len = (SAMPLE_RATE as u64 * ms / 1000) as usize;
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn config(keep_short_utterances: bool) -> SpeechConfirmationConfig {
+        SpeechConfirmationConfig {
+            enabled: true,
+            keep_short_utterances,
+            ..Default::default()
+        }
+    }
+
+    const BATCH_MS: u64 = 100;
+
+    // Feeds one 100 ms batch per level and returns the length in ms of each chunk cut
+    fn cut_lengths(strategy: &mut dyn BoundaryStrategy, levels: &[f32]) -> Vec<u64> {
+        let mut state = ChunkState {
+            buffered_samples: 0,
+            sample_rate: SAMPLE_RATE,
+            since_last_chunk: Duration::ZERO,
+            latest_rms: 0.0,
+        };
+        let mut cuts = Vec::new();
+        for &level in levels {
+            state.buffered_samples += (SAMPLE_RATE as u64 * BATCH_MS / 1000) as usize;
+            state.since_last_chunk += Duration::from_millis(BATCH_MS);
+            state.latest_rms = level;
+            if strategy.decide(&state) == ChunkDecision::CreateChunk {
+                cuts.push(state.buffered_duration().as_millis() as u64);
+                state.buffered_samples = 0;
+                state.since_last_chunk = Duration::ZERO;
+            }
+        }
+        cuts
+    }
+
+    struct CutAtMinimum(usize);
+
+    impl BoundaryStrategy for CutAtMinimum {
+        fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+            if state.buffered_samples >= self.0 {
+                ChunkDecision::CreateChunk
+            } else {
+                ChunkDecision::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn a_custom_strategy_decides_where_chunks_are_cut() {
+        assert_eq!(cut_lengths(&mut CutAtMinimum(8000), &[0.1; 12]), vec![500, 500]);
+    }
+
+    #[test]
+    fn duration_boundary_cuts_once_the_interval_has_elapsed() {
+        let mut boundary = DurationBoundary::new(48000, 16000, Duration::from_secs(2));
+        assert_eq!(cut_lengths(&mut boundary, &[0.1; 50]), vec![2000, 2000]);
+
+        let mut boundary = DurationBoundary::new(16000, 8000, Duration::from_secs(2));
+        assert_eq!(cut_lengths(&mut boundary, &[0.1; 25]), vec![1000, 1000]);
+    }
+
+    fn endpointing() -> EnergyEndpointing<DurationBoundary> {
+        let config = EndpointingConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        EnergyEndpointing::new(DurationBoundary::new(160000, 48000, Duration::from_secs(10)), config)
+    }
+
+    #[test]
+    fn endpointing_cuts_a_short_utterance_before_the_minimum() {
+        let mut levels = vec![0.0; 3];
+        levels.extend([0.1; 10]);
+        levels.extend([0.0; 10]);
+        assert_eq!(cut_lengths(&mut endpointing(), &levels), vec![1900]);
+    }
+
+    #[test]
+    fn endpointing_ignores_a_brief_noise() {
+        let mut levels = vec![0.0; 3];
+        levels.push(0.1);
+        levels.extend([0.0; 20]);
+        assert!(cut_lengths(&mut endpointing(), &levels).is_empty());
+    }
+
+    // Wants a cut at every silent batch, like a pause-based chunker in choppy speech
+    struct CutAtPause;
+
+    impl BoundaryStrategy for CutAtPause {
+        fn decide(&mut self, state: &ChunkState) -> ChunkDecision {
+            if state.latest_rms == 0.0 {
+                ChunkDecision::CreateChunk
+            } else {
+                ChunkDecision::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn coalescing_merges_short_utterances_into_one_chunk() {
+        let mut levels = Vec::new();
+        for _ in 0..3 {
+            levels.extend([0.1; 6]);
+            levels.push(0.0);
+        }
+        levels.extend([0.0; 5]);
+
+        let mut coalescing = ChunkCoalescing::new(Box::new(CutAtPause), ChunkCoalescingConfig::default());
+        // The first pause is deferred at 700 ms and waits at most 1500 ms for more speech
+        assert_eq!(cut_lengths(&mut coalescing, &levels), vec![2200]);
+    }
+
+    #[test]
+    fn a_deferred_cut_waits_for_the_speaker_to_pause() {
+        let mut levels = vec![0.1; 6];
+        levels.push(0.0);
+        // Speech runs on past the 2200 ms deadline
+        levels.extend([0.1; 20]);
+        levels.push(0.0);
+
+        let mut coalescing = ChunkCoalescing::new(Box::new(CutAtPause), ChunkCoalescingConfig::default());
+        assert_eq!(cut_lengths(&mut coalescing, &levels), vec![2800]);
+    }
+
+    #[test]
+    fn coalescing_leaves_long_enough_chunks_alone() {
+        let mut levels = vec![0.1; 35];
+        levels.push(0.0);
+        let mut coalescing = ChunkCoalescing::new(Box::new(CutAtPause), ChunkCoalescingConfig::default());
+        assert_eq!(cut_lengths(&mut coalescing, &levels), vec![3600]);
+    }
+
+    #[test]
+    fn energy_dips_cut_monotone_speech_that_never_pauses() {
+        // Six 4 s sentences, each ending in a 300 ms dip that never reaches silence
+        let mut levels = Vec::new();
+        for _ in 0..6 {
+            levels.extend([0.1; 40]);
+            levels.extend([0.03; 3]);
+        }
+        let max_duration = SAMPLE_RATE as usize * 20;
+        assert_eq!(cut_lengths(&mut CutAtMinimum(max_duration), &levels), vec![20000]);
+
+        let config = EnergyDipConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut dips = EnergyDipEndpointing::new(Box::new(CutAtMinimum(max_duration)), config);
+        // Dips in a chunk shorter than min_chunk_ms are passed over
+        assert_eq!(cut_lengths(&mut dips, &levels), vec![8600, 8600, 8600]);
+    }
+
+    #[test]
+    fn a_loud_transient_is_not_confirmed_as_speech() {
+        // One frame of broadband noise, like a door slam, then silence
+        let mut seed = 1u32;
+        let mut samples: Vec<f32> = (0..480)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect();
+        samples.extend([0.0; 16000]);
+        assert!(!has_sustained_voice(&samples, SAMPLE_RATE, &config(true)));
+
+        let mut speech = tone(0.05, 1000);
+        speech.extend([0.0; 16000]);
+        assert!(has_sustained_voice(&speech, SAMPLE_RATE, &config(false)));
+    }
+
+    #[test]
+    fn confirms_voice_sustained_for_the_confirmation_time() {
+        assert!(has_sustained_voice(&tone(0.02, 150), SAMPLE_RATE, &config(false)));
+        assert!(!has_sustained_voice(&tone(0.02, 90), SAMPLE_RATE, &config(false)));
+        assert!(!has_sustained_voice(&[0.0; 16000], SAMPLE_RATE, &config(true)));
+    }
+
+    #[test]
+    fn a_short_spoken_confirmation_is_kept_by_default() {
+        // A 300 ms "yes" between pauses
+        let mut samples = vec![0.0; 8000];
+        samples.extend(tone(0.05, 300));
+        samples.extend([0.0; 8000]);
+        let config = SpeechConfirmationConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(!config.keep_short_utterances);
+        assert!(has_sustained_voice(&samples, SAMPLE_RATE, &config));
+    }
+
+    #[test]
+    fn keeps_short_utterances_only_when_clearly_voiced() {
+        let short_and_loud = tone(0.1, 60);
+        assert!(has_sustained_voice(&short_and_loud, SAMPLE_RATE, &config(true)));
+        assert!(!has_sustained_voice(&short_and_loud, SAMPLE_RATE, &config(false)));
+        assert!(!has_sustained_voice(&tone(0.02, 60), SAMPLE_RATE, &config(true)));
+    }
+
+    #[test]
+    fn a_gap_restarts_the_voiced_run() {
+        let mut samples = tone(0.02, 90);
+        samples.extend([0.0; 480]);
+        samples.extend(tone(0.02, 90));
+        assert!(!has_sustained_voice(&samples, SAMPLE_RATE, &config(false)));
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a finished sentence is delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptSink {
+    /// The `transcript-update` event shown live in the UI.
+    Live,
+    /// The transcript kept for the summarizer and autosave.
+    SavedTranscript,
+    /// Sentences grouped into `speaking-turn-completed` events.
+    SpeakingTurns,
+}
+
+/// Minimum mean word confidence (0..1) a sentence needs to reach each sink.
+/// Sentences without word confidences always pass. 0 delivers everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SinkConfidenceConfig {
+    pub live: f32,
+    pub saved_transcript: f32,
+    pub speaking_turns: f32,
+}
+
+impl SinkConfidenceConfig {
+    pub fn accepts(&self, sink: TranscriptSink, confidence: Option<f32>) -> bool {
+        let min_confidence = match sink {
+            TranscriptSink::Live => self.live,
+            TranscriptSink::SavedTranscript => self.saved_transcript,
+            TranscriptSink::SpeakingTurns => self.speaking_turns,
+        };
+        // Sentences without a confidence are never held back
+        match confidence {
+            Some(confidence) => confidence >= min_confidence,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_sink_uses_its_own_threshold() {
+        let config = SinkConfidenceConfig {
+            live: 0.0,
+            saved_transcript: 0.6,
+            speaking_turns: 0.8,
+        };
+        assert!(config.accepts(TranscriptSink::Live, Some(0.1)));
+        assert!(!config.accepts(TranscriptSink::SavedTranscript, Some(0.5)));
+        assert!(config.accepts(TranscriptSink::SavedTranscript, Some(0.6)));
+        assert!(!config.accepts(TranscriptSink::SpeakingTurns, Some(0.7)));
+    }
+
+    #[test]
+    fn sentences_without_confidences_always_pass() {
+        let config = SinkConfidenceConfig {
+            live: 1.0,
+            saved_transcript: 1.0,
+            speaking_turns: 1.0,
+        };
+        assert!(config.accepts(TranscriptSink::Live, None));
+        assert!(config.accepts(TranscriptSink::SpeakingTurns, None));
+    }
+}
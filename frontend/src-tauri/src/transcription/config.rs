@@ -0,0 +1,479 @@
+use lazy_static::lazy_static;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use super::auto_stop::SilenceAutoStopConfig;
+use super::autosave::TranscriptAutosaveConfig;
+use super::backend::{
+    BackendRetryConfig, DecodingEscalationConfig, FallbackServerConfig, ModelEscalationConfig, RawOutputConfig,
+};
+use super::boundary::{ChunkCoalescingConfig, EndpointingConfig, EnergyDipConfig, SpeechConfirmationConfig};
+use super::confidence::SinkConfidenceConfig;
+use super::context::PromptContextConfig;
+use super::crosstalk::OverlappingSpeechConfig;
+use super::dead_letter::DeadLetterConfig;
+use super::dedup::RecoveryDedupConfig;
+use super::encoding::OutputEncodingConfig;
+use super::estimate::WarmupConfig;
+use super::filler::FillerFilterConfig;
+use super::html_export::HtmlExportConfig;
+use super::language_model::LanguageModelConfig;
+use super::loops::HallucinationLoopConfig;
+use super::normalize::TextNormalizationConfig;
+use super::offline::OfflineTranscriptionConfig;
+use super::profiling::ChunkProfilingConfig;
+use super::redaction::RedactionConfig;
+use super::segments::{GapReportingConfig, SegmentBoundaryConfig};
+use super::speakers::SpeakerHintConfig;
+use super::throttle::QualityThrottleConfig;
+use super::turns::{ParagraphConfig, TurnAggregationConfig};
+use crate::audio::{
+    CalibrationConfig, CaptureQueueConfig, CaptureThreadConfig, ChunkPaddingConfig, ChunkTimingConfig, CompressorConfig,
+    CrashBufferConfig, DeviceFallbackConfig, DisconnectGracePolicy, MonitorConfig, PermissionRecoveryConfig,
+    PreEmphasisConfig, SampleRateChangeConfig, SourceBalanceConfig, SystemPrerollConfig, TestSourceConfig,
+};
+use crate::session_stats::SessionStatsConfig;
+
+/// What the capture side does when transcription falls behind and the chunk queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOverflowPolicy {
+    /// Drop the oldest untranscribed chunk so the transcript stays live.
+    DropOldest,
+    /// Hold the new chunk until a worker frees a slot. Audio captured meanwhile is lost.
+    Backpressure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkQueueConfig {
+    /// Chunks waiting for a transcription worker, not counting the ones in progress.
+    pub max_queued_chunks: usize,
+    pub overflow_policy: QueueOverflowPolicy,
+}
+
+impl Default for ChunkQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_queued_chunks: 10,
+            overflow_policy: QueueOverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// What happens to each chunk once the chunker has cut it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkOutputMode {
+    Transcribe,
+    /// Emit a `chunk-ready` event for an external transcriber, nothing is sent to whisper.
+    ChunksOnly,
+    /// Emit `chunk-ready` and transcribe as well.
+    Both,
+}
+
+impl ChunkOutputMode {
+    pub fn emits_chunks(self) -> bool {
+        self != Self::Transcribe
+    }
+
+    pub fn transcribes(self) -> bool {
+        self != Self::ChunksOnly
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkOutputConfig {
+    pub mode: ChunkOutputMode,
+}
+
+impl Default for ChunkOutputConfig {
+    fn default() -> Self {
+        Self {
+            mode: ChunkOutputMode::Transcribe,
+        }
+    }
+}
+
+/// Settings for keeping each request within whisper's window. Longer chunks,
+/// e.g. with pre-roll or coalescing, are sent as consecutive chunks instead of
+/// being cut off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WhisperWindowConfig {
+    pub split_long_chunks: bool,
+    pub window_ms: u32,
+}
+
+impl Default for WhisperWindowConfig {
+    fn default() -> Self {
+        Self {
+            split_long_chunks: true,
+            window_ms: 30000,
+        }
+    }
+}
+
+/// Settings for a periodic `transcription-status` event while recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusHeartbeatConfig {
+    pub enabled: bool,
+    pub interval_ms: u64,
+}
+
+impl Default for StatusHeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: 2000,
+        }
+    }
+}
+
+/// Version of the config layout, bumped when a setting changes meaning.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// User-tunable settings for the live transcription pipeline. Missing settings,
+/// e.g. in a config saved by an older version, take their defaults and unknown
+/// ones are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionConfig {
+    /// `CONFIG_VERSION` of the app that saved the config, 0 from before versioning.
+    #[serde(default)]
+    pub version: u32,
+    pub filler_filter: FillerFilterConfig,
+    pub hallucination_loops: HallucinationLoopConfig,
+    pub source_balance: SourceBalanceConfig,
+    pub compressor: CompressorConfig,
+    pub pre_emphasis: PreEmphasisConfig,
+    pub chunk_padding: ChunkPaddingConfig,
+    pub chunk_timing: ChunkTimingConfig,
+    pub chunk_queue: ChunkQueueConfig,
+    pub chunk_output: ChunkOutputConfig,
+    pub whisper_window: WhisperWindowConfig,
+    pub status_heartbeat: StatusHeartbeatConfig,
+    pub endpointing: EndpointingConfig,
+    pub energy_dips: EnergyDipConfig,
+    pub speech_confirmation: SpeechConfirmationConfig,
+    pub chunk_coalescing: ChunkCoalescingConfig,
+    pub prompt_context: PromptContextConfig,
+    pub recovery_dedup: RecoveryDedupConfig,
+    pub dead_letters: DeadLetterConfig,
+    pub offline: OfflineTranscriptionConfig,
+    pub audio_devices: DeviceFallbackConfig,
+    pub permission_recovery: PermissionRecoveryConfig,
+    pub disconnect_grace: DisconnectGracePolicy,
+    pub sample_rate_changes: SampleRateChangeConfig,
+    pub capture_queue: CaptureQueueConfig,
+    pub system_preroll: SystemPrerollConfig,
+    pub test_source: TestSourceConfig,
+    pub monitor: MonitorConfig,
+    pub calibration: CalibrationConfig,
+    pub segment_boundaries: SegmentBoundaryConfig,
+    pub silence_auto_stop: SilenceAutoStopConfig,
+    pub autosave: TranscriptAutosaveConfig,
+    pub gap_reporting: GapReportingConfig,
+    pub overlapping_speech: OverlappingSpeechConfig,
+    pub speaker_hints: SpeakerHintConfig,
+    pub speaking_turns: TurnAggregationConfig,
+    pub sink_confidence: SinkConfidenceConfig,
+    pub paragraphs: ParagraphConfig,
+    pub text_normalization: TextNormalizationConfig,
+    pub redaction: RedactionConfig,
+    pub output_encoding: OutputEncodingConfig,
+    pub html_export: HtmlExportConfig,
+    pub capture_thread: CaptureThreadConfig,
+    pub crash_buffer: CrashBufferConfig,
+    pub session_stats: SessionStatsConfig,
+    pub chunk_profiling: ChunkProfilingConfig,
+    pub warmup: WarmupConfig,
+    pub backend_retry: BackendRetryConfig,
+    pub fallback_server: FallbackServerConfig,
+    pub decoding_escalation: DecodingEscalationConfig,
+    pub quality_throttle: QualityThrottleConfig,
+    pub model_escalation: ModelEscalationConfig,
+    pub language_models: LanguageModelConfig,
+    pub raw_output: RawOutputConfig,
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            filler_filter: FillerFilterConfig::default(),
+            hallucination_loops: HallucinationLoopConfig::default(),
+            source_balance: SourceBalanceConfig::default(),
+            compressor: CompressorConfig::default(),
+            pre_emphasis: PreEmphasisConfig::default(),
+            chunk_padding: ChunkPaddingConfig::default(),
+            chunk_timing: ChunkTimingConfig::default(),
+            chunk_queue: ChunkQueueConfig::default(),
+            chunk_output: ChunkOutputConfig::default(),
+            whisper_window: WhisperWindowConfig::default(),
+            status_heartbeat: StatusHeartbeatConfig::default(),
+            endpointing: EndpointingConfig::default(),
+            energy_dips: EnergyDipConfig::default(),
+            speech_confirmation: SpeechConfirmationConfig::default(),
+            chunk_coalescing: ChunkCoalescingConfig::default(),
+            prompt_context: PromptContextConfig::default(),
+            recovery_dedup: RecoveryDedupConfig::default(),
+            dead_letters: DeadLetterConfig::default(),
+            offline: OfflineTranscriptionConfig::default(),
+            audio_devices: DeviceFallbackConfig::default(),
+            permission_recovery: PermissionRecoveryConfig::default(),
+            disconnect_grace: DisconnectGracePolicy::default(),
+            sample_rate_changes: SampleRateChangeConfig::default(),
+            capture_queue: CaptureQueueConfig::default(),
+            system_preroll: SystemPrerollConfig::default(),
+            test_source: TestSourceConfig::default(),
+            monitor: MonitorConfig::default(),
+            calibration: CalibrationConfig::default(),
+            segment_boundaries: SegmentBoundaryConfig::default(),
+            silence_auto_stop: SilenceAutoStopConfig::default(),
+            autosave: TranscriptAutosaveConfig::default(),
+            gap_reporting: GapReportingConfig::default(),
+            overlapping_speech: OverlappingSpeechConfig::default(),
+            speaker_hints: SpeakerHintConfig::default(),
+            speaking_turns: TurnAggregationConfig::default(),
+            sink_confidence: SinkConfidenceConfig::default(),
+            paragraphs: ParagraphConfig::default(),
+            text_normalization: TextNormalizationConfig::default(),
+            redaction: RedactionConfig::default(),
+            output_encoding: OutputEncodingConfig::default(),
+            html_export: HtmlExportConfig::default(),
+            capture_thread: CaptureThreadConfig::default(),
+            crash_buffer: CrashBufferConfig::default(),
+            session_stats: SessionStatsConfig::default(),
+            chunk_profiling: ChunkProfilingConfig::default(),
+            warmup: WarmupConfig::default(),
+            backend_retry: BackendRetryConfig::default(),
+            fallback_server: FallbackServerConfig::default(),
+            decoding_escalation: DecodingEscalationConfig::default(),
+            quality_throttle: QualityThrottleConfig::default(),
+            model_escalation: ModelEscalationConfig::default(),
+            language_models: LanguageModelConfig::default(),
+            raw_output: RawOutputConfig::default(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref TRANSCRIPTION_CONFIG: RwLock<TranscriptionConfig> =
+        RwLock::new(TranscriptionConfig::default());
+}
+
+// Bumped on every change so a running session can notice and pick up the new settings
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Snapshot of the current config. Recording sessions take a copy when they start.
+pub fn current_config() -> TranscriptionConfig {
+    TRANSCRIPTION_CONFIG
+        .read()
+        .map(|config| config.clone())
+        .unwrap_or_default()
+}
+
+pub fn set_config(config: TranscriptionConfig) {
+    if let Ok(mut current) = TRANSCRIPTION_CONFIG.write() {
+        *current = config;
+        CONFIG_GENERATION.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+pub fn generation() -> u64 {
+    CONFIG_GENERATION.load(Ordering::SeqCst)
+}
+
+/// Replaces the config, refusing changes a running recording can't pick up.
+pub fn replace_config(config: TranscriptionConfig, is_recording: bool) -> Result<(), String> {
+    let config = upgrade_config(config);
+    if is_recording {
+        check_live_change(&current_config(), &config)?;
+    }
+    set_config(config);
+    Ok(())
+}
+
+/// Brings a config saved by another version of the app up to `CONFIG_VERSION`.
+/// Deserializing already filled in missing settings and dropped unknown ones.
+pub fn upgrade_config(mut config: TranscriptionConfig) -> TranscriptionConfig {
+    if config.version < CONFIG_VERSION {
+        info!("Upgrading transcription config from version {} to {}", config.version, CONFIG_VERSION);
+    } else if config.version > CONFIG_VERSION {
+        warn!(
+            "Transcription config is from a newer version ({}), settings this version doesn't know are ignored",
+            config.version
+        );
+    }
+    config.version = CONFIG_VERSION;
+    config
+}
+
+/// Merges a partial config (any subset of fields, nested objects merged
+/// recursively) into the current one and applies it.
+pub fn update_config(patch: Value, is_recording: bool) -> Result<TranscriptionConfig, String> {
+    let current = current_config();
+    let mut merged = serde_json::to_value(&current).map_err(|e| e.to_string())?;
+    merge_json(&mut merged, patch);
+    let updated: TranscriptionConfig =
+        serde_json::from_value(merged).map_err(|e| format!("Invalid transcription config: {}", e))?;
+
+    replace_config(updated.clone(), is_recording)?;
+    Ok(updated)
+}
+
+fn check_live_change(current: &TranscriptionConfig, updated: &TranscriptionConfig) -> Result<(), String> {
+    // Devices are opened when the recording starts
+    if current.audio_devices != updated.audio_devices {
+        return Err("Changing audio devices requires restarting the recording".to_string());
+    }
+    if current.test_source != updated.test_source {
+        return Err("Changing the test signal requires restarting the recording".to_string());
+    }
+    if current.disconnect_grace != updated.disconnect_grace {
+        return Err("Changing the disconnect grace period requires restarting the recording".to_string());
+    }
+    if current.capture_thread != updated.capture_thread {
+        return Err("Changing capture thread settings requires restarting the recording".to_string());
+    }
+    if current.crash_buffer != updated.crash_buffer {
+        return Err("Changing the crash recovery buffer requires restarting the recording".to_string());
+    }
+    // Volume and mute apply live, the monitor stream itself is opened with the recording
+    if current.monitor.enabled != updated.monitor.enabled || current.monitor.device != updated.monitor.device {
+        return Err("Turning monitoring on or off or changing its device requires restarting the recording".to_string());
+    }
+    // The backends are built when the recording starts
+    if current.fallback_server != updated.fallback_server {
+        return Err("Changing the fallback server requires restarting the recording".to_string());
+    }
+    if current.model_escalation != updated.model_escalation {
+        return Err("Changing model escalation requires restarting the recording".to_string());
+    }
+    if current.language_models != updated.language_models {
+        return Err("Changing language-specific models requires restarting the recording".to_string());
+    }
+    // Sentences are only kept for autosave from the start of the recording, turning it off applies live
+    if !current.autosave.enabled && updated.autosave.enabled {
+        return Err("Turning on transcript autosave requires restarting the recording".to_string());
+    }
+    if current.capture_queue != updated.capture_queue {
+        return Err("Changing the capture queue requires restarting the recording".to_string());
+    }
+    Ok(())
+}
+
+fn merge_json(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn live_changes_apply_unless_they_need_a_restart() {
+        let current = TranscriptionConfig::default();
+
+        let mut updated = current.clone();
+        updated.filler_filter.enabled = !current.filler_filter.enabled;
+        updated.segment_boundaries.min_silence_secs = 5;
+        assert_eq!(check_live_change(&current, &updated), Ok(()));
+
+        let mut updated = current.clone();
+        updated.audio_devices.input = vec!["USB Mic".to_string()];
+        assert!(check_live_change(&current, &updated).is_err());
+
+        let mut updated = current.clone();
+        updated.fallback_server.enabled = true;
+        assert!(check_live_change(&current, &updated).is_err());
+    }
+
+    #[test]
+    fn autosave_can_only_be_turned_off_live() {
+        let mut off = TranscriptionConfig::default();
+        off.autosave.enabled = false;
+        let mut on = off.clone();
+        on.autosave.enabled = true;
+
+        assert!(check_live_change(&off, &on).is_err());
+        assert_eq!(check_live_change(&on, &off), Ok(()));
+    }
+
+    #[test]
+    fn chunk_output_modes_pick_events_transcription_or_both() {
+        let modes = [
+            ChunkOutputMode::Transcribe,
+            ChunkOutputMode::ChunksOnly,
+            ChunkOutputMode::Both,
+        ];
+        let emits: Vec<bool> = modes.iter().map(|mode| mode.emits_chunks()).collect();
+        let transcribes: Vec<bool> = modes.iter().map(|mode| mode.transcribes()).collect();
+        assert_eq!(emits, vec![false, true, true]);
+        assert_eq!(transcribes, vec![true, false, true]);
+    }
+
+    #[test]
+    fn a_saved_chunk_output_mode_survives_the_upgrade() {
+        let saved = json!({ "chunk_output": { "mode": "chunks_only" } });
+        let config = upgrade_config(serde_json::from_value(saved).unwrap());
+        assert_eq!(config.chunk_output.mode, ChunkOutputMode::ChunksOnly);
+    }
+
+    #[test]
+    fn upgrades_a_config_saved_before_versioning() {
+        let saved = json!({
+            "filler_filter": { "enabled": true },
+            "setting_from_an_old_version": 42
+        });
+        let config = upgrade_config(serde_json::from_value(saved).unwrap());
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.filler_filter.enabled);
+        assert_eq!(
+            config.filler_filter.filler_words,
+            FillerFilterConfig::default().filler_words
+        );
+    }
+
+    #[test]
+    fn keeps_known_settings_from_a_newer_version() {
+        let saved = json!({
+            "version": CONFIG_VERSION + 1,
+            "filler_filter": { "enabled": true, "setting_from_a_new_version": true }
+        });
+        let config = upgrade_config(serde_json::from_value(saved).unwrap());
+
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.filler_filter.enabled);
+    }
+
+    #[test]
+    fn merges_patches_into_nested_settings() {
+        let mut target = json!({ "filler_filter": { "enabled": false, "collapse_repetitions": true } });
+        merge_json(&mut target, json!({ "filler_filter": { "enabled": true } }));
+        assert_eq!(
+            target,
+            json!({ "filler_filter": { "enabled": true, "collapse_repetitions": true } })
+        );
+    }
+}
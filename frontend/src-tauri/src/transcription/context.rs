@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Settings for passing recent transcript text to whisper as the initial prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptContextConfig {
+    /// Off by default, a wrong transcript can carry over into the next chunk.
+    pub enabled: bool,
+    /// Upper bound on the prompt length in characters.
+    pub max_chars: usize,
+    /// Upper bound on the prompt length in estimated whisper tokens. Whisper
+    /// only keeps the last 224 prompt tokens, anything before is cut without
+    /// regard for word boundaries. 0 disables the token limit.
+    pub max_tokens: usize,
+}
+
+impl Default for PromptContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_chars: 600,
+            max_tokens: 224,
+        }
+    }
+}
+
+/// Bounded history of emitted sentences. Mic and system audio are mixed before
+/// transcription, so the history covers both sides of the conversation.
+pub struct TranscriptContext {
+    sentences: VecDeque<String>,
+    total_chars: usize,
+    max_chars: usize,
+    max_tokens: usize,
+}
+
+/// Rough count of the tokens whisper's BPE tokenizer makes of `text`: one per
+/// three ASCII letters or digits of a word, one per byte of any other character,
+/// since the byte-level BPE may leave every byte of e.g. a CJK character as its
+/// own token. English averages about four characters per token; the margin is
+/// for names and digit strings, which split into more pieces.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|word| {
+            let ascii_alnum = word.chars().filter(|c| c.is_ascii_alphanumeric()).count();
+            let other: usize = word
+                .chars()
+                .filter(|c| !c.is_ascii_alphanumeric())
+                .map(char::len_utf8)
+                .sum();
+            ascii_alnum.div_ceil(3) + other
+        })
+        .sum()
+}
+
+impl TranscriptContext {
+    pub fn new(max_chars: usize, max_tokens: usize) -> Self {
+        Self {
+            sentences: VecDeque::new(),
+            total_chars: 0,
+            max_chars,
+            max_tokens,
+        }
+    }
+
+    pub fn set_limits(&mut self, max_chars: usize, max_tokens: usize) {
+        self.max_chars = max_chars;
+        self.max_tokens = max_tokens;
+        while self.total_chars > self.max_chars && self.sentences.len() > 1 {
+            if let Some(oldest) = self.sentences.pop_front() {
+                self.total_chars -= oldest.len() + 1;
+            }
+        }
+    }
+
+    pub fn push(&mut self, sentence: &str) {
+        let sentence = sentence.trim();
+        if sentence.is_empty() || self.max_chars == 0 {
+            return;
+        }
+        self.total_chars += sentence.len() + 1;
+        self.sentences.push_back(sentence.to_string());
+
+        // Keep at least the latest sentence, even if it alone exceeds the limit
+        while self.total_chars > self.max_chars && self.sentences.len() > 1 {
+            if let Some(oldest) = self.sentences.pop_front() {
+                self.total_chars -= oldest.len() + 1;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.sentences.clear();
+        self.total_chars = 0;
+    }
+
+    /// The history as a single prompt, trimmed from the front on a word boundary
+    /// to `max_chars` and `max_tokens`, so the most recent text is kept.
+    pub fn prompt(&self) -> Option<String> {
+        let prompt = self.prompt_within_chars()?;
+        if self.max_tokens == 0 {
+            return Some(prompt);
+        }
+        let words: Vec<&str> = prompt.split(' ').collect();
+        let mut tokens = 0;
+        let mut first = words.len();
+        while first > 0 {
+            let word_tokens = estimate_tokens(words[first - 1]);
+            if tokens + word_tokens > self.max_tokens {
+                break;
+            }
+            tokens += word_tokens;
+            first -= 1;
+        }
+        // A single word over the budget is dropped, an empty prompt is no prompt
+        (first < words.len()).then(|| words[first..].join(" "))
+    }
+
+    fn prompt_within_chars(&self) -> Option<String> {
+        if self.sentences.is_empty() {
+            return None;
+        }
+        let joined = self.sentences.iter().cloned().collect::<Vec<_>>().join(" ");
+        if joined.len() <= self.max_chars {
+            return Some(joined);
+        }
+        let mut start = joined.len() - self.max_chars;
+        while !joined.is_char_boundary(start) {
+            start += 1;
+        }
+        let tail = &joined[start..];
+        let tail = match tail.find(' ') {
+            Some(space) if space + 1 < tail.len() => &tail[space + 1..],
+            _ => tail,
+        };
+        Some(tail.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_recent_sentences_into_the_prompt() {
+        let mut context = TranscriptContext::new(600, 0);
+        assert_eq!(context.prompt(), None);
+        context.push("First point.");
+        context.push("  ");
+        context.push("Second point.");
+        assert_eq!(context.prompt().as_deref(), Some("First point. Second point."));
+    }
+
+    #[test]
+    fn drops_the_oldest_sentences_past_max_chars() {
+        let mut context = TranscriptContext::new(30, 0);
+        context.push("The first sentence.");
+        context.push("The second sentence.");
+        assert_eq!(context.prompt().as_deref(), Some("The second sentence."));
+    }
+
+    #[test]
+    fn trims_a_long_sentence_on_a_word_boundary() {
+        let mut context = TranscriptContext::new(12, 0);
+        context.push("one two three four five");
+        assert_eq!(context.prompt().as_deref(), Some("four five"));
+    }
+
+    #[test]
+    fn estimates_tokens_per_word() {
+        assert_eq!(estimate_tokens("hello world"), 4);
+        assert_eq!(estimate_tokens("Okay, 2024"), 5);
+        // Three bytes per character, each possibly a token of its own
+        assert_eq!(estimate_tokens("会議 です"), 12);
+        assert_eq!(estimate_tokens("  "), 0);
+    }
+
+    #[test]
+    fn trims_the_prompt_to_max_tokens_keeping_the_latest_words() {
+        let mut context = TranscriptContext::new(600, 6);
+        context.push("alpha beta gamma delta");
+        assert_eq!(context.prompt().as_deref(), Some("beta gamma delta"));
+
+        let mut context = TranscriptContext::new(600, 20);
+        for i in 0..30 {
+            context.push(&format!("Sentence number {}.", i));
+        }
+        let prompt = context.prompt().unwrap();
+        assert!(estimate_tokens(&prompt) <= 20);
+        assert!(prompt.ends_with("Sentence number 29."));
+    }
+
+    #[test]
+    fn no_prompt_when_the_last_word_alone_is_over_budget() {
+        let mut context = TranscriptContext::new(600, 1);
+        context.push("extraordinary");
+        assert_eq!(context.prompt(), None);
+    }
+
+    #[test]
+    fn clear_empties_the_history() {
+        let mut context = TranscriptContext::new(600, 0);
+        context.push("Something said.");
+        context.clear();
+        assert_eq!(context.prompt(), None);
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Both sources must go quiet for this long before an overlap ends, so the
+// short pauses between words don't split it up
+const OVERLAP_HANGOVER_SECS: f64 = 0.25;
+// Overlaps remembered for flagging sentences that are still being transcribed
+const MAX_OVERLAPS: usize = 100;
+
+/// Settings for flagging sentences spoken while the microphone and the system
+/// audio both had speech, e.g. people talking over each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverlappingSpeechConfig {
+    pub enabled: bool,
+    /// A source louder than this RMS counts as speaking.
+    pub speech_rms: f32,
+    /// Shorter overlaps, like a quick "mm-hm", aren't flagged.
+    pub min_overlap_ms: u64,
+}
+
+impl Default for OverlappingSpeechConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speech_rms: 0.01,
+            min_overlap_ms: 300,
+        }
+    }
+}
+
+/// Finds the stretches where both sources have speech at once. Times are
+/// seconds since recording start.
+pub struct OverlapDetector {
+    config: OverlappingSpeechConfig,
+    started: Option<f64>,
+    last_overlap: f64,
+}
+
+impl OverlapDetector {
+    pub fn new(config: OverlappingSpeechConfig) -> Self {
+        Self {
+            config,
+            started: None,
+            last_overlap: 0.0,
+        }
+    }
+
+    pub fn update_config(&mut self, config: OverlappingSpeechConfig) {
+        if !config.enabled {
+            self.started = None;
+        }
+        self.config = config;
+    }
+
+    /// Feed the level of each source for a batch captured at `elapsed_secs`.
+    /// Returns an overlap once it has ended.
+    pub fn update(&mut self, elapsed_secs: f64, mic_rms: f32, system_rms: f32) -> Option<(f64, f64)> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        if mic_rms >= self.config.speech_rms && system_rms >= self.config.speech_rms {
+            self.started.get_or_insert(elapsed_secs);
+            self.last_overlap = elapsed_secs;
+            return None;
+        }
+
+        let started = self.started?;
+        if elapsed_secs - self.last_overlap < OVERLAP_HANGOVER_SECS {
+            return None;
+        }
+        self.started = None;
+        let long_enough = (self.last_overlap - started) * 1000.0 >= self.config.min_overlap_ms as f64;
+        long_enough.then_some((started, self.last_overlap))
+    }
+}
+
+/// Recent overlaps, checked against each sentence as it is emitted.
+#[derive(Debug, Default)]
+pub struct OverlapLog {
+    overlaps: VecDeque<(f64, f64)>,
+}
+
+impl OverlapLog {
+    pub fn push(&mut self, start: f64, end: f64) {
+        if self.overlaps.len() == MAX_OVERLAPS {
+            self.overlaps.pop_front();
+        }
+        self.overlaps.push_back((start, end));
+    }
+
+    /// Whether any overlap falls within `start..end`.
+    pub fn intersects(&self, start: f64, end: f64) -> bool {
+        self.overlaps
+            .iter()
+            .any(|&(overlap_start, overlap_end)| overlap_start <= end && overlap_end >= start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> OverlapDetector {
+        OverlapDetector::new(OverlappingSpeechConfig {
+            enabled: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn reports_an_overlap_once_both_sources_stay_quiet() {
+        let mut detector = detector();
+        assert_eq!(detector.update(1.0, 0.1, 0.1), None);
+        assert_eq!(detector.update(1.5, 0.1, 0.1), None);
+        // A short pause between words doesn't end it
+        assert_eq!(detector.update(1.6, 0.1, 0.0), None);
+        assert_eq!(detector.update(1.7, 0.1, 0.1), None);
+        assert_eq!(detector.update(1.8, 0.1, 0.0), None);
+        assert_eq!(detector.update(2.0, 0.1, 0.0), Some((1.0, 1.7)));
+        assert_eq!(detector.update(3.0, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn ignores_short_overlaps() {
+        let mut detector = detector();
+        detector.update(1.0, 0.1, 0.1);
+        detector.update(1.2, 0.1, 0.1);
+        assert_eq!(detector.update(2.0, 0.0, 0.1), None);
+    }
+
+    #[test]
+    fn log_matches_sentences_that_touch_an_overlap() {
+        let mut log = OverlapLog::default();
+        log.push(5.0, 6.0);
+        assert!(log.intersects(4.0, 5.0));
+        assert!(log.intersects(5.5, 5.6));
+        assert!(!log.intersects(6.5, 8.0));
+
+        for i in 0..MAX_OVERLAPS {
+            log.push(100.0 + i as f64, 100.5 + i as f64);
+        }
+        assert!(!log.intersects(4.0, 5.0));
+    }
+}
@@ -0,0 +1,245 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::backend::{strip_blank_markers, TranscriptionBackend};
+use super::offline::DecodingPass;
+
+/// Settings for keeping the audio of chunks that failed transcription, so they
+/// can be transcribed again later, e.g. after switching to a larger model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadLetterConfig {
+    pub enabled: bool,
+    /// Failed chunks kept, the oldest is dropped beyond this.
+    pub max_chunks: usize,
+    /// Decoding used when the kept chunks are transcribed again.
+    pub pass: DecodingPass,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_chunks: 20,
+            pass: DecodingPass {
+                beam_size: 1,
+                temperature: 0.0,
+            },
+        }
+    }
+}
+
+/// A chunk whose transcription failed after all retries.
+#[derive(Debug, Clone)]
+pub struct FailedChunk {
+    pub chunk_id: u64,
+    /// Seconds since its recording started.
+    pub timestamp: f64,
+    /// 16 kHz mono.
+    pub samples: Vec<f32>,
+    pub error: String,
+}
+
+/// Payload of the `failed-chunk-transcribed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveredChunk {
+    pub chunk_id: u64,
+    pub timestamp: f64,
+    pub text: String,
+}
+
+/// Outcome of transcribing the failed chunks again.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReprocessReport {
+    pub transcribed: usize,
+    /// Chunks that failed again and are still kept.
+    pub still_failing: usize,
+}
+
+/// Failed chunks waiting to be transcribed again, oldest first.
+#[derive(Debug)]
+pub struct DeadLetterQueue {
+    chunks: VecDeque<FailedChunk>,
+}
+
+impl DeadLetterQueue {
+    pub const fn new() -> Self {
+        Self { chunks: VecDeque::new() }
+    }
+
+    /// Keeps `chunk`, dropping the oldest ones past `max_chunks`. Returns how many were dropped.
+    pub fn push(&mut self, chunk: FailedChunk, max_chunks: usize) -> usize {
+        self.chunks.push_back(chunk);
+        let excess = self.chunks.len().saturating_sub(max_chunks.max(1));
+        self.chunks.drain(..excess);
+        excess
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn take_all(&mut self) -> Vec<FailedChunk> {
+        self.chunks.drain(..).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Transcribes the chunks in `queue` again with `config.pass`, each on its own
+/// since they don't follow on from each other. Chunks that fail again go back
+/// in the queue, the others are handed to `on_recovered`.
+pub async fn reprocess_failed<B: TranscriptionBackend + ?Sized>(
+    backend: &B,
+    queue: &Mutex<DeadLetterQueue>,
+    config: &DeadLetterConfig,
+    mut on_recovered: impl FnMut(RecoveredChunk),
+) -> ReprocessReport {
+    let failed = queue.lock().map(|mut queue| queue.take_all()).unwrap_or_default();
+    let mut report = ReprocessReport::default();
+    for chunk in failed {
+        match backend.transcribe_pass(chunk.chunk_id, &chunk.samples, &config.pass).await {
+            Ok(response) => {
+                let text = response
+                    .segments
+                    .iter()
+                    .map(|segment| strip_blank_markers(&segment.text))
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                on_recovered(RecoveredChunk {
+                    chunk_id: chunk.chunk_id,
+                    timestamp: chunk.timestamp,
+                    text,
+                });
+                report.transcribed += 1;
+            }
+            Err(e) => {
+                warn!("Failed chunk {} failed again: {}", chunk.chunk_id, e);
+                let chunk = FailedChunk {
+                    error: e.to_string(),
+                    ..chunk
+                };
+                if let Ok(mut queue) = queue.lock() {
+                    queue.push(chunk, config.max_chunks);
+                }
+                report.still_failing += 1;
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::backend::{TranscriptResponse, TranscriptSegment, TranscriptionError, TranscriptionFuture};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // Fails until `recovered` is set, like a server that comes back later.
+    struct FlakyBackend {
+        recovered: AtomicBool,
+    }
+
+    impl TranscriptionBackend for FlakyBackend {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn transcribe(&self, _chunk_id: u64, _samples: Vec<f32>, _prompt: Option<String>) -> TranscriptionFuture<'_> {
+            let reply = if self.recovered.load(Ordering::Relaxed) {
+                Ok(TranscriptResponse {
+                    segments: vec![TranscriptSegment {
+                        text: "Budget approved.".to_string(),
+                        t0: 0.0,
+                        t1: 100.0,
+                        words: Vec::new(),
+                        voice: None,
+                        speaker: None,
+                    }],
+                    buffer_size_ms: 1000,
+                    language: None,
+                    raw: None,
+                    failed_attempts: Vec::new(),
+                })
+            } else {
+                Err(TranscriptionError::ServerUnavailable {
+                    message: "connection refused".to_string(),
+                })
+            };
+            Box::pin(async move { reply })
+        }
+    }
+
+    fn failed(chunk_id: u64) -> FailedChunk {
+        FailedChunk {
+            chunk_id,
+            timestamp: chunk_id as f64,
+            samples: vec![0.0; 16],
+            error: "server unavailable".to_string(),
+        }
+    }
+
+    #[test]
+    fn drops_the_oldest_chunks_past_the_limit() {
+        let mut queue = DeadLetterQueue::new();
+        assert_eq!(queue.push(failed(1), 2), 0);
+        assert_eq!(queue.push(failed(2), 2), 0);
+        assert_eq!(queue.push(failed(3), 2), 1);
+        assert_eq!(queue.len(), 2);
+
+        let ids: Vec<u64> = queue.take_all().iter().map(|chunk| chunk.chunk_id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn always_keeps_the_latest_chunk() {
+        let mut queue = DeadLetterQueue::new();
+        queue.push(failed(1), 0);
+        assert_eq!(queue.push(failed(2), 0), 1);
+        assert_eq!(queue.take_all()[0].chunk_id, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_chunks_are_reprocessed_once_the_backend_recovers() {
+        let backend = FlakyBackend {
+            recovered: AtomicBool::new(false),
+        };
+        let config = DeadLetterConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let queue = Mutex::new(DeadLetterQueue::new());
+        queue.lock().unwrap().push(failed(4), config.max_chunks);
+
+        let mut recovered = Vec::new();
+        let report = reprocess_failed(&backend, &queue, &config, |chunk| recovered.push(chunk)).await;
+        assert_eq!(report.transcribed, 0);
+        assert_eq!(report.still_failing, 1);
+        assert!(recovered.is_empty());
+        assert_eq!(queue.lock().unwrap().len(), 1);
+
+        backend.recovered.store(true, Ordering::Relaxed);
+        let report = reprocess_failed(&backend, &queue, &config, |chunk| recovered.push(chunk)).await;
+        assert_eq!(report.transcribed, 1);
+        assert_eq!(report.still_failing, 0);
+        assert!(queue.lock().unwrap().is_empty());
+        assert_eq!(recovered[0].chunk_id, 4);
+        assert_eq!(recovered[0].text, "Budget approved.");
+    }
+}
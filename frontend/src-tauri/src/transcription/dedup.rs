@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Sentences remembered for comparison after a recovery
+const RECENT_SENTENCE_COUNT: usize = 20;
+
+/// Settings for dropping sentences repeated right after a capture stream was
+/// reopened, when the same speech can be transcribed a second time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryDedupConfig {
+    pub enabled: bool,
+    /// How long after a recovery repeated sentences are dropped.
+    pub window_secs: f64,
+}
+
+impl Default for RecoveryDedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 15.0,
+        }
+    }
+}
+
+/// Remembers recent sentences and, shortly after a recovery, reports the ones
+/// that repeat an earlier sentence that ended within the window.
+pub struct RecoveryDedup {
+    config: RecoveryDedupConfig,
+    recent: VecDeque<(String, f64)>,
+    recovered_at: Option<f64>,
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl RecoveryDedup {
+    pub fn new(config: RecoveryDedupConfig) -> Self {
+        Self {
+            config,
+            recent: VecDeque::with_capacity(RECENT_SENTENCE_COUNT),
+            recovered_at: None,
+        }
+    }
+
+    pub fn update_config(&mut self, config: RecoveryDedupConfig) {
+        self.config = config;
+    }
+
+    /// Marks a recovery at `at_secs` since recording start.
+    pub fn note_recovery(&mut self, at_secs: f64) {
+        self.recovered_at = Some(at_secs);
+    }
+
+    /// Whether the sentence repeats a recent one and falls in the window after
+    /// a recovery. Sentences that aren't dropped are remembered.
+    pub fn is_duplicate(&mut self, text: &str, start_secs: f64, end_secs: f64) -> bool {
+        let normalized = normalize(text);
+        if normalized.is_empty() {
+            return false;
+        }
+
+        let in_window = self.config.enabled
+            && self
+                .recovered_at
+                .is_some_and(|recovered_at| start_secs <= recovered_at + self.config.window_secs);
+        let repeated = self
+            .recent
+            .iter()
+            .any(|(seen, seen_end)| *seen == normalized && start_secs - seen_end <= self.config.window_secs);
+        if in_window && repeated {
+            return true;
+        }
+
+        if self.recent.len() >= RECENT_SENTENCE_COUNT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((normalized, end_secs));
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dedup() -> RecoveryDedup {
+        RecoveryDedup::new(RecoveryDedupConfig {
+            enabled: true,
+            window_secs: 15.0,
+        })
+    }
+
+    #[test]
+    fn drops_a_repeat_right_after_a_recovery() {
+        let mut dedup = dedup();
+        assert!(!dedup.is_duplicate("We should ship it.", 10.0, 12.0));
+        dedup.note_recovery(13.0);
+        assert!(dedup.is_duplicate("we should ship it", 14.0, 16.0));
+    }
+
+    #[test]
+    fn keeps_repeats_without_a_recovery() {
+        let mut dedup = dedup();
+        assert!(!dedup.is_duplicate("Yes.", 1.0, 1.5));
+        assert!(!dedup.is_duplicate("Yes.", 2.0, 2.5));
+    }
+
+    #[test]
+    fn keeps_repeats_outside_the_window() {
+        let mut dedup = dedup();
+        assert!(!dedup.is_duplicate("Next slide.", 10.0, 11.0));
+        dedup.note_recovery(12.0);
+        assert!(!dedup.is_duplicate("Next slide.", 30.0, 31.0));
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Character set transcripts are written in when saved to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    /// Text is written unchanged.
+    Utf8,
+    /// Punctuation and accented Latin letters are folded to ASCII, anything
+    /// else becomes '?'.
+    AsciiFold,
+}
+
+/// Settings for rewriting transcript text for tools that can't read UTF-8.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputEncodingConfig {
+    pub encoding: OutputEncoding,
+    /// Applied before folding, e.g. "€" -> "EUR". Applies with UTF-8 output too.
+    pub replacements: BTreeMap<String, String>,
+}
+
+impl Default for OutputEncodingConfig {
+    fn default() -> Self {
+        Self {
+            encoding: OutputEncoding::Utf8,
+            replacements: BTreeMap::new(),
+        }
+    }
+}
+
+/// Rewrites `text` for the configured output encoding.
+pub fn encode_output(text: &str, config: &OutputEncodingConfig) -> String {
+    let replaced = config
+        .replacements
+        .iter()
+        .filter(|(from, _)| !from.is_empty())
+        .fold(text.to_string(), |text, (from, to)| text.replace(from.as_str(), to));
+
+    match config.encoding {
+        OutputEncoding::Utf8 => replaced,
+        OutputEncoding::AsciiFold => ascii_fold(&replaced),
+    }
+}
+
+fn ascii_fold(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            out.push_str(fold_char(c));
+        }
+    }
+    out
+}
+
+// ASCII stand-ins for the punctuation whisper emits and for Latin-1/Latin
+// Extended-A letters. Anything without one becomes '?'.
+fn fold_char(c: char) -> &'static str {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => "'",
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' | '\u{00AB}' | '\u{00BB}' => "\"",
+        '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => "-",
+        '\u{2014}' | '\u{2015}' => "--",
+        '\u{2026}' => "...",
+        '\u{00A0}' | '\u{2002}' | '\u{2003}' | '\u{2009}' | '\u{202F}' => " ",
+        '\u{2022}' | '\u{00B7}' => "*",
+        '\u{00D7}' => "x",
+        '\u{00A9}' => "(c)",
+        '\u{00AE}' => "(R)",
+        '\u{2122}' => "(TM)",
+        '\u{20AC}' => "EUR",
+        '\u{00A3}' => "GBP",
+        '\u{00A5}' => "JPY",
+        '\u{00B0}' => " deg",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'ď' | 'đ' | 'ð' => "d",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĥ' | 'Ħ' => "H",
+        'ĥ' | 'ħ' => "h",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ĵ' => "J",
+        'ĵ' => "j",
+        'Ķ' => "K",
+        'ķ' => "k",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'Ţ' | 'Ť' | 'Ŧ' => "T",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'Þ' => "Th",
+        'þ' => "th",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ŵ' => "W",
+        'ŵ' => "w",
+        'Ý' | 'Ŷ' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_punctuation_and_accents_to_ascii() {
+        let config = OutputEncodingConfig {
+            encoding: OutputEncoding::AsciiFold,
+            ..OutputEncodingConfig::default()
+        };
+        let text = "\u{201C}Café\u{201D} \u{2014} it\u{2019}s 5\u{00B0}";
+        assert_eq!(encode_output(text, &config), "\"Cafe\" -- it's 5 deg");
+        assert_eq!(encode_output("日本", &config), "??");
+    }
+
+    #[test]
+    fn applies_replacements_before_folding() {
+        let mut config = OutputEncodingConfig {
+            encoding: OutputEncoding::AsciiFold,
+            ..OutputEncodingConfig::default()
+        };
+        config.replacements.insert("€".to_string(), "euros".to_string());
+        assert_eq!(encode_output("5€", &config), "5euros");
+    }
+
+    #[test]
+    fn utf8_output_only_applies_replacements() {
+        let mut config = OutputEncodingConfig::default();
+        assert_eq!(encode_output("Café €5", &config), "Café €5");
+        config.replacements.insert("€".to_string(), "EUR ".to_string());
+        assert_eq!(encode_output("Café €5", &config), "Café EUR 5");
+    }
+}
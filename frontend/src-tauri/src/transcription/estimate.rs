@@ -0,0 +1,222 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Weight of each new measurement in the running real-time factor
+const RTF_SMOOTHING: f64 = 0.2;
+
+/// Settings for leaving the first response of a recording out of the latency
+/// and real-time factor measurements. It usually includes the server warming
+/// up, so it says little about steady-state speed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarmupConfig {
+    pub exclude_first_chunk: bool,
+}
+
+/// Expected cost of transcribing a recording of a given length.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessingEstimate {
+    pub estimated_ms: u64,
+    /// Transcription time per unit of audio time against the running whisper server.
+    pub real_time_factor: f64,
+    /// Transcriptions the real-time factor is based on.
+    pub measurements: u64,
+    /// Memory needed to hold the audio as 16 kHz f32 samples.
+    pub audio_memory_bytes: u64,
+}
+
+/// Transcription time per unit of audio time, smoothed over the transcriptions
+/// measured so far.
+#[derive(Debug, Default)]
+struct RealTimeFactor {
+    value: f64,
+    measurements: u64,
+}
+
+impl RealTimeFactor {
+    fn record(&mut self, audio_ms: u64, processing_ms: u64) {
+        if audio_ms == 0 {
+            return;
+        }
+        let measured = processing_ms as f64 / audio_ms as f64;
+        if self.measurements == 0 {
+            self.value = measured;
+        } else {
+            self.value += (measured - self.value) * RTF_SMOOTHING;
+        }
+        self.measurements += 1;
+    }
+
+    fn current(&self) -> Option<(f64, u64)> {
+        (self.measurements > 0).then_some((self.value, self.measurements))
+    }
+
+    fn speed(&self) -> Option<f64> {
+        self.current().and_then(|(rtf, _)| (rtf > 0.0).then(|| 1.0 / rtf))
+    }
+}
+
+/// Wall-clock time during which at least one transcription request was in
+/// flight. Workers send requests concurrently and the server runs them one at
+/// a time, so a request's own duration includes waiting behind the others;
+/// only the time the requests cover together measures how fast audio gets
+/// transcribed.
+#[derive(Debug, Default)]
+pub struct ThroughputMeter {
+    in_flight: usize,
+    last_event: Option<Instant>,
+    busy: Duration,
+}
+
+impl ThroughputMeter {
+    fn advance(&mut self, now: Instant) {
+        if self.in_flight > 0 {
+            if let Some(last) = self.last_event {
+                self.busy += now.saturating_duration_since(last);
+            }
+        }
+        self.last_event = Some(now);
+    }
+
+    pub fn request_started(&mut self, now: Instant) {
+        self.advance(now);
+        self.in_flight += 1;
+    }
+
+    /// Busy time since the previous finished request, which is what it took to
+    /// transcribe the audio of the request finishing now.
+    pub fn request_finished(&mut self, now: Instant) -> Duration {
+        self.advance(now);
+        self.in_flight = self.in_flight.saturating_sub(1);
+        std::mem::take(&mut self.busy)
+    }
+}
+
+lazy_static! {
+    static ref REAL_TIME_FACTOR: Mutex<RealTimeFactor> = Mutex::new(RealTimeFactor::default());
+    static ref THROUGHPUT: Mutex<ThroughputMeter> = Mutex::new(ThroughputMeter::default());
+}
+
+/// Marks a transcription request as sent, see `ThroughputMeter`.
+pub fn request_started() {
+    if let Ok(mut meter) = THROUGHPUT.lock() {
+        meter.request_started(Instant::now());
+    }
+}
+
+/// Marks a transcription request as answered and returns the processing time
+/// to attribute to its audio, see `ThroughputMeter::request_finished`.
+pub fn request_finished() -> Duration {
+    THROUGHPUT
+        .lock()
+        .map(|mut meter| meter.request_finished(Instant::now()))
+        .unwrap_or_default()
+}
+
+/// Forgets requests in flight, for the start of a recording.
+pub fn reset_throughput() {
+    if let Ok(mut meter) = THROUGHPUT.lock() {
+        *meter = ThroughputMeter::default();
+    }
+}
+
+/// Records how long whisper took for `audio_ms` of audio.
+pub fn record_processing(audio_ms: u64, processing_ms: u64) {
+    if let Ok(mut rtf) = REAL_TIME_FACTOR.lock() {
+        rtf.record(audio_ms, processing_ms);
+    }
+}
+
+/// The smoothed real-time factor and the number of measurements behind it, if
+/// anything has been transcribed yet.
+pub fn real_time_factor() -> Option<(f64, u64)> {
+    REAL_TIME_FACTOR.lock().ok().and_then(|rtf| rtf.current())
+}
+
+/// Seconds of audio whisper transcribes per second, the inverse of the
+/// real-time factor. Below 1.0 transcription can't keep up with live audio.
+pub fn transcription_speed() -> Option<f64> {
+    REAL_TIME_FACTOR.lock().ok().and_then(|rtf| rtf.speed())
+}
+
+pub fn estimate(file_duration_ms: u64, real_time_factor: f64, measurements: u64, sample_rate: u32) -> ProcessingEstimate {
+    ProcessingEstimate {
+        estimated_ms: (file_duration_ms as f64 * real_time_factor).round() as u64,
+        real_time_factor,
+        measurements,
+        audio_memory_bytes: file_duration_ms * sample_rate as u64 / 1000 * std::mem::size_of::<f32>() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_scales_with_the_file_duration() {
+        let short = estimate(60_000, 0.25, 4, 16000);
+        let long = estimate(600_000, 0.25, 4, 16000);
+
+        assert_eq!(short.estimated_ms, 15_000);
+        assert_eq!(long.estimated_ms, 10 * short.estimated_ms);
+        assert_eq!(long.audio_memory_bytes, 10 * short.audio_memory_bytes);
+        assert_eq!(short.audio_memory_bytes, 60 * 16000 * 4);
+    }
+
+    #[test]
+    fn real_time_factor_follows_new_measurements() {
+        let mut rtf = RealTimeFactor::default();
+        rtf.record(0, 500);
+        assert_eq!(rtf.current(), None);
+
+        rtf.record(10_000, 5_000);
+        assert_eq!(rtf.current(), Some((0.5, 1)));
+
+        rtf.record(10_000, 10_000);
+        let (value, measurements) = rtf.current().unwrap();
+        assert!(value > 0.5 && value < 1.0);
+        assert_eq!(measurements, 2);
+    }
+
+    #[test]
+    fn speed_is_the_inverse_of_the_real_time_factor() {
+        let mut rtf = RealTimeFactor::default();
+        assert_eq!(rtf.speed(), None);
+        rtf.record(10_000, 5_000);
+        assert_eq!(rtf.speed(), Some(2.0));
+        rtf.record(10_000, 0);
+        assert!(rtf.speed().unwrap() > 2.0);
+    }
+
+    #[test]
+    fn overlapping_requests_are_timed_by_throughput() {
+        use crate::transcription::throttle::{QualityLevel, QualityThrottle, QualityThrottleConfig};
+
+        // Four workers send 2 s chunks at once; the server answers one per second,
+        // so each request takes up to 4 s but audio is transcribed at twice real time
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::default();
+        for _ in 0..4 {
+            meter.request_started(start);
+        }
+        let config = QualityThrottleConfig {
+            enabled: true,
+            min_chunks_per_step: 1,
+            ..Default::default()
+        };
+        let mut throttle = QualityThrottle::default();
+        for second in 1..=4 {
+            let busy = meter.request_finished(start + Duration::from_secs(second));
+            assert_eq!(busy, Duration::from_secs(1));
+            let speed = 2_000.0 / busy.as_millis() as f64;
+            assert_eq!(throttle.observe(&config, speed), None);
+        }
+        assert_eq!(throttle.level(), QualityLevel::Full);
+
+        // Idle time between requests doesn't count
+        meter.request_started(start + Duration::from_secs(10));
+        assert_eq!(meter.request_finished(start + Duration::from_secs(11)), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FillerFilterConfig {
+    pub enabled: bool,
+    /// Disfluencies dropped from the clean text, matched case-insensitively
+    pub filler_words: Vec<String>,
+    /// Collapse "I I think" and false starts like "I- I think"
+    pub collapse_repetitions: bool,
+    /// Words that are legitimately doubled ("that that", "had had") and never collapsed
+    pub preserved_repetitions: Vec<String>,
+}
+
+impl Default for FillerFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            filler_words: ["um", "umm", "uh", "uhm", "er", "erm", "ah", "hmm", "mm"]
+                .iter()
+                .map(|w| w.to_string())
+                .collect(),
+            collapse_repetitions: true,
+            preserved_repetitions: ["that", "had", "is", "very", "no", "bye"]
+                .iter()
+                .map(|w| w.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Produces a "clean" variant of transcript text with disfluencies removed.
+/// The raw text is left untouched so both can be shown.
+#[derive(Debug, Clone)]
+pub struct FillerFilter {
+    config: FillerFilterConfig,
+    filler_words: HashSet<String>,
+    preserved_repetitions: HashSet<String>,
+}
+
+impl FillerFilter {
+    pub fn new(config: FillerFilterConfig) -> Self {
+        let filler_words = config.filler_words.iter().map(|w| w.to_lowercase()).collect();
+        let preserved_repetitions = config
+            .preserved_repetitions
+            .iter()
+            .map(|w| w.to_lowercase())
+            .collect();
+        Self {
+            config,
+            filler_words,
+            preserved_repetitions,
+        }
+    }
+
+    pub fn clean(&self, text: &str) -> String {
+        if !self.config.enabled {
+            return text.to_string();
+        }
+
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let mut kept: Vec<String> = Vec::with_capacity(tokens.len());
+
+        for (i, token) in tokens.iter().enumerate() {
+            let word = normalize_word(token);
+
+            if self.filler_words.contains(&word) {
+                // Keep sentence punctuation that was attached to the dropped filler
+                if let (Some(last), Some(punct)) = (kept.last_mut(), sentence_punctuation(token)) {
+                    let trimmed_len = last.trim_end_matches([',', ';', ':']).len();
+                    last.truncate(trimmed_len);
+                    if !last.ends_with(['.', '?', '!']) {
+                        last.push(punct);
+                    }
+                }
+                continue;
+            }
+
+            if self.config.collapse_repetitions && !word.is_empty() {
+                // False start: "I- I think" or "com- company"
+                if token.ends_with('-') {
+                    if let Some(next) = tokens.get(i + 1) {
+                        if normalize_word(next).starts_with(&word) {
+                            continue;
+                        }
+                    }
+                }
+
+                // Immediate repetition: keep the later occurrence, it's usually the intended one
+                if !self.preserved_repetitions.contains(&word) {
+                    if let Some(last) = kept.last() {
+                        if normalize_word(last) == word {
+                            kept.pop();
+                        }
+                    }
+                }
+            }
+
+            kept.push(token.to_string());
+        }
+
+        let mut cleaned = kept.join(" ");
+
+        // Removing a leading filler shouldn't leave the sentence starting in lowercase
+        let starts_upper = text.trim_start().chars().next().is_some_and(char::is_uppercase);
+        if starts_upper {
+            if let Some(first) = cleaned.chars().next() {
+                if first.is_lowercase() {
+                    cleaned = first.to_uppercase().collect::<String>() + &cleaned[first.len_utf8()..];
+                }
+            }
+        }
+
+        cleaned
+    }
+}
+
+fn normalize_word(token: &str) -> String {
+    token
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .to_lowercase()
+}
+
+fn sentence_punctuation(token: &str) -> Option<char> {
+    token.chars().last().filter(|c| matches!(c, '.' | '?' | '!'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(text: &str) -> String {
+        FillerFilter::new(FillerFilterConfig::default()).clean(text)
+    }
+
+    #[test]
+    fn drops_fillers_and_repeated_words() {
+        assert_eq!(clean("um I I think uh yes"), "I think yes");
+    }
+
+    #[test]
+    fn collapses_false_starts() {
+        assert_eq!(clean("I- I think the com- company grew"), "I think the company grew");
+    }
+
+    #[test]
+    fn keeps_legitimate_repetitions() {
+        assert_eq!(clean("He said that that was fine"), "He said that that was fine");
+    }
+
+    #[test]
+    fn keeps_sentence_punctuation_of_a_dropped_filler() {
+        assert_eq!(clean("We shipped it, um."), "We shipped it.");
+    }
+
+    #[test]
+    fn capitalizes_after_dropping_a_leading_filler() {
+        assert_eq!(clean("Um, so we agreed."), "So we agreed.");
+    }
+
+    #[test]
+    fn leaves_text_alone_when_disabled() {
+        let config = FillerFilterConfig {
+            enabled: false,
+            ..FillerFilterConfig::default()
+        };
+        assert_eq!(FillerFilter::new(config).clean("um I I think"), "um I I think");
+    }
+}
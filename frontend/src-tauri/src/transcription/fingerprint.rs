@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+/// Quantization step applied before hashing, so resampling or mixing rounding
+/// noise doesn't change the fingerprint of otherwise identical audio.
+const QUANTIZATION_LEVELS: f32 = 1024.0;
+
+/// Fingerprint of a chunk's samples.
+pub fn fingerprint(samples: &[f32]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    samples.len().hash(&mut hasher);
+    for &sample in samples {
+        ((sample.clamp(-1.0, 1.0) * QUANTIZATION_LEVELS).round() as i16).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Small LRU of recently transcribed chunk fingerprints.
+pub struct RecentFingerprints {
+    entries: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl RecentFingerprints {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records the fingerprint and returns true if it was already seen recently.
+    pub fn check_and_insert(&mut self, fingerprint: u64) -> bool {
+        if let Some(position) = self.entries.iter().position(|&seen| seen == fingerprint) {
+            self.entries.remove(position);
+            self.entries.push_back(fingerprint);
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(fingerprint);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_noise_keeps_the_fingerprint() {
+        // On quantization steps, so the noise can't push a sample across a rounding boundary
+        let samples: Vec<f32> = (0..1600)
+            .map(|i| (i % 200) as f32 / QUANTIZATION_LEVELS - 0.1)
+            .collect();
+        let noisy: Vec<f32> = samples.iter().map(|sample| sample + 1e-6).collect();
+        assert_eq!(fingerprint(&samples), fingerprint(&noisy));
+        assert_ne!(fingerprint(&samples), fingerprint(&samples[..1599]));
+    }
+
+    #[test]
+    fn remembers_only_the_most_recent_fingerprints() {
+        let mut recent = RecentFingerprints::new(2);
+        assert!(!recent.check_and_insert(1));
+        assert!(!recent.check_and_insert(2));
+        assert!(recent.check_and_insert(1));
+        // 2 is now the oldest and makes room for 3
+        assert!(!recent.check_and_insert(3));
+        assert!(!recent.check_and_insert(2));
+        assert!(recent.check_and_insert(3));
+    }
+}
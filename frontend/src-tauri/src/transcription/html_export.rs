@@ -0,0 +1,291 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Settings for exporting a transcript as a standalone HTML page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HtmlExportConfig {
+    /// Embed the recording in the page so it works on its own. Recordings larger
+    /// than `max_embedded_audio_mb` are linked by path instead.
+    pub embed_audio: bool,
+    pub max_embedded_audio_mb: u64,
+    /// Segments per block. Blocks off screen are skipped by the browser's layout,
+    /// which keeps long meetings responsive.
+    pub segments_per_block: usize,
+    /// Added to the times shown next to each segment, to line the transcript up
+    /// with a recording made in another tool. Seeking still uses this app's recording.
+    pub recording_epoch_offset_ms: i64,
+}
+
+impl Default for HtmlExportConfig {
+    fn default() -> Self {
+        Self {
+            embed_audio: true,
+            max_embedded_audio_mb: 50,
+            segments_per_block: 200,
+            recording_epoch_offset_ms: 0,
+        }
+    }
+}
+
+/// One transcript line in an export. Times are seconds since recording start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSegment {
+    pub text: String,
+    pub start: f64,
+    #[serde(default)]
+    pub end: Option<f64>,
+    #[serde(default)]
+    pub speaker: Option<String>,
+}
+
+/// Where the exported page gets the recording from.
+pub enum ExportAudio {
+    /// Inlined as a data URI.
+    Embedded { mime: &'static str, data: Vec<u8> },
+    /// Referenced by URL or path, relative to the page or absolute.
+    Linked(String),
+}
+
+impl ExportAudio {
+    /// Embeds the file at `path` if configured and small enough, otherwise links it.
+    pub fn from_file(path: &Path, config: &HtmlExportConfig) -> std::io::Result<Self> {
+        let size = std::fs::metadata(path)?.len();
+        if config.embed_audio && size <= config.max_embedded_audio_mb * 1024 * 1024 {
+            Ok(Self::Embedded {
+                mime: audio_mime(path),
+                data: std::fs::read(path)?,
+            })
+        } else {
+            // Percent-encodes spaces and the like, and makes a proper file URL of Windows paths
+            let url = url::Url::from_file_path(path).map_err(|()| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not an absolute path", path.display()))
+            })?;
+            Ok(Self::Linked(url.to_string()))
+        }
+    }
+
+    fn src(&self) -> String {
+        match self {
+            Self::Embedded { mime, data } => format!(
+                "data:{};base64,{}",
+                mime,
+                base64::engine::general_purpose::STANDARD.encode(data)
+            ),
+            Self::Linked(url) => url.clone(),
+        }
+    }
+}
+
+fn audio_mime(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("mp3") => "audio/mpeg",
+        Some("m4a") | Some("mp4") | Some("aac") => "audio/mp4",
+        Some("ogg") | Some("opus") => "audio/ogg",
+        Some("webm") => "audio/webm",
+        Some("flac") => "audio/flac",
+        _ => "audio/wav",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn format_clock(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:50rem;margin:0 auto;padding:0 1rem 2rem}\
+header{position:sticky;top:0;background:#fff;padding:1rem 0;border-bottom:1px solid #ddd}\
+audio{width:100%}\
+.block{content-visibility:auto;contain-intrinsic-size:auto 40rem}\
+.segment{display:flex;gap:.75rem;padding:.35rem .5rem;border-radius:.25rem;cursor:pointer}\
+.segment:hover{background:#f3f4f6}\
+.segment.active{background:#dbeafe}\
+.time{color:#6b7280;font-variant-numeric:tabular-nums;flex:none}\
+.speaker{font-weight:600;flex:none}";
+
+// Clicking a segment seeks the audio; playback highlights the segment being
+// spoken, found by binary search over the start times.
+const SCRIPT: &str = "(function(){\
+var audio=document.getElementById('audio');\
+var segments=Array.prototype.slice.call(document.querySelectorAll('.segment'));\
+var starts=segments.map(function(s){return parseFloat(s.dataset.start);});\
+var active=null;\
+segments.forEach(function(s){s.addEventListener('click',function(){\
+if(!audio)return;audio.currentTime=parseFloat(s.dataset.start);audio.play();});});\
+if(!audio)return;\
+audio.addEventListener('timeupdate',function(){\
+var t=audio.currentTime,lo=0,hi=starts.length-1,found=-1;\
+while(lo<=hi){var mid=(lo+hi)>>1;if(starts[mid]<=t){found=mid;lo=mid+1;}else{hi=mid-1;}}\
+var next=found>=0?segments[found]:null;\
+if(next===active)return;\
+if(active)active.classList.remove('active');\
+active=next;\
+if(active){active.classList.add('active');\
+var r=active.getBoundingClientRect();\
+if(r.top<100||r.bottom>window.innerHeight)active.scrollIntoView({block:'center'});}\
+});})();";
+
+/// Renders `segments` as a self-contained HTML page with an audio player.
+/// Clicking a segment seeks the recording to it. Segments are sorted by start time.
+pub fn render_html(
+    title: &str,
+    segments: &[ExportSegment],
+    audio: Option<&ExportAudio>,
+    config: &HtmlExportConfig,
+) -> String {
+    let mut sorted: Vec<&ExportSegment> = segments.iter().collect();
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let offset_secs = config.recording_epoch_offset_ms as f64 / 1000.0;
+    let mut html = String::new();
+    let title = escape_html(title);
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<header>\n<h1>{}</h1>\n",
+        title, STYLE, title
+    );
+    if let Some(audio) = audio {
+        let _ = writeln!(
+            html,
+            "<audio id=\"audio\" controls preload=\"metadata\" src=\"{}\"></audio>",
+            escape_html(&audio.src())
+        );
+    }
+    html.push_str("</header>\n<main>\n");
+
+    for block in sorted.chunks(config.segments_per_block.max(1)) {
+        html.push_str("<section class=\"block\">\n");
+        for segment in block {
+            let _ = write!(html, "<p class=\"segment\" data-start=\"{:.3}\"", segment.start.max(0.0));
+            if let Some(end) = segment.end {
+                let _ = write!(html, " data-end=\"{:.3}\"", end.max(0.0));
+            }
+            let _ = write!(html, "><span class=\"time\">{}</span>", format_clock(segment.start + offset_secs));
+            if let Some(speaker) = &segment.speaker {
+                let _ = write!(html, "<span class=\"speaker\">{}</span>", escape_html(speaker));
+            }
+            let _ = writeln!(html, "<span class=\"text\">{}</span></p>", escape_html(segment.text.trim()));
+        }
+        html.push_str("</section>\n");
+    }
+
+    let _ = write!(html, "</main>\n<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start: f64) -> ExportSegment {
+        ExportSegment {
+            text: text.to_string(),
+            start,
+            end: Some(start + 1.5),
+            speaker: None,
+        }
+    }
+
+    #[test]
+    fn renders_one_element_per_segment_in_time_order() {
+        let segments = [
+            segment("Second", 65.25),
+            segment("First", 2.0),
+            segment("Third", 3700.0),
+        ];
+        let config = HtmlExportConfig {
+            segments_per_block: 2,
+            ..Default::default()
+        };
+        let html = render_html("Standup", &segments, None, &config);
+
+        assert_eq!(html.matches("class=\"segment\"").count(), 3);
+        assert_eq!(html.matches("<section class=\"block\">").count(), 2);
+        let first = html.find("data-start=\"2.000\" data-end=\"3.500\"").unwrap();
+        let second = html.find("data-start=\"65.250\"").unwrap();
+        assert!(first < second);
+        assert!(html.contains("<span class=\"time\">01:05</span>"));
+        assert!(html.contains("<span class=\"time\">1:01:40</span>"));
+        assert!(!html.contains("<audio"));
+    }
+
+    #[test]
+    fn escapes_text_and_embeds_audio() {
+        let mut quoted = segment("<b>\"Ship it\" & done</b>", 0.0);
+        quoted.speaker = Some("Speaker 1".to_string());
+        let audio = ExportAudio::Embedded {
+            mime: "audio/wav",
+            data: b"RIFF".to_vec(),
+        };
+        let html = render_html("Q&A", &[quoted], Some(&audio), &HtmlExportConfig::default());
+
+        assert!(html.contains("<title>Q&amp;A</title>"));
+        assert!(html.contains("&lt;b&gt;&quot;Ship it&quot; &amp; done&lt;/b&gt;"));
+        assert!(html.contains("<span class=\"speaker\">Speaker 1</span>"));
+        assert!(html.contains("src=\"data:audio/wav;base64,UklGRg==\""));
+    }
+
+    #[test]
+    fn links_recordings_too_large_to_embed() {
+        let path = std::env::temp_dir().join(format!("meetily html export {}.mp3", std::process::id()));
+        std::fs::write(&path, [0u8; 16]).unwrap();
+        let config = HtmlExportConfig {
+            max_embedded_audio_mb: 0,
+            ..Default::default()
+        };
+
+        match ExportAudio::from_file(&path, &config).unwrap() {
+            ExportAudio::Linked(url) => {
+                assert!(url.starts_with("file://"));
+                assert!(url.ends_with(&format!("meetily%20html%20export%20{}.mp3", std::process::id())));
+            }
+            ExportAudio::Embedded { .. } => panic!("expected the recording to be linked"),
+        }
+        match ExportAudio::from_file(&path, &HtmlExportConfig::default()).unwrap() {
+            ExportAudio::Embedded { mime, .. } => assert_eq!(mime, "audio/mpeg"),
+            ExportAudio::Linked(_) => panic!("expected the recording to be embedded"),
+        }
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn the_offset_shifts_shown_times_but_not_seeking() {
+        let config = HtmlExportConfig {
+            recording_epoch_offset_ms: 90_500,
+            ..Default::default()
+        };
+        let html = render_html("Standup", &[segment("Hello", 10.0)], None, &config);
+        assert!(html.contains("<span class=\"time\">01:40</span>"));
+        assert!(html.contains("data-start=\"10.000\""));
+
+        // A negative offset never shows a time before zero
+        let config = HtmlExportConfig {
+            recording_epoch_offset_ms: -30_000,
+            ..Default::default()
+        };
+        let html = render_html("Standup", &[segment("Hello", 10.0)], None, &config);
+        assert!(html.contains("<span class=\"time\">00:00</span>"));
+    }
+}
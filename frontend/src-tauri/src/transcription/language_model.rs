@@ -0,0 +1,287 @@
+use log::{debug, info, warn};
+use reqwest::multipart::Form;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::backend::{TranscriptionBackend, TranscriptionFuture};
+use super::model_check::check_model_replacing;
+
+/// Settings for switching the whisper server to a language-specific model once
+/// the spoken language is known. Needs the server to run with `--language auto`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LanguageModelConfig {
+    pub enabled: bool,
+    /// Language code, e.g. "ja", to the path of the model the server loads for it.
+    pub models: HashMap<String, String>,
+    /// Loaded for languages without a model of their own, or when theirs fails
+    /// to load. Empty keeps whatever model the server started with.
+    pub default_model: String,
+    /// Chunks in a row that must agree on the language before switching.
+    pub stable_chunks: u32,
+    /// Directory the whisper server runs in, which it resolves relative model
+    /// paths against. While empty, relative paths aren't checked before loading.
+    pub model_dir: String,
+}
+
+impl Default for LanguageModelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            models: HashMap::new(),
+            default_model: String::new(),
+            stable_chunks: 3,
+            model_dir: String::new(),
+        }
+    }
+}
+
+/// Decides when to switch models. The language is locked in once it has been
+/// stable, so a few misdetected chunks later on don't reload the model.
+pub struct LanguageModelSelector {
+    config: LanguageModelConfig,
+    candidate: Option<(String, u32)>,
+    locked: Option<String>,
+}
+
+impl LanguageModelSelector {
+    pub fn new(config: LanguageModelConfig) -> Self {
+        Self {
+            config,
+            candidate: None,
+            locked: None,
+        }
+    }
+
+    /// Feed the language detected for a chunk. Returns the model to load once
+    /// the language has been stable for long enough, at most once per session.
+    pub fn observe(&mut self, language: &str) -> Option<String> {
+        if self.locked.is_some() || language.is_empty() {
+            return None;
+        }
+
+        let count = match self.candidate.as_mut() {
+            Some((candidate, count)) if candidate == language => {
+                *count += 1;
+                *count
+            }
+            _ => {
+                self.candidate = Some((language.to_string(), 1));
+                1
+            }
+        };
+        if count < self.config.stable_chunks.max(1) {
+            return None;
+        }
+
+        self.locked = Some(language.to_string());
+        self.model_for(language)
+    }
+
+    fn model_for(&self, language: &str) -> Option<String> {
+        match self.config.models.get(language) {
+            Some(model) if !model.is_empty() => Some(model.clone()),
+            _ => self.default_model(),
+        }
+    }
+
+    pub fn default_model(&self) -> Option<String> {
+        (!self.config.default_model.is_empty()).then(|| self.config.default_model.clone())
+    }
+}
+
+impl LanguageModelConfig {
+    /// Where the server finds `model`, `None` for a relative path while
+    /// `model_dir` isn't set.
+    pub fn model_file(&self, model: &str) -> Option<PathBuf> {
+        let path = Path::new(model);
+        if path.is_absolute() {
+            Some(path.to_path_buf())
+        } else if self.model_dir.is_empty() {
+            None
+        } else {
+            Some(Path::new(&self.model_dir).join(path))
+        }
+    }
+
+    /// Every configured model path, the default model first.
+    pub fn model_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        if !self.default_model.is_empty() {
+            paths.push(self.default_model.clone());
+        }
+        let mut languages: Vec<_> = self.models.iter().filter(|(_, model)| !model.is_empty()).collect();
+        languages.sort();
+        for (_, model) in languages {
+            if !paths.contains(model) {
+                paths.push(model.clone());
+            }
+        }
+        paths
+    }
+}
+
+/// Loads the model picked by a [`LanguageModelSelector`] into the whisper
+/// server behind `inner`, using the language the server reports per chunk.
+pub struct LanguageModelBackend {
+    inner: Box<dyn TranscriptionBackend>,
+    client: reqwest::Client,
+    load_url: String,
+    selector: Mutex<LanguageModelSelector>,
+    config: LanguageModelConfig,
+    /// The model the server has loaded, assumed to be the default model until
+    /// this backend loads another. Its memory is freed by the next load.
+    loaded: Mutex<Option<String>>,
+}
+
+impl LanguageModelBackend {
+    pub fn new(inner: Box<dyn TranscriptionBackend>, server_url: &str, config: LanguageModelConfig) -> Self {
+        Self {
+            inner,
+            client: reqwest::Client::new(),
+            load_url: format!("{}/load", server_url),
+            loaded: Mutex::new((!config.default_model.is_empty()).then(|| config.default_model.clone())),
+            selector: Mutex::new(LanguageModelSelector::new(config.clone())),
+            config,
+        }
+    }
+
+    async fn load_model(&self, model: &str) -> Result<(), String> {
+        // The server runs on this machine, so an incompatible model is caught here
+        // with a clear error instead of a failed load on the server
+        match self.config.model_file(model) {
+            Some(path) => {
+                let loaded = self.loaded.lock().ok().and_then(|loaded| loaded.clone());
+                let loaded = loaded.and_then(|loaded| self.config.model_file(&loaded));
+                if let Some(error) = check_model_replacing(&path, loaded.as_deref()).error {
+                    return Err(error);
+                }
+            }
+            None => debug!("Not checking {} before loading, it's relative to the server's directory", model),
+        }
+        let form = Form::new().text("model", model.to_string());
+        let response = self
+            .client
+            .post(&self.load_url)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        // The server answers 200 either way, failures come back as a JSON error
+        match serde_json::from_str::<serde_json::Value>(&body) {
+            Ok(value) if value.get("error").is_some() => Err(value["error"].as_str().unwrap_or(&body).to_string()),
+            _ => {
+                if let Ok(mut loaded) = self.loaded.lock() {
+                    *loaded = Some(model.to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TranscriptionBackend for LanguageModelBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+        Box::pin(async move {
+            let response = self.inner.transcribe(chunk_id, samples, prompt).await?;
+            let Some(language) = response.language.clone() else {
+                return Ok(response);
+            };
+
+            let (model, default_model) = match self.selector.lock() {
+                Ok(mut selector) => (selector.observe(&language), selector.default_model()),
+                Err(_) => return Ok(response),
+            };
+            if let Some(model) = model {
+                info!(chunk_id = chunk_id; "language settled on '{}', loading {}", language, model);
+                if let Err(e) = self.load_model(&model).await {
+                    warn!("Failed to load {} for '{}': {}", model, language, e);
+                    if let Some(default_model) = default_model.filter(|default_model| *default_model != model) {
+                        if let Err(e) = self.load_model(&default_model).await {
+                            warn!("Failed to load the default model {}: {}", default_model, e);
+                        }
+                    }
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LanguageModelConfig {
+        LanguageModelConfig {
+            enabled: true,
+            models: HashMap::from([("ja".to_string(), "models/ggml-ja.bin".to_string())]),
+            default_model: "models/ggml-base.bin".to_string(),
+            stable_chunks: 3,
+            model_dir: String::new(),
+        }
+    }
+
+    #[test]
+    fn switches_once_the_language_has_been_stable() {
+        let mut selector = LanguageModelSelector::new(config());
+        assert_eq!(selector.observe("ja"), None);
+        assert_eq!(selector.observe("en"), None);
+        assert_eq!(selector.observe("ja"), None);
+        assert_eq!(selector.observe("ja"), None);
+        assert_eq!(selector.observe("ja").as_deref(), Some("models/ggml-ja.bin"));
+
+        // Locked in, later misdetections don't reload
+        for _ in 0..5 {
+            assert_eq!(selector.observe("en"), None);
+        }
+    }
+
+    #[test]
+    fn a_language_without_a_model_gets_the_default() {
+        let mut selector = LanguageModelSelector::new(config());
+        selector.observe("de");
+        selector.observe("de");
+        assert_eq!(selector.observe("de").as_deref(), Some("models/ggml-base.bin"));
+
+        let mut without_default = LanguageModelSelector::new(LanguageModelConfig {
+            default_model: String::new(),
+            ..config()
+        });
+        for _ in 0..3 {
+            assert_eq!(without_default.observe("de"), None);
+        }
+    }
+
+    #[test]
+    fn lists_each_model_once_with_the_default_first() {
+        let mut config = config();
+        config
+            .models
+            .insert("en".to_string(), "models/ggml-base.bin".to_string());
+        config.models.insert("fr".to_string(), String::new());
+        assert_eq!(config.model_paths(), vec!["models/ggml-base.bin", "models/ggml-ja.bin"]);
+    }
+
+    #[test]
+    fn relative_models_are_found_in_the_server_s_directory() {
+        let mut config = config();
+        assert_eq!(config.model_file("models/ggml-ja.bin"), None);
+
+        let server_dir = std::env::temp_dir().join("whisper-server");
+        config.model_dir = server_dir.display().to_string();
+        assert_eq!(
+            config.model_file("models/ggml-ja.bin"),
+            Some(server_dir.join("models/ggml-ja.bin"))
+        );
+        let absolute = std::env::temp_dir().join("ggml-ja.bin");
+        assert_eq!(config.model_file(&absolute.display().to_string()), Some(absolute));
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings for collapsing whisper's hallucination loops, where it repeats the
+/// same phrase over and over on silence or noise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HallucinationLoopConfig {
+    pub enabled: bool,
+    /// A phrase repeated back to back more often than this is a loop.
+    pub max_repeats: usize,
+    /// Longest phrase, in words, checked for repetition.
+    pub max_phrase_words: usize,
+}
+
+impl Default for HallucinationLoopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_repeats: 4,
+            max_phrase_words: 8,
+        }
+    }
+}
+
+fn word_key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
+/// `text` with every loop cut down to a single copy of the repeated phrase, or
+/// `None` if it has no loop.
+pub fn collapse_loops(text: &str, config: &HallucinationLoopConfig) -> Option<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|word| word_key(word)).collect();
+    let min_run = config.max_repeats + 1;
+
+    let mut kept: Vec<&str> = Vec::with_capacity(words.len());
+    let mut collapsed = false;
+    let mut i = 0;
+    while i < words.len() {
+        let mut loop_found = None;
+        // Shortest phrase first, so "no no no no no" isn't read as a repeated "no no"
+        for len in 1..=config.max_phrase_words.max(1) {
+            if i + len * min_run > words.len() {
+                break;
+            }
+            let phrase = &keys[i..i + len];
+            let mut repeats = 1;
+            while i + (repeats + 1) * len <= words.len() && keys[i + repeats * len..i + (repeats + 1) * len] == *phrase {
+                repeats += 1;
+            }
+            if repeats >= min_run {
+                loop_found = Some((len, i + repeats * len));
+                break;
+            }
+        }
+        match loop_found {
+            Some((len, end)) => {
+                kept.extend_from_slice(&words[i..i + len]);
+                i = end;
+                collapsed = true;
+            }
+            None => {
+                kept.push(words[i]);
+                i += 1;
+            }
+        }
+    }
+    collapsed.then(|| kept.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_a_repeated_phrase_to_one_copy() {
+        let config = HallucinationLoopConfig::default();
+        let text = "Thank you. Thank you. Thank you. Thank you. Thank you. Bye.";
+        assert_eq!(collapse_loops(text, &config).as_deref(), Some("Thank you. Bye."));
+    }
+
+    #[test]
+    fn collapses_single_word_loops_as_single_words() {
+        let config = HallucinationLoopConfig::default();
+        assert_eq!(collapse_loops("no no no no no no", &config).as_deref(), Some("no"));
+    }
+
+    #[test]
+    fn leaves_repeats_up_to_max_repeats_alone() {
+        let config = HallucinationLoopConfig::default();
+        assert_eq!(collapse_loops("very very very very good", &config), None);
+    }
+}
@@ -0,0 +1,84 @@
+// src/transcription/mod.rs
+pub mod auto_stop;
+pub mod autosave;
+pub mod backend;
+pub mod boundary;
+pub mod config;
+pub mod confidence;
+pub mod context;
+pub mod crosstalk;
+pub mod dead_letter;
+pub mod dedup;
+pub mod encoding;
+pub mod estimate;
+pub mod filler;
+pub mod fingerprint;
+pub mod html_export;
+pub mod language_model;
+pub mod loops;
+pub mod model_check;
+pub mod normalize;
+pub mod offline;
+pub mod overlap;
+pub mod profiling;
+pub mod redaction;
+pub mod reorder;
+pub mod segments;
+pub mod speakers;
+pub mod summary;
+pub mod throttle;
+pub mod turns;
+
+/// The whisper server reads request audio as 16 kHz mono f32.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+/// Segment and word times from the server are in 10 ms ticks.
+pub const TICKS_PER_SEC: f64 = 100.0;
+
+pub use auto_stop::{SilenceAction, SilenceAutoStop, SilenceAutoStopConfig, SilenceAutoStopper, SilenceTransition};
+pub use autosave::TranscriptAutosaveConfig;
+pub use backend::{
+    backend_for_engine, AttemptInfo, BackendRetryConfig, FallbackBackend, FallbackServerConfig, ModelEscalationBackend,
+    ModelEscalationConfig, RawOutputConfig, RawTranscript, RetryReport, TranscriptionBackend, TranscriptionError, WhisperServerBackend,
+};
+pub use boundary::{
+    has_sustained_voice, BoundaryStrategy, ChunkCoalescing, ChunkCoalescingConfig, ChunkDecision, ChunkState,
+    DurationBoundary, EndpointingConfig, EnergyDipConfig, EnergyDipEndpointing, EnergyEndpointing,
+    SpeechConfirmationConfig,
+};
+pub use config::{
+    ChunkOutputConfig, ChunkOutputMode, ChunkQueueConfig, QueueOverflowPolicy, StatusHeartbeatConfig, TranscriptionConfig,
+    WhisperWindowConfig,
+};
+pub use confidence::{SinkConfidenceConfig, TranscriptSink};
+pub use context::{PromptContextConfig, TranscriptContext};
+pub use crosstalk::{OverlapDetector, OverlapLog, OverlappingSpeechConfig};
+pub use dead_letter::{
+    reprocess_failed, DeadLetterConfig, DeadLetterQueue, FailedChunk, RecoveredChunk, ReprocessReport,
+};
+pub use dedup::{RecoveryDedup, RecoveryDedupConfig};
+pub use encoding::{encode_output, OutputEncoding, OutputEncodingConfig};
+pub use estimate::{ProcessingEstimate, WarmupConfig};
+pub use filler::{FillerFilter, FillerFilterConfig};
+pub use fingerprint::RecentFingerprints;
+pub use html_export::{render_html, ExportAudio, ExportSegment, HtmlExportConfig};
+pub use language_model::{LanguageModelBackend, LanguageModelConfig, LanguageModelSelector};
+pub use loops::{collapse_loops, HallucinationLoopConfig};
+pub use model_check::{check_model, ModelCompatibility};
+pub use normalize::{InverseNormalizer, TextNormalizationConfig, TextNormalizer};
+pub use offline::{
+    batch_concurrency, read_wav_for_whisper, transcribe_files, transcribe_offline, BatchProgress, DecodingPass,
+    FileTranscript, OfflineProgress, OfflineTranscript, OfflineTranscriptionConfig,
+};
+pub use profiling::{ChunkProfilingConfig, ChunkTiming};
+pub use redaction::{RedactionConfig, RedactionFilter, RedactionPattern};
+pub use reorder::ChunkReorderBuffer;
+pub use segments::{GapReportingConfig, SegmentBoundary, SegmentBoundaryConfig, SegmentBoundaryDetector};
+pub use speakers::{SpeakerHintConfig, SpeakerTracker, VoiceFeatures};
+pub use summary::{
+    finalize_transcript, register_summarizer, registered_summarizer, FinalizedTranscript, MeetingSummary,
+    MeetingTranscript, SummarizationHook, SummaryFuture,
+};
+pub use throttle::{QualityChange, QualityLevel, QualityThrottleConfig};
+pub use turns::{
+    assemble_paragraphs, starts_paragraph, ParagraphConfig, SpeakingTurn, TurnAggregationConfig, TurnAggregator,
+};
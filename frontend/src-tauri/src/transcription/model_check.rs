@@ -0,0 +1,318 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// "ggml" read as a little-endian u32, the first field of a whisper model file
+const GGML_FILE_MAGIC: u32 = 0x6767_6d6c;
+const GGUF_FILE_MAGIC: u32 = 0x4655_4747;
+// Quantized models record the ggml quantization version in their ftype. Must
+// match GGML_QNT_VERSION of the ggml the whisper server is built against.
+const GGML_QNT_VERSION: i32 = 2;
+const GGML_QNT_VERSION_FACTOR: i32 = 1000;
+// n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer, n_text_ctx,
+// n_text_state, n_text_head, n_text_layer, n_mels, ftype
+const HPARAM_COUNT: usize = 11;
+// whisper.cpp holds the weights plus its KV caches and compute buffers, which
+// take about a quarter of the weights on top of a fixed base
+const MODEL_MEMORY_OVERHEAD_BYTES: u64 = 200 * 1024 * 1024;
+const MB: u64 = 1024 * 1024;
+// Standard whisper models from largest to smallest, with their f16 file sizes
+const STANDARD_MODELS: [(&str, u64); 5] = [
+    ("large-v3", 3_095 * MB),
+    ("medium", 1_533 * MB),
+    ("small", 488 * MB),
+    ("base", 148 * MB),
+    ("tiny", 78 * MB),
+];
+
+/// Whether a model file can be loaded by the whisper server. Checked from the
+/// file header and size before asking the server to load it, since a model
+/// written for another format version, or too large for the memory left, can
+/// crash it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCompatibility {
+    pub path: String,
+    pub compatible: bool,
+    /// Why the model can't be loaded, `None` when it can.
+    pub error: Option<String>,
+    /// Quantization version the model was written with, `None` for f32/f16 models.
+    pub quantization_version: Option<i32>,
+    /// Memory the server needs to load the model, `None` when the file can't be read.
+    pub estimated_memory_bytes: Option<u64>,
+}
+
+impl ModelCompatibility {
+    fn incompatible(path: &Path, error: impl Into<String>, quantization_version: Option<i32>) -> Self {
+        Self {
+            path: path.display().to_string(),
+            compatible: false,
+            error: Some(error.into()),
+            quantization_version,
+            estimated_memory_bytes: None,
+        }
+    }
+}
+
+/// Memory whisper.cpp needs to load a model file of `file_size` bytes.
+pub fn estimated_model_memory(file_size: u64) -> u64 {
+    file_size + file_size / 4 + MODEL_MEMORY_OVERHEAD_BYTES
+}
+
+/// Memory available to new allocations without swapping, `None` where it can't be read.
+#[cfg(target_os = "linux")]
+pub fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(target_os = "macos")]
+pub fn available_memory() -> Option<u64> {
+    // The memory pressure level is the percentage of memory still available
+    let total: u64 = sysctl_value(c"hw.memsize")?;
+    let level: u32 = sysctl_value(c"kern.memorystatus_level")?;
+    Some(total / 100 * u64::from(level.min(100)))
+}
+
+#[cfg(target_os = "macos")]
+fn sysctl_value<T: Default>(name: &std::ffi::CStr) -> Option<T> {
+    let mut value = T::default();
+    let mut size = std::mem::size_of::<T>();
+    let result = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            &mut value as *mut T as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    (result == 0 && size == std::mem::size_of::<T>()).then_some(value)
+}
+
+#[cfg(target_os = "windows")]
+pub fn available_memory() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    let ok = unsafe { GlobalMemoryStatusEx(&mut status) != 0 };
+    ok.then_some(status.ullAvailPhys)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn available_memory() -> Option<u64> {
+    None
+}
+
+/// The largest standard model that fits in `available` bytes.
+fn suggested_model(available: u64) -> Option<&'static str> {
+    STANDARD_MODELS
+        .iter()
+        .find(|(_, size)| estimated_model_memory(*size) <= available)
+        .map(|(name, _)| *name)
+}
+
+/// Checks the header of the model at `path` and whether it fits in the memory
+/// currently available, so a model too large for the machine is refused before
+/// the server runs out of memory loading it.
+pub fn check_model(path: &Path) -> ModelCompatibility {
+    check_model_with_memory(path, available_memory())
+}
+
+/// [`check_model`] for a model that replaces `loaded` on the server. The server
+/// frees the loaded model first, so its memory counts as available.
+pub fn check_model_replacing(path: &Path, loaded: Option<&Path>) -> ModelCompatibility {
+    let freed = loaded_model_memory(loaded);
+    check_model_with_memory(path, available_memory().map(|available| available + freed))
+}
+
+/// Memory the server gets back by unloading the model at `loaded`.
+fn loaded_model_memory(loaded: Option<&Path>) -> u64 {
+    loaded
+        .and_then(|loaded| std::fs::metadata(loaded).ok())
+        .map_or(0, |metadata| estimated_model_memory(metadata.len()))
+}
+
+/// [`check_model`] against `available` bytes of memory, skipping the memory
+/// check when that is unknown.
+pub fn check_model_with_memory(path: &Path, available: Option<u64>) -> ModelCompatibility {
+    let mut compatibility = check_header(path);
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return compatibility;
+    };
+    let required = estimated_model_memory(metadata.len());
+    compatibility.estimated_memory_bytes = Some(required);
+    match available {
+        Some(available) if compatibility.compatible && required > available => {
+            let suggestion = match suggested_model(available) {
+                Some(name) => format!(", try the {} model (ggml-{}.bin) instead", name, name),
+                None => ", and no whisper model fits, close other applications and try again".to_string(),
+            };
+            compatibility.compatible = false;
+            compatibility.error = Some(format!(
+                "model needs about {} MB of memory but only {} MB is available{}",
+                required / MB,
+                available / MB,
+                suggestion
+            ));
+        }
+        _ => {}
+    }
+    compatibility
+}
+
+fn check_header(path: &Path) -> ModelCompatibility {
+    let mut header = [0u8; 4 * (1 + HPARAM_COUNT)];
+    let read = File::open(path).and_then(|mut file| file.read_exact(&mut header));
+    if let Err(e) = read {
+        let error = match e.kind() {
+            std::io::ErrorKind::NotFound => "model not found".to_string(),
+            std::io::ErrorKind::UnexpectedEof => "model file is truncated, please re-download".to_string(),
+            _ => format!("couldn't read model: {}", e),
+        };
+        return ModelCompatibility::incompatible(path, error, None);
+    }
+
+    let word = |i: usize| [header[i * 4], header[i * 4 + 1], header[i * 4 + 2], header[i * 4 + 3]];
+    match u32::from_le_bytes(word(0)) {
+        GGML_FILE_MAGIC => {}
+        GGUF_FILE_MAGIC => {
+            return ModelCompatibility::incompatible(
+                path,
+                "model format incompatible (GGUF is not a whisper model format), please re-download",
+                None,
+            )
+        }
+        _ => return ModelCompatibility::incompatible(path, "model format incompatible, please re-download", None),
+    }
+
+    let ftype = i32::from_le_bytes(word(HPARAM_COUNT));
+    // f32 and f16 models don't depend on the quantization version
+    let quantization_version = (ftype % GGML_QNT_VERSION_FACTOR > 1).then_some(ftype / GGML_QNT_VERSION_FACTOR);
+    match quantization_version {
+        Some(version) if version != GGML_QNT_VERSION => ModelCompatibility::incompatible(
+            path,
+            format!(
+                "model quantization version {} is incompatible with this server (version {}), please re-download",
+                version, GGML_QNT_VERSION
+            ),
+            quantization_version,
+        ),
+        _ => ModelCompatibility {
+            path: path.display().to_string(),
+            compatible: true,
+            error: None,
+            quantization_version,
+            estimated_memory_bytes: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn model_file(dir: &Path, name: &str, magic: u32, ftype: i32) -> PathBuf {
+        let mut header = magic.to_le_bytes().to_vec();
+        for _ in 1..HPARAM_COUNT {
+            header.extend_from_slice(&0i32.to_le_bytes());
+        }
+        header.extend_from_slice(&ftype.to_le_bytes());
+        let path = dir.join(name);
+        std::fs::write(&path, header).unwrap();
+        path
+    }
+
+    #[test]
+    fn accepts_f16_and_current_quantized_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let f16 = check_model(&model_file(dir.path(), "f16.bin", GGML_FILE_MAGIC, 1));
+        assert!(f16.compatible);
+        assert_eq!(f16.quantization_version, None);
+
+        let q5 = check_model(&model_file(
+            dir.path(),
+            "q5.bin",
+            GGML_FILE_MAGIC,
+            GGML_QNT_VERSION * GGML_QNT_VERSION_FACTOR + 8,
+        ));
+        assert!(q5.compatible);
+        assert_eq!(q5.quantization_version, Some(GGML_QNT_VERSION));
+    }
+
+    #[test]
+    fn rejects_other_quantization_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = check_model(&model_file(dir.path(), "old.bin", GGML_FILE_MAGIC, 8));
+        assert!(!old.compatible);
+        assert_eq!(old.quantization_version, Some(0));
+        assert!(old.error.unwrap().contains("quantization version 0"));
+    }
+
+    #[test]
+    fn rejects_gguf_unknown_and_truncated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let gguf = check_model(&model_file(dir.path(), "model.gguf", GGUF_FILE_MAGIC, 1));
+        assert!(gguf.error.unwrap().contains("GGUF"));
+        assert!(!check_model(&model_file(dir.path(), "other.bin", 0x1234_5678, 1)).compatible);
+
+        let truncated = model_file(dir.path(), "truncated.bin", GGML_FILE_MAGIC, 1);
+        std::fs::write(&truncated, GGML_FILE_MAGIC.to_le_bytes()).unwrap();
+        assert_eq!(
+            check_model(&truncated).error.as_deref(),
+            Some("model file is truncated, please re-download")
+        );
+        assert_eq!(
+            check_model(Path::new("/nonexistent/model.bin")).error.as_deref(),
+            Some("model not found")
+        );
+    }
+
+    #[test]
+    fn rejects_a_large_model_on_a_low_memory_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = model_file(dir.path(), "large-v3.bin", GGML_FILE_MAGIC, 1);
+        // Sparse, so the test doesn't write gigabytes to disk
+        File::options().write(true).open(&path).unwrap().set_len(3_095 * MB).unwrap();
+
+        let low_memory = check_model_with_memory(&path, Some(2 * 1024 * MB));
+        std::fs::remove_file(&path).unwrap();
+        assert!(!low_memory.compatible);
+        assert_eq!(low_memory.estimated_memory_bytes, Some(estimated_model_memory(3_095 * MB)));
+        let error = low_memory.error.unwrap();
+        assert!(error.contains("needs about 4068 MB"), "{}", error);
+        assert!(error.contains("only 2048 MB is available"), "{}", error);
+        assert!(error.contains("try the small model (ggml-small.bin)"), "{}", error);
+    }
+
+    #[test]
+    fn swapping_between_models_of_the_same_size_counts_the_unloaded_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded = model_file(dir.path(), "medium-en.bin", GGML_FILE_MAGIC, 1);
+        let next = model_file(dir.path(), "medium-ja.bin", GGML_FILE_MAGIC, 1);
+        for path in [&loaded, &next] {
+            File::options().write(true).open(path).unwrap().set_len(1_533 * MB).unwrap();
+        }
+
+        let available = 1024 * MB;
+        assert!(!check_model_with_memory(&next, Some(available)).compatible);
+        let freed = loaded_model_memory(Some(&loaded));
+        assert_eq!(freed, estimated_model_memory(1_533 * MB));
+        assert!(check_model_with_memory(&next, Some(available + freed)).compatible);
+        assert_eq!(loaded_model_memory(None), 0);
+    }
+
+    #[test]
+    fn accepts_a_model_that_fits_or_when_memory_is_unknown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = model_file(dir.path(), "fits.bin", GGML_FILE_MAGIC, 1);
+        let fits = check_model_with_memory(&path, Some(1024 * MB));
+        assert!(fits.compatible);
+        assert!(fits.estimated_memory_bytes.unwrap() < 1024 * MB);
+        assert!(check_model_with_memory(&path, None).compatible);
+        assert_eq!(suggested_model(100 * MB), None);
+    }
+}
@@ -0,0 +1,520 @@
+use super::InverseNormalizer;
+
+const SMALL_NUMBERS: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+
+const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+const ORDINALS: [&str; 19] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+    "eleventh", "twelfth", "thirteenth", "fourteenth", "fifteenth", "sixteenth", "seventeenth",
+    "eighteenth", "nineteenth",
+];
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june", "july", "august", "september", "october",
+    "november", "december",
+];
+
+/// Months that are also common verbs. Whisper capitalizes months, so these only
+/// count as one when capitalized: "May first" but not "you may first check".
+const VERB_MONTHS: [&str; 2] = ["may", "march"];
+
+/// Words after which a bare "three thirty" is read as a time of day.
+const TIME_PREPOSITIONS: [&str; 9] = ["at", "by", "until", "till", "from", "before", "after", "around", "about"];
+
+const MERIDIEMS: [&str; 4] = ["am", "pm", "a.m", "p.m"];
+
+/// "twenty thirty minutes" is a range, not the year 2030.
+const COUNT_NOUNS: [&str; 16] = [
+    "seconds", "minutes", "hours", "days", "weeks", "months", "years", "times", "people", "percent",
+    "dollars", "euros", "cents", "items", "pages", "slides",
+];
+
+/// English inverse text normalization. Number words become digits when they are
+/// part of a time, date, year, amount or percentage, or when the number is ten or
+/// more; "one of them" and "two options" are left alone.
+pub struct EnglishNormalizer;
+
+struct Token<'a> {
+    leading: &'a str,
+    core: &'a str,
+    trailing: &'a str,
+    word: String,
+    // "-" between the parts of a split "twenty-four"
+    separator: &'static str,
+}
+
+impl Token<'_> {
+    /// Whether a number can continue into the next token. Punctuation ends it.
+    fn continues(&self) -> bool {
+        self.trailing.is_empty()
+    }
+
+    fn original(&self) -> String {
+        format!("{}{}{}", self.leading, self.core, self.trailing)
+    }
+}
+
+impl InverseNormalizer for EnglishNormalizer {
+    fn normalize(&self, text: &str) -> String {
+        let tokens = tokenize(text);
+        let mut pieces: Vec<(String, &str)> = Vec::with_capacity(tokens.len());
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let previous = i.checked_sub(1).map(|p| tokens[p].word.as_str());
+            let rest = &tokens[i..];
+            let matched = match_date(rest)
+                .or_else(|| match_time(rest, previous))
+                .or_else(|| match_year(rest))
+                .or_else(|| match_amount(rest));
+
+            match matched {
+                Some((written, used)) => {
+                    let last = &rest[used - 1];
+                    pieces.push((format!("{}{}{}", rest[0].leading, written, last.trailing), last.separator));
+                    i += used;
+                }
+                None => {
+                    pieces.push((tokens[i].original(), tokens[i].separator));
+                    i += 1;
+                }
+            }
+        }
+
+        let mut normalized = String::with_capacity(text.len());
+        for (index, (piece, separator)) in pieces.iter().enumerate() {
+            normalized.push_str(piece);
+            if index + 1 < pieces.len() {
+                normalized.push_str(separator);
+            }
+        }
+        normalized
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for raw in text.split_whitespace() {
+        let core_start = raw.find(char::is_alphanumeric).unwrap_or(raw.len());
+        let core_end = raw
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_alphanumeric())
+            .map_or(core_start, |(index, c)| index + c.len_utf8());
+        let (leading, core, trailing) = (&raw[..core_start], &raw[core_start..core_end], &raw[core_end..]);
+
+        // Split "twenty-four" and "thirty-first" so their parts can be parsed as numbers
+        let parts: Vec<&str> = core.split('-').collect();
+        if parts.len() > 1 && parts.iter().all(|part| is_number_word(&part.to_lowercase())) {
+            let last = parts.len() - 1;
+            for (index, part) in parts.into_iter().enumerate() {
+                tokens.push(Token {
+                    leading: if index == 0 { leading } else { "" },
+                    core: part,
+                    trailing: if index == last { trailing } else { "" },
+                    word: part.to_lowercase(),
+                    separator: if index == last { " " } else { "-" },
+                });
+            }
+        } else {
+            tokens.push(Token {
+                leading,
+                core,
+                trailing,
+                word: core.to_lowercase().replace('\u{2019}', "'"),
+                separator: " ",
+            });
+        }
+    }
+    tokens
+}
+
+fn small_number(word: &str) -> Option<u64> {
+    SMALL_NUMBERS.iter().position(|&w| w == word).map(|n| n as u64)
+}
+
+fn tens(word: &str) -> Option<u64> {
+    TENS.iter().position(|&w| w == word).map(|n| (n as u64 + 2) * 10)
+}
+
+fn scale(word: &str) -> Option<u64> {
+    match word {
+        "thousand" => Some(1_000),
+        "million" => Some(1_000_000),
+        "billion" => Some(1_000_000_000),
+        _ => None,
+    }
+}
+
+fn ordinal(word: &str) -> Option<u64> {
+    match word {
+        "twentieth" => Some(20),
+        "thirtieth" => Some(30),
+        _ => ORDINALS.iter().position(|&w| w == word).map(|n| n as u64 + 1),
+    }
+}
+
+fn is_number_word(word: &str) -> bool {
+    small_number(word).is_some()
+        || tens(word).is_some()
+        || scale(word).is_some()
+        || ordinal(word).is_some()
+        || word == "hundred"
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Last {
+    Nothing,
+    Unit,
+    Teen,
+    Tens,
+    Hundred,
+    Scale,
+}
+
+/// Parses a cardinal like "two thousand three hundred and five". Returns the value
+/// and the number of tokens used.
+fn parse_cardinal(tokens: &[Token]) -> Option<(u64, usize)> {
+    let mut total = 0;
+    let mut current = 0;
+    let mut last = Last::Nothing;
+    let mut used = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let word = token.word.as_str();
+        let after_group = matches!(last, Last::Nothing | Last::Hundred | Last::Scale);
+
+        let accepted = if let Some(n) = small_number(word) {
+            if after_group && !(n == 0 && last != Last::Nothing) {
+                current += n;
+                last = if n < 10 { Last::Unit } else { Last::Teen };
+                true
+            } else if last == Last::Tens && (1..=9).contains(&n) {
+                current += n;
+                last = Last::Unit;
+                true
+            } else {
+                false
+            }
+        } else if let Some(n) = tens(word) {
+            if after_group {
+                current += n;
+                last = Last::Tens;
+                true
+            } else {
+                false
+            }
+        } else if word == "hundred" {
+            // "three hundred", "fifteen hundred", but not "three hundred hundred"
+            if matches!(last, Last::Unit | Last::Teen | Last::Tens) && current > 0 && current < 100 {
+                current *= 100;
+                last = Last::Hundred;
+                true
+            } else {
+                false
+            }
+        } else if let Some(s) = scale(word) {
+            if current > 0 && last != Last::Scale {
+                total += current * s;
+                current = 0;
+                last = Last::Scale;
+                true
+            } else {
+                false
+            }
+        } else if word == "and" && matches!(last, Last::Hundred | Last::Scale) {
+            // Only part of the number if another number word follows: "hundred and five"
+            tokens
+                .get(i + 1)
+                .is_some_and(|next| small_number(&next.word).is_some() || tens(&next.word).is_some())
+        } else {
+            false
+        };
+
+        if !accepted {
+            break;
+        }
+        used = i + 1;
+        if !token.continues() {
+            break;
+        }
+    }
+
+    // A trailing "and" belongs to the sentence, not the number
+    while used > 0 && tokens[used - 1].word == "and" {
+        used -= 1;
+    }
+    (used > 0).then_some((total + current, used))
+}
+
+/// A cardinal with an optional spoken decimal part: "two point five".
+fn parse_number(tokens: &[Token]) -> Option<(String, usize)> {
+    let (value, mut used) = parse_cardinal(tokens)?;
+    let mut written = format_cardinal(value);
+
+    // "five million" reads better as "5 million" than as "5,000,000"
+    let last = &tokens[used - 1];
+    if let Some(scale) = scale(&last.word).filter(|s| *s >= 1_000_000 && value % s == 0) {
+        written = format!("{} {}", value / scale, last.word);
+    }
+
+    if tokens[used - 1].continues() && tokens.get(used).is_some_and(|t| t.word == "point" && t.continues()) {
+        let mut digits = String::new();
+        let mut end = used + 1;
+        while let Some(token) = tokens.get(end) {
+            let digit = match token.word.as_str() {
+                "oh" | "o" => 0,
+                word => match small_number(word) {
+                    Some(n) if n < 10 => n,
+                    _ => break,
+                },
+            };
+            digits.push_str(&digit.to_string());
+            end += 1;
+            if !token.continues() {
+                break;
+            }
+        }
+        if !digits.is_empty() {
+            written = format!("{}.{}", written, digits);
+            used = end;
+        }
+    }
+
+    Some((written, used))
+}
+
+/// Digits with thousands separators from 10,000 up; "2024" and "1500" stay as they are.
+fn format_cardinal(value: u64) -> String {
+    let digits = value.to_string();
+    if value < 10_000 {
+        return digits;
+    }
+    let mut written = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            written.push(',');
+        }
+        written.push(digit);
+    }
+    written
+}
+
+/// 10..=99 from "fifteen", "forty" or "forty two".
+fn parse_two_digit(tokens: &[Token]) -> Option<(u64, usize)> {
+    let first = tokens.first()?;
+    if let Some(n) = small_number(&first.word).filter(|n| *n >= 10) {
+        return Some((n, 1));
+    }
+    let n = tens(&first.word)?;
+    if first.continues() {
+        if let Some(unit) = tokens.get(1).and_then(|t| small_number(&t.word)).filter(|u| (1..=9).contains(u)) {
+            return Some((n + unit, 2));
+        }
+    }
+    Some((n, 1))
+}
+
+/// Minutes after the hour: "oh five", "fifteen", "forty five".
+fn parse_minutes(tokens: &[Token]) -> Option<(u64, usize)> {
+    let first = tokens.first()?;
+    if matches!(first.word.as_str(), "oh" | "o") && first.continues() {
+        let digit = small_number(&tokens.get(1)?.word).filter(|d| (1..=9).contains(d))?;
+        return Some((digit, 2));
+    }
+    parse_two_digit(tokens).filter(|(minutes, _)| *minutes < 60)
+}
+
+/// "three thirty" after "at", "three pm", "four o'clock", "ten fifteen a.m."
+fn match_time(tokens: &[Token], previous: Option<&str>) -> Option<(String, usize)> {
+    let first = tokens.first()?;
+    let after_preposition = previous.is_some_and(|word| TIME_PREPOSITIONS.contains(&word));
+
+    // Whisper often hears "at four thirty" as "at for thirty". Only read these as
+    // numbers right after "at" or "by" and where a time follows; "around for
+    // twenty minutes" is a duration.
+    let (hour, homophone) = match first.word.as_str() {
+        "for" => (4, true),
+        "to" | "too" => (2, true),
+        word => (small_number(word).filter(|h| (1..=12).contains(h))?, false),
+    };
+    if (homophone && !matches!(previous, Some("at" | "by"))) || !first.continues() {
+        return None;
+    }
+
+    let mut used = 1;
+    let minutes = parse_minutes(&tokens[1..]).map(|(minutes, n)| {
+        used += n;
+        minutes
+    });
+
+    let next = tokens.get(used).filter(|_| tokens[used - 1].continues());
+    if next.is_some_and(|t| COUNT_NOUNS.contains(&t.word.as_str())) {
+        return None;
+    }
+    if minutes.is_none() && next.is_some_and(|t| t.word == "o'clock") {
+        return Some((format!("{}:00", hour), used + 1));
+    }
+    let meridiem = next.filter(|t| MERIDIEMS.contains(&t.word.as_str()));
+
+    match (minutes, meridiem) {
+        (Some(minutes), Some(meridiem)) => Some((format!("{}:{:02} {}", hour, minutes, meridiem.core), used + 1)),
+        (None, Some(meridiem)) => Some((format!("{} {}", hour, meridiem.core), used + 1)),
+        (Some(minutes), None) if after_preposition => Some((format!("{}:{:02}", hour, minutes), used)),
+        _ => None,
+    }
+}
+
+/// Years said as two pairs: "nineteen ninety nine", "twenty twenty four", "twenty oh five".
+fn match_year(tokens: &[Token]) -> Option<(String, usize)> {
+    let (century, used) = parse_two_digit(tokens)?;
+    if !(century == 19 || century == 20) || !tokens[used - 1].continues() {
+        return None;
+    }
+
+    let rest = &tokens[used..];
+    let first = rest.first()?;
+    let (year, rest_used) = if matches!(first.word.as_str(), "oh" | "o") && first.continues() {
+        let digit = small_number(&rest.get(1)?.word).filter(|d| (1..=9).contains(d))?;
+        (digit, 2)
+    } else {
+        parse_two_digit(rest)?
+    };
+
+    let used = used + rest_used;
+    let followed_by_count = tokens[used - 1].continues()
+        && tokens.get(used).is_some_and(|t| COUNT_NOUNS.contains(&t.word.as_str()));
+    if followed_by_count {
+        return None;
+    }
+    Some(((century * 100 + year).to_string(), used))
+}
+
+/// "March third", "may twenty-first", optionally followed by a year.
+fn match_date(tokens: &[Token]) -> Option<(String, usize)> {
+    let first = tokens.first()?;
+    let month = MONTHS.iter().find(|&&m| m == first.word)?;
+    let capitalized = first.core.chars().next().is_some_and(char::is_uppercase);
+    if !first.continues() || (VERB_MONTHS.contains(month) && !capitalized) {
+        return None;
+    }
+
+    // Only ordinals: "may one" is more often a question than a date
+    let rest = &tokens[1..];
+    let (day, day_used) = match ordinal(&rest.first()?.word) {
+        Some(day) => (day, 1),
+        None => {
+            let tens_value = tens(&rest[0].word).filter(|t| *t <= 30)?;
+            let unit = rest.get(1).filter(|_| rest[0].continues()).and_then(|t| ordinal(&t.word))?;
+            (tens_value + unit, 2)
+        }
+    };
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut month_name = month.to_string();
+    month_name[..1].make_ascii_uppercase();
+    let mut used = 1 + day_used;
+    let mut written = format!("{} {}", month_name, day);
+
+    if tokens[used - 1].continues() {
+        let year = match_year(&tokens[used..]).or_else(|| {
+            parse_cardinal(&tokens[used..])
+                .filter(|(value, n)| *n > 1 && (1900..2100).contains(value))
+                .map(|(value, n)| (value.to_string(), n))
+        });
+        if let Some((year, year_used)) = year {
+            written = format!("{}, {}", written, year);
+            used += year_used;
+        }
+    }
+
+    Some((written, used))
+}
+
+/// Percentages, money and plain numbers of ten or more.
+fn match_amount(tokens: &[Token]) -> Option<(String, usize)> {
+    let (number, used) = parse_number(tokens)?;
+    let next = tokens.get(used).filter(|_| tokens[used - 1].continues());
+
+    // The start of an ordinal like "twenty-first", which is left as spoken
+    if next.is_some_and(|t| ordinal(&t.word).is_some()) {
+        return None;
+    }
+
+    let symbol = match next.map(|t| t.word.as_str()) {
+        Some("percent") => return Some((format!("{}%", number), used + 1)),
+        Some("dollars" | "dollar") => "$",
+        Some("euros" | "euro") => "\u{20ac}",
+        _ => {
+            let is_small = used == 1 && small_number(&tokens[0].word).is_some_and(|n| n < 10);
+            return (!is_small).then_some((number, used));
+        }
+    };
+
+    // "five dollars and twenty cents"
+    let mut used = used + 1;
+    let mut written = format!("{}{}", symbol, number);
+    if !number.contains('.') && tokens[used - 1].continues() {
+        let cents = tokens
+            .get(used)
+            .filter(|t| t.word == "and" && t.continues())
+            .and_then(|_| parse_cardinal(&tokens[used + 1..]))
+            .filter(|(cents, n)| *cents < 100 && tokens[used + *n].continues())
+            .filter(|(_, n)| tokens.get(used + 1 + n).is_some_and(|t| t.word == "cents" || t.word == "cent"));
+        if let Some((cents, n)) = cents {
+            written = format!("{}{}.{:02}", symbol, number, cents);
+            used += n + 2;
+        }
+    }
+    Some((written, used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(text: &str) -> String {
+        EnglishNormalizer.normalize(text)
+    }
+
+    #[test]
+    fn writes_times_after_a_preposition() {
+        assert_eq!(normalize("Let's meet at three thirty."), "Let's meet at 3:30.");
+        assert_eq!(normalize("Call me at for fifteen"), "Call me at 4:15");
+        assert_eq!(normalize("It starts at ten oh five pm"), "It starts at 10:05 pm");
+        assert_eq!(normalize("See you at four o'clock"), "See you at 4:00");
+    }
+
+    #[test]
+    fn reads_for_as_four_only_where_a_time_is_expected() {
+        assert_eq!(normalize("This is for you"), "This is for you");
+        assert_eq!(normalize("We waited around for twenty minutes"), "We waited around for 20 minutes");
+    }
+
+    #[test]
+    fn writes_dates_and_years() {
+        assert_eq!(normalize("It's due March third"), "It's due March 3");
+        assert_eq!(normalize("on May twenty-first twenty twenty four"), "on May 21, 2024");
+        assert_eq!(normalize("back in nineteen ninety nine"), "back in 1999");
+        assert_eq!(normalize("you may first check"), "you may first check");
+    }
+
+    #[test]
+    fn writes_amounts_and_percentages() {
+        assert_eq!(normalize("about fifteen percent"), "about 15%");
+        assert_eq!(normalize("five dollars and twenty cents"), "$5.20");
+        assert_eq!(normalize("twenty thirty minutes"), "20 30 minutes");
+    }
+
+    #[test]
+    fn keeps_small_counts_spoken() {
+        assert_eq!(normalize("one of them had two options"), "one of them had two options");
+        assert_eq!(normalize("we hired twelve people"), "we hired 12 people");
+    }
+}
@@ -0,0 +1,71 @@
+// src/transcription/normalize/mod.rs
+//
+// Inverse text normalization: rewrites spoken numbers, times, dates and amounts
+// in transcripts in their written form. Each language has its own normalizer.
+mod english;
+
+pub use english::EnglishNormalizer;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextNormalizationConfig {
+    /// Off by default, so transcripts stay verbatim unless asked for.
+    pub enabled: bool,
+    /// ISO 639-1 code of the transcript language. Only "en" is supported so far.
+    pub language: String,
+}
+
+impl Default for TextNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            language: "en".to_string(),
+        }
+    }
+}
+
+/// Rewrites the spoken forms of one language. To support another language,
+/// implement this and add it to `TextNormalizer::new`.
+pub trait InverseNormalizer: Send + Sync {
+    fn normalize(&self, text: &str) -> String;
+}
+
+pub struct TextNormalizer {
+    normalizer: Option<Box<dyn InverseNormalizer>>,
+}
+
+impl TextNormalizer {
+    pub fn new(config: TextNormalizationConfig) -> Self {
+        if !config.enabled {
+            return Self { normalizer: None };
+        }
+
+        let normalizer: Option<Box<dyn InverseNormalizer>> = match config.language.to_lowercase().as_str() {
+            "en" => Some(Box::new(EnglishNormalizer)),
+            other => {
+                warn!("No text normalization available for language {}, keeping the spoken form", other);
+                None
+            }
+        };
+        Self { normalizer }
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        match &self.normalizer {
+            Some(normalizer) => normalizer.normalize(text),
+            None => text.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for TextNormalizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextNormalizer")
+            .field("enabled", &self.normalizer.is_some())
+            .finish()
+    }
+}
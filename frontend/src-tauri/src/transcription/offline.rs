@@ -0,0 +1,637 @@
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::backend::{strip_blank_markers, TranscriptResponse, TranscriptionBackend, TranscriptionError};
+use super::{TICKS_PER_SEC, WHISPER_SAMPLE_RATE};
+use crate::audio::audio_processing::audio_to_mono;
+use crate::audio::StreamResampler;
+
+/// One way of decoding a chunk in offline mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodingPass {
+    /// Beam search with this many beams, greedy decoding when 1 or less.
+    pub beam_size: u32,
+    pub temperature: f32,
+}
+
+/// Settings for transcribing a whole file for quality rather than latency.
+/// Every chunk is decoded once per pass and the best result is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OfflineTranscriptionConfig {
+    pub passes: Vec<DecodingPass>,
+    pub chunk_secs: u32,
+    /// Audio either side of a chunk decoded along with it, so words cut by the
+    /// chunk boundary are heard in full. Only words inside the chunk are kept.
+    pub context_secs: u32,
+    /// How much agreement with the other passes counts against word confidence
+    /// when picking a result, from 0 (confidence only) to 1 (agreement only).
+    pub agreement_weight: f32,
+    /// Files of a batch transcribed at the same time, also limited to the CPU
+    /// cores. Each holds its decoded audio in memory until it is done.
+    pub max_concurrent_files: usize,
+}
+
+impl Default for OfflineTranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            passes: vec![
+                DecodingPass {
+                    beam_size: 1,
+                    temperature: 0.0,
+                },
+                DecodingPass {
+                    beam_size: 5,
+                    temperature: 0.0,
+                },
+                DecodingPass {
+                    beam_size: 1,
+                    temperature: 0.4,
+                },
+            ],
+            // With context on both sides this stays within whisper's 30 s window
+            chunk_secs: 24,
+            context_secs: 2,
+            agreement_weight: 0.5,
+            max_concurrent_files: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineSegment {
+    /// Seconds from the start of the file.
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// Mean word probability, `None` when the server sent no word timings.
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineTranscript {
+    pub segments: Vec<OfflineSegment>,
+    pub duration_secs: f64,
+    /// Index into the configured passes of the result kept for each chunk.
+    pub chosen_passes: Vec<usize>,
+}
+
+/// Payload of the `offline-transcription-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineProgress {
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+}
+
+/// Payload of the `batch-transcription-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    /// Position of the file in the batch.
+    pub file_index: usize,
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+}
+
+/// Outcome of one file of a batch. A file that fails leaves the others running.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTranscript {
+    pub path: PathBuf,
+    pub transcript: Option<OfflineTranscript>,
+    pub error: Option<String>,
+}
+
+/// The file at `path` as 16 kHz mono.
+pub fn read_wav_for_whisper(path: &Path) -> Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path).with_context(|| format!("couldn't open {}", path.display()))?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let mono = audio_to_mono(&samples, spec.channels.max(1));
+    Ok(StreamResampler::new(spec.sample_rate, WHISPER_SAMPLE_RATE).process(&mono))
+}
+
+// Where a chunk and the context decoded with it sit in the file, in samples
+struct ChunkWindow {
+    start: usize,
+    end: usize,
+    context_start: usize,
+    context_end: usize,
+}
+
+fn chunk_windows(total: usize, config: &OfflineTranscriptionConfig) -> Vec<ChunkWindow> {
+    let chunk = (config.chunk_secs.max(1) * WHISPER_SAMPLE_RATE) as usize;
+    let context = (config.context_secs * WHISPER_SAMPLE_RATE) as usize;
+    (0..total)
+        .step_by(chunk)
+        .map(|start| {
+            let end = (start + chunk).min(total);
+            ChunkWindow {
+                start,
+                end,
+                context_start: start.saturating_sub(context),
+                context_end: (end + context).min(total),
+            }
+        })
+        .collect()
+}
+
+// The segments of `response` that fall inside the chunk, trimmed to the words
+// inside it. A word belongs to the chunk its midpoint is in.
+fn segments_in_chunk(response: &TranscriptResponse, window: &ChunkWindow) -> Vec<OfflineSegment> {
+    let offset = window.context_start as f64 / WHISPER_SAMPLE_RATE as f64;
+    let start = window.start as f64 / WHISPER_SAMPLE_RATE as f64;
+    let end = window.end as f64 / WHISPER_SAMPLE_RATE as f64;
+    let to_secs = |ticks: f32| offset + ticks as f64 / TICKS_PER_SEC;
+    let inside = |t0: f32, t1: f32| (start..end).contains(&((to_secs(t0) + to_secs(t1)) / 2.0));
+
+    let mut segments = Vec::new();
+    for segment in response.segments.iter().filter(|segment| !segment.is_blank()) {
+        if segment.words.is_empty() {
+            if inside(segment.t0, segment.t1) {
+                segments.push(OfflineSegment {
+                    start: to_secs(segment.t0),
+                    end: to_secs(segment.t1),
+                    text: strip_blank_markers(&segment.text),
+                    confidence: None,
+                });
+            }
+            continue;
+        }
+        let words: Vec<_> = segment.words.iter().filter(|word| inside(word.t0, word.t1)).collect();
+        let (Some(first), Some(last)) = (words.first(), words.last()) else {
+            continue;
+        };
+        let text: String = words.iter().map(|word| word.text.as_str()).collect();
+        let text = strip_blank_markers(&text);
+        if text.is_empty() {
+            continue;
+        }
+        segments.push(OfflineSegment {
+            start: to_secs(first.t0),
+            end: to_secs(last.t1),
+            text,
+            confidence: Some(words.iter().map(|word| word.p).sum::<f32>() / words.len() as f32),
+        });
+    }
+    segments
+}
+
+fn word_counts(segments: &[OfflineSegment]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for word in segments.iter().flat_map(|segment| segment.text.split_whitespace()) {
+        let word: String = word.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect();
+        if !word.is_empty() {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Share of words two results have in common, 1 when both are empty
+fn agreement(a: &HashMap<String, usize>, b: &HashMap<String, usize>) -> f32 {
+    let total: usize = a.values().chain(b.values()).sum();
+    if total == 0 {
+        return 1.0;
+    }
+    let common: usize = a.iter().map(|(word, count)| (*count).min(b.get(word).copied().unwrap_or(0))).sum();
+    2.0 * common as f32 / total as f32
+}
+
+fn mean_confidence(segments: &[OfflineSegment]) -> Option<f32> {
+    let confidences: Vec<f32> = segments.iter().filter_map(|segment| segment.confidence).collect();
+    (!confidences.is_empty()).then(|| confidences.iter().sum::<f32>() / confidences.len() as f32)
+}
+
+// Index of the candidate with the best mix of confidence and agreement with the others
+fn pick_best(candidates: &[Vec<OfflineSegment>], agreement_weight: f32) -> usize {
+    let weight = agreement_weight.clamp(0.0, 1.0);
+    let counts: Vec<_> = candidates.iter().map(|segments| word_counts(segments)).collect();
+    let score = |i: usize| {
+        let others = candidates.len() - 1;
+        let agreement = if others == 0 {
+            1.0
+        } else {
+            (0..candidates.len())
+                .filter(|&j| j != i)
+                .map(|j| agreement(&counts[i], &counts[j]))
+                .sum::<f32>()
+                / others as f32
+        };
+        // Results without word timings can't be compared on confidence
+        let confidence = mean_confidence(&candidates[i]).unwrap_or(0.5);
+        (1.0 - weight) * confidence + weight * agreement
+    };
+    (0..candidates.len())
+        .max_by(|&a, &b| score(a).total_cmp(&score(b)))
+        .unwrap_or(0)
+}
+
+/// Transcribes `samples`, 16 kHz mono, chunk by chunk with every configured
+/// pass. `on_progress` is called after each chunk with the chunks done and the
+/// total. A chunk fails only if all of its passes do.
+pub async fn transcribe_offline(
+    backend: &dyn TranscriptionBackend,
+    samples: &[f32],
+    config: &OfflineTranscriptionConfig,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<OfflineTranscript, TranscriptionError> {
+    let single_pass = [DecodingPass {
+        beam_size: 1,
+        temperature: 0.0,
+    }];
+    let passes = if config.passes.is_empty() { &single_pass[..] } else { &config.passes[..] };
+    let windows = chunk_windows(samples.len(), config);
+
+    let mut transcript = OfflineTranscript {
+        segments: Vec::new(),
+        duration_secs: samples.len() as f64 / WHISPER_SAMPLE_RATE as f64,
+        chosen_passes: Vec::with_capacity(windows.len()),
+    };
+    for (index, window) in windows.iter().enumerate() {
+        let audio = &samples[window.context_start..window.context_end];
+        let mut candidates = Vec::with_capacity(passes.len());
+        let mut pass_indices = Vec::with_capacity(passes.len());
+        let mut last_error = None;
+        for (pass_index, pass) in passes.iter().enumerate() {
+            match backend.transcribe_pass(index as u64, audio, pass).await {
+                Ok(response) => {
+                    candidates.push(segments_in_chunk(&response, window));
+                    pass_indices.push(pass_index);
+                }
+                Err(e) => {
+                    warn!("Offline chunk {}: pass {} failed: {}", index, pass_index, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        if candidates.is_empty() {
+            if let Some(e) = last_error {
+                return Err(e);
+            }
+        }
+
+        let best = pick_best(&candidates, config.agreement_weight);
+        debug!("Offline chunk {}: keeping pass {} of {}", index, pass_indices[best], passes.len());
+        transcript.chosen_passes.push(pass_indices[best]);
+        transcript.segments.append(&mut candidates.swap_remove(best));
+        on_progress(index + 1, windows.len());
+    }
+    Ok(transcript)
+}
+
+/// Files of a batch to transcribe at once: `requested`, or the configured
+/// maximum when not given, within the configured maximum and the CPU cores.
+pub fn batch_concurrency(requested: Option<usize>, config: &OfflineTranscriptionConfig) -> usize {
+    let max = config.max_concurrent_files.max(1);
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    requested.unwrap_or(max).clamp(1, max).min(cores)
+}
+
+/// Transcribes each WAV file in `paths` like `transcribe_offline`, up to
+/// `concurrency` files at a time. `on_progress` is called after each chunk of
+/// any file. Results come back in the order of `paths`.
+pub async fn transcribe_files(
+    backend: Arc<dyn TranscriptionBackend>,
+    paths: Vec<PathBuf>,
+    concurrency: usize,
+    config: &OfflineTranscriptionConfig,
+    on_progress: impl Fn(BatchProgress) + Send + Sync + 'static,
+) -> Vec<FileTranscript> {
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let on_progress = Arc::new(on_progress);
+    let mut files = JoinSet::new();
+    for (file_index, path) in paths.iter().cloned().enumerate() {
+        let (backend, permits, on_progress) = (backend.clone(), permits.clone(), on_progress.clone());
+        let config = config.clone();
+        files.spawn(async move {
+            let result = match permits.acquire_owned().await {
+                Ok(_permit) => transcribe_file(backend.as_ref(), &path, &config, |chunks_done, chunks_total| {
+                    on_progress(BatchProgress {
+                        file_index,
+                        chunks_done,
+                        chunks_total,
+                    })
+                })
+                .await
+                .map_err(|e| format!("{:#}", e)),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = &result {
+                warn!("Batch file {} ({}) failed: {}", file_index, path.display(), e);
+            }
+            (file_index, result)
+        });
+    }
+
+    // A task that panicked never reports back, its file keeps this error
+    let mut results: Vec<Result<OfflineTranscript, String>> =
+        paths.iter().map(|_| Err("transcription task ended abnormally".to_string())).collect();
+    while let Some(joined) = files.join_next().await {
+        match joined {
+            Ok((file_index, result)) => results[file_index] = result,
+            Err(e) => warn!("Batch transcription task ended abnormally: {}", e),
+        }
+    }
+    paths
+        .into_iter()
+        .zip(results)
+        .map(|(path, result)| match result {
+            Ok(transcript) => FileTranscript {
+                path,
+                transcript: Some(transcript),
+                error: None,
+            },
+            Err(error) => FileTranscript {
+                path,
+                transcript: None,
+                error: Some(error),
+            },
+        })
+        .collect()
+}
+
+async fn transcribe_file(
+    backend: &dyn TranscriptionBackend,
+    path: &Path,
+    config: &OfflineTranscriptionConfig,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<OfflineTranscript> {
+    let owned_path = path.to_path_buf();
+    let samples = tokio::task::spawn_blocking(move || read_wav_for_whisper(&owned_path)).await??;
+    debug!("Batch transcribing {:.1}s from {}", samples.len() as f64 / WHISPER_SAMPLE_RATE as f64, path.display());
+    transcribe_offline(backend, &samples, config, on_progress)
+        .await
+        .map_err(|e| anyhow::anyhow!(e.user_message()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::backend::TranscriptionFuture;
+
+    fn config() -> OfflineTranscriptionConfig {
+        OfflineTranscriptionConfig {
+            chunk_secs: 24,
+            context_secs: 2,
+            ..Default::default()
+        }
+    }
+
+    fn segment(text: &str, confidence: f32) -> Vec<OfflineSegment> {
+        vec![OfflineSegment {
+            start: 0.0,
+            end: 1.0,
+            text: text.to_string(),
+            confidence: Some(confidence),
+        }]
+    }
+
+    #[test]
+    fn chunks_carry_context_from_either_side() {
+        let secs = |samples: usize| samples / WHISPER_SAMPLE_RATE as usize;
+        let windows = chunk_windows(50 * WHISPER_SAMPLE_RATE as usize, &config());
+        let bounds: Vec<_> = windows
+            .iter()
+            .map(|w| (secs(w.context_start), secs(w.start), secs(w.end), secs(w.context_end)))
+            .collect();
+        assert_eq!(bounds, vec![(0, 0, 24, 26), (22, 24, 48, 50), (46, 48, 50, 50)]);
+    }
+
+    #[test]
+    fn keeps_only_the_words_inside_the_chunk() {
+        let response: TranscriptResponse = serde_json::from_value(serde_json::json!({
+            "buffer_size_ms": 0,
+            "segments": [{
+                "text": " before inside",
+                "t0": 100.0,
+                "t1": 300.0,
+                "words": [
+                    { "text": " before", "t0": 100.0, "t1": 150.0, "p": 0.5 },
+                    { "text": " inside", "t0": 250.0, "t1": 300.0, "p": 0.9 }
+                ]
+            }]
+        }))
+        .unwrap();
+        let windows = chunk_windows(50 * WHISPER_SAMPLE_RATE as usize, &config());
+
+        let segments = segments_in_chunk(&response, &windows[1]);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "inside");
+        assert_eq!((segments[0].start, segments[0].end), (24.5, 25.0));
+        assert_eq!(segments[0].confidence, Some(0.9));
+        // Read against the first chunk, both words are inside it
+        assert_eq!(segments_in_chunk(&response, &windows[0])[0].text, "before inside");
+    }
+
+    #[test]
+    fn agreement_counts_shared_words() {
+        let a = word_counts(&segment("The plan is set.", 1.0));
+        let b = word_counts(&segment("the plan was set", 1.0));
+        assert_eq!(agreement(&a, &b), 0.75);
+        assert_eq!(agreement(&HashMap::new(), &HashMap::new()), 1.0);
+    }
+
+    #[test]
+    fn picks_by_confidence_and_agreement() {
+        let candidates = vec![
+            segment("we ship on friday", 0.6),
+            segment("we ship on friday", 0.7),
+            segment("wee sip on fried eye", 0.95),
+        ];
+        assert_eq!(pick_best(&candidates, 0.0), 2);
+        assert_eq!(pick_best(&candidates, 0.5), 1);
+        assert_eq!(pick_best(&candidates[..1], 0.5), 0);
+    }
+
+    /// Answers every pass with one segment naming the length of the audio.
+    struct LengthBackend;
+
+    impl TranscriptionBackend for LengthBackend {
+        fn name(&self) -> &str {
+            "length"
+        }
+
+        fn transcribe(&self, _chunk_id: u64, samples: Vec<f32>, _prompt: Option<String>) -> TranscriptionFuture<'_> {
+            let text = format!("{} samples", samples.len());
+            Box::pin(async move {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "buffer_size_ms": 0,
+                    "segments": [{ "text": text, "t0": 0.0, "t1": 50.0 }]
+                }))
+                .unwrap())
+            })
+        }
+    }
+
+    /// Answers each pass with its own wording and word confidence: the beam search
+    /// pass is the surest, the sampled pass mishears.
+    struct PassBackend;
+
+    impl TranscriptionBackend for PassBackend {
+        fn name(&self) -> &str {
+            "passes"
+        }
+
+        fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, _prompt: Option<String>) -> TranscriptionFuture<'_> {
+            Box::pin(async move {
+                let pass = DecodingPass {
+                    beam_size: 1,
+                    temperature: 0.0,
+                };
+                self.transcribe_pass(chunk_id, &samples, &pass).await
+            })
+        }
+
+        fn transcribe_pass<'a>(
+            &'a self,
+            _chunk_id: u64,
+            _samples: &'a [f32],
+            pass: &'a DecodingPass,
+        ) -> TranscriptionFuture<'a> {
+            let (words, p) = match (pass.beam_size, pass.temperature > 0.0) {
+                (_, true) => ([" wee", " sip", " on", " fried", " eye"].as_slice(), 0.7),
+                (1, false) => ([" we", " ship", " on", " friday"].as_slice(), 0.6),
+                _ => ([" we", " ship", " on", " friday"].as_slice(), 0.9),
+            };
+            let words: Vec<_> = words
+                .iter()
+                .enumerate()
+                .map(|(i, text)| {
+                    let t0 = 10.0 * i as f32;
+                    serde_json::json!({ "text": text, "t0": t0, "t1": t0 + 8.0, "p": p })
+                })
+                .collect();
+            Box::pin(async move {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "buffer_size_ms": 0,
+                    "segments": [{ "text": "", "t0": 0.0, "t1": 50.0, "words": words }]
+                }))
+                .unwrap())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn multiple_passes_keep_a_better_result_than_a_single_pass() {
+        let samples = vec![0.0; WHISPER_SAMPLE_RATE as usize];
+
+        let single = OfflineTranscriptionConfig {
+            passes: Vec::new(),
+            ..config()
+        };
+        let single = transcribe_offline(&PassBackend, &samples, &single, |_, _| {}).await.unwrap();
+        assert_eq!(single.chosen_passes, vec![0]);
+        assert_eq!(single.segments[0].text, "we ship on friday");
+        assert!((single.segments[0].confidence.unwrap() - 0.6).abs() < 1e-6);
+
+        let multi = transcribe_offline(&PassBackend, &samples, &config(), |_, _| {}).await.unwrap();
+        // The beam search pass, surer than the greedy one and agreeing with it
+        assert_eq!(multi.chosen_passes, vec![1]);
+        assert_eq!(multi.segments[0].text, "we ship on friday");
+        assert!((multi.segments[0].confidence.unwrap() - 0.9).abs() < 1e-6);
+    }
+
+    fn write_wav(path: &Path, secs: u32) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: WHISPER_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..secs * WHISPER_SAMPLE_RATE {
+            writer.write_sample(((i % 100) as i16 - 50) * 100).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[tokio::test]
+    async fn batch_transcribes_every_file_despite_a_failing_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = ["one.wav", "missing.wav", "two.wav"].iter().map(|name| dir.path().join(name)).collect();
+        write_wav(&paths[0], 1);
+        write_wav(&paths[2], 2);
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = OfflineTranscriptionConfig {
+            passes: Vec::new(),
+            ..config()
+        };
+
+        let results = transcribe_files(Arc::new(LengthBackend), paths.clone(), 2, &config, {
+            let progress = progress.clone();
+            move |update| progress.lock().unwrap().push(update.file_index)
+        })
+        .await;
+
+        assert_eq!(results.iter().map(|result| &result.path).collect::<Vec<_>>(), paths.iter().collect::<Vec<_>>());
+        let texts: Vec<_> = results
+            .iter()
+            .map(|result| result.transcript.as_ref().map(|transcript| transcript.segments[0].text.clone()))
+            .collect();
+        assert_eq!(texts, vec![Some("16000 samples".to_string()), None, Some("32000 samples".to_string())]);
+        assert!(results[1].error.is_some());
+        let mut files_reported = progress.lock().unwrap().clone();
+        files_reported.sort();
+        assert_eq!(files_reported, vec![0, 2]);
+    }
+
+    /// Panics on audio longer than a second, otherwise answers like [`LengthBackend`].
+    struct PanickingBackend;
+
+    impl TranscriptionBackend for PanickingBackend {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn transcribe(&self, chunk_id: u64, samples: Vec<f32>, prompt: Option<String>) -> TranscriptionFuture<'_> {
+            assert!(samples.len() <= WHISPER_SAMPLE_RATE as usize, "backend crashed");
+            LengthBackend.transcribe(chunk_id, samples, prompt)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_panicking_file_keeps_its_place_in_the_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = ["long.wav", "short.wav"].iter().map(|name| dir.path().join(name)).collect();
+        write_wav(&paths[0], 2);
+        write_wav(&paths[1], 1);
+        let config = OfflineTranscriptionConfig {
+            passes: Vec::new(),
+            ..config()
+        };
+
+        let results = transcribe_files(Arc::new(PanickingBackend), paths.clone(), 2, &config, |_| {}).await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, paths[0]);
+        assert!(results[0].transcript.is_none());
+        assert_eq!(results[0].error.as_deref(), Some("transcription task ended abnormally"));
+        assert_eq!(results[1].path, paths[1]);
+        assert_eq!(results[1].transcript.as_ref().unwrap().segments[0].text, "16000 samples");
+    }
+
+    #[test]
+    fn batch_concurrency_stays_within_the_configured_maximum() {
+        let config = OfflineTranscriptionConfig {
+            max_concurrent_files: 4,
+            ..config()
+        };
+        assert_eq!(batch_concurrency(Some(0), &config), 1);
+        assert!(batch_concurrency(Some(16), &config) <= 4);
+        assert!(batch_concurrency(None, &config) <= 4);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+/// A word with whisper timings (10 ms ticks, relative to the request audio) and
+/// the mean probability of its tokens.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimedWord {
+    pub text: String,
+    pub t0: f32,
+    pub t1: f32,
+    pub p: f32,
+}
+
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Merges two transcriptions of the same stretch of audio: the end of one chunk
+/// and the start of the next. Words are aligned with DTW on their normalized text
+/// and the more confident version of each aligned pair is kept.
+pub fn merge_overlap(previous: &[TimedWord], next: &[TimedWord]) -> Vec<TimedWord> {
+    if previous.is_empty() {
+        return next.to_vec();
+    }
+    if next.is_empty() {
+        return previous.to_vec();
+    }
+
+    let prev_norm: Vec<String> = previous.iter().map(|w| normalize(&w.text)).collect();
+    let next_norm: Vec<String> = next.iter().map(|w| normalize(&w.text)).collect();
+    let (n, m) = (previous.len(), next.len());
+
+    // cost[i][j]: best alignment of previous[..i] with next[..j]
+    let mut cost = vec![vec![f32::INFINITY; m + 1]; n + 1];
+    cost[0][0] = 0.0;
+    for i in 1..=n {
+        for j in 1..=m {
+            let word_cost = if prev_norm[i - 1] == next_norm[j - 1] { 0.0 } else { 1.0 };
+            let best = cost[i - 1][j - 1].min(cost[i - 1][j]).min(cost[i][j - 1]);
+            cost[i][j] = word_cost + best;
+        }
+    }
+
+    // Walk back to recover the alignment path
+    let mut path = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 {
+        path.push((i - 1, j - 1));
+        let diagonal = cost[i - 1][j - 1];
+        let up = cost[i - 1][j];
+        let left = cost[i][j - 1];
+        if diagonal <= up && diagonal <= left {
+            i -= 1;
+            j -= 1;
+        } else if up <= left {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    path.reverse();
+
+    let mut merged: Vec<TimedWord> = Vec::with_capacity(n.max(m));
+    let mut last_prev = None;
+    let mut last_next = None;
+
+    for (pi, ni) in path {
+        let new_prev = last_prev != Some(pi);
+        let new_next = last_next != Some(ni);
+        last_prev = Some(pi);
+        last_next = Some(ni);
+
+        let candidate = if new_prev && new_next {
+            // Both sides transcribed this word, keep the more confident version
+            if previous[pi].p >= next[ni].p { &previous[pi] } else { &next[ni] }
+        } else {
+            // Only one side has this word. Keep it if it is at least as confident
+            // as the word the other side has at this point
+            let (extra, other) = if new_prev { (&previous[pi], &next[ni]) } else { (&next[ni], &previous[pi]) };
+            if extra.p < other.p {
+                continue;
+            }
+            extra
+        };
+
+        let is_repeat = merged
+            .last()
+            .is_some_and(|last| normalize(&last.text) == normalize(&candidate.text));
+        if !is_repeat {
+            merged.push(candidate.clone());
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, t0: f32, p: f32) -> TimedWord {
+        TimedWord {
+            text: text.to_string(),
+            t0,
+            t1: t0 + 10.0,
+            p,
+        }
+    }
+
+    fn texts(words: &[TimedWord]) -> Vec<&str> {
+        words.iter().map(|word| word.text.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_the_more_confident_version_of_each_word() {
+        let previous = [word(" the", 0.0, 0.9), word(" quick", 10.0, 0.3)];
+        let next = [word(" The", 0.0, 0.5), word(" quick,", 10.0, 0.8)];
+        assert_eq!(texts(&merge_overlap(&previous, &next)), [" the", " quick,"]);
+    }
+
+    #[test]
+    fn drops_a_less_confident_word_only_one_side_heard() {
+        let previous = [word(" brown", 0.0, 0.9), word(" fox", 10.0, 0.9)];
+        let next = [word(" brown", 0.0, 0.9), word(" uh", 5.0, 0.2), word(" fox", 10.0, 0.9)];
+        assert_eq!(texts(&merge_overlap(&previous, &next)), [" brown", " fox"]);
+    }
+
+    #[test]
+    fn either_side_alone_is_kept_as_is() {
+        let words = [word(" hello", 0.0, 0.5)];
+        assert_eq!(merge_overlap(&[], &words), words);
+        assert_eq!(merge_overlap(&words, &[]), words);
+    }
+}
@@ -0,0 +1,134 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Settings for keeping a breakdown of where each chunk's time goes, to find
+/// out which stage is the bottleneck.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkProfilingConfig {
+    pub enabled: bool,
+    /// Oldest timings are dropped beyond this many.
+    pub max_chunks: usize,
+}
+
+impl Default for ChunkProfilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_chunks: 200,
+        }
+    }
+}
+
+/// Where one transcribed chunk's time went, from the cut to handing its
+/// segments to the emitter. Stages add up to `total_ms`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkTiming {
+    pub chunk_id: u64,
+    /// Resampling, speech confirmation, filtering and padding after the cut.
+    pub prepare_ms: f64,
+    /// Waiting for a worker, including the duplicate check.
+    pub queue_ms: f64,
+    /// The whisper request, including retries.
+    pub whisper_ms: f64,
+    /// Everything after the response: speaker features, sentence assembly and
+    /// emitting. A chunk that finishes before earlier ones is held back, and that
+    /// wait is counted by the chunk that releases it.
+    pub emit_ms: f64,
+    pub total_ms: f64,
+}
+
+impl ChunkTiming {
+    pub fn from_stages(chunk_id: u64, prepare: Duration, queue: Duration, whisper: Duration, emit: Duration) -> Self {
+        let [prepare_ms, queue_ms, whisper_ms, emit_ms] =
+            [prepare, queue, whisper, emit].map(|stage| stage.as_secs_f64() * 1000.0);
+        Self {
+            chunk_id,
+            prepare_ms,
+            queue_ms,
+            whisper_ms,
+            emit_ms,
+            total_ms: prepare_ms + queue_ms + whisper_ms + emit_ms,
+        }
+    }
+
+    /// Splits a chunk's time into stages from when it was cut, when its request
+    /// started and how long whisper took. `now` is when its segments were emitted.
+    pub fn measure(
+        chunk_id: u64,
+        prepare: Duration,
+        cut_at: Instant,
+        request_started: Instant,
+        whisper: Duration,
+        now: Instant,
+    ) -> Self {
+        let queue = request_started.saturating_duration_since(cut_at);
+        let emit = now.saturating_duration_since(request_started).saturating_sub(whisper);
+        Self::from_stages(chunk_id, prepare, queue, whisper, emit)
+    }
+}
+
+lazy_static! {
+    static ref CHUNK_TIMINGS: Mutex<VecDeque<ChunkTiming>> = Mutex::new(VecDeque::new());
+}
+
+pub fn record_chunk_timing(timing: ChunkTiming, max_chunks: usize) {
+    if let Ok(mut timings) = CHUNK_TIMINGS.lock() {
+        while timings.len() >= max_chunks.max(1) {
+            timings.pop_front();
+        }
+        timings.push_back(timing);
+    }
+}
+
+/// Up to `limit` of the most recent timings, oldest first.
+pub fn recent_chunk_timings(limit: usize) -> Vec<ChunkTiming> {
+    CHUNK_TIMINGS
+        .lock()
+        .map(|timings| timings.iter().skip(timings.len().saturating_sub(limit)).cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn clear_chunk_timings() {
+    if let Ok(mut timings) = CHUNK_TIMINGS.lock() {
+        timings.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_stage_is_measured_from_the_chunk_s_timestamps() {
+        let cut_at = Instant::now();
+        let request_started = cut_at + Duration::from_micros(2500);
+        let whisper = Duration::from_millis(840);
+        let now = request_started + whisper + Duration::from_millis(6);
+        let timing = ChunkTiming::measure(3, Duration::from_millis(12), cut_at, request_started, whisper, now);
+
+        assert!((timing.prepare_ms - 12.0).abs() < 1e-6);
+        assert!((timing.queue_ms - 2.5).abs() < 1e-6);
+        assert!((timing.whisper_ms - 840.0).abs() < 1e-6);
+        assert!((timing.emit_ms - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn keeps_only_the_most_recent_timings() {
+        clear_chunk_timings();
+        let zero = Duration::ZERO;
+        for chunk_id in 0..5 {
+            let timing = ChunkTiming::from_stages(chunk_id, zero, zero, zero, zero);
+            record_chunk_timing(timing, 3);
+        }
+        let ids: Vec<u64> = recent_chunk_timings(2).iter().map(|timing| timing.chunk_id).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(recent_chunk_timings(10).len(), 3);
+
+        clear_chunk_timings();
+        assert!(recent_chunk_timings(10).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionPattern {
+    pub name: String,
+    pub pattern: String,
+    /// Only redact matches whose digits pass the Luhn check, so ordinary long
+    /// numbers aren't mistaken for card numbers.
+    pub luhn_check: bool,
+}
+
+impl RedactionPattern {
+    fn new(name: &str, pattern: &str, luhn_check: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            luhn_check,
+        }
+    }
+}
+
+/// Settings for masking sensitive information in transcripts before they leave the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    pub replacement: String,
+    pub patterns: Vec<RedactionPattern>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            replacement: "[REDACTED]".to_string(),
+            patterns: vec![
+                RedactionPattern::new("email", r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b", false),
+                RedactionPattern::new("credit_card", r"\b(?:\d[ -]?){12,18}\d\b", true),
+                RedactionPattern::new("ssn", r"\b\d{3}-\d{2}-\d{4}\b", false),
+            ],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CompiledPattern {
+    regex: Regex,
+    luhn_check: bool,
+}
+
+#[derive(Debug)]
+pub struct RedactionFilter {
+    enabled: bool,
+    replacement: String,
+    patterns: Vec<CompiledPattern>,
+}
+
+impl RedactionFilter {
+    pub fn new(config: RedactionConfig) -> Self {
+        let patterns = config
+            .patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(&pattern.pattern) {
+                Ok(regex) => Some(CompiledPattern {
+                    regex,
+                    luhn_check: pattern.luhn_check,
+                }),
+                Err(e) => {
+                    warn!("Ignoring invalid redaction pattern {}: {}", pattern.name, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            enabled: config.enabled,
+            replacement: config.replacement,
+            patterns,
+        }
+    }
+
+    /// Returns the redacted text and the number of matches replaced.
+    pub fn redact(&self, text: &str) -> (String, usize) {
+        if !self.enabled {
+            return (text.to_string(), 0);
+        }
+
+        let mut redacted = text.to_string();
+        let mut count = 0;
+        for pattern in &self.patterns {
+            redacted = pattern
+                .regex
+                .replace_all(&redacted, |caps: &regex::Captures| {
+                    let matched = &caps[0];
+                    if pattern.luhn_check && !passes_luhn(matched) {
+                        return matched.to_string();
+                    }
+                    count += 1;
+                    self.replacement.clone()
+                })
+                .into_owned();
+        }
+        (redacted, count)
+    }
+}
+
+fn passes_luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| {
+            if i % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> RedactionFilter {
+        RedactionFilter::new(RedactionConfig {
+            enabled: true,
+            ..RedactionConfig::default()
+        })
+    }
+
+    #[test]
+    fn redacts_emails_and_social_security_numbers() {
+        let (text, count) = filter().redact("Mail jane.doe@example.com, SSN 123-45-6789.");
+        assert_eq!(text, "Mail [REDACTED], SSN [REDACTED].");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn only_redacts_card_numbers_passing_the_luhn_check() {
+        let (text, count) = filter().redact("Card 4111 1111 1111 1111, order 1234567890123");
+        assert_eq!(text, "Card [REDACTED], order 1234567890123");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn skips_invalid_patterns() {
+        let config = RedactionConfig {
+            enabled: true,
+            patterns: vec![
+                RedactionPattern::new("broken", "(", false),
+                RedactionPattern::new("pin", r"\b\d{4}\b", false),
+            ],
+            ..RedactionConfig::default()
+        };
+        assert_eq!(
+            RedactionFilter::new(config).redact("PIN 1234"),
+            ("PIN [REDACTED]".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn leaves_text_alone_when_disabled() {
+        let filter = RedactionFilter::new(RedactionConfig::default());
+        assert_eq!(filter.redact("jane@example.com"), ("jane@example.com".to_string(), 0));
+    }
+}
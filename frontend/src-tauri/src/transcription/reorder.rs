@@ -0,0 +1,124 @@
+use log::{debug, warn};
+use std::collections::BTreeMap;
+
+/// Collects per-chunk results from concurrent transcription workers and releases
+/// them strictly in chunk order, so a fast worker can't emit chunk N+1 before N.
+pub struct ChunkReorderBuffer<T> {
+    next_chunk_id: u64,
+    pending: BTreeMap<u64, Option<T>>,
+    max_pending: usize,
+}
+
+impl<T> ChunkReorderBuffer<T> {
+    pub fn new(first_chunk_id: u64, max_pending: usize) -> Self {
+        Self {
+            next_chunk_id: first_chunk_id,
+            pending: BTreeMap::new(),
+            max_pending: max_pending.max(1),
+        }
+    }
+
+    /// Store the result for `chunk_id` and return every result that is now in order.
+    pub fn complete(&mut self, chunk_id: u64, result: T) -> Vec<T> {
+        self.insert(chunk_id, Some(result))
+    }
+
+    /// Mark a chunk that will never produce a result (dropped or failed) so later
+    /// chunks aren't held back waiting for it.
+    pub fn skip(&mut self, chunk_id: u64) -> Vec<T> {
+        self.insert(chunk_id, None)
+    }
+
+    /// Release everything still pending, in chunk order, regardless of gaps.
+    pub fn flush(&mut self) -> Vec<T> {
+        let pending = std::mem::take(&mut self.pending);
+        if let Some(&last_id) = pending.keys().next_back() {
+            self.next_chunk_id = last_id + 1;
+        }
+        pending.into_values().flatten().collect()
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn insert(&mut self, chunk_id: u64, result: Option<T>) -> Vec<T> {
+        if chunk_id < self.next_chunk_id {
+            // We already gave up waiting for this chunk; emit it late rather than lose it
+            warn!("Chunk {} arrived after it was skipped in ordering, releasing immediately", chunk_id);
+            return result.into_iter().collect();
+        }
+
+        self.pending.insert(chunk_id, result);
+        self.release_ready()
+    }
+
+    fn release_ready(&mut self) -> Vec<T> {
+        let mut ready = Vec::new();
+        loop {
+            if let Some(result) = self.pending.remove(&self.next_chunk_id) {
+                ready.extend(result);
+                self.next_chunk_id += 1;
+                continue;
+            }
+
+            // Bound memory: if a missing chunk holds back too many results, stop waiting for it
+            if self.pending.len() > self.max_pending {
+                if let Some(&first_pending) = self.pending.keys().next() {
+                    debug!(
+                        "Reorder buffer full ({} pending), skipping ahead from chunk {} to {}",
+                        self.pending.len(),
+                        self.next_chunk_id,
+                        first_pending
+                    );
+                    self.next_chunk_id = first_pending;
+                    continue;
+                }
+            }
+
+            break;
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_results_in_chunk_order() {
+        let mut buffer = ChunkReorderBuffer::new(0, 8);
+        assert!(buffer.complete(1, "b").is_empty());
+        assert!(buffer.complete(2, "c").is_empty());
+        assert_eq!(buffer.complete(0, "a"), vec!["a", "b", "c"]);
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn skipped_chunks_dont_hold_back_later_ones() {
+        let mut buffer = ChunkReorderBuffer::new(0, 8);
+        assert!(buffer.complete(1, "b").is_empty());
+        assert_eq!(buffer.skip(0), vec!["b"]);
+    }
+
+    #[test]
+    fn stops_waiting_once_too_many_results_are_pending() {
+        let mut buffer = ChunkReorderBuffer::new(0, 2);
+        assert!(buffer.complete(1, "b").is_empty());
+        assert!(buffer.complete(2, "c").is_empty());
+        assert_eq!(buffer.complete(3, "d"), vec!["b", "c", "d"]);
+
+        // The chunk given up on is still released when it finally arrives
+        assert_eq!(buffer.complete(0, "a"), vec!["a"]);
+    }
+
+    #[test]
+    fn flush_releases_everything_despite_gaps() {
+        let mut buffer = ChunkReorderBuffer::new(0, 8);
+        buffer.complete(3, "d");
+        buffer.complete(1, "b");
+        assert_eq!(buffer.flush(), vec!["b", "d"]);
+        assert_eq!(buffer.complete(4, "e"), vec!["e"]);
+    }
+}
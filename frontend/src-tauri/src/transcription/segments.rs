@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Settings for marking long pauses as boundaries between meeting segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentBoundaryConfig {
+    pub enabled: bool,
+    /// Silence longer than this starts a new segment. Raise it if long thinking
+    /// pauses are being split off.
+    pub min_silence_secs: u64,
+    /// Audio quieter than this RMS counts as silence.
+    pub silence_rms: f32,
+}
+
+impl Default for SegmentBoundaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_silence_secs: 30,
+            silence_rms: 0.005,
+        }
+    }
+}
+
+/// Settings for reporting the pause before each sentence, e.g. to spot hesitations.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GapReportingConfig {
+    pub enabled: bool,
+}
+
+/// Payload of the `segment-boundary` event. Times are seconds since recording start.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SegmentBoundary {
+    pub segment_index: u32,
+    pub silence_start: f64,
+    pub detected_at: f64,
+}
+
+/// Watches the captured audio level and reports a boundary once per long silence.
+pub struct SegmentBoundaryDetector {
+    config: SegmentBoundaryConfig,
+    silence_started: Option<Duration>,
+    heard_speech: bool,
+    reported: bool,
+    segment_index: u32,
+}
+
+impl SegmentBoundaryDetector {
+    pub fn new(config: SegmentBoundaryConfig) -> Self {
+        Self {
+            config,
+            silence_started: None,
+            heard_speech: false,
+            reported: false,
+            segment_index: 0,
+        }
+    }
+
+    pub fn update_config(&mut self, config: SegmentBoundaryConfig) {
+        self.config = config;
+    }
+
+    /// Forgets the current silence and whether anyone has spoken, keeping the
+    /// segment count.
+    pub fn reset(&mut self) {
+        self.silence_started = None;
+        self.heard_speech = false;
+        self.reported = false;
+    }
+
+    /// Feed the RMS of a batch of audio captured at `elapsed` into the recording.
+    pub fn update(&mut self, elapsed: Duration, rms: f32) -> Option<SegmentBoundary> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        if rms >= self.config.silence_rms {
+            self.heard_speech = true;
+            self.silence_started = None;
+            self.reported = false;
+            return None;
+        }
+
+        // Leading silence before anyone has spoken isn't a segment break
+        if !self.heard_speech {
+            return None;
+        }
+
+        let silence_started = *self.silence_started.get_or_insert(elapsed);
+        if self.reported || elapsed.saturating_sub(silence_started) < Duration::from_secs(self.config.min_silence_secs) {
+            return None;
+        }
+
+        self.reported = true;
+        self.segment_index += 1;
+        Some(SegmentBoundary {
+            segment_index: self.segment_index,
+            silence_start: silence_started.as_secs_f64(),
+            detected_at: elapsed.as_secs_f64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> SegmentBoundaryDetector {
+        SegmentBoundaryDetector::new(SegmentBoundaryConfig {
+            min_silence_secs: 10,
+            ..Default::default()
+        })
+    }
+
+    // Feeds one level per second and returns the boundaries reported
+    fn boundaries(detector: &mut SegmentBoundaryDetector, levels: &[f32]) -> Vec<SegmentBoundary> {
+        levels
+            .iter()
+            .enumerate()
+            .filter_map(|(second, &rms)| detector.update(Duration::from_secs(second as u64), rms))
+            .collect()
+    }
+
+    #[test]
+    fn reports_a_long_silence_between_speech_once() {
+        let mut levels = vec![0.1; 5];
+        levels.extend([0.0; 20]);
+        levels.extend([0.1; 5]);
+
+        let expected = SegmentBoundary {
+            segment_index: 1,
+            silence_start: 5.0,
+            detected_at: 15.0,
+        };
+        assert_eq!(boundaries(&mut detector(), &levels), vec![expected]);
+    }
+
+    #[test]
+    fn ignores_leading_silence_and_short_pauses() {
+        let mut levels = vec![0.0; 30];
+        levels.extend([0.1; 5]);
+        levels.extend([0.0; 9]);
+        levels.push(0.1);
+        assert!(boundaries(&mut detector(), &levels).is_empty());
+    }
+
+    #[test]
+    fn a_new_silence_threshold_applies_mid_silence() {
+        let mut detector = SegmentBoundaryDetector::new(SegmentBoundaryConfig::default());
+        let mut levels = vec![0.1];
+        levels.extend([0.0; 12]);
+        assert!(boundaries(&mut detector, &levels).is_empty());
+
+        detector.update_config(SegmentBoundaryConfig {
+            min_silence_secs: 10,
+            ..Default::default()
+        });
+        let boundary = detector.update(Duration::from_secs(13), 0.0).unwrap();
+        assert_eq!(boundary.silence_start, 1.0);
+    }
+
+    #[test]
+    fn numbers_segments_across_resets() {
+        let mut detector = detector();
+        let mut levels = vec![0.1];
+        levels.extend([0.0; 11]);
+        assert_eq!(boundaries(&mut detector, &levels).len(), 1);
+
+        detector.reset();
+        assert!(boundaries(&mut detector, &[0.0; 12]).is_empty());
+        let second = boundaries(&mut detector, &levels);
+        assert_eq!(second[0].segment_index, 2);
+    }
+}
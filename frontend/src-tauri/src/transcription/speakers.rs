@@ -0,0 +1,256 @@
+use realfft::RealFftPlanner;
+use serde::{Deserialize, Serialize};
+
+// Analysis frame and the voiced pitch range searched in it
+const FRAME_SECS: f32 = 0.04;
+const MIN_PITCH_HZ: f32 = 60.0;
+const MAX_PITCH_HZ: f32 = 400.0;
+// Frames quieter than this, or with weaker periodicity, aren't treated as voiced
+const MIN_FRAME_RMS: f32 = 0.01;
+const MIN_PERIODICITY: f32 = 0.5;
+// Frames analysed per segment at most, spread evenly over it
+const MAX_ANALYSIS_FRAMES: usize = 50;
+// Feature differences that count as one unit of distance between voices
+const PITCH_SCALE_SEMITONES: f32 = 4.0;
+const CENTROID_SCALE_OCTAVES: f32 = 0.5;
+
+/// Settings for labelling transcript segments with provisional speakers. This is a
+/// pitch/timbre heuristic for several people sharing one mic, not diarization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeakerHintConfig {
+    pub enabled: bool,
+    /// Voices beyond this many are assigned to the closest known speaker.
+    pub max_speakers: usize,
+    /// How different a voice has to be from every known speaker to count as a new one.
+    /// 1.0 is roughly four semitones of pitch or half an octave of spectral centroid.
+    pub new_speaker_distance: f32,
+}
+
+impl Default for SpeakerHintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_speakers: 4,
+            new_speaker_distance: 1.0,
+        }
+    }
+}
+
+/// Voice characteristics of one segment, from its voiced frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceFeatures {
+    pub pitch_hz: f32,
+    pub spectral_centroid_hz: f32,
+}
+
+impl VoiceFeatures {
+    fn distance(&self, other: &VoiceFeatures) -> f32 {
+        let pitch = (12.0 * (self.pitch_hz / other.pitch_hz).log2()).abs() / PITCH_SCALE_SEMITONES;
+        let centroid = (self.spectral_centroid_hz / other.spectral_centroid_hz).log2().abs() / CENTROID_SCALE_OCTAVES;
+        pitch + centroid
+    }
+}
+
+/// Median pitch and spectral centroid of the voiced frames in `samples`, or None
+/// if there's no voiced audio.
+pub fn voice_features(samples: &[f32], sample_rate: u32) -> Option<VoiceFeatures> {
+    let frame_len = (sample_rate as f32 * FRAME_SECS) as usize;
+    if frame_len == 0 || samples.len() < frame_len {
+        return None;
+    }
+
+    let frame_count = samples.len() / frame_len;
+    let step = (frame_count / MAX_ANALYSIS_FRAMES).max(1);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(frame_len);
+    let mut spectrum = fft.make_output_vec();
+
+    let mut pitches = Vec::new();
+    let mut centroids = Vec::new();
+    for frame in samples.chunks_exact(frame_len).step_by(step) {
+        let Some(pitch) = frame_pitch(frame, sample_rate) else {
+            continue;
+        };
+        let mut input = frame.to_vec();
+        if fft.process(&mut input, &mut spectrum).is_err() {
+            continue;
+        }
+        let bin_hz = sample_rate as f32 / frame_len as f32;
+        let (weighted, total) = spectrum
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(weighted, total), (bin, value)| {
+                let magnitude = value.norm();
+                (weighted + bin as f32 * bin_hz * magnitude, total + magnitude)
+            });
+        if total > 0.0 {
+            pitches.push(pitch);
+            centroids.push(weighted / total);
+        }
+    }
+
+    Some(VoiceFeatures {
+        pitch_hz: median(&mut pitches)?,
+        spectral_centroid_hz: median(&mut centroids)?,
+    })
+}
+
+// Pitch from the strongest normalized autocorrelation peak in the voice range
+pub(crate) fn frame_pitch(frame: &[f32], sample_rate: u32) -> Option<f32> {
+    let energy: f32 = frame.iter().map(|x| x * x).sum();
+    if (energy / frame.len() as f32).sqrt() < MIN_FRAME_RMS {
+        return None;
+    }
+
+    let min_lag = (sample_rate as f32 / MAX_PITCH_HZ) as usize;
+    let max_lag = ((sample_rate as f32 / MIN_PITCH_HZ) as usize).min(frame.len() - 1);
+    let mut best = None;
+    let mut best_score = MIN_PERIODICITY;
+    for lag in min_lag.max(1)..=max_lag {
+        let correlation: f32 = frame[..frame.len() - lag]
+            .iter()
+            .zip(&frame[lag..])
+            .map(|(a, b)| a * b)
+            .sum();
+        let score = correlation / energy;
+        if score > best_score {
+            best_score = score;
+            best = Some(lag);
+        }
+    }
+    best.map(|lag| sample_rate as f32 / lag as f32)
+}
+
+fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    Some(values[values.len() / 2])
+}
+
+struct SpeakerProfile {
+    features: VoiceFeatures,
+    segments: u32,
+}
+
+/// Assigns segments to provisional speakers by comparing their voice features
+/// with the running average of each speaker heard so far.
+pub struct SpeakerTracker {
+    config: SpeakerHintConfig,
+    speakers: Vec<SpeakerProfile>,
+}
+
+impl SpeakerTracker {
+    pub fn new(config: SpeakerHintConfig) -> Self {
+        Self {
+            config,
+            speakers: Vec::new(),
+        }
+    }
+
+    /// Applies new settings, keeping the speakers heard so far.
+    pub fn update_config(&mut self, config: SpeakerHintConfig) {
+        self.config = config;
+    }
+
+    pub fn speaker_count(&self) -> usize {
+        self.speakers.len()
+    }
+
+    /// Returns the 1-based speaker id for a segment with these features.
+    pub fn assign(&mut self, features: &VoiceFeatures) -> u32 {
+        let nearest = self
+            .speakers
+            .iter()
+            .enumerate()
+            .map(|(index, speaker)| (index, speaker.features.distance(features)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let index = match nearest {
+            Some((index, distance))
+                if distance <= self.config.new_speaker_distance
+                    || self.speakers.len() >= self.config.max_speakers.max(1) =>
+            {
+                let speaker = &mut self.speakers[index];
+                speaker.segments += 1;
+                let weight = 1.0 / speaker.segments as f32;
+                speaker.features.pitch_hz += (features.pitch_hz - speaker.features.pitch_hz) * weight;
+                speaker.features.spectral_centroid_hz +=
+                    (features.spectral_centroid_hz - speaker.features.spectral_centroid_hz) * weight;
+                index
+            }
+            _ => {
+                self.speakers.push(SpeakerProfile {
+                    features: *features,
+                    segments: 1,
+                });
+                self.speakers.len() - 1
+            }
+        };
+        index as u32 + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    // One second of a voice-like tone: the pitch and a few weaker harmonics
+    fn voice(pitch_hz: f32) -> Vec<f32> {
+        (0..SAMPLE_RATE)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                (1..=4)
+                    .map(|harmonic| {
+                        let phase = 2.0 * std::f32::consts::PI * pitch_hz * harmonic as f32 * t;
+                        0.2 / harmonic as f32 * phase.sin()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn tracker(max_speakers: usize) -> SpeakerTracker {
+        SpeakerTracker::new(SpeakerHintConfig {
+            enabled: true,
+            max_speakers,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn measures_the_pitch_of_a_voice() {
+        let features = voice_features(&voice(120.0), SAMPLE_RATE).unwrap();
+        assert!((features.pitch_hz - 120.0).abs() < 5.0, "pitch {}", features.pitch_hz);
+        assert_eq!(voice_features(&[0.0; 16000], SAMPLE_RATE), None);
+    }
+
+    #[test]
+    fn tells_two_voices_apart() {
+        let low = voice_features(&voice(110.0), SAMPLE_RATE).unwrap();
+        let high = voice_features(&voice(220.0), SAMPLE_RATE).unwrap();
+
+        let mut tracker = tracker(4);
+        let ids: Vec<u32> = [low, high, low, high]
+            .iter()
+            .map(|features| tracker.assign(features))
+            .collect();
+        assert_eq!(ids, vec![1, 2, 1, 2]);
+        assert_eq!(tracker.speaker_count(), 2);
+    }
+
+    #[test]
+    fn extra_voices_go_to_the_closest_speaker() {
+        let mut tracker = tracker(2);
+        for pitch_hz in [100.0, 200.0, 190.0] {
+            tracker.assign(&voice_features(&voice(pitch_hz), SAMPLE_RATE).unwrap());
+        }
+        let third = voice_features(&voice(300.0), SAMPLE_RATE).unwrap();
+        assert_eq!(tracker.assign(&third), 2);
+        assert_eq!(tracker.speaker_count(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use lazy_static::lazy_static;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+use super::html_export::ExportSegment;
+
+/// The sentences of a finished recording, in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MeetingTranscript {
+    pub sentences: Vec<ExportSegment>,
+}
+
+/// What a summarizer made of a transcript.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MeetingSummary {
+    pub summary: String,
+    pub action_items: Vec<String>,
+}
+
+/// A finished transcript and its summary, sent when a recording's transcription
+/// is complete and a summarizer is registered.
+#[derive(Debug, Clone, Serialize)]
+pub struct FinalizedTranscript {
+    pub transcript: MeetingTranscript,
+    /// `None` if summarizing failed.
+    pub summary: Option<MeetingSummary>,
+}
+
+pub type SummaryFuture<'a> = Pin<Box<dyn Future<Output = Result<MeetingSummary, String>> + Send + 'a>>;
+
+/// Summarizes a finished transcript, e.g. with an LLM.
+pub trait SummarizationHook: Send + Sync {
+    fn summarize<'a>(&'a self, transcript: &'a MeetingTranscript) -> SummaryFuture<'a>;
+}
+
+lazy_static! {
+    static ref SUMMARIZER: RwLock<Option<Arc<dyn SummarizationHook>>> = RwLock::new(None);
+}
+
+/// Sets the summarizer run after each recording, or removes it with `None`.
+/// Recordings already in progress keep the choice made when they started.
+pub fn register_summarizer(hook: Option<Arc<dyn SummarizationHook>>) {
+    if let Ok(mut summarizer) = SUMMARIZER.write() {
+        *summarizer = hook;
+    }
+}
+
+pub fn registered_summarizer() -> Option<Arc<dyn SummarizationHook>> {
+    SUMMARIZER.read().ok().and_then(|summarizer| summarizer.clone())
+}
+
+/// Runs `summarizer` on a finished transcript. A failed summary is logged and
+/// left out, so the transcript is still delivered.
+pub async fn finalize_transcript(
+    summarizer: &dyn SummarizationHook,
+    transcript: MeetingTranscript,
+) -> FinalizedTranscript {
+    let summary = match summarizer.summarize(&transcript).await {
+        Ok(summary) => Some(summary),
+        Err(e) => {
+            error!("Failed to summarize transcript: {}", e);
+            None
+        }
+    };
+    FinalizedTranscript { transcript, summary }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records the transcripts it is given and answers with their sentence count.
+    struct MockSummarizer {
+        received: Mutex<Vec<MeetingTranscript>>,
+        fail: bool,
+    }
+
+    impl SummarizationHook for MockSummarizer {
+        fn summarize<'a>(&'a self, transcript: &'a MeetingTranscript) -> SummaryFuture<'a> {
+            self.received.lock().unwrap().push(transcript.clone());
+            Box::pin(async move {
+                if self.fail {
+                    return Err("model not loaded".to_string());
+                }
+                Ok(MeetingSummary {
+                    summary: format!("{} sentences", transcript.sentences.len()),
+                    action_items: vec!["Send the notes".to_string()],
+                })
+            })
+        }
+    }
+
+    fn transcript() -> MeetingTranscript {
+        let sentence = |text: &str, start: f64| ExportSegment {
+            text: text.to_string(),
+            start,
+            end: Some(start + 1.0),
+            speaker: None,
+        };
+        MeetingTranscript {
+            sentences: vec![sentence("Welcome.", 0.0), sentence("Let's begin.", 1.5)],
+        }
+    }
+
+    #[tokio::test]
+    async fn the_summarizer_gets_the_whole_transcript() {
+        let summarizer = MockSummarizer {
+            received: Mutex::new(Vec::new()),
+            fail: false,
+        };
+        let finalized = finalize_transcript(&summarizer, transcript()).await;
+
+        let received = summarizer.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].sentences[1].text, "Let's begin.");
+        let summary = finalized.summary.unwrap();
+        assert_eq!(summary.summary, "2 sentences");
+        assert_eq!(summary.action_items, vec!["Send the notes"]);
+        assert_eq!(finalized.transcript.sentences.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_failed_summary_still_delivers_the_transcript() {
+        let summarizer = MockSummarizer {
+            received: Mutex::new(Vec::new()),
+            fail: true,
+        };
+        let finalized = finalize_transcript(&summarizer, transcript()).await;
+        assert!(finalized.summary.is_none());
+        assert_eq!(finalized.transcript.sentences.len(), 2);
+    }
+}
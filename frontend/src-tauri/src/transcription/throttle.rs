@@ -0,0 +1,177 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Settings for lowering decoding quality while whisper can't keep up with
+/// the audio, and raising it again once it can.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityThrottleConfig {
+    pub enabled: bool,
+    /// Seconds of audio transcribed per second below which quality is lowered
+    /// a step. Below 1.0 transcription falls further behind.
+    pub slow_speed: f64,
+    /// Speed above which quality is raised a step again. Kept well above
+    /// `slow_speed` so the levels don't flip back and forth.
+    pub recover_speed: f64,
+    /// Chunks measured at a level before it is changed again.
+    pub min_chunks_per_step: u64,
+}
+
+impl Default for QualityThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            slow_speed: 1.0,
+            recover_speed: 1.5,
+            min_chunks_per_step: 3,
+        }
+    }
+}
+
+/// Decoding quality, cheapest last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityLevel {
+    #[default]
+    Full,
+    /// A single candidate per decode, overriding decoding escalation's `best_of`.
+    SingleCandidate,
+    /// Also no re-decoding at higher temperatures when a decode looks poor.
+    NoFallback,
+}
+
+impl QualityLevel {
+    fn lower(self) -> Self {
+        match self {
+            Self::Full => Self::SingleCandidate,
+            Self::SingleCandidate | Self::NoFallback => Self::NoFallback,
+        }
+    }
+
+    fn raise(self) -> Self {
+        match self {
+            Self::Full | Self::SingleCandidate => Self::Full,
+            Self::NoFallback => Self::SingleCandidate,
+        }
+    }
+}
+
+/// Payload of the `quality-throttle-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityChange {
+    pub level: QualityLevel,
+    /// Seconds of audio transcribed per second when the level changed.
+    pub speed: f64,
+}
+
+/// Steps the quality level down while transcription is slower than the audio
+/// and back up once there is headroom.
+#[derive(Debug, Default)]
+pub struct QualityThrottle {
+    level: QualityLevel,
+    chunks_at_level: u64,
+}
+
+impl QualityThrottle {
+    pub fn level(&self) -> QualityLevel {
+        self.level
+    }
+
+    /// Feeds the speed measured after a chunk. Returns the new level when it changed.
+    pub fn observe(&mut self, config: &QualityThrottleConfig, speed: f64) -> Option<QualityLevel> {
+        if !config.enabled {
+            self.level = QualityLevel::Full;
+            self.chunks_at_level = 0;
+            return None;
+        }
+        self.chunks_at_level += 1;
+        if self.chunks_at_level < config.min_chunks_per_step.max(1) {
+            return None;
+        }
+        let next = if speed < config.slow_speed {
+            self.level.lower()
+        } else if speed > config.recover_speed {
+            self.level.raise()
+        } else {
+            self.level
+        };
+        if next == self.level {
+            return None;
+        }
+        self.level = next;
+        self.chunks_at_level = 0;
+        Some(next)
+    }
+}
+
+lazy_static! {
+    static ref QUALITY_THROTTLE: Mutex<QualityThrottle> = Mutex::new(QualityThrottle::default());
+}
+
+/// The level chunks are currently decoded at.
+pub fn current_level() -> QualityLevel {
+    QUALITY_THROTTLE.lock().map(|throttle| throttle.level()).unwrap_or_default()
+}
+
+/// Feeds the shared throttle, see `QualityThrottle::observe`.
+pub fn observe_speed(config: &QualityThrottleConfig, speed: f64) -> Option<QualityLevel> {
+    QUALITY_THROTTLE.lock().ok().and_then(|mut throttle| throttle.observe(config, speed))
+}
+
+/// Back to full quality, for the start of a recording.
+pub fn reset() {
+    if let Ok(mut throttle) = QUALITY_THROTTLE.lock() {
+        *throttle = QualityThrottle::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> QualityThrottleConfig {
+        QualityThrottleConfig {
+            enabled: true,
+            min_chunks_per_step: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn steps_down_while_slow_and_back_up_with_headroom() {
+        let config = config();
+        let mut throttle = QualityThrottle::default();
+        assert_eq!(throttle.observe(&config, 0.5), None);
+        assert_eq!(throttle.observe(&config, 0.5), Some(QualityLevel::SingleCandidate));
+        throttle.observe(&config, 0.5);
+        assert_eq!(throttle.observe(&config, 0.5), Some(QualityLevel::NoFallback));
+
+        assert_eq!(throttle.observe(&config, 2.0), None);
+        assert_eq!(throttle.observe(&config, 2.0), Some(QualityLevel::SingleCandidate));
+        throttle.observe(&config, 2.0);
+        assert_eq!(throttle.observe(&config, 2.0), Some(QualityLevel::Full));
+    }
+
+    #[test]
+    fn holds_the_level_between_the_thresholds() {
+        let config = config();
+        let mut throttle = QualityThrottle::default();
+        throttle.observe(&config, 0.5);
+        throttle.observe(&config, 0.5);
+        for _ in 0..5 {
+            assert_eq!(throttle.observe(&config, 1.2), None);
+        }
+        assert_eq!(throttle.level(), QualityLevel::SingleCandidate);
+    }
+
+    #[test]
+    fn disabling_restores_full_quality() {
+        let mut throttle = QualityThrottle::default();
+        throttle.observe(&config(), 0.5);
+        throttle.observe(&config(), 0.5);
+
+        assert_eq!(throttle.observe(&QualityThrottleConfig::default(), 0.5), None);
+        assert_eq!(throttle.level(), QualityLevel::Full);
+    }
+}
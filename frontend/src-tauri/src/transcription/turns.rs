@@ -0,0 +1,273 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::html_export::ExportSegment;
+
+/// Settings for grouping consecutive sentences from the same speaker into turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnAggregationConfig {
+    pub enabled: bool,
+    /// A pause longer than this between two sentences ends the turn.
+    pub max_gap_secs: f64,
+    /// A turn that gets no new sentence for this many seconds is ended, so the
+    /// last speaker's turn doesn't wait for someone else to talk. Longer than a
+    /// chunk, as a speaker who keeps talking only adds sentences once per chunk.
+    pub idle_flush_secs: f64,
+}
+
+impl Default for TurnAggregationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_gap_secs: 2.0,
+            idle_flush_secs: 40.0,
+        }
+    }
+}
+
+/// Payload of the `speaking-turn-completed` event. Times are seconds since
+/// recording start.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SpeakingTurn {
+    pub source: String,
+    /// Provisional speaker label, when speaker hints are enabled.
+    pub speaker_hint: Option<String>,
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    /// Sequence ids of the transcript updates the turn is made of.
+    pub sequence_ids: Vec<u64>,
+}
+
+/// Collects sentences into the current turn and hands the turn back once a
+/// different speaker or a long enough pause ends it.
+pub struct TurnAggregator {
+    config: TurnAggregationConfig,
+    current: Option<SpeakingTurn>,
+    // When the current turn last got a sentence
+    last_push: Instant,
+}
+
+impl TurnAggregator {
+    pub fn new(config: TurnAggregationConfig) -> Self {
+        Self {
+            config,
+            current: None,
+            last_push: Instant::now(),
+        }
+    }
+
+    pub fn update_config(&mut self, config: TurnAggregationConfig) {
+        self.config = config;
+    }
+
+    /// Adds a sentence. Returns the previous turn if this sentence starts a new one.
+    pub fn push(
+        &mut self,
+        source: &str,
+        speaker_hint: Option<&str>,
+        text: &str,
+        sequence_id: u64,
+        start: f64,
+        end: f64,
+    ) -> Option<SpeakingTurn> {
+        self.last_push = Instant::now();
+        if let Some(turn) = self.current.as_mut() {
+            let same_speaker = turn.source == source && turn.speaker_hint.as_deref() == speaker_hint;
+            if same_speaker && start - turn.end <= self.config.max_gap_secs {
+                if !turn.text.is_empty() && !text.is_empty() {
+                    turn.text.push(' ');
+                }
+                turn.text.push_str(text);
+                turn.end = turn.end.max(end);
+                turn.sequence_ids.push(sequence_id);
+                return None;
+            }
+        }
+
+        self.current.replace(SpeakingTurn {
+            source: source.to_string(),
+            speaker_hint: speaker_hint.map(str::to_string),
+            text: text.to_string(),
+            start,
+            end: end.max(start),
+            sequence_ids: vec![sequence_id],
+        })
+    }
+
+    /// Ends the current turn if it hasn't had a new sentence for `idle_flush_secs`.
+    pub fn flush_idle(&mut self) -> Option<SpeakingTurn> {
+        let idle = Duration::from_secs_f64(self.config.idle_flush_secs.max(0.0));
+        if self.last_push.elapsed() < idle {
+            return None;
+        }
+        self.current.take()
+    }
+
+    /// Ends the current turn, e.g. when the recording stops.
+    pub fn finish(&mut self) -> Option<SpeakingTurn> {
+        self.current.take()
+    }
+}
+
+/// Settings for breaking the transcript into paragraphs at speaker changes and
+/// long pauses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParagraphConfig {
+    /// Marks paragraph starts on live transcript updates. Assembling a saved
+    /// transcript into paragraphs works either way.
+    pub enabled: bool,
+    /// A pause at least this long between two sentences starts a new paragraph.
+    pub min_pause_secs: f64,
+}
+
+impl Default for ParagraphConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_pause_secs: 3.0,
+        }
+    }
+}
+
+/// Whether a sentence starts a new paragraph, given the pause before it and
+/// whether its speaker differs from the previous sentence's.
+pub fn starts_paragraph(config: &ParagraphConfig, gap_secs: f64, speaker_changed: bool) -> bool {
+    speaker_changed || gap_secs >= config.min_pause_secs
+}
+
+/// Joins sentences into text with a blank line between paragraphs. Each
+/// paragraph after a speaker change is prefixed with the speaker's label.
+pub fn assemble_paragraphs(segments: &[ExportSegment], config: &ParagraphConfig) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&ExportSegment> = None;
+    for segment in segments {
+        let sentence = segment.text.trim();
+        if sentence.is_empty() {
+            continue;
+        }
+        match previous {
+            None => {
+                if let Some(speaker) = &segment.speaker {
+                    text.push_str(&format!("{}: ", speaker));
+                }
+            }
+            Some(previous) => {
+                let speaker_changed = previous.speaker != segment.speaker;
+                let gap_secs = segment.start - previous.end.unwrap_or(previous.start);
+                if starts_paragraph(config, gap_secs, speaker_changed) {
+                    text.push_str("\n\n");
+                    if let (true, Some(speaker)) = (speaker_changed, &segment.speaker) {
+                        text.push_str(&format!("{}: ", speaker));
+                    }
+                } else {
+                    text.push(' ');
+                }
+            }
+        }
+        text.push_str(sentence);
+        previous = Some(segment);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregator() -> TurnAggregator {
+        TurnAggregator::new(TurnAggregationConfig {
+            enabled: true,
+            max_gap_secs: 2.0,
+            idle_flush_secs: 40.0,
+        })
+    }
+
+    fn segment(text: &str, start: f64, end: f64, speaker: Option<&str>) -> ExportSegment {
+        ExportSegment {
+            text: text.to_string(),
+            start,
+            end: Some(end),
+            speaker: speaker.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn merges_sentences_from_the_same_speaker_within_the_gap() {
+        let mut turns = aggregator();
+        assert_eq!(turns.push("mic", None, "Hello there.", 1, 0.0, 1.5), None);
+        assert_eq!(turns.push("mic", None, "How are you?", 2, 3.0, 4.0), None);
+
+        let turn = turns.finish().unwrap();
+        assert_eq!(turn.text, "Hello there. How are you?");
+        assert_eq!((turn.start, turn.end), (0.0, 4.0));
+        assert_eq!(turn.sequence_ids, vec![1, 2]);
+        assert_eq!(turns.finish(), None);
+    }
+
+    #[test]
+    fn a_speaker_change_or_long_pause_ends_the_turn() {
+        let mut turns = aggregator();
+        turns.push("mic", None, "First.", 1, 0.0, 1.0);
+
+        let ended = turns.push("system", None, "Second.", 2, 1.5, 2.0).unwrap();
+        assert_eq!(ended.text, "First.");
+        assert_eq!(ended.source, "mic");
+
+        let ended = turns.push("system", None, "Third.", 3, 4.5, 5.0).unwrap();
+        assert_eq!(ended.text, "Second.");
+
+        let ended = turns.push("system", Some("Speaker 2"), "Fourth.", 4, 5.5, 6.0).unwrap();
+        assert_eq!(ended.text, "Third.");
+        assert_eq!(turns.finish().unwrap().speaker_hint.as_deref(), Some("Speaker 2"));
+    }
+
+    #[test]
+    fn a_turn_without_new_sentences_is_ended_after_the_idle_time() {
+        let mut turns = aggregator();
+        turns.push("mic", None, "Any questions?", 1, 0.0, 1.0);
+        assert_eq!(turns.flush_idle(), None);
+
+        turns.update_config(TurnAggregationConfig {
+            enabled: true,
+            max_gap_secs: 2.0,
+            idle_flush_secs: 0.0,
+        });
+        assert_eq!(turns.flush_idle().unwrap().text, "Any questions?");
+        assert_eq!(turns.flush_idle(), None);
+    }
+
+    #[test]
+    fn paragraphs_break_at_speaker_changes_and_long_pauses() {
+        let config = ParagraphConfig {
+            enabled: true,
+            min_pause_secs: 3.0,
+        };
+        let segments = vec![
+            segment("Let's start.", 0.0, 1.0, Some("Alex")),
+            segment("First item.", 1.5, 2.5, Some("Alex")),
+            segment("Sounds good.", 3.0, 4.0, Some("Sam")),
+            segment(" ", 4.5, 5.0, Some("Sam")),
+            segment("After a pause.", 8.0, 9.0, Some("Sam")),
+        ];
+
+        assert_eq!(
+            assemble_paragraphs(&segments, &config),
+            "Alex: Let's start. First item.\n\nSam: Sounds good.\n\nAfter a pause."
+        );
+    }
+
+    #[test]
+    fn a_pause_at_the_threshold_starts_a_paragraph() {
+        let config = ParagraphConfig {
+            enabled: true,
+            min_pause_secs: 2.0,
+        };
+        assert!(!starts_paragraph(&config, 1.9, false));
+        assert!(starts_paragraph(&config, 2.0, false));
+        assert!(starts_paragraph(&config, 0.1, true));
+    }
+}
@@ -1,7 +1,7 @@
 'use client';
 
 import { useState, useEffect, useContext, useCallback, useRef } from 'react';
-import { Transcript, TranscriptUpdate, Summary, SummaryResponse } from '@/types';
+import { Transcript, TranscriptUpdate, Summary, SummaryResponse, MeetingTranscript } from '@/types';
 import { EditableTitle } from '@/components/EditableTitle';
 import { TranscriptView } from '@/components/TranscriptView';
 import { RecordingControls } from '@/components/RecordingControls';
@@ -64,10 +64,13 @@ export default function Home() {
   const [showModelSettings, setShowModelSettings] = useState(false);
   const [showErrorAlert, setShowErrorAlert] = useState(false);
   const [errorMessage, setErrorMessage] = useState('');
+  const [errorStoppedRecording, setErrorStoppedRecording] = useState(true);
   const [showChunkDropWarning, setShowChunkDropWarning] = useState(false);
   const [chunkDropMessage, setChunkDropMessage] = useState('');
   const [isSavingTranscript, setIsSavingTranscript] = useState(false);
   const [isRecordingDisabled, setIsRecordingDisabled] = useState(false);
+  const [autosavedTranscript, setAutosavedTranscript] = useState<MeetingTranscript | null>(null);
+  const [autosaveCheckPending, setAutosaveCheckPending] = useState(true);
 
   const { setCurrentMeeting, setMeetings, meetings, isMeetingActive, setIsMeetingActive, setIsRecording: setSidebarIsRecording , serverAddress} = useSidebar();
   const handleNavigation = useNavigation('', ''); // Initialize with empty values
@@ -362,6 +365,76 @@ export default function Home() {
   }, []);
 
   // Set up chunk drop warning listener
+  useEffect(() => {
+    let unlistenFn: (() => void) | undefined;
+
+    const setupAutosaveListener = async () => {
+      try {
+        // A recording that didn't stop cleanly left its transcript behind, offered once this one stops
+        unlistenFn = await listen<string>('transcript-autosave-recovered', (event) => {
+          console.log('Autosaved transcript kept at:', event.payload);
+          setAutosaveCheckPending(true);
+        });
+      } catch (error) {
+        console.error('Failed to setup transcript autosave listener:', error);
+      }
+    };
+
+    setupAutosaveListener();
+
+    return () => {
+      if (unlistenFn) {
+        unlistenFn();
+      }
+    };
+  }, []);
+
+  useEffect(() => {
+    // The backend only hands out the autosave while nothing is recording
+    if (!autosaveCheckPending || isRecording) {
+      return;
+    }
+    setAutosaveCheckPending(false);
+    invoke<MeetingTranscript | null>('get_autosaved_transcript')
+      .then((transcript) => {
+        if (transcript && transcript.sentences.length > 0) {
+          setAutosavedTranscript(transcript);
+        }
+      })
+      .catch((error) => console.error('Failed to check for an autosaved transcript:', error));
+  }, [autosaveCheckPending, isRecording]);
+
+  const formatSentenceTime = (seconds: number) => {
+    const total = Math.floor(seconds);
+    const pad = (value: number) => value.toString().padStart(2, '0');
+    return `${pad(Math.floor(total / 3600))}:${pad(Math.floor((total % 3600) / 60))}:${pad(total % 60)}`;
+  };
+
+  const discardAutosave = async () => {
+    setAutosavedTranscript(null);
+    try {
+      await invoke('discard_autosaved_transcript');
+    } catch (error) {
+      console.error('Failed to discard the autosaved transcript:', error);
+    }
+  };
+
+  const handleRestoreAutosave = async () => {
+    if (!autosavedTranscript) {
+      return;
+    }
+    setTranscripts(autosavedTranscript.sentences.map((sentence, index) => ({
+      id: `restored-${index}`,
+      text: sentence.text,
+      timestamp: formatSentenceTime(sentence.start),
+      sequence_id: index,
+      chunk_start_time: sentence.start,
+      is_partial: false,
+    })));
+    setAutosavedTranscript(null);
+    await discardAutosave();
+  };
+
   useEffect(() => {
     let unlistenFn: (() => void) | undefined;
 
@@ -1083,7 +1156,9 @@ export default function Home() {
       {showErrorAlert && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
           <Alert className="max-w-md mx-4 border-red-200 bg-white shadow-xl">
-            <AlertTitle className="text-red-800">Recording Stopped</AlertTitle>
+            <AlertTitle className="text-red-800">
+              {errorStoppedRecording ? 'Recording Stopped' : 'Transcription Failing, Still Recording'}
+            </AlertTitle>
             <AlertDescription className="text-red-700">
               {errorMessage}
               <button
@@ -1096,6 +1171,30 @@ export default function Home() {
           </Alert>
         </div>
       )}
+      {autosavedTranscript && !isRecording && (
+        <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
+          <Alert className="max-w-md mx-4 border-blue-200 bg-white shadow-xl">
+            <AlertTitle className="text-blue-800">Restore Unsaved Transcript?</AlertTitle>
+            <AlertDescription className="text-blue-700">
+              A recording didn&apos;t stop cleanly and left {autosavedTranscript.sentences.length} transcribed sentences behind.
+              <div className="mt-3 flex space-x-3">
+                <button
+                  onClick={handleRestoreAutosave}
+                  className="text-blue-600 hover:text-blue-800 underline"
+                >
+                  Restore
+                </button>
+                <button
+                  onClick={discardAutosave}
+                  className="text-gray-600 hover:text-gray-800 underline"
+                >
+                  Discard
+                </button>
+              </div>
+            </AlertDescription>
+          </Alert>
+        </div>
+      )}
       {showChunkDropWarning && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
           <Alert className="max-w-lg mx-4 border-yellow-200 bg-white shadow-xl">
@@ -1267,8 +1366,9 @@ export default function Home() {
                 onRecordingStart={handleRecordingStart}
                 onTranscriptReceived={handleTranscriptUpdate}
                 barHeights={barHeights}
-                onTranscriptionError={(message) => {
+                onTranscriptionError={(message, recordingStopped) => {
                   setErrorMessage(message);
+                  setErrorStoppedRecording(recordingStopped);
                   setShowErrorAlert(true);
                 }}
                 isRecordingDisabled={isRecordingDisabled}
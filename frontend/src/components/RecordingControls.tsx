@@ -15,7 +15,7 @@ interface RecordingControlsProps {
   onRecordingStop: (callApi?: boolean) => void;
   onRecordingStart: () => void;
   onTranscriptReceived: (summary: SummaryResponse) => void;
-  onTranscriptionError?: (message: string) => void;
+  onTranscriptionError?: (message: string, recordingStopped: boolean) => void;
   isRecordingDisabled: boolean;
 }
 
@@ -146,13 +146,35 @@ export const RecordingControls: React.FC<RecordingControlsProps> = ({
     };
   }, []);
 
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+
+    // The backend only detects the silence, saving the recording is done here
+    const setupListener = async () => {
+      unsubscribe = await listen<{ action: 'stop' | 'pause' }>('silence-auto-stop', (event) => {
+        console.log('silence-auto-stop event received:', event.payload);
+        if (event.payload.action === 'stop') {
+          handleStopRecording();
+        }
+      });
+    };
+
+    setupListener();
+
+    return () => {
+      if (unsubscribe) {
+        unsubscribe();
+      }
+    };
+  }, [handleStopRecording]);
+
   useEffect(() => {
     console.log('Setting up transcript-error event listener');
     let unsubscribe: (() => void) | undefined;
     
     const setupListener = async () => {
       try {
-        unsubscribe = await listen('transcript-error', (event) => {
+        unsubscribe = await listen('transcript-error', async (event) => {
           console.log('transcript-error event received:', event);
           console.error('Transcription error received:', event.payload);
           const errorMessage = event.payload as string;
@@ -166,11 +188,15 @@ export const RecordingControls: React.FC<RecordingControlsProps> = ({
             console.log('Transcription error count incremented:', newCount);
             return newCount;
           });
-          setIsProcessing(false);
-          console.log('Calling onRecordingStop(false) due to transcript error');
-          onRecordingStop(false);
+          // With failed chunks kept for reprocessing the backend keeps recording
+          const stillRecording = await invoke<boolean>('is_recording');
+          if (!stillRecording) {
+            setIsProcessing(false);
+            console.log('Calling onRecordingStop(false) due to transcript error');
+            onRecordingStop(false);
+          }
           if (onTranscriptionError) {
-            onTranscriptionError(errorMessage);
+            onTranscriptionError(errorMessage, !stillRecording);
           }
         });
         console.log('transcript-error event listener set up successfully');
@@ -15,11 +15,50 @@ export interface Transcript {
 
 export interface TranscriptUpdate {
   text: string;
+  clean_text: string;
   timestamp: string;
   source: string;
   sequence_id: number;
   chunk_start_time: number;
   is_partial: boolean;
+  speaker_hint?: string;
+  gap_before_ms?: number;
+  starts_paragraph?: boolean;
+  hallucination_detected?: boolean;
+  confidence?: number;
+  overlapping_speech?: boolean;
+}
+
+export interface SegmentBoundary {
+  segment_index: number;
+  silence_start: number;
+  detected_at: number;
+}
+
+export interface SpeakingTurn {
+  source: string;
+  speaker_hint: string | null;
+  text: string;
+  start: number;
+  end: number;
+  sequence_ids: number[];
+}
+
+export interface TranscriptSentence {
+  text: string;
+  start: number;
+  end?: number;
+  speaker?: string;
+}
+
+export interface MeetingTranscript {
+  sentences: TranscriptSentence[];
+}
+
+export interface RawTranscript {
+  chunk_id: number;
+  chunk_start_time: number;
+  response: unknown;
 }
 
 export interface Block {